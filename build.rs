@@ -0,0 +1,5 @@
+fn main() {
+    // The gRPC stubs are only generated when the `grpc` feature is enabled
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/vehreg.proto").expect("failed to compile proto/vehreg.proto");
+}
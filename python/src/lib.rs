@@ -0,0 +1,169 @@
+use chrono::NaiveDate;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::time::Duration;
+use veh_reg::config::AppConfig;
+use veh_reg::ordering::SearchOrder;
+use veh_reg::stop::StopConditions;
+use veh_reg::{CheckOutcome, ScanEvent, ScanOptions, VehicleClient};
+
+// Python bindings for the scan engine:
+//
+//     import veh_reg
+//     client = veh_reg.Client()
+//     client.check("ABC-123", "2021-03-04")
+//     client.scan("ABC-123", "2021-01-01", "2021-12-31", threads=4, callback=print)
+//
+// Results come back as plain dicts, dates as "YYYY-MM-DD" strings.
+#[pyclass(name = "Client", module = "veh_reg")]
+struct PyClient {
+    inner: VehicleClient,
+}
+
+#[pymethods]
+impl PyClient {
+    // Endpoint, headers and classifier rules from the app's saved settings
+    #[new]
+    fn new() -> PyResult<Self> {
+        let config = AppConfig::load().map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let inner = VehicleClient::from_config(&config).map_err(PyRuntimeError::new_err)?;
+        Ok(Self { inner })
+    }
+
+    // Looks up a single date; the GIL is released during the request
+    fn check<'py>(&self, py: Python<'py>, vehicle_no: &str, date: &str) -> PyResult<Bound<'py, PyDict>> {
+        let date = parse_date(date)?;
+        let outcome = py
+        .allow_threads(|| self.inner.check_blocking(vehicle_no, date))
+        .map_err(PyRuntimeError::new_err)?;
+        outcome_dict(py, &outcome)
+    }
+
+    // Checks every date from `start` to `end` and returns the hits. When given,
+    // `callback` is called with a dict for every progress, miss, hit, error,
+    // rate_limited and paused event as it happens. Rate limits are waited out
+    // and the date retried; a challenge page (paused) ends the scan. Raising
+    // from the callback, or Ctrl+C, stops it too. At most 20 threads are used.
+    #[pyo3(signature = (vehicle_no, start, end, threads=4, order="forward", max_hits=1, max_requests=0, time_limit_mins=0, delay_ms=0, callback=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn scan<'py>(
+        &self,
+        py: Python<'py>,
+        vehicle_no: &str,
+        start: &str,
+        end: &str,
+        threads: usize,
+        order: &str,
+        max_hits: u32,
+        max_requests: u64,
+        time_limit_mins: u64,
+        delay_ms: u64,
+        callback: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let (start, end) = (parse_date(start)?, parse_date(end)?);
+        if threads == 0 {
+            return Err(PyValueError::new_err("threads must be at least 1"));
+        }
+        let options = ScanOptions {
+            threads,
+            order: parse_order(order)?,
+            stop: StopConditions { max_hits, time_limit_mins, max_requests },
+            delay: Duration::from_millis(delay_ms),
+            ..ScanOptions::default()
+        };
+
+        let mut scan = self.inner.scan(vehicle_no, start, end, options);
+        while let Some(event) = py.allow_threads(|| scan.next_blocking()) {
+            let result = py.check_signals().and_then(|_| match &callback {
+                Some(callback) => callback.call1((event_dict(py, &event)?,)).map(|_| ()),
+                None => Ok(()),
+            });
+            if let Err(e) = result {
+                scan.stop();
+                // Let the workers finish their current request before raising
+                py.allow_threads(|| while scan.next_blocking().is_some() {});
+                return Err(e);
+            }
+            // There is no way to clear a challenge page from here
+            if let ScanEvent::Paused { .. } = event {
+                scan.stop();
+            }
+        }
+        scan.hits().iter().map(|outcome| outcome_dict(py, outcome)).collect()
+    }
+}
+
+fn parse_date(date: &str) -> PyResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| PyValueError::new_err(format!("Invalid date '{}', expected YYYY-MM-DD", date)))
+}
+
+fn parse_order(order: &str) -> PyResult<SearchOrder> {
+    match order {
+        "forward" => Ok(SearchOrder::Forward),
+        "backward" => Ok(SearchOrder::Backward),
+        "newest_first" => Ok(SearchOrder::NewestFirstInterleaved),
+        _ => Err(PyValueError::new_err(format!("Unknown order '{}', expected forward, backward or newest_first", order))),
+    }
+}
+
+fn outcome_dict<'py>(py: Python<'py>, outcome: &CheckOutcome) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("vehicle_no", &outcome.vehicle_no)?;
+    dict.set_item("date", outcome.date.format("%Y-%m-%d").to_string())?;
+    dict.set_item("status", outcome.status)?;
+    dict.set_item("classification", outcome.classification.label())?;
+    dict.set_item("rule", &outcome.rule)?;
+    let fields = PyDict::new_bound(py);
+    for (label, value) in &outcome.fields {
+        fields.set_item(label, value)?;
+    }
+    dict.set_item("fields", fields)?;
+    dict.set_item("body", &outcome.body)?;
+    Ok(dict)
+}
+
+fn event_dict<'py>(py: Python<'py>, event: &ScanEvent) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    match event {
+        ScanEvent::Progress { checked, total } => {
+            dict.set_item("type", "progress")?;
+            dict.set_item("checked", checked)?;
+            dict.set_item("total", total)?;
+        }
+        ScanEvent::Miss { date } => {
+            dict.set_item("type", "miss")?;
+            dict.set_item("date", date.format("%Y-%m-%d").to_string())?;
+        }
+        ScanEvent::Hit(outcome) => {
+            dict.set_item("type", "hit")?;
+            dict.set_item("date", outcome.date.format("%Y-%m-%d").to_string())?;
+            dict.set_item("outcome", outcome_dict(py, outcome)?)?;
+        }
+        ScanEvent::RateLimited { date, status, cooloff } => {
+            dict.set_item("type", "rate_limited")?;
+            dict.set_item("date", date.format("%Y-%m-%d").to_string())?;
+            dict.set_item("status", status)?;
+            dict.set_item("cooloff_secs", cooloff.as_secs())?;
+        }
+        ScanEvent::Paused { date, detail } => {
+            dict.set_item("type", "paused")?;
+            dict.set_item("date", date.format("%Y-%m-%d").to_string())?;
+            dict.set_item("detail", detail)?;
+        }
+        ScanEvent::Error { date, status, detail } => {
+            dict.set_item("type", "error")?;
+            dict.set_item("date", date.format("%Y-%m-%d").to_string())?;
+            dict.set_item("status", status)?;
+            dict.set_item("detail", detail)?;
+        }
+    }
+    Ok(dict)
+}
+
+#[pymodule]
+#[pyo3(name = "veh_reg")]
+fn veh_reg_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    Ok(())
+}
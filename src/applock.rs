@@ -0,0 +1,38 @@
+#[cfg(feature = "app-lock")]
+use argon2::Argon2;
+#[cfg(feature = "app-lock")]
+use argon2::password_hash::rand_core::OsRng;
+#[cfg(feature = "app-lock")]
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+
+// The app's own startup passphrase, for laptops several people use. Only an
+// Argon2 hash of it is kept, in config.json. It hides history, results and
+// settings behind a lock screen; it doesn't encrypt anything (see vault.rs).
+#[cfg(feature = "app-lock")]
+pub const AVAILABLE: bool = true;
+
+#[cfg(feature = "app-lock")]
+pub fn hash(passphrase: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default().hash_password(passphrase.as_bytes(), &salt).map(|hash| hash.to_string()).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "app-lock")]
+pub fn verify(hash: &str, passphrase: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|parsed| Argon2::default().verify_password(passphrase.as_bytes(), &parsed).is_ok())
+}
+
+// Without the feature no passphrase can be set, and one set by another build
+// can't be checked, so the app never locks
+#[cfg(not(feature = "app-lock"))]
+pub const AVAILABLE: bool = false;
+
+#[cfg(not(feature = "app-lock"))]
+pub fn hash(_passphrase: &str) -> Result<String, String> {
+    Err(crate::i18n::tr!("this build has no startup passphrase support (--features app-lock)"))
+}
+
+#[cfg(not(feature = "app-lock"))]
+pub fn verify(_hash: &str, _passphrase: &str) -> bool {
+    false
+}
@@ -0,0 +1,76 @@
+use crate::vault;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+// One JSON object per line, appended across runs and sessions, so the events
+// of a scan can be picked apart with jq or shipped to a SIEM. Not kept while
+// the results are encrypted.
+pub const AUDIT_FILE: &str = "audit.jsonl";
+
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+#[derive(Serialize, Default)]
+pub struct Event {
+    pub timestamp: String,
+    pub event: &'static str,
+    pub thread: Option<usize>,
+    pub vehicle: String,
+    pub date: Option<String>,
+    pub status: Option<u16>,
+    pub detail: Option<String>,
+}
+
+impl Event {
+    pub fn new(event: &'static str, vehicle: &str) -> Self {
+        Self { event, vehicle: vehicle.to_string(), ..Self::default() }
+    }
+
+    pub fn thread(self, thread: usize) -> Self {
+        Self { thread: Some(thread), ..self }
+    }
+
+    pub fn date(self, date: &str) -> Self {
+        Self { date: Some(date.to_string()), ..self }
+    }
+
+    pub fn status(self, status: u16) -> Self {
+        Self { status: Some(status), ..self }
+    }
+
+    pub fn detail(self, detail: impl Into<String>) -> Self {
+        Self { detail: Some(detail.into()), ..self }
+    }
+}
+
+// Starts appending to <results>/audit.jsonl
+pub fn open(results_dir: &Path) -> io::Result<()> {
+    if let Err(e) = vault::allow_plain() {
+        close();
+        return Err(e);
+    }
+    fs::create_dir_all(results_dir)?;
+    let file = OpenOptions::new().create(true).append(true).open(results_dir.join(AUDIT_FILE))?;
+    if let Ok(mut current) = FILE.lock() {
+        *current = Some(file);
+    }
+    Ok(())
+}
+
+pub fn close() {
+    if let Ok(mut current) = FILE.lock() {
+        *current = None;
+    }
+}
+
+// Does nothing while the audit log is closed
+pub fn record(mut event: Event) {
+    let Ok(mut current) = FILE.lock() else { return };
+    let Some(file) = current.as_mut() else { return };
+    event.timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string();
+    if let Ok(line) = serde_json::to_string(&event) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+// What a token may do on the server. Submit includes everything ReadOnly can.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TokenScope {
+    // Dashboard, status, results and the live event socket
+    ReadOnly,
+    // Also starting and stopping runs
+    Submit,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 2] = [TokenScope::ReadOnly, TokenScope::Submit];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TokenScope::ReadOnly => "Read-only",
+            TokenScope::Submit => "Submit jobs",
+        }
+    }
+
+    pub fn allows(&self, needed: TokenScope) -> bool {
+        *self == TokenScope::Submit || needed == TokenScope::ReadOnly
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    // Only for telling tokens apart in the settings and the log
+    pub name: String,
+    pub token: String,
+    pub scope: TokenScope,
+}
+
+pub enum AuthError {
+    // No token, or one that isn't configured
+    Unauthorized,
+    // A valid token without the scope the request needs
+    Forbidden,
+}
+
+// With no tokens configured the server is open, as it was before tokens
+// existed. Otherwise the presented token has to match one with enough scope.
+pub fn authorize<'a>(tokens: &'a [ApiToken], presented: Option<&str>, needed: TokenScope) -> Result<Option<&'a ApiToken>, AuthError> {
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let presented = presented.ok_or(AuthError::Unauthorized)?;
+    let token = tokens
+    .iter()
+    .find(|t| !t.token.is_empty() && constant_time_eq(t.token.as_bytes(), presented.as_bytes()))
+    .ok_or(AuthError::Unauthorized)?;
+    if token.scope.allows(needed) { Ok(Some(token)) } else { Err(AuthError::Forbidden) }
+}
+
+// Doesn't stop at the first differing byte, so response times say nothing
+// about how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// 32 hex characters, 16 bytes straight from the OS's random source
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the OS random source is unavailable");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
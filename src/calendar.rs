@@ -0,0 +1,134 @@
+use crate::coverage::CoverageMap;
+use crate::i18n;
+use crate::search::SearchBy;
+use crate::storage;
+use chrono::{Datelike, NaiveDate};
+use eframe::egui::{self, Color32, Sense, Vec2};
+use std::collections::HashMap;
+use std::path::Path;
+
+const CELL: f32 = 11.0;
+const GAP: f32 = 2.0;
+const LABEL_WIDTH: f32 = 70.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DayState {
+    Checked,
+    Error,
+    Hit,
+}
+
+impl DayState {
+    fn color(&self) -> Color32 {
+        match self {
+            DayState::Checked => Color32::from_rgb(70, 130, 180),
+            DayState::Error => Color32::from_rgb(220, 60, 60),
+            DayState::Hit => Color32::from_rgb(0, 220, 0),
+        }
+    }
+}
+
+// What is known about each date for one vehicle: checked dates from its
+// coverage bitmap, errors and hits from the saved responses and the current
+// run. A hit outranks an error, which outranks a plain check.
+pub struct CoverageCalendar {
+    pub vehicle_no: String,
+    coverage: CoverageMap,
+    marked: HashMap<NaiveDate, DayState>,
+}
+
+impl CoverageCalendar {
+    pub fn build(results_dir: &Path, provider: &str, search_by: SearchBy, vehicle_no: &str, live: Option<&CoverageMap>, error_dates: &[NaiveDate], hit_dates: &[NaiveDate]) -> Self {
+        let mut coverage = CoverageMap::load(&CoverageMap::path_for(results_dir, provider, search_by, vehicle_no)).unwrap_or_default();
+        if let Some(live) = live {
+            coverage.merge(live);
+        }
+
+        let mut marked = HashMap::new();
+        let mut mark = |date: NaiveDate, state: DayState| {
+            let current = marked.entry(date).or_insert(state);
+            if state == DayState::Hit {
+                *current = state;
+            }
+        };
+
+        for path in storage::walk_results(results_dir) {
+            let Some(result) = storage::parse_result_path(&path) else { continue };
+            if result.vehicle_no != vehicle_no {
+                continue;
+            }
+            mark(result.date, if result.status == 200 { DayState::Hit } else { DayState::Error });
+        }
+        for date in error_dates {
+            mark(*date, DayState::Error);
+        }
+        for date in hit_dates {
+            mark(*date, DayState::Hit);
+        }
+
+        Self { vehicle_no: vehicle_no.to_string(), coverage, marked }
+    }
+
+    pub fn state(&self, date: NaiveDate) -> Option<DayState> {
+        match self.marked.get(&date) {
+            Some(state) => Some(*state),
+            None if self.coverage.contains(date) => Some(DayState::Checked),
+            None => None,
+        }
+    }
+
+    pub fn count_in(&self, state: DayState, start: NaiveDate, end: NaiveDate) -> usize {
+        start.iter_days()
+        .take_while(|d| *d <= end)
+        .filter(|d| self.state(*d) == Some(state))
+        .count()
+    }
+
+    // One row per month from `start` to `end`, one cell per day. Hovering a
+    // cell shows its date.
+    pub fn show(&self, ui: &mut egui::Ui, start: NaiveDate, end: NaiveDate) {
+        let empty = ui.visuals().faint_bg_color;
+        let mut month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1).unwrap_or(start);
+
+        while month <= end {
+            ui.horizontal(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::new(GAP, GAP);
+                let (label_rect, _) = ui.allocate_exact_size(Vec2::new(LABEL_WIDTH, CELL), Sense::hover());
+                ui.painter().text(
+                    label_rect.left_center(),
+                    egui::Align2::LEFT_CENTER,
+                    month.format("%Y %b").to_string(),
+                    egui::FontId::monospace(10.0),
+                    ui.visuals().text_color(),
+                );
+
+                for day in month.iter_days().take_while(|d| d.month() == month.month()) {
+                    let (rect, response) = ui.allocate_exact_size(Vec2::splat(CELL), Sense::hover());
+                    let in_range = day >= start && day <= end;
+                    let fill = match self.state(day) {
+                        Some(state) => state.color(),
+                        None => empty,
+                    };
+                    // Days outside the selected range are drawn faded
+                    ui.painter().rect_filled(rect, 2.0, if in_range { fill } else { fill.gamma_multiply(0.3) });
+                    response.on_hover_text(day.format("%Y-%m-%d").to_string());
+                }
+            });
+
+            month = match month.checked_add_months(chrono::Months::new(1)) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+
+    pub fn legend(ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            for (state, name) in [(DayState::Checked, "Checked"), (DayState::Error, "Error"), (DayState::Hit, "Hit")] {
+                let (rect, _) = ui.allocate_exact_size(Vec2::splat(CELL), Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, state.color());
+                ui.label(i18n::label(name));
+            }
+        });
+    }
+}
@@ -0,0 +1,62 @@
+use crate::i18n::tr;
+use crate::storage::{self, CASES_DIR};
+use crate::vault;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Sealed like the results while those are encrypted, since the name and
+// notes say what the case is about
+pub const CASE_FILE: &str = "case.json";
+
+// A named investigation: its searches save under <results>/cases/<name>/, so
+// the history, tags, notes and exports of a case stay apart from the rest
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Case {
+    pub name: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub notes: String,
+}
+
+pub fn dir(results_root: &Path, name: &str) -> PathBuf {
+    results_root.join(CASES_DIR).join(storage::sanitize(name.trim()))
+}
+
+// Every case under `results_root`, by name
+pub fn list(results_root: &Path) -> Vec<Case> {
+    let Ok(entries) = fs::read_dir(results_root.join(CASES_DIR)) else { return Vec::new() };
+    let mut cases: Vec<Case> = entries
+    .flatten()
+    .filter_map(|entry| vault::read_to_string(&entry.path().join(CASE_FILE)).ok())
+    .filter_map(|content| serde_json::from_str(&content).ok())
+    .collect();
+    cases.sort_by_key(|case: &Case| case.name.to_lowercase());
+    cases
+}
+
+pub fn load(results_root: &Path, name: &str) -> Option<Case> {
+    let content = vault::read_to_string(&dir(results_root, name).join(CASE_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn create(results_root: &Path, name: &str) -> Result<Case, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(tr!("the case needs a name"));
+    }
+    let case_dir = dir(results_root, name);
+    if case_dir.join(CASE_FILE).exists() {
+        return Err(tr!("there is already a case called '{}'", name));
+    }
+    let case = Case { name: name.to_string(), created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(), notes: String::new() };
+    save(results_root, &case)?;
+    Ok(case)
+}
+
+pub fn save(results_root: &Path, case: &Case) -> Result<(), String> {
+    let case_dir = dir(results_root, &case.name);
+    fs::create_dir_all(&case_dir).map_err(|e| tr!("cannot create {} - {}", case_dir.display(), e))?;
+    let content = serde_json::to_string_pretty(case).map_err(|e| e.to_string())?;
+    vault::write(&case_dir.join(CASE_FILE), content).map_err(|e| tr!("cannot write {} - {}", case_dir.join(CASE_FILE).display(), e))
+}
@@ -0,0 +1,200 @@
+use crate::provider::Provider;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Classification {
+    Hit,
+    Miss,
+    Error,
+    RateLimited,
+    // Captcha or JS challenge; the run pauses until someone resumes it
+    Challenge,
+}
+
+impl Classification {
+    pub const ALL: [Classification; 5] = [
+        Classification::Hit,
+        Classification::Miss,
+        Classification::Error,
+        Classification::RateLimited,
+        Classification::Challenge,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Classification::Hit => "Hit",
+            Classification::Miss => "Miss",
+            Classification::Error => "Error",
+            Classification::RateLimited => "Rate Limited",
+            Classification::Challenge => "Challenge",
+        }
+    }
+
+    // From a label or its snake_case name ("rate_limited"), as scripts and
+    // other text-based callers write it
+    pub fn parse(name: &str) -> Option<Classification> {
+        let wanted = name.trim().replace('_', " ");
+        Classification::ALL.into_iter().find(|c| c.label().eq_ignore_ascii_case(&wanted))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MatchKind {
+    // Case-insensitive; several terms joined with "&&" must all be present
+    Substring,
+    Regex,
+}
+
+impl MatchKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchKind::Substring => "Substring",
+            MatchKind::Regex => "Regex",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClassifierRule {
+    pub name: String,
+    pub enabled: bool,
+    pub kind: MatchKind,
+    // Empty pattern matches any body, which is useful together with `status`
+    pub pattern: String,
+    // Only match responses with this HTTP status, if set
+    pub status: Option<u16>,
+    pub outcome: Classification,
+}
+
+pub fn default_rules() -> Vec<ClassifierRule> {
+    vec![
+        ClassifierRule {
+            name: "Captcha / JS challenge".to_string(),
+            enabled: true,
+            kind: MatchKind::Regex,
+            pattern: "(?i)(captcha|cf-chl|challenge-platform|verify you are (a )?human|please enable javascript)".to_string(),
+            status: None,
+            outcome: Classification::Challenge,
+        },
+        ClassifierRule {
+            name: "Block page".to_string(),
+            enabled: true,
+            kind: MatchKind::Regex,
+            pattern: "(?i)(access denied|too many requests|request (was )?blocked|temporarily blocked)".to_string(),
+            status: None,
+            outcome: Classification::RateLimited,
+        },
+        ClassifierRule {
+            name: "Service unavailable".to_string(),
+            enabled: true,
+            kind: MatchKind::Substring,
+            pattern: String::new(),
+            status: Some(503),
+            outcome: Classification::RateLimited,
+        },
+        ClassifierRule {
+            name: "Too many requests".to_string(),
+            enabled: true,
+            kind: MatchKind::Substring,
+            pattern: String::new(),
+            status: Some(429),
+            outcome: Classification::RateLimited,
+        },
+        no_record_rule(Provider::Islamabad),
+    ]
+}
+
+pub const NO_RECORD_RULE: &str = "No record page";
+
+pub fn no_record_rule(provider: Provider) -> ClassifierRule {
+    let (kind, pattern) = provider.no_record_match();
+    ClassifierRule {
+        name: NO_RECORD_RULE.to_string(),
+        enabled: true,
+        kind,
+        pattern: pattern.to_string(),
+        status: Some(200),
+        outcome: Classification::Miss,
+    }
+}
+
+enum Matcher {
+    Terms(Vec<String>),
+    Pattern(Regex),
+}
+
+// Decides a response before the rules do, or returns None to leave it to them
+pub type ClassifyHook = Arc<dyn Fn(u16, &str) -> Option<Classification> + Send + Sync>;
+
+// Name reported for outcomes the hook decided
+pub const HOOK_RULE: &str = "script";
+
+// Compiled rule set. Rules are tried in order and the first match wins; a
+// response no rule matches is a Hit when it is a 200 and an Error otherwise.
+pub struct Classifier {
+    rules: Vec<(ClassifierRule, Matcher)>,
+    hook: Option<ClassifyHook>,
+}
+
+impl Classifier {
+    pub fn new(rules: &[ClassifierRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+
+        for rule in rules.iter().filter(|r| r.enabled) {
+            let matcher = match rule.kind {
+                MatchKind::Substring => Matcher::Terms(
+                    rule.pattern
+                    .split("&&")
+                    .map(|t| t.trim().to_uppercase())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                ),
+                MatchKind::Regex => match Regex::new(&rule.pattern) {
+                    Ok(re) => Matcher::Pattern(re),
+                    Err(e) => return Err(format!("Rule '{}' has an invalid regex - {}", rule.name, e)),
+                },
+            };
+            compiled.push((rule.clone(), matcher));
+        }
+
+        Ok(Self { rules: compiled, hook: None })
+    }
+
+    // Consults `hook` (a script's, say) before the rules
+    pub fn with_hook(mut self, hook: Option<ClassifyHook>) -> Self {
+        self.hook = hook;
+        self
+    }
+
+    // Returns the outcome and the name of the rule that decided it
+    pub fn classify(&self, status: u16, body: &str) -> (Classification, String) {
+        if let Some(outcome) = self.hook.as_ref().and_then(|hook| hook(status, body)) {
+            return (outcome, HOOK_RULE.to_string());
+        }
+
+        let upper = body.to_uppercase();
+
+        for (rule, matcher) in &self.rules {
+            if rule.status.is_some_and(|s| s != status) {
+                continue;
+            }
+
+            let matched = match matcher {
+                Matcher::Terms(terms) => terms.iter().all(|t| upper.contains(t.as_str())),
+                Matcher::Pattern(re) => re.is_match(body),
+            };
+
+            if matched {
+                return (rule.outcome, rule.name.clone());
+            }
+        }
+
+        if status == 200 {
+            (Classification::Hit, "default".to_string())
+        } else {
+            (Classification::Error, "default".to_string())
+        }
+    }
+}
@@ -0,0 +1,611 @@
+use crate::classify::{Classification, Classifier};
+use crate::concurrency::{ConcurrencyLimiter, LimitChange};
+use crate::config::{self, AppConfig};
+use crate::coverage::CoverageMap;
+use crate::http::HttpTransport;
+use crate::i18n::tr;
+use crate::ordering::{self, SearchOrder};
+use crate::record::FieldRules;
+use crate::stats::NetworkStats;
+use crate::stop::{RunLimits, StopConditions, StopReason};
+use crate::throttle::{self, Throttle};
+use crate::transport::{Transport, TransportResult};
+use crate::{partition, record};
+use chrono::NaiveDate;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio_stream::Stream;
+
+// Idle connections kept per host
+const POOL_SIZE: usize = 4;
+
+// The pre-scan test lookup: a series that isn't issued, on a date before any
+// registration
+const HEALTH_CHECK_PLATE: &str = "ZZZ-0000";
+const HEALTH_CHECK_DATE: &str = "1900-01-01";
+
+// What one vehicle/date lookup came back with
+#[derive(Clone, Debug)]
+pub struct CheckOutcome {
+    pub vehicle_no: String,
+    pub date: NaiveDate,
+    pub status: u16,
+    pub classification: Classification,
+    // Name of the classifier rule that decided it
+    pub rule: String,
+    // Labelled fields from the record page; empty unless it's a hit
+    pub fields: Vec<(String, String)>,
+    pub body: String,
+}
+
+// What a running scan reports, in the order it happens. `thread` counts
+// from 1, in the order of the lanes `plan` returns.
+#[derive(Clone, Debug)]
+pub enum ScanEvent {
+    // After every date that got an answer, hit or miss
+    Progress { checked: usize, total: usize },
+    // Every request as it comes back, before it's classified: the status
+    // and body size, or why it failed
+    Response { thread: usize, date: NaiveDate, latency: Duration, result: Result<(u16, usize), String> },
+    // Already checked in an earlier session, see `ScanOptions::skip_checked`
+    Skipped { thread: usize, date: NaiveDate },
+    Miss { thread: usize, outcome: CheckOutcome },
+    // `hits` so far in this scan; `stopping` when this one reached the hit limit
+    Hit { thread: usize, outcome: CheckOutcome, hits: u32, stopping: bool },
+    // Every thread waits out `cooloff` before the date is tried again. Also
+    // sent for a hit or error page that turned out to be a generic page.
+    RateLimited { thread: usize, outcome: CheckOutcome, cooloff: Duration },
+    // The scan waits until `ScanControl::resume` or `stop` and the date is
+    // tried again on resume. `paused` is false when another thread had
+    // already paused it.
+    Challenge { thread: usize, outcome: CheckOutcome, paused: bool },
+    // `failures` failed requests in a row opened the circuit breaker; the
+    // scan waits like it does for a challenge
+    BreakerOpen { thread: usize, date: NaiveDate, failures: u32 },
+    // A failed date held back while the breaker is open, tried again on resume
+    Deferred { thread: usize, date: NaiveDate },
+    // A failed date tried again because the transport has a fallback
+    // endpoint left to switch to
+    Retrying { thread: usize, date: NaiveDate, reason: String },
+    // Repeated failures made the transport switch endpoints
+    FailedOver { thread: usize, from: String, to: String },
+    // Adaptive concurrency moved the number of requests in flight
+    ConcurrencyChanged { thread: usize, change: LimitChange },
+    // The server answered with an error page. Ends the scan; `stopping` is
+    // true for the error that stopped it.
+    Error { thread: usize, outcome: CheckOutcome, stopping: bool },
+    // No answer at all. The date is not counted as checked.
+    Failed { thread: usize, date: NaiveDate, error: String },
+    // A time or request limit ended the scan; sent once
+    Stopped { reason: StopReason },
+    // A thread has no dates left or was stopped, after `checked` answers
+    Finished { thread: usize, checked: usize },
+}
+
+#[derive(Clone)]
+pub struct ScanOptions {
+    pub threads: usize,
+    pub order: SearchOrder,
+    // Issuance period of the plate's series, checked first from its middle
+    // outwards; ignored for NewestFirstInterleaved
+    pub series_window: Option<(NaiveDate, NaiveDate)>,
+    pub stop: StopConditions,
+    // Each thread waits this long between its requests
+    pub delay: Duration,
+    // The first pause after a rate limit; it doubles while they keep coming
+    pub cooloff: Duration,
+    // Start at half of `threads` in flight and let the error rate and
+    // latency move it between one and `threads`
+    pub adaptive: bool,
+    // Failed requests in a row that pause the scan; 0 = never pause
+    pub breaker_threshold: u32,
+    // Hits and misses are added to it as they come in
+    pub coverage: Option<Arc<Mutex<CoverageMap>>>,
+    // Dates already in `coverage` are left out
+    pub skip_checked: bool,
+    // Request counts, latencies and errors; shared with whoever shows them
+    pub stats: Arc<NetworkStats>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            threads: 4,
+            order: SearchOrder::Forward,
+            series_window: None,
+            stop: StopConditions::default(),
+            delay: Duration::ZERO,
+            cooloff: throttle::BASE_COOLOFF,
+            adaptive: false,
+            breaker_threshold: 0,
+            coverage: None,
+            skip_checked: false,
+            stats: Arc::new(NetworkStats::default()),
+        }
+    }
+}
+
+// How `scan` deals the dates out, one list per thread in the order each
+// thread checks them
+pub fn plan(start: NaiveDate, end: NaiveDate, options: &ScanOptions) -> Vec<Vec<NaiveDate>> {
+    let threads = options.threads.clamp(1, config::MAX_THREADS);
+    match (options.order, options.series_window) {
+        (SearchOrder::NewestFirstInterleaved, _) => ordering::interleave_newest_first(start, end, threads),
+        (_, Some(window)) => ordering::prioritize_window(start, end, window, threads),
+        (order, None) => partition::split(start, end, threads)
+        .into_iter()
+        .map(|(from, to)| ordering::chunk_dates(from, to, order))
+        .collect(),
+    }
+}
+
+// The checking logic without the GUI: single lookups and whole-range scans
+// against the endpoint, classified with the same rules as the app.
+//
+//     let client = VehicleClient::from_config(&AppConfig::load()?)?;
+//     let outcome = client.check("ABC-123", date).await?;
+#[derive(Clone)]
+pub struct VehicleClient {
+    transport: Arc<dyn Transport>,
+    classifier: Arc<Classifier>,
+    fields: Arc<FieldRules>,
+}
+
+impl VehicleClient {
+    // Endpoint, TLS, headers, classifier and field extraction rules from the
+    // app's settings
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        let transport = HttpTransport::from_config(config, POOL_SIZE).map_err(|e| e.to_string())?;
+        let classifier = Classifier::new(&config.classifier_rules)?;
+        let fields = FieldRules::new(&config.extraction_rules)?;
+        Ok(Self::with_transport(Arc::new(transport), classifier).with_field_rules(Arc::new(fields)))
+    }
+
+    // E.g. a FixtureTransport, to run against saved pages. Hits get the
+    // generic fields only until with_field_rules adds the user's.
+    pub fn with_transport(transport: Arc<dyn Transport>, classifier: Classifier) -> Self {
+        Self { transport, classifier: Arc::new(classifier), fields: Arc::default() }
+    }
+
+    pub fn with_field_rules(mut self, fields: Arc<FieldRules>) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    // Runs the blocking request on tokio's blocking pool
+    pub async fn check(&self, vehicle_no: &str, date: NaiveDate) -> Result<CheckOutcome, String> {
+        let client = self.clone();
+        let vehicle_no = vehicle_no.to_string();
+        tokio::task::spawn_blocking(move || client.check_blocking(&vehicle_no, date))
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    pub fn check_blocking(&self, vehicle_no: &str, date: NaiveDate) -> Result<CheckOutcome, String> {
+        let date_str = date.format("%Y-%m-%d").to_string();
+        let (status, body) = self.transport.check(vehicle_no, &date_str).map_err(|e| e.to_string())?;
+        Ok(self.outcome(vehicle_no, date, status, body))
+    }
+
+    fn outcome(&self, vehicle_no: &str, date: NaiveDate, status: u16, body: String) -> CheckOutcome {
+        let (classification, rule) = self.classifier.classify(status, &body);
+        let fields = if classification == Classification::Hit { record::parse_fields(&self.fields, &body) } else { Vec::new() };
+        CheckOutcome { vehicle_no: vehicle_no.to_string(), date, status, classification, rule, fields, body }
+    }
+
+    // Fetches a page once before a scan so the server can hand out whatever
+    // session cookies it wants; they are then sent with every lookup
+    pub fn bootstrap(&self, url: &str) -> Result<u16, String> {
+        self.transport.bootstrap(url).map_err(|e| e.to_string())
+    }
+
+    // Asks for a plate that can't exist, so anything other than a miss means
+    // the endpoint is down, blocking us, or no longer answers the way the
+    // classifier rules expect
+    pub fn health_check(&self) -> Result<(), String> {
+        let (status, body) = self
+        .transport
+        .check(HEALTH_CHECK_PLATE, HEALTH_CHECK_DATE)
+        .map_err(|e| tr!("endpoint unreachable - {}", e))?;
+        match self.classifier.classify(status, &body) {
+            (Classification::Miss, _) => Ok(()),
+            (outcome, rule) => Err(tr!("test lookup returned {} (HTTP {}, rule: {}) instead of the no-record page",
+                                       tr!(outcome.label()), status, rule)),
+        }
+    }
+
+    // Checks every date from `start` to `end` on background threads. The
+    // handle is a stream of ScanEvents that ends when the scan does:
+    //
+    //     use tokio_stream::StreamExt;
+    //     let mut scan = client.scan("ABC-123", start, end, ScanOptions::default());
+    //     while let Some(event) = scan.next().await { ... }
+    //
+    // Events are buffered until read, so a caller that only wants the hits
+    // can ignore the stream and call `finish`.
+    pub fn scan(&self, vehicle_no: &str, start: NaiveDate, end: NaiveDate, options: ScanOptions) -> ScanHandle {
+        self.scan_lanes(vehicle_no, plan(start, end, &options), options)
+    }
+
+    // Like `scan`, for lanes from `plan` that the caller has already looked at
+    pub fn scan_lanes(&self, vehicle_no: &str, lanes: Vec<Vec<NaiveDate>>, options: ScanOptions) -> ScanHandle {
+        let threads = options.threads.clamp(1, config::MAX_THREADS);
+        let coverage = options.coverage.clone().filter(|_| options.skip_checked);
+        let total = match coverage.as_ref().and_then(|map| map.lock().ok()) {
+            Some(map) => lanes.iter().flatten().filter(|date| !map.contains(**date)).count(),
+            None => lanes.iter().map(Vec::len).sum(),
+        };
+
+        let state = Arc::new(ScanState {
+            running: AtomicBool::new(true),
+            checked: AtomicUsize::new(0),
+            total,
+            errors: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            hits: Mutex::new(Vec::new()),
+            limits: RunLimits::new(options.stop),
+            throttle: Throttle::with_pacing(options.delay).with_cooloff(options.cooloff).with_breaker(options.breaker_threshold),
+            limiter: ConcurrencyLimiter::new(threads, options.adaptive),
+            coverage: options.coverage,
+            skip_checked: options.skip_checked,
+            stats: options.stats,
+        });
+
+        // The stream ends once every worker has dropped its sender
+        let (sender, events) = mpsc::unbounded_channel();
+        let workers = lanes
+        .into_iter()
+        .enumerate()
+        .map(|(i, dates)| {
+            let client = self.clone();
+            let state = Arc::clone(&state);
+            let sender = sender.clone();
+            let vehicle_no = vehicle_no.to_string();
+            thread::spawn(move || client.scan_lane(&vehicle_no, i + 1, dates, &state, &sender))
+        })
+        .collect();
+
+        ScanHandle { control: ScanControl { state }, workers, events }
+    }
+
+    fn timed_check(&self, stats: &NetworkStats, vehicle_no: &str, date_str: &str) -> (TransportResult<(u16, String)>, Duration) {
+        stats.begin(self.transport.request_size(vehicle_no, date_str));
+        let started = Instant::now();
+        let result = self.transport.check(vehicle_no, date_str);
+        let latency = started.elapsed();
+        stats.finish(latency, result.as_ref().ok().map(|(_, body)| body.len() as u64));
+        (result, latency)
+    }
+
+    // Catches block/maintenance pages that the rules don't know about and that
+    // would otherwise look like a found record: the same body for two dates.
+    // Hits are confirmed against a neighbouring control date, which waits for
+    // its turn like any other request.
+    fn detect_generic_page(&self, vehicle_no: &str, date: NaiveDate, body: &str, state: &ScanState) -> Option<String> {
+        let hash = throttle::body_hash(body);
+        let date_str = date.format("%Y-%m-%d").to_string();
+        if state.throttle.seen_for_other_date(hash, &date_str) {
+            return Some(tr!("identical body already returned for another date"));
+        }
+
+        let control_date = date.pred_opt().or_else(|| date.succ_opt())?;
+        let control_str = control_date.format("%Y-%m-%d").to_string();
+        if !state.throttle.pace(&state.running) || !state.throttle.wait(&state.running) || !state.limiter.acquire(&state.running) {
+            return None;
+        }
+        let (result, latency) = self.timed_check(&state.stats, vehicle_no, &control_str);
+        state.limiter.release(latency, result.as_ref().map_or(true, |(status, _)| *status >= 500));
+        match result {
+            Ok((_, control_body)) if throttle::body_hash(&control_body) == hash => Some(tr!("identical body for control date {}", control_str)),
+            _ => None,
+        }
+    }
+
+    fn scan_lane(&self, vehicle_no: &str, thread: usize, dates: Vec<NaiveDate>, state: &ScanState, events: &UnboundedSender<ScanEvent>) {
+        // A closed stream only means nobody is listening
+        let emit = |event: ScanEvent| {
+            let _ = events.send(event);
+        };
+        let mut checked = 0;
+        let mut pending: VecDeque<NaiveDate> = dates.into();
+        while let Some(date) = pending.pop_front() {
+            if let Some(reason) = state.limits.check(state.stats.requests()) {
+                emit(ScanEvent::Stopped { reason });
+            }
+            if !state.running.load(Ordering::SeqCst) || state.limits.is_tripped() {
+                break;
+            }
+            if state.skip_checked && state.coverage.as_ref().is_some_and(|map| map.lock().is_ok_and(|map| map.contains(date))) {
+                emit(ScanEvent::Skipped { thread, date });
+                continue;
+            }
+            if !state.throttle.pace(&state.running) || !state.throttle.wait(&state.running) || !state.limiter.acquire(&state.running) {
+                break;
+            }
+
+            let date_str = date.format("%Y-%m-%d").to_string();
+            let (result, latency) = self.timed_check(&state.stats, vehicle_no, &date_str);
+            let is_error = result.as_ref().map_or(true, |(status, _)| *status >= 500);
+            if let Some(change) = state.limiter.release(latency, is_error) {
+                emit(ScanEvent::ConcurrencyChanged { thread, change });
+            }
+            let result = result.map_err(|e| e.to_string());
+            emit(ScanEvent::Response { thread, date, latency, result: result.as_ref().map(|(status, body)| (*status, body.len())).map_err(Clone::clone) });
+
+            // Classified before anything else, so a 503 rate-limit page is
+            // waited out below instead of being retried or failed over
+            let result = result.map(|(status, body)| {
+                let mut outcome = self.outcome(vehicle_no, date, status, body);
+                if matches!(outcome.classification, Classification::Hit | Classification::Error) {
+                    if let Some(reason) = self.detect_generic_page(vehicle_no, date, &outcome.body, state) {
+                        outcome.classification = Classification::RateLimited;
+                        outcome.rule = reason;
+                        outcome.fields.clear();
+                    }
+                }
+                outcome
+            });
+
+            // What the endpoint failover and the circuit breaker count: no
+            // answer at all, or a server error that isn't a rate limit or challenge
+            let failed = match &result {
+                Ok(outcome) => is_error && !matches!(outcome.classification, Classification::RateLimited | Classification::Challenge),
+                Err(_) => true,
+            };
+            if let Some((from, to)) = self.transport.report_outcome(failed) {
+                emit(ScanEvent::FailedOver { thread, from, to });
+            }
+            if !failed {
+                state.throttle.record_reachable();
+            } else if state.throttle.record_failure() {
+                emit(ScanEvent::BreakerOpen { thread, date, failures: state.throttle.breaker_threshold() });
+            }
+            if failed && state.throttle.is_breaker_open() {
+                emit(ScanEvent::Deferred { thread, date });
+                pending.push_front(date);
+                continue;
+            }
+            if failed && self.transport.can_fail_over() {
+                let reason = match &result {
+                    Ok(outcome) => format!("HTTP {}", outcome.status),
+                    Err(e) => e.clone(),
+                };
+                state.stats.record_error(&date_str, "Endpoint failure", reason.clone());
+                emit(ScanEvent::Retrying { thread, date, reason });
+                pending.push_front(date);
+                continue;
+            }
+
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    state.errors.fetch_add(1, Ordering::SeqCst);
+                    state.failed.fetch_add(1, Ordering::SeqCst);
+                    state.stats.record_error(&date_str, "Request failed", error.clone());
+                    emit(ScanEvent::Failed { thread, date, error });
+                    continue;
+                }
+            };
+            state.stats.record_outcome(outcome.classification);
+            if !matches!(outcome.classification, Classification::RateLimited | Classification::Challenge) {
+                state.throttle.record_success();
+            }
+            match outcome.classification {
+                // Neither counts as checked; the date goes back on the lane
+                Classification::RateLimited => {
+                    state.errors.fetch_add(1, Ordering::SeqCst);
+                    state.stats.record_error(&date_str, "Rate limited", format!("HTTP {} - {}", outcome.status, outcome.rule));
+                    let cooloff = state.throttle.trip();
+                    emit(ScanEvent::RateLimited { thread, outcome, cooloff });
+                    pending.push_front(date);
+                    continue;
+                }
+                Classification::Challenge => {
+                    state.errors.fetch_add(1, Ordering::SeqCst);
+                    state.stats.record_error(&date_str, "Challenge", format!("HTTP {} - {}", outcome.status, outcome.rule));
+                    let paused = state.throttle.pause_for_challenge();
+                    emit(ScanEvent::Challenge { thread, outcome, paused });
+                    pending.push_front(date);
+                    continue;
+                }
+                // An error page isn't an answer either; the scan stops on it
+                Classification::Error => {
+                    state.errors.fetch_add(1, Ordering::SeqCst);
+                    state.stats.record_error(&date_str, &format!("HTTP {}", outcome.status), outcome.rule.clone());
+                    let stopping = state.limits.trip(StopReason::ServerError(outcome.status));
+                    emit(ScanEvent::Error { thread, outcome, stopping });
+                    break;
+                }
+                Classification::Hit => {
+                    state.record_checked(date);
+                    if let Ok(mut hits) = state.hits.lock() {
+                        hits.push(outcome.clone());
+                    }
+                    let stopping = state.limits.record_hit();
+                    emit(ScanEvent::Hit { thread, outcome, hits: state.limits.hits(), stopping });
+                }
+                Classification::Miss => {
+                    state.record_checked(date);
+                    emit(ScanEvent::Miss { thread, outcome });
+                }
+            }
+            checked += 1;
+            let checked = state.checked.fetch_add(1, Ordering::SeqCst) + 1;
+            emit(ScanEvent::Progress { checked, total: state.total });
+        }
+        emit(ScanEvent::Finished { thread, checked });
+    }
+}
+
+struct ScanState {
+    running: AtomicBool,
+    checked: AtomicUsize,
+    total: usize,
+    errors: AtomicUsize,
+    // Dates that got no answer at all
+    failed: AtomicUsize,
+    hits: Mutex<Vec<CheckOutcome>>,
+    limits: RunLimits,
+    throttle: Throttle,
+    limiter: ConcurrencyLimiter,
+    coverage: Option<Arc<Mutex<CoverageMap>>>,
+    skip_checked: bool,
+    stats: Arc<NetworkStats>,
+}
+
+impl ScanState {
+    fn record_checked(&self, date: NaiveDate) {
+        if let Some(Ok(mut map)) = self.coverage.as_ref().map(|map| map.lock()) {
+            map.insert(date);
+        }
+    }
+}
+
+// Steering for a running scan from other threads, e.g. a GUI that shows the
+// pause state while another thread reads the events
+#[derive(Clone)]
+pub struct ScanControl {
+    state: Arc<ScanState>,
+}
+
+impl ScanControl {
+    // Workers finish the request they're on and then quit
+    pub fn stop(&self) {
+        self.state.running.store(false, Ordering::SeqCst);
+    }
+
+    // Lets a paused scan go on, once the challenge page has been dealt with
+    // or the server is back
+    pub fn resume(&self) {
+        self.state.throttle.resume();
+    }
+
+    pub fn is_challenge_paused(&self) -> bool {
+        self.state.throttle.is_challenge_paused()
+    }
+
+    pub fn is_breaker_open(&self) -> bool {
+        self.state.throttle.is_breaker_open()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_challenge_paused() || self.is_breaker_open()
+    }
+
+    // Requests allowed in flight right now
+    pub fn concurrency(&self) -> usize {
+        self.state.limiter.limit()
+    }
+
+    // (checked, total) dates
+    pub fn progress(&self) -> (usize, usize) {
+        (self.state.checked.load(Ordering::SeqCst), self.state.total)
+    }
+
+    pub fn errors(&self) -> usize {
+        self.state.errors.load(Ordering::SeqCst)
+    }
+
+    // Dates left unchecked because the request got no answer
+    pub fn failed(&self) -> usize {
+        self.state.failed.load(Ordering::SeqCst)
+    }
+
+    // Why the scan stopped early, if a stop condition ended it
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.state.limits.reason()
+    }
+
+    // Workers only look at the time limit between requests; this catches
+    // it while they're paused. Returns the reason when this call stopped the scan.
+    pub fn check_limits(&self) -> Option<StopReason> {
+        self.state.limits.check(self.state.stats.requests())
+    }
+}
+
+// A scan started by `VehicleClient::scan`. Dropping it leaves the scan
+// running; call `stop` first to end it.
+pub struct ScanHandle {
+    control: ScanControl,
+    workers: Vec<thread::JoinHandle<()>>,
+    events: UnboundedReceiver<ScanEvent>,
+}
+
+impl ScanHandle {
+    pub fn control(&self) -> ScanControl {
+        self.control.clone()
+    }
+
+    pub fn stop(&self) {
+        self.control.stop();
+    }
+
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.is_paused()
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        self.control.progress()
+    }
+
+    pub fn errors(&self) -> usize {
+        self.control.errors()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.control.failed()
+    }
+
+    pub fn hits(&self) -> Vec<CheckOutcome> {
+        self.control.state.hits.lock().map(|h| h.clone()).unwrap_or_default()
+    }
+
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.control.stop_reason()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.workers.iter().all(|worker| worker.is_finished())
+    }
+
+    // The next event for callers outside an async runtime; None once the
+    // scan has ended. Must not be called from inside one.
+    pub fn next_blocking(&mut self) -> Option<ScanEvent> {
+        self.events.blocking_recv()
+    }
+
+    // The next event if one is waiting, without blocking
+    pub fn try_next(&mut self) -> Option<ScanEvent> {
+        self.events.try_recv().ok()
+    }
+
+    // Waits for the scan to end and returns everything it found
+    pub async fn finish(self) -> Vec<CheckOutcome> {
+        let ScanHandle { control, workers, .. } = self;
+        let _ = tokio::task::spawn_blocking(move || {
+            for worker in workers {
+                let _ = worker.join();
+            }
+        })
+        .await;
+        control.state.hits.lock().map(|h| h.clone()).unwrap_or_default()
+    }
+}
+
+impl Stream for ScanHandle {
+    type Item = ScanEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ScanEvent>> {
+        self.events.poll_recv(cx)
+    }
+}
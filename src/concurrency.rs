@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+// Number of completed requests between limit adjustments
+const WINDOW: usize = 20;
+// Error rate above which the limit is halved
+const BACKOFF_ERROR_RATE: f32 = 0.10;
+// Average latency below which the limit is allowed to grow
+const FAST_LATENCY: Duration = Duration::from_millis(1500);
+
+struct LimiterState {
+    limit: usize,
+    in_flight: usize,
+    samples: usize,
+    errors: usize,
+    total_latency: Duration,
+}
+
+// Caps how many requests are in flight at once. With `adaptive` set the cap
+// grows by one after a fast, error-free window and halves when errors spike
+// (additive increase / multiplicative decrease), bounded by 1..=max.
+pub struct ConcurrencyLimiter {
+    state: Mutex<LimiterState>,
+    available: Condvar,
+    max: usize,
+    adaptive: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LimitChange {
+    Increased(usize),
+    Decreased(usize),
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(max: usize, adaptive: bool) -> Self {
+        // Adaptive runs start at half the maximum and find their own level
+        let initial = if adaptive { max.div_ceil(2).max(1) } else { max };
+        Self {
+            state: Mutex::new(LimiterState {
+                limit: initial,
+                in_flight: 0,
+                samples: 0,
+                errors: 0,
+                total_latency: Duration::ZERO,
+            }),
+            available: Condvar::new(),
+            max,
+            adaptive,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.state.lock().map(|s| s.limit).unwrap_or(self.max)
+    }
+
+    // Blocks until a slot is free. Returns false if the run was stopped while waiting.
+    pub fn acquire(&self, is_running: &AtomicBool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while state.in_flight >= state.limit {
+            if !is_running.load(Ordering::SeqCst) {
+                return false;
+            }
+            state = self.available.wait_timeout(state, Duration::from_millis(200)).unwrap().0;
+        }
+        state.in_flight += 1;
+        true
+    }
+
+    pub fn release(&self, latency: Duration, is_error: bool) -> Option<LimitChange> {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight = state.in_flight.saturating_sub(1);
+
+        let mut change = None;
+        if self.adaptive {
+            state.samples += 1;
+            state.total_latency += latency;
+            if is_error {
+                state.errors += 1;
+            }
+
+            if state.samples >= WINDOW {
+                let error_rate = state.errors as f32 / state.samples as f32;
+                let avg_latency = state.total_latency / state.samples as u32;
+
+                if error_rate > BACKOFF_ERROR_RATE && state.limit > 1 {
+                    state.limit = (state.limit / 2).max(1);
+                    change = Some(LimitChange::Decreased(state.limit));
+                } else if state.errors == 0 && avg_latency < FAST_LATENCY && state.limit < self.max {
+                    state.limit += 1;
+                    change = Some(LimitChange::Increased(state.limit));
+                }
+
+                state.samples = 0;
+                state.errors = 0;
+                state.total_latency = Duration::ZERO;
+            }
+        }
+
+        drop(state);
+        self.available.notify_all();
+        change
+    }
+}
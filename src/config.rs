@@ -0,0 +1,276 @@
+use crate::auth::ApiToken;
+use crate::classify::{self, ClassifierRule};
+use crate::form::RequestTemplate;
+use crate::i18n::Language;
+use crate::storage::{self, RetentionPolicy};
+use crate::logging::Verbosity;
+use crate::record::ExtractionRule;
+use crate::provider::Provider;
+use crate::search::SearchBy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const DEFAULT_RESULTS_DIR: &str = "vehicle_results";
+
+// The most worker threads a scan runs with, whoever asks for more
+pub const MAX_THREADS: usize = 20;
+
+// A file with this name next to the executable turns on portable mode
+const PORTABLE_MARKER: &str = "portable";
+
+pub const DEFAULT_ENDPOINT: &str = "http://58.65.189.226:8080/ovd/API_FOR_VEH_REG_DATA/VEHDATA.php";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub language: Language,
+    // Font with Urdu glyphs; empty tries a few common system fonts
+    pub ui_font_path: String,
+    // Relative paths are relative to the working directory
+    pub results_dir: String,
+    // Name of the case searches are filed under, in <results_dir>/cases/;
+    // empty for none
+    pub active_case: String,
+    // Seal new responses and results.db with a key from a passphrase asked
+    // for at startup. Only works in builds with the `encryption` feature.
+    pub encrypt_results: bool,
+    // Argon2 hash of the passphrase asked for at startup, empty for none
+    pub lock_passphrase: String,
+    // Mask personal fields in logs and everything sent out (record::set_redaction)
+    pub redact_personal: bool,
+    // Minutes without input before the app locks itself, 0 = never
+    pub auto_lock_mins: u64,
+    // Name of each saved response inside <results_dir>/<vehicle>/<run time>/;
+    // {vehicle}, {date}, {status} and {thread} are filled in
+    pub filename_template: String,
+    pub retention: RetentionPolicy,
+    // Placed between the series letters and the number of a normalized plate
+    pub plate_separator: String,
+    // Whose lookup form requests are built for; the endpoint and the no-record
+    // rule follow it when it is changed in Settings
+    pub provider: Provider,
+    // Whether the vehicle field holds a plate or a chassis number
+    pub search_by: SearchBy,
+    // Name of the loaded provider plugin lookups go through instead of the
+    // built-in provider, empty for none. Only used by builds with the
+    // `plugins` feature
+    pub provider_plugin: String,
+    pub endpoint_url: String,
+    // Tried in order once the current endpoint keeps failing
+    pub fallback_endpoints: Vec<String>,
+    pub request_template: RequestTemplate,
+    // Seconds, 0 = no limit. The server is often slow but reachable, so the
+    // total limit is generous by default.
+    pub connect_timeout_secs: u64,
+    pub request_timeout_secs: u64,
+    // Failed requests in a row that pause the run; 0 = never pause
+    pub breaker_threshold: u32,
+    // How hard a scan goes: worker threads, each waiting `request_delay_ms`
+    // between its requests. Adaptive concurrency makes `threads` the maximum.
+    // The app saves what its Scan tab was set to; the daemon's jobs use them
+    // unless a job says otherwise.
+    pub threads: usize,
+    pub request_delay_ms: u64,
+    pub adaptive_concurrency: bool,
+    // Debug aid: every request and response, in full, to results/transcripts/
+    pub transcript_enabled: bool,
+    // Hits, errors and run starts/stops as JSON lines in results/audit.jsonl
+    pub audit_log_enabled: bool,
+    // PEM file trusted in addition to the system roots, e.g. a self-signed server cert
+    pub ca_cert_path: String,
+    pub accept_invalid_certs: bool,
+    // When set, requests are answered from saved files here instead of the network
+    pub offline_fixture_dir: String,
+    pub classifier_rules: Vec<ClassifierRule>,
+    // Extra fields pulled out of hit pages; none leaves it to the generic parser
+    pub extraction_rules: Vec<ExtractionRule>,
+    pub cookies_enabled: bool,
+    // Page fetched once before each run to pick up session cookies; empty disables it
+    pub session_bootstrap_url: String,
+    // One request for a plate that can't exist before each run, which must
+    // come back as a miss or the run doesn't start
+    pub health_check_enabled: bool,
+    pub extra_headers: Vec<(String, String)>,
+    pub metrics_enabled: bool,
+    pub metrics_bind: String,
+    // Embedded HTTP/WebSocket server for remote monitoring
+    pub server_enabled: bool,
+    pub server_bind: String,
+    // Bearer tokens the server accepts; with none it is open to anyone who
+    // can reach it
+    pub api_tokens: Vec<ApiToken>,
+    // Only used by builds with the `tray` feature
+    pub minimize_to_tray: bool,
+    // Only used by builds with the `grpc` feature
+    pub grpc_enabled: bool,
+    pub grpc_bind: String,
+    // Hits and finished runs published to an MQTT broker
+    pub mqtt: MqttSettings,
+    // Shell command run for every hit, empty for none
+    pub found_command: String,
+    // Headless Chrome/Edge/Chromium used to save results as images, empty
+    // to look for one
+    pub browser_path: String,
+    // Rhai script with hook functions, empty for none. Only used by builds
+    // with the `scripting` feature
+    pub script_path: String,
+    // Opt-in: ask GitHub at startup whether a newer release is out
+    pub check_for_updates: bool,
+    pub verbosity: Verbosity,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            language: Language::English,
+            ui_font_path: String::new(),
+            results_dir: DEFAULT_RESULTS_DIR.to_string(),
+            active_case: String::new(),
+            encrypt_results: false,
+            lock_passphrase: String::new(),
+            redact_personal: false,
+            auto_lock_mins: 10,
+            filename_template: storage::DEFAULT_FILENAME_TEMPLATE.to_string(),
+            retention: RetentionPolicy::default(),
+            plate_separator: "-".to_string(),
+            provider: Provider::Islamabad,
+            search_by: SearchBy::Plate,
+            provider_plugin: String::new(),
+            endpoint_url: DEFAULT_ENDPOINT.to_string(),
+            fallback_endpoints: Vec::new(),
+            request_template: RequestTemplate::default(),
+            connect_timeout_secs: 10,
+            request_timeout_secs: 60,
+            breaker_threshold: 20,
+            threads: 6,
+            request_delay_ms: 0,
+            adaptive_concurrency: false,
+            transcript_enabled: false,
+            audit_log_enabled: false,
+            ca_cert_path: String::new(),
+            accept_invalid_certs: false,
+            offline_fixture_dir: String::new(),
+            classifier_rules: classify::default_rules(),
+            extraction_rules: Vec::new(),
+            cookies_enabled: true,
+            session_bootstrap_url: String::new(),
+            health_check_enabled: true,
+            extra_headers: Vec::new(),
+            metrics_enabled: false,
+            metrics_bind: "127.0.0.1:9898".to_string(),
+            server_enabled: false,
+            server_bind: "127.0.0.1:8787".to_string(),
+            api_tokens: Vec::new(),
+            minimize_to_tray: false,
+            grpc_enabled: false,
+            grpc_bind: "127.0.0.1:50051".to_string(),
+            mqtt: MqttSettings::default(),
+            found_command: String::new(),
+            browser_path: String::new(),
+            script_path: String::new(),
+            check_for_updates: false,
+            verbosity: Verbosity::Normal,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    // host or host:port, 1883 if no port is given
+    pub broker: String,
+    // Events go to <topic>/hit and <topic>/finished
+    pub topic: String,
+    // 0, 1 or 2
+    pub qos: u8,
+    pub client_id: String,
+    // Both empty for brokers without authentication
+    pub username: String,
+    pub password: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            broker: "localhost:1883".to_string(),
+            topic: "veh-reg".to_string(),
+            qos: 1,
+            client_id: "veh-reg".to_string(),
+            username: String::new(),
+            password: String::new(),
+        }
+    }
+}
+
+// The executable's folder when it holds the portable marker. In portable
+// mode the app runs from that folder, so settings, results, logs and window
+// state all stay on the stick next to the binary.
+pub fn portable_dir() -> Option<&'static Path> {
+    static DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let exe = std::env::current_exe().ok()?;
+        let dir = exe.parent()?;
+        dir.join(PORTABLE_MARKER).is_file().then(|| dir.to_path_buf())
+    })
+    .as_deref()
+}
+
+// Paths inside the portable folder are stored relative to it, so they still
+// work when the stick gets another drive letter
+pub fn portable_relative(path: &Path) -> String {
+    match portable_dir().and_then(|dir| path.strip_prefix(dir).ok()) {
+        Some(relative) if !relative.as_os_str().is_empty() => relative.to_string_lossy().to_string(),
+        _ => path.to_string_lossy().to_string(),
+    }
+}
+
+impl AppConfig {
+    pub fn path() -> PathBuf {
+        PathBuf::from("config.json")
+    }
+
+    // A missing file is not an error, it just means defaults
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    // Points the endpoint and the "No record page" rule at another
+    // provider; the rule is added back if it was deleted. Falls back to plate
+    // search when the new provider can't search by the current kind of number.
+    pub fn switch_provider(&mut self, provider: Provider) {
+        self.provider = provider;
+        self.provider_plugin.clear();
+        if !provider.supports(self.search_by) {
+            self.search_by = SearchBy::Plate;
+        }
+        self.endpoint_url = provider.endpoint().to_string();
+        let rule = classify::no_record_rule(provider);
+        match self.classifier_rules.iter_mut().find(|r| r.name == classify::NO_RECORD_RULE) {
+            Some(existing) => *existing = rule,
+            None => self.classifier_rules.push(rule),
+        }
+    }
+
+    // The plugin lookups go through, or the built-in provider's label
+    pub fn provider_name(&self) -> &str {
+        if self.provider_plugin.is_empty() { self.provider.label() } else { &self.provider_plugin }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(Self::path(), content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{HitRecord, LogEntry, LogLevel};
+use crate::storage;
+use crate::vault;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub struct ScanRequest {
+    pub vehicle_no: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub threads: Option<usize>,
+}
+
+// Commands from remote APIs, applied by the GUI on its next frame so remote
+// and local starts go through exactly the same validation.
+pub enum ControlCommand {
+    Start(ScanRequest),
+    Stop,
+}
+
+// The run the GUI last started, shown on the dashboard
+#[derive(Clone)]
+pub struct JobInfo {
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub threads: usize,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+// Something that happened in the engine. The GUI, the dashboard's live
+// socket and the gRPC stream all get the same events, in the same order.
+#[derive(Clone)]
+pub enum EngineEvent {
+    Log(LogEntry),
+    Hit(HitRecord),
+    // At most once a second while a run is going
+    Progress { checked: usize, total: usize },
+    RunStarted(JobInfo),
+    RunFinished(JobInfo),
+}
+
+// One sender per subscriber; ones whose receiver is gone are dropped on the
+// next publish
+static SUBSCRIBERS: Mutex<Vec<Sender<EngineEvent>>> = Mutex::new(Vec::new());
+
+pub fn subscribe() -> Receiver<EngineEvent> {
+    let (sender, receiver) = mpsc::channel();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(sender);
+    }
+    receiver
+}
+
+// Calls `callback` for every event on its own thread, for as long as the
+// process runs
+pub fn on_event(callback: impl Fn(&EngineEvent) + Send + 'static) {
+    let events = subscribe();
+    thread::spawn(move || {
+        for event in events {
+            callback(&event);
+        }
+    });
+}
+
+pub fn publish(event: EngineEvent) {
+    let Ok(mut subscribers) = SUBSCRIBERS.lock() else { return };
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
+
+pub struct ResultFile {
+    pub name: String,
+    pub size: u64,
+    pub modified: String,
+}
+
+// What the remote APIs can see and do. Cheap to clone, one per server.
+#[derive(Clone)]
+pub struct ControlHandle {
+    commands: Sender<ControlCommand>,
+    logs: Arc<Mutex<Vec<LogEntry>>>,
+    hits: Arc<Mutex<Vec<HitRecord>>>,
+    is_running: Arc<AtomicBool>,
+    checked_dates: Arc<Mutex<usize>>,
+    total_dates: Arc<Mutex<usize>>,
+    job: Arc<Mutex<Option<JobInfo>>>,
+    results_dir: Arc<Mutex<PathBuf>>,
+}
+
+impl ControlHandle {
+    pub fn new(
+        commands: Sender<ControlCommand>,
+        logs: Arc<Mutex<Vec<LogEntry>>>,
+        hits: Arc<Mutex<Vec<HitRecord>>>,
+        is_running: Arc<AtomicBool>,
+        checked_dates: Arc<Mutex<usize>>,
+        total_dates: Arc<Mutex<usize>>,
+        results_dir: PathBuf,
+    ) -> Self {
+        Self {
+            commands,
+            logs,
+            hits,
+            is_running,
+            checked_dates,
+            total_dates,
+            job: Arc::new(Mutex::new(None)),
+            results_dir: Arc::new(Mutex::new(results_dir)),
+        }
+    }
+
+    // The directory can be changed from the settings while servers are running
+    pub fn set_results_dir(&self, dir: PathBuf) {
+        if let Ok(mut current) = self.results_dir.lock() {
+            *current = dir;
+        }
+    }
+
+    pub fn results_dir(&self) -> PathBuf {
+        self.results_dir.lock().map(|d| d.clone()).unwrap_or_default()
+    }
+
+    pub fn set_job(&self, job: JobInfo) {
+        publish(EngineEvent::RunStarted(job.clone()));
+        if let Ok(mut current) = self.job.lock() {
+            *current = Some(job);
+        }
+    }
+
+    // Stamps the current job as finished, called when its last worker exits
+    pub fn finish_job(&self) {
+        if let Ok(mut current) = self.job.lock() {
+            if let Some(job) = current.as_mut() {
+                job.finished_at = Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+                publish(EngineEvent::RunFinished(job.clone()));
+            }
+        }
+    }
+
+    pub fn job(&self) -> Option<JobInfo> {
+        self.job.lock().ok().and_then(|j| j.clone())
+    }
+
+    // (checked, total) dates of the current or last run
+    pub fn progress(&self) -> (usize, usize) {
+        let checked = self.checked_dates.lock().map(|c| *c).unwrap_or(0);
+        let total = self.total_dates.lock().map(|t| *t).unwrap_or(0);
+        (checked, total)
+    }
+
+    pub fn publish_progress(&self) {
+        let (checked, total) = self.progress();
+        publish(EngineEvent::Progress { checked, total });
+    }
+
+    // Hits recorded this session, skipping the first `already_seen`
+    pub fn hits_since(&self, already_seen: usize) -> Vec<HitRecord> {
+        match self.hits.lock() {
+            Ok(hits) => hits.iter().skip(already_seen).cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::SeqCst)
+    }
+
+    // Returns false if a scan is already running or the GUI has gone away
+    pub fn start(&self, request: ScanRequest) -> bool {
+        !self.is_running() && self.commands.send(ControlCommand::Start(request)).is_ok()
+    }
+
+    pub fn stop(&self) -> bool {
+        self.commands.send(ControlCommand::Stop).is_ok()
+    }
+
+    // Log entries newer than `after_seq`, oldest first
+    pub fn events_since(&self, after_seq: u64) -> Vec<LogEntry> {
+        match self.logs.lock() {
+            Ok(logs) => logs.iter().filter(|e| e.seq > after_seq).cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // Saved responses and their pictures from every run, named by their
+    // path relative to the results directory
+    pub fn list_results(&self) -> Vec<ResultFile> {
+        let root = self.results_dir();
+        let mut files: Vec<ResultFile> = storage::walk_results(&root)
+        .into_iter()
+        .filter(|name| storage::is_saved_response(name))
+        .filter_map(|name| {
+            let meta = fs::metadata(root.join(&name)).ok()?;
+            let modified = meta
+            .modified()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+            Some(ResultFile {
+                name,
+                size: meta.len(),
+                modified,
+            })
+        })
+        .collect();
+        files.sort_by(|a, b| b.modified.cmp(&a.modified));
+        files
+    }
+
+    // Contents of a saved response or its picture. Anything else in the
+    // results directory (databases, logs, summaries, the vault) is refused,
+    // and so are paths that could climb out of it.
+    pub fn read_result(&self, name: &str) -> Option<Vec<u8>> {
+        if !storage::is_saved_response(name) {
+            return None;
+        }
+        vault::read(&self.results_dir().join(name)).ok()
+    }
+}
+
+pub fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Info => "info",
+        LogLevel::Success => "success",
+        LogLevel::Error => "error",
+        LogLevel::Warning => "warning",
+        LogLevel::Debug => "debug",
+        LogLevel::Trace => "trace",
+    }
+}
@@ -0,0 +1,200 @@
+use crate::search::SearchBy;
+use crate::storage;
+use chrono::NaiveDate;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// On-disk format: magic, version byte, then the bitmap words (little-endian),
+// sealed like the responses while the results are encrypted. Bit N is set
+// when the date EPOCH + N days has been checked for the vehicle.
+const MAGIC: &[u8; 4] = b"VCOV";
+const VERSION: u8 = 1;
+
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+}
+
+#[derive(Clone, Default)]
+pub struct CoverageMap {
+    words: Vec<u64>,
+}
+
+impl CoverageMap {
+    fn index(date: NaiveDate) -> Option<usize> {
+        let days = (date - epoch()).num_days();
+        if days < 0 { None } else { Some(days as usize) }
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        match Self::index(date) {
+            Some(i) => self.words.get(i / 64).is_some_and(|w| w & (1 << (i % 64)) != 0),
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, date: NaiveDate) {
+        if let Some(i) = Self::index(date) {
+            if self.words.len() <= i / 64 {
+                self.words.resize(i / 64 + 1, 0);
+            }
+            self.words[i / 64] |= 1 << (i % 64);
+        }
+    }
+
+    pub fn merge(&mut self, other: &CoverageMap) {
+        if self.words.len() < other.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a |= *b;
+        }
+    }
+
+    pub fn count_in(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        start.iter_days()
+        .take_while(|d| *d <= end)
+        .filter(|d| self.contains(*d))
+        .count()
+    }
+
+    // Runs of unchecked dates between `start` and `end`, inclusive
+    pub fn gaps(&self, start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut gaps: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+        for date in start.iter_days().take_while(|d| *d <= end) {
+            if self.contains(date) {
+                continue;
+            }
+            match gaps.last_mut() {
+                Some((_, last)) if last.succ_opt() == Some(date) => *last = date,
+                _ => gaps.push((date, date)),
+            }
+        }
+        gaps
+    }
+
+    // Kept per provider and search mode: a date that missed on one
+    // province's site, or when searching by chassis, says nothing about
+    // another. `provider` is empty for runs that searched every province.
+    pub fn path_for(results_dir: &Path, provider: &str, search_by: SearchBy, vehicle_no: &str) -> PathBuf {
+        let provider = if provider.is_empty() { "everywhere".to_string() } else { storage::sanitize(provider) };
+        let search_by = match search_by {
+            SearchBy::Plate => "plate",
+            SearchBy::Chassis => "chassis",
+            SearchBy::Engine => "engine",
+        };
+        results_dir.join("coverage").join(provider).join(search_by).join(format!("{}.bitmap", storage::sanitize(vehicle_no)))
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(b) => storage::unseal(&b)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        if bytes.len() < 5 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a coverage bitmap"));
+        }
+
+        let words = bytes[5..]
+        .chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+
+        Ok(Self { words })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut bytes = Vec::with_capacity(5 + self.words.len() * 8);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        for w in &self.words {
+            bytes.extend_from_slice(&w.to_le_bytes());
+        }
+
+        // Write to a temp file first so a crash never leaves a truncated bitmap
+        let tmp = path.with_extension("bitmap.tmp");
+        fs::write(&tmp, storage::seal(&bytes)?)?;
+        fs::rename(&tmp, path)
+    }
+
+    // Merge whatever is on disk into this map before saving, so two sessions
+    // writing the same vehicle never drop each other's coverage.
+    pub fn merge_and_save(&mut self, path: &Path) -> io::Result<()> {
+        if let Ok(existing) = Self::load(path) {
+            self.merge(&existing);
+        }
+        self.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn indexes_days_from_the_1970_epoch() {
+        let mut map = CoverageMap::default();
+        map.insert(date(1970, 1, 1));
+        map.insert(date(1970, 3, 6));
+        map.insert(date(1969, 12, 31));
+        assert_eq!(map.words.len(), 2);
+        assert_eq!(map.words[0], 1);
+        assert_eq!(map.words[1], 1, "day 64 starts the second word");
+        assert!(map.contains(date(1970, 1, 1)) && map.contains(date(1970, 3, 6)));
+        assert!(!map.contains(date(1970, 1, 2)));
+        // Nothing before the epoch can be stored, so it never counts as checked
+        assert!(!map.contains(date(1969, 12, 31)));
+    }
+
+    #[test]
+    fn merges_overlapping_ranges() {
+        let mut first = CoverageMap::default();
+        let mut second = CoverageMap::default();
+        for day in date(2015, 1, 1).iter_days().take(10) {
+            first.insert(day);
+        }
+        for day in date(2015, 1, 6).iter_days().take(10) {
+            second.insert(day);
+        }
+        second.insert(date(2020, 1, 1));
+        first.merge(&second);
+        assert_eq!(first.count_in(date(2015, 1, 1), date(2015, 1, 31)), 15);
+        assert!(first.contains(date(2020, 1, 1)));
+        assert_eq!(first.gaps(date(2014, 12, 30), date(2015, 1, 17)),
+                   vec![(date(2014, 12, 30), date(2014, 12, 31)), (date(2015, 1, 16), date(2015, 1, 17))]);
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CoverageMap::path_for(dir.path(), "Punjab", SearchBy::Plate, "ABC-123");
+        assert!(CoverageMap::load(&path).unwrap().words.is_empty());
+
+        let mut map = CoverageMap::default();
+        map.insert(date(1970, 1, 1));
+        map.insert(date(2015, 6, 1));
+        map.save(&path).unwrap();
+        let loaded = CoverageMap::load(&path).unwrap();
+        assert_eq!(loaded.words, map.words);
+
+        // Another session's dates are kept when this one saves
+        let mut other = CoverageMap::default();
+        other.insert(date(2016, 2, 29));
+        other.merge_and_save(&path).unwrap();
+        let loaded = CoverageMap::load(&path).unwrap();
+        assert!(loaded.contains(date(2015, 6, 1)) && loaded.contains(date(2016, 2, 29)));
+
+        fs::write(&path, b"junk").unwrap();
+        assert!(CoverageMap::load(&path).is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use crate::form::{BOUNDARY, RequestTemplate};
+use crate::plate;
+use crate::provider::{Encoding, Provider};
+use crate::search::{self, SearchBy};
+
+// A request copied from the browser's developer tools with "Copy as cURL",
+// turned into settings: where it goes, the headers worth keeping and the
+// form as a request template.
+pub struct CurlImport {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub template: RequestTemplate,
+    // Fields whose value was swapped for a placeholder, e.g. ("registrationNo", "{vehicle}")
+    pub placeholders: Vec<(String, String)>,
+}
+
+// Headers the app sets itself, or that would break the request when copied:
+// reqwest here doesn't decompress, and the body length changes per lookup
+const DROPPED_HEADERS: [&str; 3] = ["content-type", "content-length", "accept-encoding"];
+
+// What a browser says about itself. The app sends its own User-Agent and
+// doesn't pass for the browser the command was copied from.
+const BROWSER_HEADERS: [&str; 5] = ["user-agent", "sec-gpc", "dnt", "priority", "upgrade-insecure-requests"];
+const BROWSER_HEADER_PREFIXES: [&str; 2] = ["sec-ch-", "sec-fetch-"];
+
+// Also applied to the extra headers typed into the settings
+pub fn is_browser_header(name: &str) -> bool {
+    let name = name.trim().to_ascii_lowercase();
+    BROWSER_HEADERS.contains(&name.as_str()) || BROWSER_HEADER_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+// Parses the bash flavour of "Copy as cURL" (Chrome, Firefox, Edge), with
+// its line continuations and $'...' strings. The value that looks like a
+// date becomes {date} or its parts, and the one that looks like a plate,
+// chassis or engine number (or equals `vehicle_hint`) becomes {vehicle}.
+pub fn parse(command: &str, vehicle_hint: &str) -> Result<CurlImport, String> {
+    let words = split_words(command)?;
+    let mut words = words.into_iter();
+    match words.next() {
+        Some(first) if first == "curl" || first.ends_with("/curl") || first.ends_with("curl.exe") => {}
+        _ => return Err("this is not a curl command".to_string()),
+    }
+
+    let mut url = None;
+    let mut headers = Vec::new();
+    let mut data: Vec<String> = Vec::new();
+    let mut form: Vec<(String, String)> = Vec::new();
+    while let Some(word) = words.next() {
+        // --flag=value as well as --flag value
+        let (flag, inline) = match word.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (word.clone(), None),
+        };
+        let mut value = || inline.clone().or_else(|| words.next()).ok_or_else(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "-H" | "--header" => {
+                let header = value()?;
+                if let Some((name, value)) = header.split_once(':') {
+                    headers.push((name.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" | "--data-urlencode" => data.push(value()?),
+            "-F" | "--form" | "--form-string" => {
+                let field = value()?;
+                let (name, value) = field.split_once('=').unwrap_or((field.as_str(), ""));
+                form.push((name.to_string(), value.to_string()));
+            }
+            "-b" | "--cookie" => headers.push(("Cookie".to_string(), value()?)),
+            "--url" => url = Some(value()?),
+            "-X" | "--request" | "-A" | "--user-agent" | "-e" | "--referer" | "-u" | "--user" | "-o" | "--output" | "-m" | "--max-time" | "--connect-timeout" => {
+                value()?;
+            }
+            flag if flag.starts_with('-') => {}
+            _ => url = url.or(Some(word)),
+        }
+    }
+    let url = url.ok_or_else(|| "the command has no URL".to_string())?;
+
+    let content_type = headers
+    .iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    .map(|(_, value)| value.clone())
+    .unwrap_or_default();
+    headers.retain(|(name, _)| !DROPPED_HEADERS.iter().any(|dropped| name.eq_ignore_ascii_case(dropped)) && !is_browser_header(name));
+
+    let body = data.join("&");
+    let (encoding, boundary, fields) = if !form.is_empty() {
+        (Encoding::Multipart, BOUNDARY.to_string(), form)
+    } else if content_type.to_lowercase().starts_with("multipart/form-data") {
+        let boundary = content_type
+        .split(';')
+        .find_map(|part| part.trim().strip_prefix("boundary="))
+        .map(|b| b.trim_matches('"').to_string())
+        .ok_or_else(|| "the multipart body has no boundary in its Content-Type".to_string())?;
+        let fields = multipart_fields(&body, &boundary);
+        (Encoding::Multipart, boundary, fields)
+    } else if content_type.to_lowercase().contains("json") || body.trim_start().starts_with('{') {
+        let json: serde_json::Value = serde_json::from_str(&body).map_err(|e| format!("the JSON body doesn't parse - {}", e))?;
+        let fields = json
+        .as_object()
+        .ok_or_else(|| "the JSON body is not an object".to_string())?
+        .iter()
+        .map(|(name, value)| (name.clone(), value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string())))
+        .collect();
+        (Encoding::Json, BOUNDARY.to_string(), fields)
+    } else {
+        let fields = body
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (url_decode(name), url_decode(value))
+        })
+        .collect();
+        (Encoding::UrlEncoded, BOUNDARY.to_string(), fields)
+    };
+    if fields.is_empty() {
+        return Err("the command sends no form fields".to_string());
+    }
+
+    let (fields, placeholders) = with_placeholders(fields, vehicle_hint);
+    Ok(CurlImport {
+        url,
+        headers,
+        template: RequestTemplate { enabled: true, encoding, boundary, fields },
+        placeholders,
+    })
+}
+
+// The fields to send, and which of them got a placeholder
+type PlaceholderSplit = (Vec<(String, String)>, Vec<(String, String)>);
+
+fn with_placeholders(fields: Vec<(String, String)>, vehicle_hint: &str) -> PlaceholderSplit {
+    let hint = plate::normalize(vehicle_hint, "");
+    let mut placeholders = Vec::new();
+    let mut have_date = false;
+    let mut have_vehicle = false;
+    let fields = fields
+    .into_iter()
+    .map(|(name, value)| {
+        let trimmed = value.trim();
+        let date = (!have_date).then(|| date_placeholder(trimmed)).flatten();
+        let looks_like_number = (!hint.is_empty() && plate::normalize(trimmed, "") == hint)
+        || Provider::ALL.iter().any(|p| plate::validate(trimmed, plate::VehicleType::Any, *p).is_ok())
+        || search::validate(SearchBy::Chassis, trimmed).is_ok()
+        || search::validate(SearchBy::Engine, trimmed).is_ok();
+        let placeholder = if let Some(date) = date {
+            have_date = true;
+            Some(date.to_string())
+        } else if !have_vehicle && looks_like_number {
+            have_vehicle = true;
+            Some("{vehicle}".to_string())
+        } else {
+            None
+        };
+        match placeholder {
+            Some(placeholder) => {
+                placeholders.push((name.clone(), placeholder.clone()));
+                (name, placeholder)
+            }
+            None => (name, value),
+        }
+    })
+    .collect();
+    (fields, placeholders)
+}
+
+// The placeholder for a date written the way the form wants it
+fn date_placeholder(value: &str) -> Option<&'static str> {
+    let digits = |s: &str, n: usize| s.len() == n && s.chars().all(|c| c.is_ascii_digit());
+    for separator in ['-', '/', '.'] {
+        let parts: Vec<&str> = value.split(separator).collect();
+        if let [a, b, c] = parts[..] {
+            if digits(a, 4) && digits(b, 2) && digits(c, 2) {
+                return Some(match separator {
+                    '-' => "{date}",
+                    '/' => "{year}/{month}/{day}",
+                    _ => "{year}.{month}.{day}",
+                });
+            }
+            if digits(a, 2) && digits(b, 2) && digits(c, 4) {
+                return Some(match separator {
+                    '-' => "{day}-{month}-{year}",
+                    '/' => "{day}/{month}/{year}",
+                    _ => "{day}.{month}.{year}",
+                });
+            }
+        }
+    }
+    None
+}
+
+// name="..." and value of each part of a multipart body
+fn multipart_fields(body: &str, boundary: &str) -> Vec<(String, String)> {
+    body.split(&format!("--{}", boundary))
+    .filter_map(|part| {
+        let (head, value) = part.split_once("\r\n\r\n").or_else(|| part.split_once("\n\n"))?;
+        let name = head.split("name=").nth(1)?;
+        let name = name.trim_start_matches('"').split(['"', ';', '\r', '\n']).next()?;
+        Some((name.to_string(), value.trim_end_matches(['\r', '\n']).to_string()))
+    })
+    .collect()
+}
+
+fn url_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// Shell words: '...' literally, "..." with backslash escapes, $'...' with
+// C escapes like \r\n, and backslash-newline joining lines
+fn split_words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    word.push(escaped);
+                    in_word = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated ' quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated \" quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated \" quote".to_string()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('r') => word.push('\r'),
+                            Some('t') => word.push('\t'),
+                            Some(c) => word.push(c),
+                            None => return Err("unterminated $' quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated $' quote".to_string()),
+                    }
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chrome's "Copy as cURL (bash)", trimmed to what matters here
+    const CHROME: &str = r#"curl 'https://example.gov.pk/lookup.php' \
+  -H 'Accept: text/html,application/xhtml+xml' \
+  -H 'Accept-Language: en-US,en;q=0.9' \
+  -H 'Content-Type: application/x-www-form-urlencoded' \
+  -b 'PHPSESSID=abc123' \
+  -H 'Origin: https://example.gov.pk' \
+  -H 'Referer: https://example.gov.pk/' \
+  -H 'Sec-Fetch-Dest: document' \
+  -H 'Sec-Fetch-Mode: navigate' \
+  -H 'Upgrade-Insecure-Requests: 1' \
+  -H 'User-Agent: Mozilla/5.0 (Windows NT 10.0; Win64; x64) Chrome/126.0.0.0 Safari/537.36' \
+  -H 'sec-ch-ua: "Chromium";v="126", "Not.A/Brand";v="24"' \
+  -H 'sec-ch-ua-mobile: ?0' \
+  -H 'sec-ch-ua-platform: "Windows"' \
+  --data-raw 'regNo=ABC-123&regDate=2020-01-05&submit=Search'"#;
+
+    fn header_names(import: &CurlImport) -> Vec<&str> {
+        import.headers.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn browser_and_managed_headers_are_dropped() {
+        let import = parse(CHROME, "ABC-123").unwrap();
+        assert_eq!(import.url, "https://example.gov.pk/lookup.php");
+        assert_eq!(header_names(&import), vec!["Accept", "Accept-Language", "Cookie", "Origin", "Referer"]);
+        assert_eq!(import.headers[2].1, "PHPSESSID=abc123");
+    }
+
+    #[test]
+    fn browser_headers_are_recognised_in_any_case() {
+        for name in ["User-Agent", "sec-ch-ua", "Sec-CH-UA-Platform", "sec-fetch-site", "DNT", "Sec-GPC", "priority", " upgrade-insecure-requests "] {
+            assert!(is_browser_header(name), "{}", name);
+        }
+        for name in ["Accept", "Cookie", "Referer", "Origin", "X-Requested-With", "secret"] {
+            assert!(!is_browser_header(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn url_encoded_data_gets_placeholders() {
+        let import = parse(CHROME, "ABC-123").unwrap();
+        assert_eq!(import.template.encoding, Encoding::UrlEncoded);
+        assert_eq!(import.template.fields, vec![
+            ("regNo".to_string(), "{vehicle}".to_string()),
+            ("regDate".to_string(), "{date}".to_string()),
+            ("submit".to_string(), "Search".to_string()),
+        ]);
+        assert_eq!(import.placeholders.len(), 2);
+    }
+
+    #[test]
+    fn data_flags_are_joined_and_decoded() {
+        let command = "curl https://example.gov.pk/q -d 'a=1' --data=b=two%20words --data-urlencode 'c=x+y' --data-binary 'day=05/01/2020'";
+        let import = parse(command, "").unwrap();
+        assert_eq!(import.template.fields, vec![
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "two words".to_string()),
+            ("c".to_string(), "x y".to_string()),
+            ("day".to_string(), "{day}/{month}/{year}".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn double_quotes_and_ansi_c_strings_are_unquoted() {
+        let command = "curl \"https://example.gov.pk/q\" -H \"X-Note: say \\\"hi\\\" \\$HOME\" --data-raw $'a=1\\tb'";
+        let import = parse(command, "").unwrap();
+        assert_eq!(import.headers, vec![("X-Note".to_string(), "say \"hi\" $HOME".to_string())]);
+        assert_eq!(import.template.fields, vec![("a".to_string(), "1\tb".to_string())]);
+    }
+
+    #[test]
+    fn multipart_body_is_split_on_its_boundary() {
+        let command = "curl 'https://example.gov.pk/q' -H 'content-type: multipart/form-data; boundary=----XYZ' \
+            --data-raw $'------XYZ\\r\\nContent-Disposition: form-data; name=\"regNo\"\\r\\n\\r\\nABC-123\\r\\n------XYZ\\r\\nContent-Disposition: form-data; name=\"regDate\"\\r\\n\\r\\n2020-01-05\\r\\n------XYZ--\\r\\n'";
+        let import = parse(command, "ABC-123").unwrap();
+        assert_eq!(import.template.encoding, Encoding::Multipart);
+        assert_eq!(import.template.boundary, "----XYZ");
+        assert_eq!(import.template.fields, vec![
+            ("regNo".to_string(), "{vehicle}".to_string()),
+            ("regDate".to_string(), "{date}".to_string()),
+        ]);
+        assert!(import.headers.is_empty());
+    }
+
+    #[test]
+    fn json_bodies_and_form_flags() {
+        let json = parse(r#"curl https://example.gov.pk/q -H 'Content-Type: application/json' --data-raw '{"regNo":"ABC-123","page":2}'"#, "ABC-123").unwrap();
+        assert_eq!(json.template.encoding, Encoding::Json);
+        assert_eq!(json.template.fields, vec![("page".to_string(), "2".to_string()), ("regNo".to_string(), "{vehicle}".to_string())]);
+
+        let form = parse("curl https://example.gov.pk/q -F regNo=ABC-123 -F 'go=1'", "ABC-123").unwrap();
+        assert_eq!(form.template.encoding, Encoding::Multipart);
+        assert_eq!(form.template.fields[0], ("regNo".to_string(), "{vehicle}".to_string()));
+    }
+
+    #[test]
+    fn broken_commands_are_refused() {
+        assert!(parse("wget https://example.gov.pk/q", "").is_err());
+        assert!(parse("curl -d 'a=1'", "").is_err());
+        assert!(parse("curl https://example.gov.pk/q", "").is_err());
+        assert!(parse("curl 'https://example.gov.pk/q -d a=1", "").is_err());
+        assert!(parse("curl https://example.gov.pk/q -H", "").is_err());
+    }
+}
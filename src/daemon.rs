@@ -0,0 +1,861 @@
+use crate::audit::{self, Event};
+use crate::auth::{self, ApiToken, AuthError, TokenScope};
+use crate::case;
+use crate::classify::Classifier;
+use crate::config::{self, AppConfig};
+use crate::http::HttpTransport;
+use crate::i18n::{self, tr};
+use crate::jobstore::{JobStore, NewJob, StoredJob};
+use crate::coverage::CoverageMap;
+use crate::ordering::SearchOrder;
+use crate::record;
+use crate::run;
+use crate::schedule::Schedule;
+use crate::server;
+use crate::stop::{StopConditions, StopReason};
+use crate::storage::SaveLocation;
+use crate::transport::{FixtureTransport, Transport};
+use crate::vault;
+use crate::control::{EngineEvent, JobInfo};
+use crate::{HitRecord, LogLevel, VehicleChecker, Verbosity, control, hook, mqtt};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+use veh_reg::{ScanEvent, ScanOptions, VehicleClient};
+
+// Headless mode for servers and containers, started with --daemon. Settings
+// come from config.json with a few VEH_REG_* variables on top, the scans to
+// run from daemon.json, which is re-read every poll so jobs can be added
+// while it runs. Hits are saved under the results folder the same way the
+// app saves them, and GET /health reports what the daemon is doing.
+const JOBS_FILE: &str = "daemon.json";
+
+// Which scans have finished, so a restarted container doesn't redo them
+pub const STATE_FILE: &str = "daemon_state.json";
+
+const DEFAULT_HEALTH_BIND: &str = "127.0.0.1:8080";
+
+// Idle connections kept per host
+const POOL_SIZE: usize = 4;
+
+// No scan event or poll for this long and /health answers 503
+const STALL_SECS: u64 = 600;
+
+// How scheduled run times are kept in the state file, in local time
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+// A scheduled run picked up later than this was missed, e.g. while the
+// container was down, and is logged as a catch-up
+const CATCH_UP_AFTER_MINS: i64 = 5;
+
+// Longest range a job submitted over the API may cover. Registration dates
+// go back decades, but a century of dates is a typo, not a scan.
+const MAX_JOB_DAYS: i64 = 50 * 366;
+
+// A one-shot job that keeps failing is retried after 1, 2, 4... minutes, up
+// to RETRY_MAX_MINS apart, and given up after MAX_ATTEMPTS failures
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_MAX_MINS: i64 = 60;
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct DaemonFile {
+    // Seconds between looks at the jobs file
+    poll_secs: u64,
+    jobs: Vec<Job>,
+}
+
+impl Default for DaemonFile {
+    fn default() -> Self {
+        Self { poll_secs: 60, jobs: Vec::new() }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct Job {
+    name: String,
+    vehicles: Vec<String>,
+    // YYYY-MM-DD
+    start: String,
+    end: String,
+    // Unset takes `threads` and `request_delay_ms` from config.json
+    threads: Option<usize>,
+    max_hits: u32,
+    delay_ms: Option<u64>,
+    // Cron expression, e.g. "0 3 * * SUN"; empty scans once
+    schedule: String,
+    // Scheduled runs whose results are kept, oldest removed first; 0 keeps all
+    keep_runs: usize,
+}
+
+impl Default for Job {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            vehicles: Vec::new(),
+            start: String::new(),
+            end: String::new(),
+            threads: None,
+            max_hits: 1,
+            delay_ms: None,
+            schedule: String::new(),
+            keep_runs: 0,
+        }
+    }
+}
+
+impl Job {
+    fn dates(&self) -> Result<(NaiveDate, NaiveDate), String> {
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| tr!("job '{}': invalid date '{}', expected YYYY-MM-DD", self.name, date))
+        };
+        Ok((parse(&self.start)?, parse(&self.end)?))
+    }
+
+    // A changed range makes it a new scan
+    fn key(&self, vehicle_no: &str) -> String {
+        format!("{}|{}|{}|{}", self.name, vehicle_no, self.start.trim(), self.end.trim())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct DaemonState {
+    // Job key -> when its scan finished
+    done: BTreeMap<String, String>,
+    // Job key -> failed attempts of a one-shot scan not yet done
+    failed: BTreeMap<String, Attempts>,
+    // Scheduled job name -> its runs
+    schedules: BTreeMap<String, ScheduleState>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Attempts {
+    count: u32,
+    last: String,
+    error: String,
+}
+
+impl Attempts {
+    fn given_up(&self) -> bool {
+        self.count >= MAX_ATTEMPTS
+    }
+
+    // Whether the backoff since the last failure has passed
+    fn retry_due(&self, now: NaiveDateTime) -> bool {
+        let Ok(last) = NaiveDateTime::parse_from_str(&self.last, TIME_FORMAT) else { return true };
+        let wait = 1_i64.checked_shl(self.count.saturating_sub(1)).unwrap_or(RETRY_MAX_MINS).min(RETRY_MAX_MINS);
+        now - last >= chrono::Duration::minutes(wait)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct ScheduleState {
+    last_run: String,
+    // Each run's folders, one per vehicle, oldest run first
+    runs: Vec<Vec<String>>,
+}
+
+impl DaemonState {
+    // Sealed like the responses while the results are encrypted
+    fn load(results_dir: &Path) -> Self {
+        vault::read_to_string(&results_dir.join(STATE_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+    }
+
+    fn save(&self, results_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        vault::write(&results_dir.join(STATE_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn save_or_warn(&self, results_dir: &Path) {
+        if let Err(e) = self.save(results_dir) {
+            log(tr!("Could not save {} - {}", STATE_FILE, e), LogLevel::Warning);
+        }
+    }
+}
+
+struct Health {
+    started_at: String,
+    last_activity: Instant,
+    // "<job>: <vehicle>" while a scan runs
+    scanning: Option<String>,
+    scans_finished: u64,
+    hits: u64,
+    last_error: Option<String>,
+    // Scheduled job name -> next run, None when the schedule never comes round
+    next_runs: BTreeMap<String, Option<String>>,
+}
+
+impl Health {
+    fn touch(health: &Mutex<Health>) {
+        if let Ok(mut health) = health.lock() {
+            health.last_activity = Instant::now();
+        }
+    }
+
+    fn json(&self) -> serde_json::Value {
+        let stalled = self.last_activity.elapsed().as_secs() >= STALL_SECS;
+        json!({
+            "status": if stalled { "stalled" } else { "ok" },
+            "started_at": self.started_at,
+            "scanning": self.scanning,
+            "scans_finished": self.scans_finished,
+            "hits": self.hits,
+            "last_error": self.last_error,
+            "next_runs": self.next_runs,
+        })
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+fn log(message: String, level: LogLevel) {
+    if Verbosity::current().shows(level) {
+        println!("[{}] {}: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), control::level_name(level), record::redact_text(&message));
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn plugin_transport(name: &str) -> Result<Arc<dyn Transport>, String> {
+    let plugin = crate::plugin::find(name).ok_or_else(|| tr!("Provider plugin '{}' is not loaded - check {}/", name, crate::plugin::PLUGIN_DIR))?;
+    log(tr!("Loaded provider plugin {} from {}", plugin.name, plugin.path.display()), LogLevel::Info);
+    Ok(Arc::new(crate::plugin::PluginTransport(plugin)))
+}
+
+#[cfg(not(feature = "plugins"))]
+fn plugin_transport(name: &str) -> Result<Arc<dyn Transport>, String> {
+    Err(tr!("provider plugin '{}' is configured, but this build has no plugin support (--features plugins)", name))
+}
+
+// Runs until the process is killed
+pub fn run() -> Result<(), String> {
+    let config_path = env("VEH_REG_CONFIG").map(PathBuf::from).unwrap_or_else(AppConfig::path);
+    let mut config = AppConfig::load_from(&config_path).map_err(|e| tr!("cannot read settings {} - {}", config_path.display(), e))?;
+    if let Some(dir) = env("VEH_REG_RESULTS_DIR") {
+        config.results_dir = dir;
+    }
+    if let Some(url) = env("VEH_REG_ENDPOINT") {
+        config.endpoint_url = url;
+    }
+    if let Some(path) = env("VEH_REG_SCRIPT") {
+        config.script_path = path;
+    }
+    if let Some(name) = env("VEH_REG_CASE") {
+        config.active_case = name;
+    }
+    let root = PathBuf::from(config.results_dir.trim());
+    // No one is there to type it, so an encrypted results folder needs the
+    // passphrase up front
+    vault::set_sealing(config.encrypt_results);
+    if config.encrypt_results || vault::exists(&root) {
+        let passphrase = env("VEH_REG_PASSPHRASE").ok_or_else(|| tr!("the results in {} are encrypted - set VEH_REG_PASSPHRASE", root.display()))?;
+        vault::unlock(&root, &passphrase).map_err(|e| tr!("cannot unlock the results - {}", e))?;
+    }
+    // Filed under the case like the app does, opening it if it's new
+    if !config.active_case.trim().is_empty() {
+        if case::load(&root, &config.active_case).is_none() {
+            case::create(&root, &config.active_case).map_err(|e| tr!("cannot open case '{}' - {}", config.active_case, e))?;
+        }
+        config.results_dir = case::dir(&root, &config.active_case).display().to_string();
+    }
+    if let Some(verbosity) = env("VEH_REG_VERBOSITY") {
+        config.verbosity = Verbosity::ALL
+        .into_iter()
+        .find(|v| v.label().eq_ignore_ascii_case(verbosity.trim()))
+        .ok_or_else(|| tr!("unknown verbosity '{}', expected quiet, normal, debug or trace", verbosity))?;
+    }
+    i18n::set_language(config.language);
+    config.verbosity.set();
+    record::set_redaction(config.redact_personal);
+    record::set_rules(&config.extraction_rules).map_err(|e| tr!("field extraction rules not applied - {}", e))?;
+
+    let jobs_path = env("VEH_REG_JOBS").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(JOBS_FILE));
+    let results_dir = PathBuf::from(config.results_dir.trim());
+    fs::create_dir_all(&results_dir).map_err(|e| tr!("cannot create {} - {}", results_dir.display(), e))?;
+    if config.mqtt.enabled {
+        match mqtt::spawn_publisher(&config.mqtt, log) {
+            Ok(_) => log(tr!("Publishing hits to MQTT broker {} under {}/", config.mqtt.broker, config.mqtt.topic), LogLevel::Info),
+            Err(e) => log(tr!("Could not start MQTT publishing - {}", e), LogLevel::Error),
+        }
+    }
+    if !config.found_command.trim().is_empty() {
+        let dir = results_dir.clone();
+        hook::spawn_runner(&config.found_command, move || dir.clone(), log);
+        log(tr!("Running '{}' for every hit", config.found_command.trim()), LogLevel::Info);
+    }
+    // A script that doesn't load stops the daemon: running without the
+    // hooks it was configured with would classify differently
+    #[cfg(feature = "scripting")]
+    let script_hook = if config.script_path.trim().is_empty() {
+        None
+    } else {
+        let path = PathBuf::from(config.script_path.trim());
+        let script = crate::scripting::load(&path, log)?;
+        log(tr!("Script {} loaded, hooks: {}", path.display(), script.hooks.join(", ")), LogLevel::Info);
+        script.classify
+    };
+    #[cfg(not(feature = "scripting"))]
+    let script_hook: Option<crate::classify::ClassifyHook> = None;
+
+    #[cfg(feature = "plugins")]
+    for (path, e) in crate::plugin::load_all(Path::new(crate::plugin::PLUGIN_DIR)) {
+        log(tr!("Provider plugin {} not loaded - {}", path.display(), e), LogLevel::Error);
+    }
+
+    let transport: Arc<dyn Transport> = if !config.offline_fixture_dir.trim().is_empty() {
+        Arc::new(FixtureTransport::new(PathBuf::from(config.offline_fixture_dir.trim())))
+    } else if !config.provider_plugin.is_empty() {
+        plugin_transport(&config.provider_plugin)?
+    } else {
+        Arc::new(HttpTransport::from_config(&config, POOL_SIZE).map_err(|e| e.to_string())?)
+    };
+    let classifier = Classifier::new(&config.classifier_rules)?.with_hook(script_hook);
+    let client = VehicleClient::with_transport(transport, classifier).with_field_rules(record::rules());
+
+    run::open_outputs(&config, &results_dir, log);
+
+    let health = Arc::new(Mutex::new(Health {
+        started_at: chrono::Local::now().to_rfc3339(),
+        last_activity: Instant::now(),
+        scanning: None,
+        scans_finished: 0,
+        hits: 0,
+        last_error: None,
+        next_runs: BTreeMap::new(),
+    }));
+    let store = JobStore::open(&results_dir).map_err(|e| tr!("cannot open the job queue - {}", e))?;
+    match store.requeue_interrupted() {
+        Ok(0) => {}
+        Ok(n) => log(tr!("Resuming {} jobs that were running when the daemon stopped", n), LogLevel::Warning),
+        Err(e) => log(tr!("Job queue error - {}", e), LogLevel::Error),
+    }
+
+    // Submitting a job cuts the wait for the next poll short
+    let (wake, woken) = mpsc::channel();
+    let health_bind = env("VEH_REG_HEALTH_BIND").unwrap_or_else(|| DEFAULT_HEALTH_BIND.to_string());
+    // Without tokens anyone who can reach the port could queue scans
+    if !is_loopback(&health_bind) && !config.api_tokens.iter().any(|t| !t.token.is_empty() && t.scope.allows(TokenScope::Submit)) {
+        return Err(tr!("refusing to listen on {} without an API token with the submit scope; add one or bind to 127.0.0.1", health_bind));
+    }
+    spawn_api(&health_bind, Arc::clone(&health), config.api_tokens.clone(), config.threads, results_dir.clone(), wake)
+    .map_err(|e| tr!("cannot listen on {} - {}", health_bind, e))?;
+
+    log(tr!("Daemon started: jobs from {}, results in {}, health on http://{}/health",
+            jobs_path.display(), results_dir.display(), health_bind), LogLevel::Info);
+
+    let mut state = DaemonState::load(&results_dir);
+    loop {
+        Health::touch(&health);
+        let poll_secs = match load_jobs(&jobs_path) {
+            Ok(file) => {
+                run_pending(&client, &config, &file.jobs, &mut state, &health);
+                file.poll_secs
+            }
+            Err(e) => {
+                log(e.clone(), LogLevel::Error);
+                if let Ok(mut health) = health.lock() {
+                    health.last_error = Some(e);
+                }
+                DaemonFile::default().poll_secs
+            }
+        };
+        run_queue(&client, &config, &store, &health);
+        let _ = woken.recv_timeout(Duration::from_secs(poll_secs.max(1)));
+    }
+}
+
+fn load_jobs(path: &Path) -> Result<DaemonFile, String> {
+    if !path.exists() {
+        return Ok(DaemonFile::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| tr!("cannot read {} - {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| tr!("invalid jobs file {} - {}", path.display(), e))
+}
+
+fn run_pending(client: &VehicleClient, config: &AppConfig, jobs: &[Job], state: &mut DaemonState, health: &Mutex<Health>) {
+    let now = chrono::Local::now().naive_local();
+    let once: Vec<(&Job, &String)> = jobs
+    .iter()
+    .filter(|job| job.schedule.trim().is_empty())
+    .flat_map(|job| job.vehicles.iter().map(move |vehicle| (job, vehicle)))
+    .filter(|(job, vehicle)| !state.done.contains_key(&job.key(vehicle)))
+    .filter(|(job, vehicle)| state.failed.get(&job.key(vehicle)).is_none_or(|a| !a.given_up() && a.retry_due(now)))
+    .collect();
+    let due: Vec<&Job> = jobs
+    .iter()
+    .filter(|job| !job.schedule.trim().is_empty())
+    .filter(|job| is_due(job, state, now, health))
+    .collect();
+    if once.is_empty() && due.is_empty() {
+        return;
+    }
+
+    if !endpoint_ok(client, config, health) {
+        return;
+    }
+
+    let results_dir = Path::new(config.results_dir.trim());
+    for (job, vehicle) in once {
+        let key = job.key(vehicle);
+        match tracked_scan(client, config, job, vehicle, health).and_then(Scanned::complete) {
+            Ok(_) => {
+                state.failed.remove(&key);
+                state.done.insert(key, chrono::Local::now().to_rfc3339());
+            }
+            Err(e) => {
+                log(e.clone(), LogLevel::Error);
+                let attempts = state.failed.entry(key).or_default();
+                attempts.count += 1;
+                attempts.last = chrono::Local::now().naive_local().format(TIME_FORMAT).to_string();
+                attempts.error = e;
+                if attempts.given_up() {
+                    log(tr!("Job '{}': giving up on {} after {} failed attempts; change the job or clear it from {} to try again",
+                            job.name, vehicle, attempts.count, STATE_FILE), LogLevel::Error);
+                    audit::record(Event::new("job_given_up", vehicle).detail(attempts.error.clone()));
+                }
+            }
+        }
+        state.save_or_warn(results_dir);
+    }
+
+    for job in due {
+        let run_dirs = job
+        .vehicles
+        .iter()
+        .filter_map(|vehicle| tracked_scan(client, config, job, vehicle, health).map_err(|e| log(e, LogLevel::Error)).ok())
+        // Kept with the others even when cut short, so it ages out with them
+        .inspect(|scanned| if let Some(reason) = &scanned.incomplete { log(reason.clone(), LogLevel::Error) })
+        .map(|scanned| scanned.run_dir.to_string_lossy().replace('\\', "/"))
+        .collect();
+        let schedule = state.schedules.entry(job.name.clone()).or_default();
+        // From when this pass started, so any number of missed runs add up
+        // to the one catch-up run
+        schedule.last_run = now.format(TIME_FORMAT).to_string();
+        schedule.runs.push(run_dirs);
+        if job.keep_runs > 0 && schedule.runs.len() > job.keep_runs {
+            let expired = schedule.runs.len() - job.keep_runs;
+            for run_dir in schedule.runs.drain(..expired).flatten() {
+                match fs::remove_dir_all(results_dir.join(&run_dir)) {
+                    Ok(()) => log(tr!("Job '{}': removed old results {}", job.name, run_dir), LogLevel::Info),
+                    Err(e) => log(tr!("Could not remove {} - {}", run_dir, e), LogLevel::Warning),
+                }
+            }
+        }
+        state.save_or_warn(results_dir);
+    }
+}
+
+// Jobs submitted over the API, oldest first, until the queue is empty
+fn run_queue(client: &VehicleClient, config: &AppConfig, store: &JobStore, health: &Mutex<Health>) {
+    if !store.has_queued().unwrap_or(false) || !endpoint_ok(client, config, health) {
+        return;
+    }
+    loop {
+        let queued = match store.take_next() {
+            Ok(Some(queued)) => queued,
+            Ok(None) => return,
+            Err(e) => {
+                log(tr!("Job queue error - {}", e), LogLevel::Error);
+                return;
+            }
+        };
+        let job = Job {
+            name: format!("#{}", queued.id),
+            vehicles: vec![queued.vehicle_no.clone()],
+            start: queued.start_date.clone(),
+            end: queued.end_date.clone(),
+            threads: Some(queued.threads),
+            max_hits: queued.max_hits,
+            ..Job::default()
+        };
+        let saved = match tracked_scan(client, config, &job, &queued.vehicle_no, health) {
+            Ok(Scanned { hits, incomplete: None, .. }) => store.finish(queued.id, hits as u32),
+            Ok(Scanned { hits, incomplete: Some(reason), .. }) => {
+                log(reason.clone(), LogLevel::Error);
+                store.finish_partly(queued.id, hits as u32, &reason)
+            }
+            Err(e) => {
+                log(e.clone(), LogLevel::Error);
+                store.fail(queued.id, &e)
+            }
+        };
+        if let Err(e) = saved {
+            log(tr!("Job queue error - {}", e), LogLevel::Error);
+        }
+    }
+}
+
+// Not worth burning through a job list against an endpoint that's down or
+// answering differently; the next poll tries again
+fn endpoint_ok(client: &VehicleClient, config: &AppConfig, health: &Mutex<Health>) -> bool {
+    if !config.health_check_enabled {
+        return true;
+    }
+    let Err(e) = client.health_check() else { return true };
+    let message = tr!("Health check failed, not scanning: {}", e);
+    log(message.clone(), LogLevel::Error);
+    audit::record(Event::new("health_check_failed", "").detail(e));
+    if let Ok(mut health) = health.lock() {
+        health.last_error = Some(message);
+    }
+    false
+}
+
+// Whether a scheduled job's next run has come, noting when it will otherwise.
+// A job seen for the first time waits for its first scheduled time.
+fn is_due(job: &Job, state: &mut DaemonState, now: NaiveDateTime, health: &Mutex<Health>) -> bool {
+    let schedule = match Schedule::parse(&job.schedule) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            let message = tr!("job '{}': invalid schedule '{}' - {}", job.name, job.schedule, e);
+            log(message.clone(), LogLevel::Error);
+            if let Ok(mut health) = health.lock() {
+                health.last_error = Some(message);
+            }
+            return false;
+        }
+    };
+
+    let last_run = state
+    .schedules
+    .get(&job.name)
+    .and_then(|s| NaiveDateTime::parse_from_str(&s.last_run, TIME_FORMAT).ok());
+    let Some(last_run) = last_run else {
+        state.schedules.entry(job.name.clone()).or_default().last_run = now.format(TIME_FORMAT).to_string();
+        if let Some(next) = schedule.next_after(now) {
+            log(tr!("Job '{}' scheduled, first run at {}", job.name, next.format("%Y-%m-%d %H:%M")), LogLevel::Info);
+        }
+        return false;
+    };
+
+    let next = schedule.next_after(last_run);
+    if let Ok(mut health) = health.lock() {
+        let shown = next.map(|next| next.max(now).format("%Y-%m-%d %H:%M").to_string());
+        health.next_runs.insert(job.name.clone(), shown);
+    }
+    let Some(next) = next.filter(|next| *next <= now) else { return false };
+    if now - next > chrono::Duration::minutes(CATCH_UP_AFTER_MINS) {
+        log(tr!("Job '{}': catching up on the run due at {}", job.name, next.format("%Y-%m-%d %H:%M")), LogLevel::Warning);
+    }
+    true
+}
+
+// Runs one scan and keeps /health up to date
+fn tracked_scan(client: &VehicleClient, config: &AppConfig, job: &Job, vehicle_no: &str, health: &Mutex<Health>) -> Result<Scanned, String> {
+    if let Ok(mut health) = health.lock() {
+        health.scanning = Some(format!("{}: {}", job.name, vehicle_no));
+    }
+    let result = scan(client, config, job, vehicle_no, health);
+    if let Ok(mut health) = health.lock() {
+        health.scanning = None;
+        match &result {
+            Ok(scanned) => {
+                health.scans_finished += 1;
+                if let Some(reason) = &scanned.incomplete {
+                    health.last_error = Some(reason.clone());
+                }
+            }
+            Err(e) => health.last_error = Some(e.clone()),
+        }
+    }
+    result
+}
+
+// What a scan that ran left behind
+struct Scanned {
+    // The run's folder relative to the results root
+    run_dir: PathBuf,
+    hits: usize,
+    // Why part of the range is still unchecked, if it is: a challenge page,
+    // the circuit breaker, a server error or dates that got no answer. A stop
+    // at the job's own hit limit isn't one.
+    incomplete: Option<String>,
+}
+
+impl Scanned {
+    // A scan cut short is a failed attempt, to be tried again
+    fn complete(self) -> Result<Scanned, String> {
+        match self.incomplete {
+            Some(reason) => Err(reason),
+            None => Ok(self),
+        }
+    }
+}
+
+fn scan(client: &VehicleClient, config: &AppConfig, job: &Job, vehicle_no: &str, health: &Mutex<Health>) -> Result<Scanned, String> {
+    let (start, end) = job.dates()?;
+    let results_dir = Path::new(config.results_dir.trim());
+    let location = SaveLocation::new(results_dir, vehicle_no, chrono::Local::now(), &config.filename_template);
+    location.create().map_err(|e| tr!("cannot create {} - {}", location.run_dir.display(), e))?;
+    let threads = job.threads.unwrap_or(config.threads).clamp(1, config::MAX_THREADS);
+    // The same lock, run state and coverage as a run started in the app, so
+    // the two can't scan the same dates at once. Every date is scanned again.
+    let target = run::Target {
+        vehicle_no,
+        start,
+        end,
+        provider: config.provider_name(),
+        search_by: config.search_by,
+        threads,
+        search_order: SearchOrder::Forward.label(),
+        skip_checked: false,
+    };
+    let coverage = Arc::new(Mutex::new(CoverageMap::default()));
+    let mut run = run::Run::begin(results_dir, &target, Arc::clone(&coverage), log)?;
+
+    let started_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    log(tr!("Job '{}': scanning {} from {} to {}", job.name, vehicle_no, start, end), LogLevel::Info);
+    audit::record(Event::new("run_started", vehicle_no).detail(format!("{} {} to {}", job.name, start, end)));
+
+    let options = ScanOptions {
+        threads,
+        stop: StopConditions { max_hits: job.max_hits, ..StopConditions::default() },
+        delay: Duration::from_millis(job.delay_ms.unwrap_or(config.request_delay_ms)),
+        adaptive: config.adaptive_concurrency,
+        breaker_threshold: config.breaker_threshold,
+        coverage: Some(coverage),
+        ..ScanOptions::default()
+    };
+    let mut scan = client.scan(vehicle_no, start, end, options);
+    let mut hits = 0;
+    let mut paused_at = None;
+    let mut last_checkpoint = Instant::now();
+    while let Some(event) = scan.next_blocking() {
+        Health::touch(health);
+        if last_checkpoint.elapsed().as_secs() >= run::CHECKPOINT_SECS {
+            last_checkpoint = Instant::now();
+            run.checkpoint(scan.progress().0, log);
+        }
+        match event {
+            ScanEvent::Hit { thread, outcome, .. } => {
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                let relative = location.relative_path(vehicle_no, &date_str, outcome.status, thread);
+                if let Err(e) = vault::write(&location.root.join(&relative), &outcome.body) {
+                    log(tr!("Could not save {} - {}", relative, e), LogLevel::Error);
+                }
+                log(tr!("Hit: {} registered on {}", vehicle_no, record::shareable_date(&date_str)), LogLevel::Success);
+                audit::record(Event::new("hit", vehicle_no).thread(thread).date(&record::shareable_date(&date_str)).status(outcome.status)
+                              .detail(record::without_date(&relative, &date_str)));
+                // For MQTT, like the app's hits
+                control::publish(EngineEvent::Hit(HitRecord {
+                    vehicle_no: vehicle_no.to_string(),
+                    date: date_str,
+                    file_name: Some(relative),
+                    found_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    fields: outcome.fields,
+                    provider: config.provider_name().to_string(),
+                }));
+                hits += 1;
+                if let Ok(mut health) = health.lock() {
+                    health.hits += 1;
+                }
+            }
+            ScanEvent::Miss { outcome, .. } => log(tr!("{}: no record", outcome.date), LogLevel::Debug),
+            ScanEvent::RateLimited { thread, outcome, cooloff } => {
+                log(tr!("{}: rate limited (HTTP {}) - pausing for {}s, date will be retried", outcome.date, outcome.status, cooloff.as_secs()), LogLevel::Warning);
+                audit::record(Event::new("rate_limited", vehicle_no).thread(thread).date(&outcome.date.format("%Y-%m-%d").to_string()).status(outcome.status));
+            }
+            // Nobody is at the screen to resume, so the job ends here
+            ScanEvent::Challenge { thread, outcome, paused: true } => {
+                let detail = format!("{} ({})", outcome.classification.label(), outcome.rule);
+                paused_at = Some(tr!("paused at {} ({})", outcome.date, detail));
+                paused(job, vehicle_no, thread, outcome.date, detail);
+                scan.stop();
+            }
+            ScanEvent::BreakerOpen { thread, date, failures } => {
+                let detail = format!("{} failed requests in a row", failures);
+                paused_at = Some(tr!("paused at {} ({})", date, detail));
+                paused(job, vehicle_no, thread, date, detail);
+                scan.stop();
+            }
+            ScanEvent::Retrying { date, reason, .. } => log(tr!("{}: {}, will retry", date, reason), LogLevel::Warning),
+            ScanEvent::FailedOver { from, to, .. } => log(tr!("{} keeps failing, failing over to {}", from, to), LogLevel::Warning),
+            ScanEvent::Error { thread, outcome, .. } => {
+                let detail = format!("{} ({})", outcome.classification.label(), outcome.rule);
+                log(format!("{}: {}", outcome.date, detail), LogLevel::Warning);
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                audit::record(Event::new("server_error", vehicle_no).thread(thread).date(&date_str).status(outcome.status).detail(detail));
+            }
+            ScanEvent::Failed { thread, date, error } => {
+                log(format!("{}: {}", date, error), LogLevel::Warning);
+                audit::record(Event::new("request_failed", vehicle_no).thread(thread).date(&date.format("%Y-%m-%d").to_string()).detail(error));
+            }
+            ScanEvent::Stopped { reason } => log(tr!("Job '{}': stopping - {}", job.name, VehicleChecker::describe_stop_reason(reason)), LogLevel::Warning),
+            _ => {}
+        }
+    }
+
+    let (checked, total) = scan.progress();
+    run.finish(log);
+    if scan.failed() > 0 {
+        log(tr!("Job '{}': {} dates got no answer and were not checked", job.name, scan.failed()), LogLevel::Warning);
+    }
+    let reason = scan.stop_reason().map(VehicleChecker::describe_stop_reason);
+    log(tr!("Job '{}': {} done, {} of {} dates checked, {} hits{}", job.name, vehicle_no, checked, total, hits,
+            reason.as_ref().map(|r| format!(" ({})", r)).unwrap_or_default()), LogLevel::Info);
+    audit::record(Event::new("run_finished", vehicle_no).detail(format!("{} of {} dates checked, {} hits", checked, total, hits)));
+    let cut_short = match scan.stop_reason() {
+        Some(StopReason::ServerError(_)) => reason,
+        _ => paused_at.or_else(|| (scan.failed() > 0).then(|| tr!("{} dates got no answer", scan.failed()))),
+    };
+    let incomplete = cut_short.map(|why| tr!("Job '{}': {} was not fully scanned - {}", job.name, vehicle_no, why));
+    control::publish(EngineEvent::RunFinished(JobInfo {
+        vehicle_no: vehicle_no.to_string(),
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        threads,
+        started_at,
+        finished_at: Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+    }));
+    Ok(Scanned { run_dir: location.run_dir, hits, incomplete })
+}
+
+fn paused(job: &Job, vehicle_no: &str, thread: usize, date: NaiveDate, detail: String) {
+    log(tr!("Job '{}': paused at {} ({}) - stopping, submit the job again once the server is reachable", job.name, date, detail), LogLevel::Error);
+    audit::record(Event::new("scan_paused", vehicle_no).thread(thread).date(&date.format("%Y-%m-%d").to_string()).detail(detail));
+}
+
+// GET /health for container health checks, plus the job queue: GET /api/jobs
+// lists recent jobs, POST /api/jobs submits one. The queue takes the same
+// tokens as server mode, and without them the same same-machine checks;
+// /health never needs either.
+fn spawn_api(bind: &str, health: Arc<Mutex<Health>>, tokens: Vec<ApiToken>, default_threads: usize, results_dir: PathBuf, wake: mpsc::Sender<()>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(bind)?;
+    let api = Arc::new(Api { health, tokens, default_threads, results_dir, wake });
+    let open = Arc::new(AtomicUsize::new(0));
+    // A connection per thread, capped like server mode's
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(server::READ_TIMEOUT_SECS)));
+            if open.fetch_add(1, Ordering::SeqCst) >= server::MAX_CONNECTIONS {
+                open.fetch_sub(1, Ordering::SeqCst);
+                let _ = server::respond(&mut stream, "503 Service Unavailable", "text/plain", b"too many connections");
+                continue;
+            }
+            let api = Arc::clone(&api);
+            let open = Arc::clone(&open);
+            thread::spawn(move || {
+                serve_api(stream, &api);
+                open.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    Ok(())
+}
+
+// What every connection to the API shares
+struct Api {
+    health: Arc<Mutex<Health>>,
+    tokens: Vec<ApiToken>,
+    default_threads: usize,
+    results_dir: PathBuf,
+    wake: mpsc::Sender<()>,
+}
+
+fn serve_api(mut stream: TcpStream, api: &Api) {
+    let Ok(request) = server::read_request(&stream) else { return };
+    if request.path == "/health" {
+        let body = api.health.lock().map(|h| h.json()).unwrap_or_else(|_| json!({ "status": "stalled" }));
+        let status = if body["status"] == "ok" { "200 OK" } else { "503 Service Unavailable" };
+        let _ = server::respond(&mut stream, status, "application/json", body.to_string().as_bytes());
+        return;
+    }
+
+    if let Some(reason) = server::cross_site(&request, &api.tokens) {
+        let _ = server::respond(&mut stream, "403 Forbidden", "application/json", json!({ "error": reason }).to_string().as_bytes());
+        return;
+    }
+
+    let needed = if request.method == "POST" { TokenScope::Submit } else { TokenScope::ReadOnly };
+    let (status, body) = match auth::authorize(&api.tokens, server::presented_token(&request).as_deref(), needed) {
+        Err(AuthError::Unauthorized) => ("401 Unauthorized", json!({ "error": "missing or unknown token" })),
+        Err(AuthError::Forbidden) => ("403 Forbidden", json!({ "error": "token is read-only" })),
+        Ok(_) => match (JobStore::open(&api.results_dir), request.method.as_str(), request.path.as_str()) {
+            (Err(e), _, "/api/jobs") => ("500 Internal Server Error", json!({ "error": e.to_string() })),
+            (Ok(store), "GET", "/api/jobs") => match store.recent(100) {
+                Ok(jobs) => ("200 OK", jobs.iter().map(job_json).collect()),
+                Err(e) => ("500 Internal Server Error", json!({ "error": e.to_string() })),
+            },
+            (Ok(store), "POST", "/api/jobs") => match new_job(&request.body, api.default_threads) {
+                Err(e) => ("400 Bad Request", json!({ "error": e })),
+                Ok(job) => match store.submit(&job) {
+                    Ok(id) => {
+                        log(tr!("Job #{} queued: {} from {} to {}", id, job.vehicle_no, job.start_date, job.end_date), LogLevel::Info);
+                        let _ = api.wake.send(());
+                        ("201 Created", json!({ "id": id }))
+                    }
+                    Err(e) => ("500 Internal Server Error", json!({ "error": e.to_string() })),
+                },
+            },
+            _ => ("404 Not Found", json!({ "error": "not found" })),
+        },
+    };
+    let _ = server::respond(&mut stream, status, "application/json", body.to_string().as_bytes());
+}
+
+// Every address the bind resolves to is on this machine
+pub fn is_loopback(bind: &str) -> bool {
+    let addrs: Vec<_> = bind.to_socket_addrs().map(|addrs| addrs.collect()).unwrap_or_default();
+    !addrs.is_empty() && addrs.iter().all(|addr| addr.ip().is_loopback())
+}
+
+// {"vehicle_no": "ABC-123", "start_date": "2020-01-01", "end_date": "2020-12-31", "threads": 4, "max_hits": 1}
+fn new_job(body: &[u8], default_threads: usize) -> Result<NewJob, String> {
+    let value: serde_json::Value = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let text = |name: &str| value.get(name).and_then(|v| v.as_str()).map(|s| s.trim().to_string()).unwrap_or_default();
+    let job = NewJob {
+        vehicle_no: text("vehicle_no"),
+        start_date: text("start_date"),
+        end_date: text("end_date"),
+        threads: value.get("threads").and_then(|v| v.as_u64()).map_or(default_threads, |threads| usize::try_from(threads).unwrap_or(usize::MAX)).clamp(1, config::MAX_THREADS),
+        max_hits: match value.get("max_hits") {
+            None => 1,
+            Some(v) => v.as_u64().and_then(|n| u32::try_from(n).ok()).ok_or_else(|| format!("max_hits must be a whole number up to {}", u32::MAX))?,
+        },
+    };
+    if job.vehicle_no.is_empty() {
+        return Err("vehicle_no is required".to_string());
+    }
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("invalid date '{}', expected YYYY-MM-DD", date));
+    let (start, end) = (parse(&job.start_date)?, parse(&job.end_date)?);
+    if start > end {
+        return Err(format!("start_date {} is after end_date {}", job.start_date, job.end_date));
+    }
+    if (end - start).num_days() >= MAX_JOB_DAYS {
+        return Err(format!("the range covers more than {} days; split it into smaller jobs", MAX_JOB_DAYS));
+    }
+    Ok(job)
+}
+
+fn job_json(job: &StoredJob) -> serde_json::Value {
+    json!({
+        "id": job.id,
+        "vehicle_no": job.vehicle_no,
+        "start_date": job.start_date,
+        "end_date": job.end_date,
+        "threads": job.threads,
+        "max_hits": job.max_hits,
+        "status": job.status,
+        "submitted_at": job.submitted_at,
+        "started_at": job.started_at,
+        "finished_at": job.finished_at,
+        "hits": job.hits,
+        "error": job.error,
+    })
+}
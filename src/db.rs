@@ -0,0 +1,371 @@
+use crate::classify::Classifier;
+use crate::i18n::tr;
+use crate::record;
+use crate::storage;
+use crate::vault;
+use rusqlite::{Connection, params};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+// SQLite index of everything saved under the results directory, kept next to
+// the responses themselves so the folder stays self-contained
+const DB_FILE: &str = "results.db";
+
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+pub struct ImportSummary {
+    pub scanned: usize,
+    pub added: usize,
+    pub updated: usize,
+    // Files whose names don't say which vehicle and date they are for
+    pub skipped: usize,
+}
+
+// One indexed response, as shown in the History tab
+pub struct ResultRow {
+    pub path: String,
+    pub vehicle_no: String,
+    pub date: String,
+    pub status: u16,
+    pub classification: String,
+    pub owner: String,
+}
+
+// A full-text match and where it came from
+pub struct SearchHit {
+    pub path: String,
+    pub vehicle_no: String,
+    pub date: String,
+    // Matched text with the hit in [brackets]
+    pub snippet: String,
+}
+
+// What someone wrote about a saved response: tags as typed, comma separated
+// ("confirmed, follow up"), and a free-text note
+#[derive(Clone, Default, PartialEq)]
+pub struct Annotation {
+    pub tags: String,
+    pub note: String,
+}
+
+// Offered as one-click tags; any other tag can be typed
+pub const SUGGESTED_TAGS: [&str; 3] = ["confirmed", "wrong vehicle", "follow up"];
+
+impl Annotation {
+    pub fn tag_list(&self) -> Vec<&str> {
+        self.tags.split(',').map(str::trim).filter(|t| !t.is_empty()).collect()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tag_list().iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    // Adds the tag, or takes it off if it is there
+    pub fn toggle_tag(&mut self, tag: &str) {
+        let mut tags: Vec<&str> = self.tag_list();
+        match tags.iter().position(|t| t.eq_ignore_ascii_case(tag)) {
+            Some(i) => {
+                tags.remove(i);
+            }
+            None => tags.push(tag),
+        }
+        self.tags = tags.join(", ");
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tag_list().is_empty() && self.note.trim().is_empty()
+    }
+}
+
+fn path_for(results_dir: &Path) -> PathBuf {
+    results_dir.join(DB_FILE)
+}
+
+// An unencrypted database starts with this; a SQLCipher one looks like noise
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+// SQL that holds the key, built in one buffer and wiped after use like the
+// key itself
+fn keyed(parts: &[&str]) -> Zeroizing<String> {
+    let mut sql = Zeroizing::new(String::with_capacity(parts.iter().map(|part| part.len()).sum()));
+    for part in parts {
+        sql.push_str(part);
+    }
+    sql
+}
+
+fn is_plain(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path).and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header)).is_ok() && header == SQLITE_HEADER
+}
+
+// Opens a database kept with the results, with the vault's key when it is
+// encrypted. New databases are encrypted while sealing, and not created at
+// all while the results are locked. One from before encryption was turned on
+// is used as it is until encrypt() converts it.
+pub fn open_keyed(path: &Path) -> rusqlite::Result<Connection> {
+    let existing = fs::metadata(path).is_ok_and(|meta| meta.len() > 0);
+    let conn = Connection::open(path)?;
+    let encrypted = if existing { !is_plain(path) } else { vault::is_encrypting() };
+    match vault::sqlcipher_key() {
+        Some(key) if encrypted => conn.execute_batch(&keyed(&["PRAGMA key = \"", &key, "\";"]))?,
+        None if encrypted => {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_AUTH),
+                Some(tr!("{} is encrypted - unlock the results first", path.display())),
+            ));
+        }
+        _ => {}
+    }
+    Ok(conn)
+}
+
+// Rewrites a database from before encryption was turned on as an encrypted
+// one, with SQLCipher's own export. False when there was nothing to do.
+pub fn encrypt(path: &Path) -> rusqlite::Result<bool> {
+    if !vault::is_encrypting() || !is_plain(path) {
+        return Ok(false);
+    }
+    let Some(key) = vault::sqlcipher_key() else { return Ok(false) };
+    let sealed = path.with_extension("db.sealing");
+    let _ = fs::remove_file(&sealed);
+    {
+        let conn = Connection::open(path)?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let target = sealed.display().to_string().replace('\'', "''");
+        conn.execute_batch(&keyed(&["ATTACH DATABASE '", &target, "' AS sealed KEY \"", &key, "\";"]))?;
+        conn.query_row("SELECT sqlcipher_export('sealed')", [], |_| Ok(()))?;
+        conn.execute_batch(&format!("PRAGMA sealed.user_version = {}; DETACH DATABASE sealed;", version))?;
+    }
+    fs::rename(&sealed, path).map_err(|e| rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_IOERR), Some(e.to_string())))?;
+    Ok(true)
+}
+
+impl ResultsDb {
+    pub fn open(results_dir: &Path) -> rusqlite::Result<Self> {
+        let conn = open_keyed(&path_for(results_dir))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS responses (
+                path TEXT PRIMARY KEY,
+                vehicle_no TEXT NOT NULL,
+                date TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                classification TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                modified TEXT NOT NULL,
+                indexed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS fields (
+                path TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                label TEXT NOT NULL,
+                value TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS annotations (
+                path TEXT PRIMARY KEY,
+                tags TEXT NOT NULL,
+                note TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS responses_vehicle ON responses(vehicle_no);
+            CREATE INDEX IF NOT EXISTS fields_path ON fields(path);",
+        )?;
+
+        // Version 1 added the full-text table. Indexes from before it are
+        // emptied so the next import fills the new table too.
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < 1 {
+            // The trigram tokenizer matches any fragment of 3+ characters,
+            // e.g. the middle of a chassis number
+            conn.execute_batch(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS responses_fts USING fts5(path UNINDEXED, text, tokenize = 'trigram');
+                DELETE FROM responses;
+                DELETE FROM fields;
+                PRAGMA user_version = 1;",
+            )?;
+        }
+        Ok(Self { conn })
+    }
+
+    // Walks the results directory and indexes every response that is new or
+    // changed since the last import. Entries for deleted files are dropped.
+    pub fn import(&mut self, root: &Path, classifier: &Classifier) -> rusqlite::Result<ImportSummary> {
+        let mut summary = ImportSummary { scanned: 0, added: 0, updated: 0, skipped: 0 };
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let rules = record::rules();
+        let files: HashSet<String> = storage::walk_results(root).into_iter().filter(|p| p.ends_with(".html")).collect();
+
+        let tx = self.conn.transaction()?;
+        for path in &files {
+            summary.scanned += 1;
+            let Some(name) = storage::parse_result_path(path) else {
+                summary.skipped += 1;
+                continue;
+            };
+            let Ok(meta) = fs::metadata(root.join(path)) else { continue };
+            let modified = meta
+            .modified()
+            .map(|t| chrono::DateTime::<chrono::Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+
+            let known: Option<(i64, String)> = tx
+            .query_row("SELECT size, modified FROM responses WHERE path = ?1", params![path], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .ok();
+            if known.as_ref().is_some_and(|(size, m)| *size == meta.len() as i64 && *m == modified) {
+                continue;
+            }
+            let Ok(body) = vault::read_to_string(&root.join(path)) else { continue };
+            let (classification, _) = classifier.classify(name.status, &body);
+
+            tx.execute("DELETE FROM fields WHERE path = ?1", params![path])?;
+            tx.execute("DELETE FROM responses_fts WHERE path = ?1", params![path])?;
+            tx.execute(
+                "INSERT INTO responses_fts (path, text) VALUES (?1, ?2)",
+                params![path, record::clean_text_lines(&body).join("\n")],
+            )?;
+            tx.execute(
+                "INSERT OR REPLACE INTO responses (path, vehicle_no, date, status, classification, size, modified, indexed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    path,
+                    name.vehicle_no,
+                    name.date.format("%Y-%m-%d").to_string(),
+                    name.status,
+                    classification.label(),
+                    meta.len() as i64,
+                    modified,
+                    now,
+                ],
+            )?;
+            for (i, (label, value)) in record::parse_fields(&rules, &body).iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO fields (path, position, label, value) VALUES (?1, ?2, ?3, ?4)",
+                    params![path, i as i64, label, value],
+                )?;
+            }
+            if known.is_some() {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+        }
+
+        // Gone from disk (retention, manual cleanup)
+        let indexed: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT path FROM responses")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for path in indexed.iter().filter(|p| !files.contains(*p)) {
+            tx.execute("DELETE FROM fields WHERE path = ?1", params![path])?;
+            tx.execute("DELETE FROM responses_fts WHERE path = ?1", params![path])?;
+            tx.execute("DELETE FROM responses WHERE path = ?1", params![path])?;
+            tx.execute("DELETE FROM annotations WHERE path = ?1", params![path])?;
+        }
+
+        tx.commit()?;
+        Ok(summary)
+    }
+
+    // Responses whose visible text contains `query` (owner name, chassis or
+    // engine fragment...), best matches first. The query is taken literally;
+    // the trigram index needs at least 3 characters.
+    pub fn search(&self, query: &str, limit: usize) -> rusqlite::Result<Vec<SearchHit>> {
+        let phrase = format!("\"{}\"", query.trim().replace('"', "\"\""));
+        let mut stmt = self.conn.prepare(
+            "SELECT r.path, r.vehicle_no, r.date, snippet(responses_fts, 1, '[', ']', '...', 12)
+             FROM responses_fts JOIN responses r ON r.path = responses_fts.path
+             WHERE responses_fts MATCH ?1
+             ORDER BY rank
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![phrase, limit as i64], |row| {
+            Ok(SearchHit {
+                path: row.get(0)?,
+                vehicle_no: row.get(1)?,
+                date: row.get(2)?,
+                snippet: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // Every annotation, by the path of the response it is on
+    pub fn annotations(&self) -> rusqlite::Result<HashMap<String, Annotation>> {
+        let mut stmt = self.conn.prepare("SELECT path, tags, note FROM annotations")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, Annotation { tags: row.get(1)?, note: row.get(2)? })))?;
+        rows.collect()
+    }
+
+    // Saves the annotation of `path`; an empty one removes it
+    pub fn annotate(&self, path: &str, annotation: &Annotation) -> rusqlite::Result<()> {
+        if annotation.is_empty() {
+            self.conn.execute("DELETE FROM annotations WHERE path = ?1", params![path])?;
+            return Ok(());
+        }
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO annotations (path, tags, note, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![path, annotation.tag_list().join(", "), annotation.note.trim(), now],
+        )?;
+        Ok(())
+    }
+
+    // Newest dates first; an empty filter lists everything
+    pub fn list(&self, vehicle_filter: &str, limit: usize) -> rusqlite::Result<Vec<ResultRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.path, r.vehicle_no, r.date, r.status, r.classification,
+                    COALESCE((SELECT value FROM fields f WHERE f.path = r.path AND lower(f.label) LIKE '%owner%' ORDER BY position LIMIT 1), '')
+             FROM responses r
+             WHERE r.vehicle_no LIKE ?1
+             ORDER BY r.date DESC, r.path
+             LIMIT ?2",
+        )?;
+        let pattern = format!("%{}%", vehicle_filter.trim());
+        let rows = stmt.query_map(params![pattern, limit as i64], |row| {
+            Ok(ResultRow {
+                path: row.get(0)?,
+                vehicle_no: row.get(1)?,
+                date: row.get(2)?,
+                status: row.get(3)?,
+                classification: row.get(4)?,
+                owner: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    // Drops what was indexed and noted about responses that were removed
+    pub fn forget(&mut self, paths: &[String]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for path in paths {
+            for table in ["fields", "responses_fts", "responses", "annotations"] {
+                tx.execute(&format!("DELETE FROM {} WHERE path = ?1", table), params![path])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // Follows responses that were moved, keeping what was indexed and noted
+    // about them. Pairs are (old, new) paths.
+    pub fn rename(&mut self, renamed: &[(String, String)]) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        for (old, new) in renamed {
+            for table in ["responses", "fields", "responses_fts", "annotations"] {
+                tx.execute(&format!("UPDATE {} SET path = ?1 WHERE path = ?2", table), params![new, old])?;
+            }
+        }
+        tx.commit()
+    }
+
+    // Converts results.db from before encryption, see encrypt()
+    pub fn encrypt(results_dir: &Path) -> rusqlite::Result<bool> {
+        encrypt(&path_for(results_dir))
+    }
+}
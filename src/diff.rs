@@ -0,0 +1,44 @@
+use similar::{ChangeTag, TextDiff};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineKind {
+    Same,
+    Removed,
+    Added,
+}
+
+// One line of the diff, split into segments; `true` marks the words that
+// changed within a changed line
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub segments: Vec<(bool, String)>,
+}
+
+// Line diff with word-level highlighting inside changed lines. Unchanged runs
+// longer than `context` lines on either side of a change are dropped.
+pub fn diff(old: &str, new: &str, context: usize) -> Vec<DiffLine> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = Vec::new();
+
+    for group in diff.grouped_ops(context) {
+        if !lines.is_empty() {
+            lines.push(DiffLine { kind: LineKind::Same, segments: vec![(false, "...".to_string())] });
+        }
+        for op in group {
+            for change in diff.iter_inline_changes(&op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => LineKind::Same,
+                    ChangeTag::Delete => LineKind::Removed,
+                    ChangeTag::Insert => LineKind::Added,
+                };
+                let segments = change
+                .iter_strings_lossy()
+                .map(|(emphasized, text)| (emphasized, text.trim_end_matches('\n').to_string()))
+                .collect();
+                lines.push(DiffLine { kind, segments });
+            }
+        }
+    }
+
+    lines
+}
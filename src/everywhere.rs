@@ -0,0 +1,94 @@
+use crate::classify::{Classification, Classifier, ClassifyHook};
+use crate::config::AppConfig;
+use crate::http::HttpTransport;
+use crate::provider::Provider;
+use crate::transport::{Transport, TransportResult};
+use std::thread;
+
+// Told which provider has the hit, for which plate and date
+type HitCallback = Box<dyn Fn(Provider, &str, &str) + Send + Sync>;
+
+// Sends every check to all providers at once, for plates whose issuing
+// province isn't known. Each answer is classified with the rules as they
+// would be for its own provider; the first hit is what the check returns,
+// and `on_hit` is told which authority has it. Without a hit the configured
+// provider's answer is returned, so misses, errors and rate limits go
+// through the engine as usual. `hook` is consulted first, as it is by the
+// engine's own classifier.
+pub struct EverywhereTransport {
+    // The configured provider first
+    providers: Vec<(Provider, HttpTransport, Classifier)>,
+    on_hit: HitCallback,
+}
+
+impl EverywhereTransport {
+    pub fn from_config(
+        config: &AppConfig,
+        pool_size: usize,
+        hook: Option<ClassifyHook>,
+        on_hit: impl Fn(Provider, &str, &str) + Send + Sync + 'static,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Providers that can't be searched by this kind of number sit it out
+        let others = Provider::ALL.into_iter().filter(|p| *p != config.provider && p.supports(config.search_by));
+        let mut providers = Vec::new();
+        for provider in std::iter::once(config.provider).chain(others) {
+            let mut settings = config.clone();
+            if provider != config.provider {
+                // The fallbacks, the transcript and a custom request template
+                // belong to the configured provider
+                settings.switch_provider(provider);
+                settings.fallback_endpoints.clear();
+                settings.transcript_enabled = false;
+                settings.request_template.enabled = false;
+            }
+            let classifier = Classifier::new(&settings.classifier_rules)?.with_hook(hook.clone());
+            providers.push((provider, HttpTransport::from_config(&settings, pool_size)?, classifier));
+        }
+        Ok(Self { providers, on_hit: Box::new(on_hit) })
+    }
+}
+
+impl Transport for EverywhereTransport {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        // Errors become strings to cross back from the threads
+        let mut answers: Vec<Result<(u16, String), String>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+            .providers
+            .iter()
+            .map(|(_, transport, _)| scope.spawn(move || transport.check(vehicle_no, date_str).map_err(|e| e.to_string())))
+            .collect();
+            handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|_| Err("lookup thread panicked".to_string())))
+            .collect()
+        });
+
+        let hit = answers.iter().zip(&self.providers).position(|(answer, (_, _, classifier))| {
+            answer.as_ref().is_ok_and(|(status, body)| classifier.classify(*status, body).0 == Classification::Hit)
+        });
+        let index = match hit {
+            Some(index) => {
+                (self.on_hit)(self.providers[index].0, vehicle_no, date_str);
+                index
+            }
+            None => 0,
+        };
+        answers.swap_remove(index).map_err(Into::into)
+    }
+
+    fn bootstrap(&self, url: &str) -> TransportResult<u16> {
+        self.providers[0].1.bootstrap(url)
+    }
+
+    fn request_size(&self, vehicle_no: &str, date_str: &str) -> u64 {
+        self.providers.iter().map(|(_, transport, _)| transport.request_size(vehicle_no, date_str)).sum()
+    }
+
+    fn report_outcome(&self, failed: bool) -> Option<(String, String)> {
+        self.providers[0].1.report_outcome(failed)
+    }
+
+    fn can_fail_over(&self) -> bool {
+        self.providers[0].1.can_fail_over()
+    }
+}
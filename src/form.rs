@@ -0,0 +1,118 @@
+use crate::provider::{Encoding, Provider};
+use crate::search::SearchBy;
+use serde::{Deserialize, Serialize};
+
+// The lookup form each provider's endpoint takes, shared by the blocking
+// backend and the browser bindings
+pub const BOUNDARY: &str = "wL36Yn8afVp8Ag7AmP8qZ0SA4n1v9T";
+
+pub fn content_type() -> String {
+    format!("multipart/form-data; boundary={}", BOUNDARY)
+}
+
+// The Islamabad form
+pub fn multipart_body(vehicle_no: &str, date_str: &str) -> Vec<u8> {
+    RequestTemplate::from_provider(Provider::Islamabad, SearchBy::Plate).render(vehicle_no, date_str).1
+}
+
+// The form as data, so a renamed field can be fixed in Settings instead of
+// with a new build. Values can use {vehicle}, {date} (YYYY-MM-DD), {day},
+// {month} and {year}; a field without placeholders is sent as it is.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestTemplate {
+    // Off sends the provider's built-in form
+    pub enabled: bool,
+    pub encoding: Encoding,
+    // Multipart only
+    pub boundary: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl Default for RequestTemplate {
+    fn default() -> Self {
+        Self::from_provider(Provider::Islamabad, SearchBy::Plate)
+    }
+}
+
+impl RequestTemplate {
+    // The provider's built-in form, switched off. A provider without a field
+    // for `by` gets the number in its plate field.
+    pub fn from_provider(provider: Provider, by: SearchBy) -> Self {
+        let number_field = provider.field(by).or(provider.field(SearchBy::Plate)).unwrap_or_default();
+        Self {
+            enabled: false,
+            encoding: provider.encoding(),
+            boundary: BOUNDARY.to_string(),
+            fields: vec![
+                (number_field.to_string(), "{vehicle}".to_string()),
+                (provider.date_field().to_string(), provider.date_value().to_string()),
+            ],
+        }
+    }
+
+    // Content type and body for one lookup
+    pub fn render(&self, number: &str, date_str: &str) -> (String, Vec<u8>) {
+        let (year, month, day) = match date_str.split('-').collect::<Vec<_>>()[..] {
+            [year, month, day] => (year, month, day),
+            _ => ("", "", ""),
+        };
+        let fields: Vec<(&str, String)> = self
+        .fields
+        .iter()
+        .filter(|(name, _)| !name.trim().is_empty())
+        .map(|(name, value)| {
+            let value = value
+            .replace("{vehicle}", number)
+            .replace("{date}", date_str)
+            .replace("{day}", day)
+            .replace("{month}", month)
+            .replace("{year}", year);
+            (name.trim(), value)
+        })
+        .collect();
+
+        match self.encoding {
+            Encoding::Multipart => {
+                let boundary = if self.boundary.trim().is_empty() { BOUNDARY } else { self.boundary.trim() };
+                (format!("multipart/form-data; boundary={}", boundary), multipart(boundary, &fields))
+            }
+            Encoding::UrlEncoded => ("application/x-www-form-urlencoded".to_string(), urlencoded(&fields)),
+            Encoding::Json => {
+                let object: serde_json::Map<String, serde_json::Value> = fields.into_iter().map(|(name, value)| (name.to_string(), value.into())).collect();
+                ("application/json".to_string(), serde_json::Value::Object(object).to_string().into_bytes())
+            }
+        }
+    }
+}
+
+fn multipart(boundary: &str, fields: &[(&str, String)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(format!("Content-Disposition: form-data; name={};\r\n", name).as_bytes());
+        body.extend_from_slice(b"Content-Type: text/plain\r\n\r\n");
+        body.extend_from_slice(value.as_bytes());
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+fn urlencoded(fields: &[(&str, String)]) -> Vec<u8> {
+    let encode = |text: &str| {
+        text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect::<String>()
+    };
+    fields
+    .iter()
+    .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+    .collect::<Vec<_>>()
+    .join("&")
+    .into_bytes()
+}
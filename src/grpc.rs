@@ -0,0 +1,172 @@
+use crate::auth::{self, ApiToken, TokenScope};
+use crate::control::{self, ControlHandle, EngineEvent, ScanRequest};
+use crate::i18n::tr;
+use crate::{LogLevel, daemon};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio_stream::Stream;
+use tonic::service::Interceptor;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+
+pub mod pb {
+    tonic::include_proto!("vehreg");
+}
+
+use pb::vehicle_scanner_server::{VehicleScanner, VehicleScannerServer};
+
+struct ScannerService {
+    control: ControlHandle,
+}
+
+// What the caller's token allows, put on every request by `TokenCheck`
+#[derive(Clone, Copy)]
+struct Granted(TokenScope);
+
+// The same API tokens as server mode, sent as `authorization: Bearer <token>`
+// metadata. Every call needs at least a read-only token once any exist.
+#[derive(Clone)]
+struct TokenCheck {
+    tokens: Arc<Vec<ApiToken>>,
+}
+
+impl Interceptor for TokenCheck {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let presented = request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+        let scope = match auth::authorize(&self.tokens, presented, TokenScope::ReadOnly) {
+            Ok(Some(token)) => token.scope,
+            // No tokens configured, which only a loopback bind allows
+            Ok(None) => TokenScope::Submit,
+            Err(_) => return Err(Status::unauthenticated("missing or unknown token")),
+        };
+        request.extensions_mut().insert(Granted(scope));
+        Ok(request)
+    }
+}
+
+fn may_submit<T>(request: &Request<T>) -> bool {
+    request.extensions().get::<Granted>().is_some_and(|Granted(scope)| scope.allows(TokenScope::Submit))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.trim().is_empty() { None } else { Some(value) }
+}
+
+#[tonic::async_trait]
+impl VehicleScanner for ScannerService {
+    async fn start_scan(&self, request: Request<pb::StartScanRequest>) -> Result<Response<pb::StartScanReply>, Status> {
+        if !may_submit(&request) {
+            return Err(Status::permission_denied("token is read-only"));
+        }
+        let req = request.into_inner();
+        if req.vehicle_no.trim().is_empty() {
+            return Err(Status::invalid_argument("vehicle_no is required"));
+        }
+
+        let accepted = self.control.start(ScanRequest {
+            vehicle_no: req.vehicle_no,
+            start_date: non_empty(req.start_date),
+            end_date: non_empty(req.end_date),
+            threads: if req.threads == 0 { None } else { Some(req.threads as usize) },
+        });
+
+        let message = if accepted {
+            "Scan queued, follow StreamEvents for validation errors and progress"
+        } else {
+            "A scan is already running"
+        };
+        Ok(Response::new(pb::StartScanReply { accepted, message: message.to_string() }))
+    }
+
+    async fn stop_scan(&self, request: Request<pb::StopScanRequest>) -> Result<Response<pb::StopScanReply>, Status> {
+        if !may_submit(&request) {
+            return Err(Status::permission_denied("token is read-only"));
+        }
+        let was_running = self.control.is_running();
+        self.control.stop();
+        Ok(Response::new(pb::StopScanReply { was_running }))
+    }
+
+    type StreamEventsStream = Pin<Box<dyn Stream<Item = Result<pb::Event, Status>> + Send>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<pb::StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let mut after_seq = request.into_inner().after_seq;
+        // Subscribed before reading the backlog so nothing falls in between
+        let events = control::subscribe();
+        let backlog = self.control.events_since(after_seq);
+        let (tx, rx) = tokio::sync::mpsc::channel(256);
+
+        // The subscription is a blocking channel, so it gets its own thread
+        std::thread::spawn(move || {
+            let live = events.into_iter().filter_map(|event| match event {
+                EngineEvent::Log(entry) => Some(entry),
+                _ => None,
+            });
+            for entry in backlog.into_iter().chain(live) {
+                if entry.seq <= after_seq {
+                    continue;
+                }
+                after_seq = entry.seq;
+                let event = pb::Event {
+                    seq: entry.seq,
+                    timestamp: entry.timestamp,
+                    level: control::level_name(entry.level).to_string(),
+                    message: entry.message,
+                };
+                if tx.blocking_send(Ok(event)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx))))
+    }
+
+    async fn list_results(&self, _request: Request<pb::ListResultsRequest>) -> Result<Response<pb::ListResultsReply>, Status> {
+        let files = self
+        .control
+        .list_results()
+        .into_iter()
+        .map(|f| pb::ResultFile { name: f.name, size: f.size, modified: f.modified })
+        .collect();
+        Ok(Response::new(pb::ListResultsReply { files }))
+    }
+}
+
+// Runs the gRPC server on its own thread with a dedicated tokio runtime.
+// The port is bound before returning, so a bind error comes back here.
+pub fn spawn_server(
+    bind: &str,
+    control: ControlHandle,
+    tokens: Vec<ApiToken>,
+    log: impl Fn(String, LogLevel) + Send + 'static,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Without tokens anyone who can reach the port could start scans
+    if !daemon::is_loopback(bind) && !tokens.iter().any(|t| !t.token.is_empty()) {
+        return Err(tr!("refusing to listen on {} without API tokens; add one or bind to 127.0.0.1", bind).into());
+    }
+    let addr = bind.parse()?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let incoming = {
+        let _context = runtime.enter();
+        TcpIncoming::new(addr, true, None)?
+    };
+    let service = VehicleScannerServer::with_interceptor(ScannerService { control }, TokenCheck { tokens: Arc::new(tokens) });
+
+    std::thread::spawn(move || {
+        let served = runtime.block_on(tonic::transport::Server::builder().add_service(service).serve_with_incoming(incoming));
+        if let Err(e) = served {
+            log(tr!("gRPC server stopped - {}", e), LogLevel::Error);
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use eframe::egui::{self, Color32, FontId, text::LayoutJob};
+
+struct Palette {
+    text: Color32,
+    tag: Color32,
+    attribute: Color32,
+    string: Color32,
+    comment: Color32,
+}
+
+const DARK: Palette = Palette {
+    text: Color32::from_rgb(212, 212, 212),
+    tag: Color32::from_rgb(86, 156, 214),
+    attribute: Color32::from_rgb(156, 220, 254),
+    string: Color32::from_rgb(206, 145, 120),
+    comment: Color32::from_rgb(106, 153, 85),
+};
+
+const LIGHT: Palette = Palette {
+    text: Color32::from_rgb(30, 30, 30),
+    tag: Color32::from_rgb(128, 0, 0),
+    attribute: Color32::from_rgb(200, 0, 0),
+    string: Color32::from_rgb(0, 0, 200),
+    comment: Color32::from_rgb(0, 128, 0),
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Text,
+    Comment,
+    TagName,
+    Attribute,
+    Quoted(char),
+}
+
+// Colours the raw HTML of a saved page: tags, attribute names, quoted values
+// and comments. Good enough for reading server pages, not a real parser.
+pub fn html(source: &str, dark_mode: bool) -> LayoutJob {
+    let palette = if dark_mode { &DARK } else { &LIGHT };
+    let font = FontId::monospace(12.0);
+    let mut job = LayoutJob::default();
+    let mut state = State::Text;
+    let mut run = String::new();
+    let mut run_color = palette.text;
+
+    let flush = |job: &mut LayoutJob, run: &mut String, color: Color32| {
+        if !run.is_empty() {
+            job.append(run, 0.0, egui::TextFormat::simple(font.clone(), color));
+            run.clear();
+        }
+    };
+
+    for (i, c) in source.char_indices() {
+        // Closing characters are coloured with the span they close
+        let (next_state, color) = match state {
+            State::Text if c == '<' && source[i..].starts_with("<!--") => (State::Comment, palette.comment),
+            State::Text if c == '<' => (State::TagName, palette.tag),
+            State::Text => (State::Text, palette.text),
+            State::Comment if c == '>' && source[..i].ends_with("--") => (State::Text, palette.comment),
+            State::Comment => (State::Comment, palette.comment),
+            State::TagName | State::Attribute if c == '>' => (State::Text, palette.tag),
+            State::TagName if c.is_whitespace() => (State::Attribute, palette.attribute),
+            State::TagName => (State::TagName, palette.tag),
+            State::Attribute if c == '"' || c == '\'' => (State::Quoted(c), palette.string),
+            State::Attribute if c == '=' || c == '/' => (State::Attribute, palette.tag),
+            State::Attribute => (State::Attribute, palette.attribute),
+            State::Quoted(quote) if c == quote => (State::Attribute, palette.string),
+            State::Quoted(_) => (state, palette.string),
+        };
+        if color != run_color {
+            flush(&mut job, &mut run, run_color);
+            run_color = color;
+        }
+        run.push(c);
+        state = next_state;
+    }
+    flush(&mut job, &mut run, run_color);
+    job
+}
@@ -0,0 +1,107 @@
+use crate::control::{self, EngineEvent};
+use crate::i18n::tr;
+use crate::record;
+use crate::{HitRecord, LogLevel};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A command still running after this is killed, so one that hangs can't
+// hold up every hit after it
+const TIMEOUT_SECS: u64 = 60;
+// How much of what it prints to stderr is kept for the log; the rest is
+// read and dropped so it can't fill the pipe and stall
+const MAX_STDERR: u64 = 4096;
+
+// Runs `command` through the shell for every hit, one at a time, with the
+// hit in environment variables rather than spliced into the command line, so
+// an odd plate or field value can't change what runs:
+//
+//   VEH_REG_VEHICLE   the vehicle number
+//   VEH_REG_DATE      the registration date, YYYY-MM-DD, only the year
+//                     when redaction is on
+//   VEH_REG_RESULT    full path of the saved response, empty if not saved
+//   VEH_REG_FOUND_AT  when it was found
+//   VEH_REG_FIELDS    the record's fields as a JSON object, personal ones
+//                     masked when redaction is on
+//
+// `results_dir` is asked on every hit, as the GUI can switch directories.
+pub fn spawn_runner(
+    command: &str,
+    results_dir: impl Fn() -> PathBuf + Send + 'static,
+    log: impl Fn(String, LogLevel) + Send + 'static,
+) {
+    let command = command.trim().to_string();
+    control::on_event(move |event| {
+        let EngineEvent::Hit(hit) = event else { return };
+        let result = hit
+        .file_name
+        .as_ref()
+        .map(|name| {
+            let path = results_dir().join(name);
+            std::path::absolute(&path).unwrap_or(path)
+        })
+        .unwrap_or_default();
+        match run(&command, hit, &result) {
+            Ok(()) => log(tr!("Found command ran for {} on {}", hit.vehicle_no, record::shareable_date(&hit.date)), LogLevel::Debug),
+            Err(e) => log(tr!("Found command failed for {} on {} - {}", hit.vehicle_no, record::shareable_date(&hit.date), e), LogLevel::Warning),
+        }
+    });
+}
+
+fn run(command: &str, hit: &HitRecord, result: &Path) -> Result<(), String> {
+    let fields: serde_json::Map<String, serde_json::Value> = record::shareable_fields(&hit.fields).into_iter().map(|(label, value)| (label, value.into())).collect();
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    };
+    let mut child = shell
+    .env("VEH_REG_VEHICLE", &hit.vehicle_no)
+    .env("VEH_REG_DATE", record::shareable_date(&hit.date))
+    .env("VEH_REG_RESULT", result)
+    .env("VEH_REG_FOUND_AT", &hit.found_at)
+    .env("VEH_REG_FIELDS", serde_json::Value::Object(fields).to_string())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| e.to_string())?;
+
+    let (stderr_tx, stderr_rx) = mpsc::channel();
+    if let Some(mut stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            let mut kept = Vec::new();
+            let _ = (&mut stderr).take(MAX_STDERR).read_to_end(&mut kept);
+            let _ = stderr_tx.send(String::from_utf8_lossy(&kept).trim().to_string());
+            let _ = std::io::copy(&mut stderr, &mut std::io::sink());
+        });
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(TIMEOUT_SECS);
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(tr!("still running after {} seconds, stopped", TIMEOUT_SECS));
+            }
+            None => thread::sleep(Duration::from_millis(50)),
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    // Something the command left running in the background may hold stderr
+    // open, so what has been printed by now is all that is waited for
+    let stderr = stderr_rx.recv_timeout(Duration::from_secs(1)).unwrap_or_default();
+    Err(format!("{}: {}", status, stderr))
+}
@@ -0,0 +1,209 @@
+use crate::config::AppConfig;
+use crate::curl;
+use crate::form::RequestTemplate;
+use crate::record;
+use crate::storage;
+use crate::transport::{Transport, TransportResult};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Consecutive failures on one endpoint before moving to the next
+const FAILOVER_AFTER: usize = 5;
+
+struct Failover {
+    active: usize,
+    failures: usize,
+    // Each fallback is tried once; after the last one failures surface as usual
+    switches: usize,
+}
+
+// Talks to the real endpoint. Built once per run and shared by every worker,
+// so they all use the same cookie jar and keep-alive connection pool.
+pub struct HttpTransport {
+    // Primary first, then the fallbacks in order
+    endpoints: Vec<String>,
+    // The settings' custom template, or the provider's built-in form
+    template: RequestTemplate,
+    failover: Mutex<Failover>,
+    client: reqwest::blocking::Client,
+    extra_headers: Vec<(String, String)>,
+    // Debug transcript of every request and response, when enabled
+    transcript: Option<Mutex<File>>,
+}
+
+impl HttpTransport {
+    pub fn from_config(config: &AppConfig, pool_size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        // Zero leaves a timeout off
+        let secs = |s: u64| (s > 0).then(|| Duration::from_secs(s));
+        let mut builder = reqwest::blocking::Client::builder()
+        .timeout(secs(config.request_timeout_secs))
+        .pool_max_idle_per_host(pool_size)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+        .cookie_store(config.cookies_enabled)
+        // Says plainly which program is asking; extra headers can't replace it
+        .user_agent(format!("ISL-Veh-Reg-Hacking/{}", env!("CARGO_PKG_VERSION")));
+        if let Some(timeout) = secs(config.connect_timeout_secs) {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        let ca_path = config.ca_cert_path.trim();
+        if !ca_path.is_empty() {
+            let pem = fs::read(ca_path).map_err(|e| format!("cannot read CA certificate {} - {}", ca_path, e))?;
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+        if config.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        if !config.request_template.enabled && !config.provider.supports(config.search_by) {
+            return Err(format!("{} can't be searched by {}", config.provider.label(), config.search_by.label().to_lowercase()).into());
+        }
+
+        let endpoints = std::iter::once(&config.endpoint_url)
+        .chain(&config.fallback_endpoints)
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+
+        // Appended to as the run goes, so it can't be sealed like the other
+        // files; with the results encrypted it isn't written at all
+        let transcript = if config.transcript_enabled && !storage::sealing() {
+            let path = transcript_path(config);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let file = File::create(&path).map_err(|e| format!("cannot create transcript {} - {}", path.display(), e))?;
+            Some(Mutex::new(file))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            endpoints,
+            template: if config.request_template.enabled {
+                config.request_template.clone()
+            } else {
+                RequestTemplate::from_provider(config.provider, config.search_by)
+            },
+            transcript,
+            failover: Mutex::new(Failover { active: 0, failures: 0, switches: 0 }),
+            client: builder.build()?,
+            extra_headers: config
+            .extra_headers
+            .iter()
+            .filter(|(name, _)| !name.trim().is_empty() && !curl::is_browser_header(name))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect(),
+        })
+    }
+
+    fn endpoint(&self) -> String {
+        let active = self.failover.lock().map(|f| f.active).unwrap_or(0);
+        self.endpoints.get(active).cloned().unwrap_or_default()
+    }
+
+    fn apply(&self, mut request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        for (name, value) in &self.extra_headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+}
+
+// <results>/transcripts/transcript_<timestamp>.log, one file per run
+fn transcript_path(config: &AppConfig) -> PathBuf {
+    PathBuf::from(config.results_dir.trim())
+    .join(TRANSCRIPT_DIR)
+    .join(format!("transcript_{}.log", chrono::Local::now().format("%Y%m%d_%H%M%S")))
+}
+
+pub const TRANSCRIPT_DIR: &str = "transcripts";
+
+fn write_headers(out: &mut String, headers: &reqwest::header::HeaderMap) {
+    for (name, value) in headers {
+        out.push_str(&format!("{}: {}\n", name, String::from_utf8_lossy(value.as_bytes())));
+    }
+}
+
+impl Transport for HttpTransport {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        let (content_type, body) = self.template.render(vehicle_no, date_str);
+
+        let request = self.client
+        .post(self.endpoint())
+        .header("Content-Type", content_type)
+        .body(body.clone());
+        let request = self.apply(request).build()?;
+
+        let Some(transcript) = &self.transcript else {
+            let response = self.client.execute(request)?;
+            let status = response.status().as_u16();
+            return Ok((status, response.text()?));
+        };
+
+        // Cookies from the jar are added by the client while sending, so
+        // they don't show up in the request headers here
+        let mut entry = format!("===== {} REQUEST\n{} {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"), request.method(), request.url());
+        write_headers(&mut entry, request.headers());
+        entry.push('\n');
+        entry.push_str(&String::from_utf8_lossy(&body));
+        entry.push('\n');
+
+        let result = self.client.execute(request).and_then(|response| {
+            let status = response.status();
+            let headers = response.headers().clone();
+            response.text().map(|text| (status, headers, text))
+        });
+        entry.push_str(&format!("===== {} RESPONSE\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f")));
+        match &result {
+            Ok((status, headers, text)) => {
+                entry.push_str(&format!("HTTP {}\n", status));
+                write_headers(&mut entry, headers);
+                entry.push('\n');
+                entry.push_str(&record::redact_body(text));
+                entry.push_str("\n\n");
+            }
+            Err(e) => entry.push_str(&format!("ERROR {}\n\n", e)),
+        }
+        if let Ok(mut file) = transcript.lock() {
+            let _ = file.write_all(entry.as_bytes());
+        }
+
+        let (status, _, text) = result?;
+        Ok((status.as_u16(), text))
+    }
+
+    fn bootstrap(&self, url: &str) -> TransportResult<u16> {
+        let response = self.apply(self.client.get(url)).send()?;
+        Ok(response.status().as_u16())
+    }
+
+    fn request_size(&self, vehicle_no: &str, date_str: &str) -> u64 {
+        self.template.render(vehicle_no, date_str).1.len() as u64
+    }
+
+    fn report_outcome(&self, failed: bool) -> Option<(String, String)> {
+        let mut failover = self.failover.lock().ok()?;
+        if !failed {
+            failover.failures = 0;
+            return None;
+        }
+        failover.failures += 1;
+        if failover.failures < FAILOVER_AFTER || failover.switches + 1 >= self.endpoints.len() {
+            return None;
+        }
+        let from = self.endpoints[failover.active].clone();
+        failover.active = (failover.active + 1) % self.endpoints.len();
+        failover.failures = 0;
+        failover.switches += 1;
+        Some((from, self.endpoints[failover.active].clone()))
+    }
+
+    fn can_fail_over(&self) -> bool {
+        self.failover.lock().is_ok_and(|f| f.switches + 1 < self.endpoints.len())
+    }
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Catalogs are keyed by the English text, one "English<TAB>translation" pair
+// per line, so anything missing from a catalog simply stays in English.
+const URDU_CATALOG: &str = include_str!("../locales/ur.tsv");
+
+// Fonts tried when no font is configured. egui's built-in fonts have no
+// Arabic-script glyphs.
+const URDU_FONT_CANDIDATES: [&str; 6] = [
+    "C:\\Windows\\Fonts\\tahoma.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/freefont/FreeSerif.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    Urdu,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Urdu];
+
+    // In the language itself, so it can be found by someone who can't read the current one
+    pub fn label(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Urdu => "اردو",
+        }
+    }
+}
+
+// Process-wide so worker threads translate their log messages too
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Language::Urdu,
+        _ => Language::English,
+    }
+}
+
+fn urdu_catalog() -> &'static HashMap<&'static str, &'static str> {
+    static CATALOG: OnceLock<HashMap<&'static str, &'static str>> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        URDU_CATALOG
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('\t'))
+        .collect()
+    })
+}
+
+pub fn translate(key: &str) -> String {
+    match language() {
+        Language::English => key.to_string(),
+        Language::Urdu => urdu_catalog().get(key).copied().unwrap_or(key).to_string(),
+    }
+}
+
+// Fills `{}` placeholders in order, or `{0}`, `{1}`... by position so a
+// translation can put the arguments in a different order
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            out.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+
+        let spec = &after[..close];
+        let index = if spec.is_empty() {
+            next += 1;
+            Some(next - 1)
+        } else {
+            spec.parse::<usize>().ok()
+        };
+        match index.and_then(|i| args.get(i)) {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str(&rest[open..open + close + 2]),
+        }
+        rest = &after[close + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+// Like format!, but the template is looked up in the current language first.
+// Only plain `{}` placeholders are supported.
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::translate($key)
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill(&$crate::i18n::translate($key), &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+pub use crate::tr;
+
+// A translated string ready to hand to egui
+pub fn label(key: &str) -> String {
+    display(&translate(key))
+}
+
+// egui lays text out left to right with one glyph per character and does no
+// shaping, so Arabic-script text is prepared here: letters are replaced by
+// their joined presentation forms and right-to-left runs are reversed.
+// Text without Arabic script is returned unchanged.
+pub fn display(text: &str) -> String {
+    if !text.chars().any(is_rtl) {
+        return text.to_string();
+    }
+    text.lines().map(|line| reorder(&shape(line))).collect::<Vec<_>>().join("\n")
+}
+
+pub fn install_fonts(ctx: &egui::Context, font_path: &str) -> Result<String, String> {
+    let configured = font_path.trim();
+    let (path, bytes) = if !configured.is_empty() {
+        let bytes = fs::read(configured).map_err(|e| format!("cannot read font {} - {}", configured, e))?;
+        (configured.to_string(), bytes)
+    } else {
+        URDU_FONT_CANDIDATES
+        .iter()
+        .find_map(|path| fs::read(path).ok().map(|bytes| (path.to_string(), bytes)))
+        .ok_or("no font with Urdu glyphs found, set one in the language settings")?
+    };
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert("urdu".to_owned(), egui::FontData::from_owned(bytes));
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("urdu".to_owned());
+    }
+    ctx.set_fonts(fonts);
+    Ok(path)
+}
+
+fn is_rtl(c: char) -> bool {
+    matches!(c as u32, 0x0600..=0x06FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+fn is_mark(c: char) -> bool {
+    matches!(c as u32, 0x064B..=0x065F | 0x0670)
+}
+
+// Isolated, final, initial and medial presentation forms. Letters that only
+// join to the preceding letter have no initial/medial forms (0).
+fn forms(c: char) -> Option<[u32; 4]> {
+    let forms = match c as u32 {
+        0x0622 => [0xFE81, 0xFE82, 0, 0],
+        0x0623 => [0xFE83, 0xFE84, 0, 0],
+        0x0624 => [0xFE85, 0xFE86, 0, 0],
+        0x0625 => [0xFE87, 0xFE88, 0, 0],
+        0x0626 => [0xFE89, 0xFE8A, 0xFE8B, 0xFE8C],
+        0x0627 => [0xFE8D, 0xFE8E, 0, 0],
+        0x0628 => [0xFE8F, 0xFE90, 0xFE91, 0xFE92],
+        0x062A => [0xFE95, 0xFE96, 0xFE97, 0xFE98],
+        0x062B => [0xFE99, 0xFE9A, 0xFE9B, 0xFE9C],
+        0x062C => [0xFE9D, 0xFE9E, 0xFE9F, 0xFEA0],
+        0x062D => [0xFEA1, 0xFEA2, 0xFEA3, 0xFEA4],
+        0x062E => [0xFEA5, 0xFEA6, 0xFEA7, 0xFEA8],
+        0x062F => [0xFEA9, 0xFEAA, 0, 0],
+        0x0630 => [0xFEAB, 0xFEAC, 0, 0],
+        0x0631 => [0xFEAD, 0xFEAE, 0, 0],
+        0x0632 => [0xFEAF, 0xFEB0, 0, 0],
+        0x0633 => [0xFEB1, 0xFEB2, 0xFEB3, 0xFEB4],
+        0x0634 => [0xFEB5, 0xFEB6, 0xFEB7, 0xFEB8],
+        0x0635 => [0xFEB9, 0xFEBA, 0xFEBB, 0xFEBC],
+        0x0636 => [0xFEBD, 0xFEBE, 0xFEBF, 0xFEC0],
+        0x0637 => [0xFEC1, 0xFEC2, 0xFEC3, 0xFEC4],
+        0x0638 => [0xFEC5, 0xFEC6, 0xFEC7, 0xFEC8],
+        0x0639 => [0xFEC9, 0xFECA, 0xFECB, 0xFECC],
+        0x063A => [0xFECD, 0xFECE, 0xFECF, 0xFED0],
+        0x0641 => [0xFED1, 0xFED2, 0xFED3, 0xFED4],
+        0x0642 => [0xFED5, 0xFED6, 0xFED7, 0xFED8],
+        0x0644 => [0xFEDD, 0xFEDE, 0xFEDF, 0xFEE0],
+        0x0645 => [0xFEE1, 0xFEE2, 0xFEE3, 0xFEE4],
+        0x0646 => [0xFEE5, 0xFEE6, 0xFEE7, 0xFEE8],
+        0x0648 => [0xFEED, 0xFEEE, 0, 0],
+        0x064A => [0xFEF1, 0xFEF2, 0xFEF3, 0xFEF4],
+        0x0679 => [0xFB66, 0xFB67, 0xFB68, 0xFB69],
+        0x067E => [0xFB56, 0xFB57, 0xFB58, 0xFB59],
+        0x0686 => [0xFB7A, 0xFB7B, 0xFB7C, 0xFB7D],
+        0x0688 => [0xFB88, 0xFB89, 0, 0],
+        0x0691 => [0xFB8C, 0xFB8D, 0, 0],
+        0x0698 => [0xFB8A, 0xFB8B, 0, 0],
+        0x06A9 => [0xFB8E, 0xFB8F, 0xFB90, 0xFB91],
+        0x06AF => [0xFB92, 0xFB93, 0xFB94, 0xFB95],
+        // Noon ghunna has no joined forms of its own; mid-word it looks like noon
+        0x06BA => [0xFB9E, 0xFB9F, 0xFEE7, 0xFEE8],
+        0x06BE => [0xFBAA, 0xFBAB, 0xFBAC, 0xFBAD],
+        0x06C1 => [0xFBA6, 0xFBA7, 0xFBA8, 0xFBA9],
+        0x06CC => [0xFBFC, 0xFBFD, 0xFBFE, 0xFBFF],
+        0x06D2 => [0xFBAE, 0xFBAF, 0, 0],
+        0x06D3 => [0xFBB0, 0xFBB1, 0, 0],
+        _ => return None,
+    };
+    Some(forms)
+}
+
+fn joins_next(c: char) -> bool {
+    forms(c).is_some_and(|f| f[2] != 0)
+}
+
+// Isolated and final forms of lam followed by an alef
+fn lam_alef(alef: char) -> Option<[u32; 2]> {
+    match alef as u32 {
+        0x0622 => Some([0xFEF5, 0xFEF6]),
+        0x0623 => Some([0xFEF7, 0xFEF8]),
+        0x0625 => Some([0xFEF9, 0xFEFA]),
+        0x0627 => Some([0xFEFB, 0xFEFC]),
+        _ => None,
+    }
+}
+
+fn shape(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    // Nearest letter before/after position i, skipping vowel marks
+    let letter_before = |i: usize| chars[..i].iter().rev().find(|c| !is_mark(**c)).copied();
+    let letter_after = |i: usize| chars[i + 1..].iter().find(|c| !is_mark(**c)).copied();
+
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let Some(f) = forms(c) else {
+            out.push(c);
+            i += 1;
+            continue;
+        };
+
+        let joined_before = letter_before(i).is_some_and(joins_next);
+
+        if let Some(lig) = chars.get(i + 1).filter(|_| c == '\u{0644}').and_then(|next| lam_alef(*next)) {
+            out.extend(char::from_u32(lig[joined_before as usize]));
+            i += 2;
+            continue;
+        }
+
+        let joined_after = joins_next(c) && letter_after(i).is_some_and(|next| forms(next).is_some());
+        let form = match (joined_before, joined_after) {
+            (false, false) => f[0],
+            (true, false) => f[1],
+            (false, true) => f[2],
+            (true, true) => f[3],
+        };
+        out.extend(char::from_u32(form));
+        i += 1;
+    }
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Ltr,
+    Rtl,
+    Neutral,
+}
+
+fn direction(c: char) -> Direction {
+    if is_rtl(c) {
+        Direction::Rtl
+    } else if c.is_alphanumeric() {
+        Direction::Ltr
+    } else {
+        Direction::Neutral
+    }
+}
+
+fn mirror(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '<' => '>',
+        '>' => '<',
+        _ => c,
+    }
+}
+
+// Visual order for a right-to-left line: runs are laid out from the right, and
+// within a run only the right-to-left ones have their characters reversed, so
+// embedded numbers, dates and plate numbers still read normally.
+fn reorder(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let strong: Vec<Direction> = chars.iter().map(|c| direction(*c)).collect();
+
+    // Neutrals between two left-to-right characters stay with them, anything
+    // else follows the right-to-left base direction
+    let resolved: Vec<Direction> = (0..chars.len())
+    .map(|i| {
+        if strong[i] != Direction::Neutral {
+            return strong[i];
+        }
+        let before = strong[..i].iter().rev().find(|d| **d != Direction::Neutral);
+        let after = strong[i + 1..].iter().find(|d| **d != Direction::Neutral);
+        if before == Some(&Direction::Ltr) && after == Some(&Direction::Ltr) {
+            Direction::Ltr
+        } else {
+            Direction::Rtl
+        }
+    })
+    .collect();
+
+    let mut runs: Vec<(Direction, Vec<char>)> = Vec::new();
+    for (c, dir) in chars.into_iter().zip(resolved) {
+        match runs.last_mut() {
+            Some((last, run)) if *last == dir => run.push(c),
+            _ => runs.push((dir, vec![c])),
+        }
+    }
+
+    runs.into_iter()
+    .rev()
+    .flat_map(|(dir, run)| {
+        if dir == Direction::Rtl {
+            run.into_iter().rev().map(mirror).collect::<Vec<_>>()
+        } else {
+            run
+        }
+    })
+    .collect()
+}
@@ -0,0 +1,154 @@
+use crate::db;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+use std::time::Duration;
+
+// Jobs submitted to the daemon over its API, kept in SQLite next to the
+// results so a restart picks the queue up where it was. Encrypted with
+// results.db while the results are.
+const DB_FILE: &str = "daemon.db";
+
+pub struct JobStore {
+    conn: Connection,
+}
+
+// What a client submits
+pub struct NewJob {
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub threads: usize,
+    pub max_hits: u32,
+}
+
+pub struct StoredJob {
+    pub id: i64,
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub threads: usize,
+    pub max_hits: u32,
+    // queued, running, done, partial (stopped before the whole range was
+    // checked) or failed
+    pub status: String,
+    pub submitted_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub hits: u32,
+    pub error: Option<String>,
+}
+
+const COLUMNS: &str = "id, vehicle_no, start_date, end_date, threads, max_hits, status, submitted_at, started_at, finished_at, hits, error";
+
+fn now() -> String {
+    chrono::Local::now().to_rfc3339()
+}
+
+fn row_to_job(row: &rusqlite::Row) -> rusqlite::Result<StoredJob> {
+    Ok(StoredJob {
+        id: row.get(0)?,
+        vehicle_no: row.get(1)?,
+        start_date: row.get(2)?,
+        end_date: row.get(3)?,
+        threads: row.get::<_, i64>(4)? as usize,
+        max_hits: row.get(5)?,
+        status: row.get(6)?,
+        submitted_at: row.get(7)?,
+        started_at: row.get(8)?,
+        finished_at: row.get(9)?,
+        hits: row.get(10)?,
+        error: row.get(11)?,
+    })
+}
+
+impl JobStore {
+    // The daemon's loop and each API request open their own connection. A
+    // queue from before encryption was turned on is encrypted first.
+    pub fn open(results_dir: &Path) -> rusqlite::Result<Self> {
+        let path = results_dir.join(DB_FILE);
+        db::encrypt(&path)?;
+        let conn = db::open_keyed(&path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                vehicle_no TEXT NOT NULL,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL,
+                threads INTEGER NOT NULL,
+                max_hits INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                submitted_at TEXT NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                hits INTEGER NOT NULL DEFAULT 0,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS jobs_status ON jobs(status);",
+        )?;
+        Ok(Self { conn })
+    }
+
+    // Jobs that were running when the daemon stopped go back to the front of
+    // the queue; returns how many
+    pub fn requeue_interrupted(&self) -> rusqlite::Result<usize> {
+        self.conn.execute("UPDATE jobs SET status = 'queued', started_at = NULL WHERE status = 'running'", [])
+    }
+
+    pub fn submit(&self, job: &NewJob) -> rusqlite::Result<i64> {
+        self.conn.execute(
+            "INSERT INTO jobs (vehicle_no, start_date, end_date, threads, max_hits, status, submitted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'queued', ?6)",
+            params![job.vehicle_no, job.start_date, job.end_date, job.threads as i64, job.max_hits, now()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn has_queued(&self) -> rusqlite::Result<bool> {
+        self.conn.query_row("SELECT EXISTS(SELECT 1 FROM jobs WHERE status = 'queued')", [], |row| row.get(0))
+    }
+
+    // Oldest first, and marks it running
+    pub fn take_next(&self) -> rusqlite::Result<Option<StoredJob>> {
+        let job = self
+        .conn
+        .query_row(&format!("SELECT {} FROM jobs WHERE status = 'queued' ORDER BY id LIMIT 1", COLUMNS), [], row_to_job)
+        .optional()?;
+        if let Some(job) = &job {
+            self.conn.execute("UPDATE jobs SET status = 'running', started_at = ?1 WHERE id = ?2", params![now(), job.id])?;
+        }
+        Ok(job)
+    }
+
+    pub fn finish(&self, id: i64, hits: u32) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET status = 'done', finished_at = ?1, hits = ?2 WHERE id = ?3",
+            params![now(), hits, id],
+        )?;
+        Ok(())
+    }
+
+    // Ran, but left part of the range unchecked
+    pub fn finish_partly(&self, id: i64, hits: u32, error: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET status = 'partial', finished_at = ?1, hits = ?2, error = ?3 WHERE id = ?4",
+            params![now(), hits, error, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn fail(&self, id: i64, error: &str) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET status = 'failed', finished_at = ?1, error = ?2 WHERE id = ?3",
+            params![now(), error, id],
+        )?;
+        Ok(())
+    }
+
+    // Newest first
+    pub fn recent(&self, limit: usize) -> rusqlite::Result<Vec<StoredJob>> {
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM jobs ORDER BY id DESC LIMIT ?1", COLUMNS))?;
+        let rows = stmt.query_map(params![limit as i64], row_to_job)?;
+        rows.collect()
+    }
+}
@@ -0,0 +1,38 @@
+// The scan engine behind the app: talking to the endpoint, classifying the
+// answers and splitting a date range across threads. `client::VehicleClient`
+// is the entry point for other Rust programs; the GUI lives in main.rs.
+// On wasm32 only the pieces without threads or blocking I/O are built,
+// for the browser bindings in wasm/.
+
+pub mod auth;
+pub mod classify;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod concurrency;
+pub mod config;
+pub mod coverage;
+pub mod curl;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod everywhere;
+pub mod form;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http;
+pub mod i18n;
+pub mod logging;
+pub mod ordering;
+pub mod partition;
+pub mod plate;
+pub mod provider;
+pub mod record;
+pub mod search;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod stats;
+pub mod stop;
+pub mod storage;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod throttle;
+pub mod transport;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::{CheckOutcome, ScanControl, ScanEvent, ScanHandle, ScanOptions, VehicleClient};
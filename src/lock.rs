@@ -0,0 +1,147 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Lock files live under <results>/locks/, one per running app and one per
+// running scan. The holder touches its file every few seconds; a file nobody
+// has touched for STALE_SECS belongs to a copy that crashed or was killed.
+// Run locks name a vehicle and its dates, so they are sealed like the
+// results while those are encrypted.
+const LOCK_DIR: &str = "locks";
+const HEARTBEAT_SECS: u64 = 5;
+const STALE_SECS: u64 = 30;
+
+// What a lock file says about its holder. Instance locks leave the run
+// fields empty.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    // The run's id in its saved run state
+    pub run_id: String,
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub started_at: String,
+}
+
+impl LockInfo {
+    fn overlaps(&self, other: &LockInfo) -> bool {
+        // Dates are YYYY-MM-DD, so they compare as strings
+        self.vehicle_no == other.vehicle_no && self.start_date <= other.end_date && other.start_date <= self.end_date
+    }
+}
+
+// Held for as long as the value lives; dropping it removes the file
+pub struct LockFile {
+    path: PathBuf,
+    alive: Arc<AtomicBool>,
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::SeqCst);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn is_fresh(path: &Path) -> bool {
+    fs::metadata(path)
+    .and_then(|meta| meta.modified())
+    .ok()
+    .and_then(|modified| modified.elapsed().ok())
+    .is_some_and(|age| age.as_secs() < STALE_SECS)
+}
+
+// Live locks with the given prefix, ours included. Stale ones are removed
+// on the way.
+fn fresh_locks(results_dir: &Path, prefix: &str) -> Vec<LockInfo> {
+    let Ok(entries) = fs::read_dir(results_dir.join(LOCK_DIR)) else { return Vec::new() };
+    let mut locks = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !entry.file_name().to_string_lossy().starts_with(prefix) {
+            continue;
+        }
+        if !is_fresh(&path) {
+            let _ = fs::remove_file(&path);
+            continue;
+        }
+        let text = fs::read(&path).ok().and_then(|bytes| storage::unseal(&bytes).ok());
+        if let Some(info) = text.and_then(|text| serde_json::from_slice::<LockInfo>(&text).ok()) {
+            locks.push(info);
+        }
+    }
+    locks
+}
+
+// `name` is the file name without its extension and starts with the
+// prefix `fresh_locks` looks for. `contents` is the holder's LockInfo, as
+// JSON, sealed or not.
+fn hold(results_dir: &Path, name: &str, contents: &[u8]) -> io::Result<LockFile> {
+    let dir = results_dir.join(LOCK_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.lock", name));
+    // A stale file by this name is left from an earlier process with the
+    // same PID or run id. create_new fails on anything else already there.
+    if path.exists() && !is_fresh(&path) {
+        let _ = fs::remove_file(&path);
+    }
+    let mut file = File::options().write(true).create_new(true).open(&path)?;
+    file.write_all(contents)?;
+
+    let alive = Arc::new(AtomicBool::new(true));
+    let heartbeat_alive = Arc::clone(&alive);
+    let heartbeat_path = path.clone();
+    thread::spawn(move || {
+        while heartbeat_alive.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(HEARTBEAT_SECS));
+            if let Ok(file) = File::options().write(true).open(&heartbeat_path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        }
+    });
+
+    Ok(LockFile { path, alive })
+}
+
+// Another copy of the app using the same results folder, if any
+pub fn other_instance(results_dir: &Path) -> Option<LockInfo> {
+    let own = std::process::id();
+    fresh_locks(results_dir, "instance").into_iter().find(|info| info.pid != own)
+}
+
+pub fn hold_instance(results_dir: &Path, started_at: &str) -> io::Result<LockFile> {
+    let info = LockInfo { pid: std::process::id(), started_at: started_at.to_string(), ..Default::default() };
+    hold(results_dir, &format!("instance_{}", info.pid), &to_json(&info)?)
+}
+
+// Another scan, by this copy of the app or another one, that covers some of
+// the same dates of the same vehicle. Checked after taking our own lock, so
+// two scans starting at the same moment both see each other instead of both
+// missing it.
+pub fn conflicting_run(results_dir: &Path, run: &LockInfo) -> Option<LockInfo> {
+    fresh_locks(results_dir, "run").into_iter().find(|other| other.run_id != run.run_id && other.overlaps(run))
+}
+
+// Whether some copy of the app, this one included, still holds the lock of
+// the given run
+pub fn run_is_live(results_dir: &Path, run_id: &str) -> bool {
+    fresh_locks(results_dir, "run").iter().any(|lock| lock.run_id == run_id)
+}
+
+// One file per run, so two scans of the same process each hold their own.
+// Fails while the results are encrypted and locked.
+pub fn hold_run(results_dir: &Path, run: &LockInfo) -> io::Result<LockFile> {
+    hold(results_dir, &format!("run_{}", run.run_id), &storage::seal(&to_json(run)?)?)
+}
+
+fn to_json(info: &LockInfo) -> io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(info).map_err(io::Error::other)
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Process-wide so worker threads filter their messages too
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Info,
+    Success,
+    Error,
+    Warning,
+    // Per-date decisions: classifications, skipped and requeued dates
+    Debug,
+    // Per-request timings
+    Trace,
+}
+
+// How much of a scan reaches the console
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Verbosity {
+    // Hits and errors only
+    Quiet,
+    Normal,
+    Debug,
+    Trace,
+}
+
+impl Verbosity {
+    pub const ALL: [Verbosity; 4] = [Verbosity::Quiet, Verbosity::Normal, Verbosity::Debug, Verbosity::Trace];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "Quiet",
+            Verbosity::Normal => "Normal",
+            Verbosity::Debug => "Debug",
+            Verbosity::Trace => "Trace",
+        }
+    }
+
+    pub fn current() -> Verbosity {
+        match VERBOSITY.load(Ordering::Relaxed) {
+            0 => Verbosity::Quiet,
+            2 => Verbosity::Debug,
+            3 => Verbosity::Trace,
+            _ => Verbosity::Normal,
+        }
+    }
+
+    pub fn set(self) {
+        VERBOSITY.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn shows(&self, level: LogLevel) -> bool {
+        match level {
+            LogLevel::Success | LogLevel::Error => true,
+            LogLevel::Info | LogLevel::Warning => *self != Verbosity::Quiet,
+            LogLevel::Debug => matches!(self, Verbosity::Debug | Verbosity::Trace),
+            LogLevel::Trace => *self == Verbosity::Trace,
+        }
+    }
+}
@@ -1,379 +1,3892 @@
 use eframe::egui;
-use chrono::{NaiveDate, Duration};
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use serde::{Deserialize, Serialize};
+use chrono::NaiveDate;
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, AtomicU64, Ordering}};
+use std::sync::mpsc::{self, Receiver};
 use std::thread;
 use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::Instant;
+
+mod applock;
+mod audit;
+mod calendar;
+mod case;
+mod control;
+mod daemon;
+mod db;
+mod diff;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod highlight;
+mod hook;
+mod jobstore;
+mod lock;
+mod metrics;
+mod mqtt;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod palette;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod queue;
+mod recovery;
+mod replay;
+mod report;
+mod results;
+mod run;
+mod schedule;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod series;
+mod server;
+mod snapshot;
+mod summary;
+#[cfg(feature = "tray")]
+mod tray;
+mod update;
+mod vault;
+mod verify;
+
+use veh_reg::{auth, classify, client, concurrency, config, coverage, curl, everywhere, form, http, i18n, ordering, partition, plate, provider, record, search, stats, stop, storage, throttle, transport};
+use veh_reg::logging::{LogLevel, Verbosity};
+
+use auth::{ApiToken, TokenScope};
+use classify::{Classification, Classifier, ClassifierRule, ClassifyHook, MatchKind};
+use client::{CheckOutcome, ScanControl, ScanEvent, ScanOptions, VehicleClient};
+use concurrency::LimitChange;
+use config::AppConfig;
+use control::{ControlCommand, ControlHandle, EngineEvent, JobInfo};
+use coverage::CoverageMap;
+use everywhere::EverywhereTransport;
+use form::RequestTemplate;
+use http::HttpTransport;
+use i18n::{Language, tr};
+use ordering::SearchOrder;
+use provider::{Encoding, Provider};
+use record::{ExtractKind, ExtractionRule};
+use report::RunReport;
+use search::SearchBy;
+use stats::NetworkStats;
+use storage::SaveLocation;
+use transport::{FixtureTransport, Transport};
+
+// Monotonic id for log entries so remote streams can resume where they left off
+static LOG_SEQ: AtomicU64 = AtomicU64::new(0);
+
+// Portable mode only: the console is also appended to logs/<date>.log
+static LOG_FILE: std::sync::OnceLock<Mutex<fs::File>> = std::sync::OnceLock::new();
 
 #[derive(Clone)]
 struct LogEntry {
+    seq: u64,
     timestamp: String,
     message: String,
     level: LogLevel,
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum LogLevel {
-    Info,
-    Success,
-    Error,
-    Warning,
+#[derive(Clone)]
+struct HitRecord {
+    vehicle_no: String,
+    date: String,
+    file_name: Option<String>,
+    found_at: String,
+    fields: Vec<(String, String)>,
+    // Label of the provider or plugin that answered, empty when not known
+    provider: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Tab {
+    Scan,
+    Settings,
+    Rules,
+    Compare,
+    History,
+    Queue,
+}
+
+impl Tab {
+    const ALL: [Tab; 6] = [Tab::Scan, Tab::Settings, Tab::Rules, Tab::Compare, Tab::History, Tab::Queue];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Tab::Scan => "Scan",
+            Tab::Settings => "Settings",
+            Tab::Rules => "Rules",
+            Tab::Compare => "Compare",
+            Tab::History => "History",
+            Tab::Queue => "Queue",
+        }
+    }
+}
+
+// A saved response opened in the detail window
+struct ResultDetail {
+    path: String,
+    fields: Vec<(String, String)>,
+    raw: egui::text::LayoutJob,
+    text: String,
+    show_text: bool,
+}
+
+// The two saved responses a diff was made for, and whether it was text-only
+type CompareKey = (String, String, bool);
+
+// Span of the throughput chart
+const THROUGHPUT_WINDOW_SECS: u64 = 300;
+
+
+// How often the coverage calendar re-reads the results folder
+const CALENDAR_REFRESH_SECS: u64 = 2;
+
+// Rows loaded into the History tab at once
+const HISTORY_LIMIT: usize = 500;
+
+// Characters of a note shown in the Found Records table
+const NOTE_PREVIEW_CHARS: usize = 40;
+
+// What the request template preview is filled in with
+const TEMPLATE_PREVIEW_VEHICLE: &str = "ABC-123";
+const TEMPLATE_PREVIEW_DATE: &str = "2015-06-01";
+
+// One-click bundles of the scan knobs for people who don't want to tune each one
+#[derive(Clone, Copy)]
+enum Preset {
+    Polite,
+    Fast,
+}
+
+impl Preset {
+    const ALL: [Preset; 2] = [Preset::Polite, Preset::Fast];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Preset::Polite => "Polite",
+            Preset::Fast => "Fast",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Preset::Polite => "2 threads, 2 s between requests, backs off when errors rise",
+            Preset::Fast => "10 threads, no delay, fixed concurrency",
+        }
+    }
+}
+
+// Everything the command palette can do
+#[derive(Clone, Copy)]
+enum Action {
+    Start,
+    Stop,
+    ClearConsole,
+    ExportPdf,
+    ExportExcel,
+    ExportMarkdown,
+    VerifyCsv,
+    OpenResultsFolder,
+    ToggleTheme,
+    ApplyPreset(Preset),
+    SwitchTab(Tab),
+    ReplayResponses,
+    ImportResults,
+    CleanUpResults,
+    SaveSettings,
+    ShowShortcuts,
+}
+
+// Window layout restored on the next start. eframe keeps the window geometry
+// itself; this is the part it doesn't know about.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct UiState {
+    tab: Tab,
+    console_height: f32,
+    dark_mode: bool,
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            tab: Tab::Scan,
+            console_height: 400.0,
+            dark_mode: true,
+        }
+    }
 }
 
+// Listed in the help overlay; handled in handle_shortcuts
+const SHORTCUTS: [(&str, &str); 8] = [
+    ("Ctrl+P", "Command palette"),
+    ("Ctrl+Enter", "Start"),
+    ("Esc", "Stop"),
+    ("Ctrl+L", "Clear Console"),
+    ("Ctrl+F", "Focus the vehicle number"),
+    ("Ctrl+1 ... 6", "Switch tab"),
+    ("Ctrl+Tab", "Next tab"),
+    ("F1", "Show or hide this help"),
+];
+
 struct VehicleChecker {
     vehicle_no: String,
+    // A malformed plate the user was already warned about; starting again scans it anyway
+    plate_warned: Option<String>,
+    // Plate the provider was last detected for, and whether it was picked by
+    // hand since; a new plate detects again
+    provider_detected_for: String,
+    provider_manual: bool,
+    // Ask every province at once instead of only the selected provider
+    search_everywhere: bool,
+    vehicle_type: plate::VehicleType,
     start_date: String,
     end_date: String,
     num_threads: usize,
+    skip_checked: bool,
+    stop_conditions: stop::StopConditions,
+    adaptive_concurrency: bool,
+    // Per worker, between requests
+    request_delay_ms: u64,
+    search_order: SearchOrder,
+    series_csv_path: String,
+    series_mapping: HashMap<String, (NaiveDate, NaiveDate)>,
+    config: AppConfig,
+    // The loaded script's classify_response, for every classifier built
+    script_hook: Option<ClassifyHook>,
 
     is_running: Arc<AtomicBool>,
     record_found: Arc<AtomicBool>,
     logs: Arc<Mutex<Vec<LogEntry>>>,
+    hits: Arc<Mutex<Vec<HitRecord>>>,
     found_count: Arc<Mutex<usize>>,
     checked_dates: Arc<Mutex<usize>>,
     total_dates: Arc<Mutex<usize>>,
+    coverage: Arc<Mutex<CoverageMap>>,
+    // The current or last scan, for its pause state, concurrency and stop
+    // reason; set once the scan has passed its health check
+    scan: Arc<Mutex<Option<ScanControl>>>,
+    challenge_alerted: bool,
+    stats: Arc<NetworkStats>,
+    control: ControlHandle,
+    control_rx: Receiver<ControlCommand>,
 
+    tab: Tab,
+    console_height: f32,
+    dark_mode: bool,
+    show_shortcuts: bool,
+    // The text of the "Import cURL command" window while it is open
+    curl_import: Option<String>,
+    // Saved response the field extraction rules are previewed against
+    extraction_sample: Option<(PathBuf, String)>,
+    palette_open: bool,
+    palette_query: String,
+    // Index into the filtered list
+    palette_selected: usize,
+    queue: queue::JobQueue,
+    compare_left: String,
+    compare_right: String,
+    compare_text_only: bool,
+    // Inputs of the last diff and its result, so it isn't redone every frame
+    compare_cache: Option<(CompareKey, Result<Vec<diff::DiffLine>, String>)>,
+    history_filter: String,
+    // Quick single-date check, outside of any run
+    quick_date: String,
+    quick_pending: Arc<AtomicBool>,
+    quick_result: Arc<Mutex<Option<verify::Outcome>>>,
+    // Built when the calendar section is open, with when it was built
+    calendar: Option<(Instant, calendar::CoverageCalendar)>,
+    history_rows: Vec<db::ResultRow>,
+    history_search: String,
+    hit_sort: results::Sort,
+    hit_filter: results::Filter,
+    // Row keys ticked in Found Records, for exporting just those
+    hit_selection: HashSet<String>,
+    // Tags and notes of saved responses, from the results database
+    annotations: HashMap<String, db::Annotation>,
+    // Response being annotated and the annotation as edited so far
+    annotating: Option<(String, db::Annotation)>,
+    detail: Option<ResultDetail>,
+    // None until a search has been run
+    search_hits: Option<Vec<db::SearchHit>>,
+    // Set when the index changed (import finished, results directory moved)
+    history_stale: Arc<AtomicBool>,
+    #[cfg(feature = "tray")]
+    tray: Option<tray::Tray>,
+    // Set by the tray's Quit so the close isn't turned into a hide
+    #[cfg(feature = "tray")]
+    quitting: bool,
+    // Read off a dropped photo, waiting for the user to confirm or correct it
+    #[cfg(feature = "ocr")]
+    photo_plate: Arc<Mutex<Option<String>>>,
     status_text: String,
+    // The case searches are filed under, if any, and the others to switch to
+    active_case: Option<case::Case>,
+    cases: Vec<case::Case>,
+    new_case_name: String,
+    // Typed into the unlock window, cleared once used
+    passphrase: String,
+    passphrase_confirm: String,
+    // "Not now" was pressed on the unlock window
+    unlock_dismissed: bool,
+    // Behind the startup passphrase: at startup, on "Lock" and after
+    // auto_lock_mins without input
+    locked: bool,
+    lock_failed: bool,
+    lock_input: String,
+    last_activity: Instant,
+    // Typed in Settings to set the startup passphrase
+    new_lock: String,
+    new_lock_confirm: String,
+    // The configured directory, or the active case's inside it
     results_dir: PathBuf,
+    // This copy's claim on the results folder, and another copy found using it
+    instance_lock: Option<lock::LockFile>,
+    other_instance: Option<lock::LockInfo>,
+    // A scan left unfinished by a crash, waiting for the user to resume or discard it
+    orphaned_run: Option<recovery::RunState>,
+    // Set by the startup update check when a newer release is out
+    available_update: Arc<Mutex<Option<update::Release>>>,
 }
 
 impl Default for VehicleChecker {
     fn default() -> Self {
-        let results_dir = PathBuf::from("vehicle_results");
-        if !results_dir.exists() {
-            let _ = fs::create_dir(&results_dir);
-        }
+        // Replaced by the configured directory (and created) once the config is loaded
+        let results_dir = PathBuf::from(config::DEFAULT_RESULTS_DIR);
 
-        Self {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let hits = Arc::new(Mutex::new(Vec::new()));
+        let is_running = Arc::new(AtomicBool::new(false));
+        let checked_dates = Arc::new(Mutex::new(0));
+        let total_dates = Arc::new(Mutex::new(0));
+        let (control_tx, control_rx) = mpsc::channel();
+        let control = ControlHandle::new(
+            control_tx,
+            Arc::clone(&logs),
+            Arc::clone(&hits),
+            Arc::clone(&is_running),
+            Arc::clone(&checked_dates),
+            Arc::clone(&total_dates),
+            results_dir.clone(),
+        );
+
+        let mut checker = Self {
             vehicle_no: String::new(),
+            plate_warned: None,
+            provider_detected_for: String::new(),
+            provider_manual: false,
+            search_everywhere: false,
+            vehicle_type: plate::VehicleType::Any,
             start_date: "2000-01-01".to_string(),
             end_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
             num_threads: 6,
-            is_running: Arc::new(AtomicBool::new(false)),
+            skip_checked: true,
+            stop_conditions: stop::StopConditions::default(),
+            adaptive_concurrency: false,
+            request_delay_ms: 0,
+            search_order: SearchOrder::Forward,
+            series_csv_path: String::new(),
+            series_mapping: HashMap::new(),
+            config: AppConfig::default(),
+            script_hook: None,
+            is_running,
             record_found: Arc::new(AtomicBool::new(false)),
-            logs: Arc::new(Mutex::new(Vec::new())),
+            logs,
+            hits,
             found_count: Arc::new(Mutex::new(0)),
-            checked_dates: Arc::new(Mutex::new(0)),
-            total_dates: Arc::new(Mutex::new(0)),
+            checked_dates,
+            total_dates,
+            coverage: Arc::new(Mutex::new(CoverageMap::default())),
+            scan: Arc::new(Mutex::new(None)),
+            challenge_alerted: false,
+            stats: Arc::new(NetworkStats::default()),
+            control,
+            control_rx,
+            tab: Tab::Scan,
+            console_height: 400.0,
+            dark_mode: true,
+            show_shortcuts: false,
+            curl_import: None,
+            extraction_sample: None,
+            palette_open: false,
+            palette_query: String::new(),
+            palette_selected: 0,
+            queue: queue::JobQueue::default(),
+            compare_left: String::new(),
+            compare_right: String::new(),
+            compare_text_only: true,
+            compare_cache: None,
+            history_filter: String::new(),
+            quick_date: String::new(),
+            quick_pending: Arc::new(AtomicBool::new(false)),
+            quick_result: Arc::new(Mutex::new(None)),
+            calendar: None,
+            history_rows: Vec::new(),
+            history_search: String::new(),
+            hit_sort: results::Sort::default(),
+            hit_filter: results::Filter::default(),
+            hit_selection: HashSet::new(),
+            annotations: HashMap::new(),
+            annotating: None,
+            detail: None,
+            search_hits: None,
+            history_stale: Arc::new(AtomicBool::new(true)),
+            #[cfg(feature = "tray")]
+            tray: None,
+            #[cfg(feature = "tray")]
+            quitting: false,
+            #[cfg(feature = "ocr")]
+            photo_plate: Arc::new(Mutex::new(None)),
             status_text: "Ready".to_string(),
+            active_case: None,
+            cases: Vec::new(),
+            new_case_name: String::new(),
+            passphrase: String::new(),
+            passphrase_confirm: String::new(),
+            unlock_dismissed: false,
+            locked: false,
+            lock_failed: false,
+            lock_input: String::new(),
+            last_activity: Instant::now(),
+            new_lock: String::new(),
+            new_lock_confirm: String::new(),
             results_dir,
+            instance_lock: None,
+            other_instance: None,
+            orphaned_run: None,
+            available_update: Arc::new(Mutex::new(None)),
+        };
+
+        match AppConfig::load() {
+            Ok(config) => checker.config = config,
+            Err(e) => checker.log(tr!("Error reading {}, using defaults - {}", AppConfig::path().display(), e), LogLevel::Error),
+        }
+        checker.num_threads = checker.config.threads.clamp(1, config::MAX_THREADS);
+        checker.request_delay_ms = checker.config.request_delay_ms;
+        checker.adaptive_concurrency = checker.config.adaptive_concurrency;
+        i18n::set_language(checker.config.language);
+        checker.config.verbosity.set();
+        record::set_redaction(checker.config.redact_personal);
+        if let Err(e) = record::set_rules(&checker.config.extraction_rules) {
+            checker.log(tr!("Field extraction rules not applied - {}", e), LogLevel::Warning);
+        }
+        vault::set_sealing(checker.config.encrypt_results);
+        checker.locked = applock::AVAILABLE && !checker.config.lock_passphrase.is_empty();
+        if !applock::AVAILABLE && !checker.config.lock_passphrase.is_empty() {
+            checker.log(tr!("A startup passphrase is set, but this build can't check it (--features app-lock); not locking"), LogLevel::Warning);
+        }
+        checker.open_case();
+
+        #[cfg(feature = "plugins")]
+        {
+            for (path, e) in plugin::load_all(std::path::Path::new(plugin::PLUGIN_DIR)) {
+                checker.log(tr!("Provider plugin {} not loaded - {}", path.display(), e), LogLevel::Error);
+            }
+            for loaded in plugin::all() {
+                checker.log(tr!("Loaded provider plugin {} from {}", loaded.name, loaded.path.display()), LogLevel::Info);
+            }
+        }
+
+        if checker.config.metrics_enabled {
+            let bind = checker.config.metrics_bind.clone();
+            match metrics::spawn_exporter(&bind, Arc::clone(&checker.stats), Arc::clone(&checker.is_running), checker.config.api_tokens.clone()) {
+                Ok(_) => checker.log(tr!("Prometheus metrics available at http://{}/metrics", bind), LogLevel::Info),
+                Err(e) => checker.log(tr!("Could not start metrics exporter on {} - {}", bind, e), LogLevel::Error),
+            }
+        }
+
+        if checker.config.server_enabled {
+            let bind = checker.config.server_bind.clone();
+            match server::spawn_server(&bind, checker.control.clone(), checker.config.api_tokens.clone()) {
+                Ok(_) => checker.log(tr!("Server mode: dashboard on http://{}/ (live events on /ws)", bind), LogLevel::Info),
+                Err(e) => checker.log(tr!("Could not start server on {} - {}", bind, e), LogLevel::Error),
+            }
+        }
+
+        if checker.config.mqtt.enabled {
+            let logs = Arc::clone(&checker.logs);
+            match mqtt::spawn_publisher(&checker.config.mqtt, move |message, level| Self::log_static(&logs, message, level)) {
+                Ok(_) => checker.log(tr!("Publishing hits to MQTT broker {} under {}/", checker.config.mqtt.broker, checker.config.mqtt.topic),
+                                     LogLevel::Info),
+                Err(e) => checker.log(tr!("Could not start MQTT publishing - {}", e), LogLevel::Error),
+            }
         }
+
+        if !checker.config.found_command.trim().is_empty() {
+            let logs = Arc::clone(&checker.logs);
+            let control = checker.control.clone();
+            hook::spawn_runner(&checker.config.found_command, move || control.results_dir(), move |message, level| Self::log_static(&logs, message, level));
+            checker.log(tr!("Running '{}' for every hit", checker.config.found_command.trim()), LogLevel::Info);
+        }
+
+        #[cfg(feature = "scripting")]
+        if !checker.config.script_path.trim().is_empty() {
+            let logs = Arc::clone(&checker.logs);
+            let path = PathBuf::from(checker.config.script_path.trim());
+            match scripting::load(&path, move |message, level| Self::log_static(&logs, message, level)) {
+                Ok(script) => {
+                    checker.log(tr!("Script {} loaded, hooks: {}", path.display(), script.hooks.join(", ")), LogLevel::Info);
+                    checker.script_hook = script.classify;
+                }
+                Err(e) => checker.log(tr!("Script not loaded - {}", e), LogLevel::Error),
+            }
+        }
+
+        if checker.config.check_for_updates {
+            checker.check_for_updates();
+        }
+
+        #[cfg(feature = "grpc")]
+        if checker.config.grpc_enabled {
+            let bind = checker.config.grpc_bind.clone();
+            let logs = Arc::clone(&checker.logs);
+            match grpc::spawn_server(&bind, checker.control.clone(), checker.config.api_tokens.clone(), move |message, level| Self::log_static(&logs, message, level)) {
+                Ok(_) => checker.log(tr!("gRPC control API listening on {}", bind), LogLevel::Info),
+                Err(e) => checker.log(tr!("Could not start gRPC server on {} - {}", bind, e), LogLevel::Error),
+            }
+        }
+
+        checker
     }
 }
 
 impl VehicleChecker {
     fn log(&self, message: String, level: LogLevel) {
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let entry = LogEntry {
-            timestamp,
-            message,
-            level,
+        Self::log_static(&self.logs, message, level);
+    }
+
+    // Urdu needs a font with Arabic-script glyphs; English uses egui's own fonts
+    fn apply_language(&self, ctx: &egui::Context) {
+        i18n::set_language(self.config.language);
+        if self.config.language == Language::Urdu {
+            match i18n::install_fonts(ctx, &self.config.ui_font_path) {
+                Ok(path) => self.log(tr!("Using font {}", path), LogLevel::Info),
+                Err(e) => self.log(tr!("Urdu text may not display correctly - {}", e), LogLevel::Warning),
+            }
+        }
+    }
+
+    // Runs in the background so a slow or blocked GitHub never holds up startup
+    fn check_for_updates(&self) {
+        let logs = Arc::clone(&self.logs);
+        let available = Arc::clone(&self.available_update);
+        thread::spawn(move || match update::check() {
+            Ok(Some(release)) => {
+                Self::log_static(&logs, tr!("Version {} is available (this is {})", release.tag_name, update::CURRENT_VERSION), LogLevel::Info);
+                *available.lock().unwrap() = Some(release);
+            }
+            Ok(None) => {}
+            Err(e) => Self::log_static(&logs, tr!("Could not check for updates - {}", e), LogLevel::Warning),
+        });
+    }
+
+    fn curl_import_window(&mut self, ctx: &egui::Context) {
+        let Some(command) = self.curl_import.as_mut() else { return };
+        let mut open = true;
+        let mut import = false;
+        egui::Window::new(i18n::label("Import cURL command"))
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(560.0)
+        .show(ctx, |ui| {
+            ui.label(i18n::label("In the browser's developer tools, right-click the lookup request and choose Copy > Copy as cURL (bash), then paste it here:"));
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(command).code_editor().desired_width(f32::INFINITY).desired_rows(10));
+            });
+            ui.label(i18n::label("This replaces the endpoint URL, the extra headers and the request template."));
+            import = ui.button(i18n::label("Import")).clicked();
+        });
+        if import {
+            let command = command.clone();
+            self.import_curl(&command);
+        } else if !open {
+            self.curl_import = None;
+        }
+    }
+
+    fn annotation_window(&mut self, ctx: &egui::Context) {
+        let Some((path, annotation)) = self.annotating.as_mut() else { return };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new(i18n::label("Tags and note"))
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(420.0)
+        .show(ctx, |ui| {
+            ui.label(path.as_str());
+            ui.horizontal(|ui| {
+                for tag in db::SUGGESTED_TAGS {
+                    if ui.selectable_label(annotation.has_tag(tag), i18n::label(tag)).clicked() {
+                        annotation.toggle_tag(tag);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label(i18n::label("Tags:"));
+                ui.add(egui::TextEdit::singleline(&mut annotation.tags).desired_width(300.0).hint_text(i18n::label("comma separated")));
+            });
+            ui.label(i18n::label("Note:"));
+            ui.add(egui::TextEdit::multiline(&mut annotation.note).desired_width(f32::INFINITY).desired_rows(5));
+            save = ui.button(i18n::label("Save")).clicked();
+        });
+        if save {
+            let Some((path, annotation)) = self.annotating.take() else { return };
+            match db::ResultsDb::open(&self.results_dir).and_then(|db| db.annotate(&path, &annotation)) {
+                Ok(()) if annotation.is_empty() => {
+                    self.annotations.remove(&path);
+                }
+                Ok(()) => {
+                    self.annotations.insert(path, annotation);
+                }
+                Err(e) => self.log(tr!("Could not save tags and note for {} - {}", path, e), LogLevel::Error),
+            }
+        } else if !open {
+            self.annotating = None;
+        }
+    }
+
+    fn import_curl(&mut self, command: &str) {
+        let imported = match curl::parse(command, &self.vehicle_no) {
+            Ok(imported) => imported,
+            Err(e) => {
+                self.log(tr!("Could not import the cURL command - {}", e), LogLevel::Error);
+                return;
+            }
         };
+        self.config.endpoint_url = imported.url;
+        self.config.extra_headers = imported.headers;
+        self.config.request_template = imported.template;
+        self.curl_import = None;
 
-        if let Ok(mut logs) = self.logs.lock() {
-            logs.push(entry);
-            // Keep only last 1000 entries to prevent memory issues
-            if logs.len() > 1000 {
-                logs.drain(0..100);
+        self.log(tr!("Imported a request to {} with {} fields and {} headers",
+                     self.config.endpoint_url, self.config.request_template.fields.len(), self.config.extra_headers.len()), LogLevel::Info);
+        for (field, placeholder) in &imported.placeholders {
+            self.log(tr!("{} is now {}", field, placeholder), LogLevel::Info);
+        }
+        if !imported.placeholders.iter().any(|(_, p)| p == "{vehicle}") {
+            self.log(tr!("No field looked like the vehicle number - put {vehicle} in the right one under Settings"), LogLevel::Warning);
+        }
+        if !imported.placeholders.iter().any(|(_, p)| p.contains("{date}") || p.contains("{day}")) {
+            self.log(tr!("No field looked like a date - put {date} (or {day}, {month} and {year}) in the right one under Settings"), LogLevel::Warning);
+        }
+    }
+
+    fn update_window(&mut self, ctx: &egui::Context) {
+        let Some(release) = self.available_update.lock().unwrap().clone() else { return };
+        let mut open = true;
+        let mut dismissed = false;
+        egui::Window::new(i18n::label("Update available"))
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(480.0)
+        .show(ctx, |ui| {
+            ui.label(tr!("Version {} is available (this is {}). Endpoint and page changes are only handled by recent builds.", release.tag_name, update::CURRENT_VERSION));
+            ui.add_space(5.0);
+            ui.strong(i18n::label("What's new"));
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                ui.label(release.body.as_deref().unwrap_or_default());
+            });
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                if ui.button(i18n::label("Open release page")).clicked() {
+                    if let Err(e) = open::that_detached(&release.html_url) {
+                        self.log(tr!("Could not open {} - {}", release.html_url, e), LogLevel::Error);
+                    }
+                }
+                if ui.button(i18n::label("Dismiss")).clicked() {
+                    dismissed = true;
+                }
+            });
+        });
+        if !open || dismissed {
+            *self.available_update.lock().unwrap() = None;
+        }
+    }
+
+    // A photo dropped on the window is read in the background; the plate
+    // found goes into a confirmation window, not straight into the field
+    #[cfg(feature = "ocr")]
+    fn read_dropped_photos(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        let Some(path) = dropped.into_iter().find(|path| ocr::is_image(path)) else { return };
+        self.log(tr!("Reading the plate in {}...", path.display()), LogLevel::Info);
+        let logs = Arc::clone(&self.logs);
+        let photo_plate = Arc::clone(&self.photo_plate);
+        let separator = self.config.plate_separator.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            match ocr::read_plate(&path, &separator) {
+                Ok(plate) => *photo_plate.lock().unwrap() = Some(plate),
+                Err(e) => Self::log_static(&logs, tr!("Could not read a plate from {} - {}", path.display(), e), LogLevel::Warning),
             }
+            ctx.request_repaint();
+        });
+    }
+
+    #[cfg(feature = "ocr")]
+    fn photo_plate_window(&mut self, ctx: &egui::Context) {
+        let mut photo_plate = self.photo_plate.lock().unwrap();
+        let Some(plate) = photo_plate.as_mut() else { return };
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new(i18n::label("Plate from photo"))
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(i18n::label("Check the number read from the photo and correct it if needed:"));
+            ui.add(egui::TextEdit::singleline(plate).desired_width(200.0));
+            ui.horizontal(|ui| {
+                if ui.button(i18n::label("Use this number")).clicked() {
+                    self.vehicle_no = plate.clone();
+                    self.plate_warned = None;
+                    done = true;
+                }
+                if ui.button(i18n::label("Cancel")).clicked() {
+                    done = true;
+                }
+            });
+        });
+        if !open || done {
+            *photo_plate = None;
         }
     }
 
-    fn clear_logs(&self) {
-        if let Ok(mut logs) = self.logs.lock() {
-            logs.clear();
+    fn set_results_dir(&mut self, dir: PathBuf) {
+        if let Err(e) = fs::create_dir_all(&dir) {
+            self.log(tr!("Could not create results directory {} - {}", dir.display(), e), LogLevel::Error);
+        }
+        self.control.set_results_dir(dir.clone());
+
+        self.instance_lock = None;
+        self.other_instance = lock::other_instance(&dir);
+        if let Some(other) = &self.other_instance {
+            self.log(tr!("Another copy of the app (PID {}, started {}) is using {}", other.pid, other.started_at, dir.display()), LogLevel::Warning);
         }
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        match lock::hold_instance(&dir, &now) {
+            Ok(held) => self.instance_lock = Some(held),
+            Err(e) => self.log(tr!("Could not create lock file in {} - {}", dir.display(), e), LogLevel::Warning),
+        }
+
+        self.orphaned_run = recovery::orphaned(&dir).into_iter().next();
+        if let Some(run) = &self.orphaned_run {
+            self.log(tr!("Found an unfinished scan of {} ({} to {}) interrupted after {}", run.vehicle_no, run.start_date, run.end_date, run.checkpoint_at), LogLevel::Warning);
+        }
+
+        self.annotations = match db::ResultsDb::open(&dir).and_then(|db| db.annotations()) {
+            Ok(annotations) => annotations,
+            Err(e) => {
+                self.log(tr!("Could not read tags and notes from {} - {}", dir.display(), e), LogLevel::Warning);
+                HashMap::new()
+            }
+        };
+
+        self.results_dir = dir;
+        self.history_stale.store(true, Ordering::SeqCst);
     }
 
-    fn start_checking(&mut self) {
-        let vehicle_no = self.vehicle_no.trim().to_uppercase();
-        let start_date_str = self.start_date.trim();
-        let end_date_str = self.end_date.trim();
+    // Points the results directory at the active case, or at the configured
+    // directory itself when there is none
+    fn open_case(&mut self) {
+        let root = PathBuf::from(self.config.results_dir.trim());
+        self.cases = case::list(&root);
+        self.active_case = None;
+        let name = self.config.active_case.trim().to_string();
+        if !name.is_empty() {
+            match case::load(&root, &name) {
+                Some(found) => self.active_case = Some(found),
+                None => {
+                    self.log(tr!("Case '{}' not found in {}, searches go to the results directory", name, root.display()), LogLevel::Warning);
+                    self.config.active_case.clear();
+                }
+            }
+        }
+        let dir = match &self.active_case {
+            Some(active) => case::dir(&root, &active.name),
+            None => root,
+        };
+        self.set_results_dir(dir);
+    }
 
-        // Validate inputs
+    // Found Records of the case switched from don't belong to the new one
+    fn switch_case(&mut self, name: String) {
+        if name == self.config.active_case {
+            return;
+        }
+        self.config.active_case = name;
+        self.open_case();
+        if let Ok(mut hits) = self.hits.lock() {
+            hits.clear();
+        }
+        *self.found_count.lock().unwrap() = 0;
+        self.hit_selection.clear();
+        self.detail = None;
+        self.calendar = None;
+        match &self.active_case {
+            Some(active) => self.log(tr!("Switched to case '{}' - results go to {}", active.name, self.results_dir.display()), LogLevel::Info),
+            None => self.log(tr!("No case - results go to {}", self.results_dir.display()), LogLevel::Info),
+        }
+        self.save_config();
+    }
+
+    fn create_case(&mut self) {
+        let root = PathBuf::from(self.config.results_dir.trim());
+        match case::create(&root, &self.new_case_name) {
+            Ok(created) => {
+                self.new_case_name.clear();
+                self.switch_case(created.name);
+            }
+            Err(e) => self.log(tr!("Could not create case - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn case_ui(&mut self, ui: &mut egui::Ui, is_running: bool) {
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Case:"));
+            let mut chosen = None;
+            ui.add_enabled_ui(!is_running, |ui| {
+                let current = self.active_case.as_ref().map(|c| c.name.clone()).unwrap_or_else(|| i18n::label("No case"));
+                egui::ComboBox::from_id_source("active_case").selected_text(current).show_ui(ui, |ui| {
+                    if ui.selectable_label(self.active_case.is_none(), i18n::label("No case")).clicked() {
+                        chosen = Some(String::new());
+                    }
+                    for listed in &self.cases {
+                        let selected = self.active_case.as_ref().is_some_and(|c| c.name == listed.name);
+                        if ui.selectable_label(selected, &listed.name).on_hover_text(tr!("Opened {}", listed.created_at)).clicked() {
+                            chosen = Some(listed.name.clone());
+                        }
+                    }
+                });
+            })
+            .response
+            .on_hover_text(i18n::label("Each case keeps its own searches, found records, tags, notes and exports"));
+            if let Some(name) = chosen {
+                self.switch_case(name);
+            }
+
+            ui.separator();
+            ui.add_enabled(!is_running, egui::TextEdit::singleline(&mut self.new_case_name).hint_text(i18n::label("New case name")).desired_width(140.0));
+            if ui.add_enabled(!is_running && !self.new_case_name.trim().is_empty(), egui::Button::new(i18n::label("Create"))).clicked() {
+                self.create_case();
+            }
+        });
+
+        let Some(active) = &mut self.active_case else { return };
+        let mut save = false;
+        ui.collapsing(i18n::label("Case notes"), |ui| {
+            ui.add(egui::TextEdit::multiline(&mut active.notes).desired_rows(3).desired_width(f32::INFINITY));
+            save = ui.button(i18n::label("Save notes")).clicked();
+        });
+        if save {
+            let active = active.clone();
+            match case::save(&PathBuf::from(self.config.results_dir.trim()), &active) {
+                Ok(()) => self.log(tr!("Notes of case '{}' saved", active.name), LogLevel::Info),
+                Err(e) => self.log(tr!("Could not save case notes - {}", e), LogLevel::Error),
+            }
+        }
+    }
+
+    fn apply_preset(&mut self, preset: Preset) {
+        match preset {
+            Preset::Polite => {
+                self.num_threads = 2;
+                self.request_delay_ms = 2000;
+                // The limiter drops below the thread count as soon as errors spike
+                self.adaptive_concurrency = true;
+            }
+            Preset::Fast => {
+                self.num_threads = 10;
+                self.request_delay_ms = 0;
+                self.adaptive_concurrency = false;
+            }
+        }
+        self.log(tr!("Applied the {} preset: {}", tr!(preset.label()), tr!(preset.description())), LogLevel::Info);
+    }
+
+    // Re-classifies and re-parses everything already on disk with the current
+    // rules, in the background. Hits not seen before join Found Records.
+    fn replay_saved_responses(&self) {
+        let classifier = match self.classifier() {
+            Ok(c) => c,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+        let results_dir = self.results_dir.clone();
+        let logs = Arc::clone(&self.logs);
+        let hits = Arc::clone(&self.hits);
+        self.log(tr!("Replaying saved responses from {}", results_dir.display()), LogLevel::Info);
+
+        thread::spawn(move || {
+            let entries = replay::replay(&results_dir, &classifier);
+            let mut counts: HashMap<Classification, usize> = HashMap::new();
+            let mut new_hits = 0;
+
+            for entry in entries {
+                *counts.entry(entry.classification).or_default() += 1;
+                if entry.classification != Classification::Hit {
+                    continue;
+                }
+                let mut line = tr!("Replay: {} - {} {} ({})", entry.path, entry.vehicle_no, entry.date, entry.rule);
+                for (name, value) in record::key_fields(&entry.fields) {
+                    line.push_str(&format!(", {}: {}", i18n::translate(name), record::shareable(name, value)));
+                }
+                Self::log_static(&logs, line, LogLevel::Success);
+
+                if let Ok(mut hits) = hits.lock() {
+                    if !hits.iter().any(|hit| hit.file_name.as_deref() == Some(entry.path.as_str())) {
+                        hits.push(HitRecord {
+                            vehicle_no: entry.vehicle_no,
+                            date: entry.date,
+                            file_name: Some(entry.path),
+                            found_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                            fields: entry.fields,
+                            provider: String::new(),
+                        });
+                        new_hits += 1;
+                    }
+                }
+            }
+
+            let summary = Classification::ALL
+            .iter()
+            .map(|c| format!("{} {}", i18n::translate(c.label()), counts.get(c).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(", ");
+            Self::log_static(&logs, tr!("Replay finished: {}; {} new found records", summary, new_hits), LogLevel::Info);
+        });
+    }
+
+    // One request for the plate on one date, answered in the Scan tab
+    fn quick_verify(&mut self) {
+        let vehicle_no = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
         if vehicle_no.is_empty() {
-            self.log("Please enter a vehicle registration number".to_string(), LogLevel::Error);
+            self.log(tr!("Please enter a vehicle registration number"), LogLevel::Error);
+            return;
+        }
+        let Some(date) = verify::parse_date(&self.quick_date) else {
+            self.log(tr!("Invalid verify date. Use YYYY-MM-DD"), LogLevel::Error);
+            return;
+        };
+        let classifier = match self.classifier() {
+            Ok(c) => c,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+        let Some(transport) = self.build_transport(1) else { return };
+
+        self.quick_pending.store(true, Ordering::SeqCst);
+        if let Ok(mut result) = self.quick_result.lock() {
+            *result = None;
+        }
+        let pending = Arc::clone(&self.quick_pending);
+        let result = Arc::clone(&self.quick_result);
+        let logs = Arc::clone(&self.logs);
+        thread::spawn(move || {
+            let claim = verify::Claim { line: 0, plate: vehicle_no, date };
+            let outcome = verify::check(transport.as_ref(), &classifier, claim);
+            let msg = tr!("Verify {} {}: {} - {}", outcome.claim.plate, outcome.claim.date.format("%Y-%m-%d"),
+                          tr!(outcome.verdict.label()), outcome.detail);
+            Self::log_static(&logs, msg, LogLevel::Info);
+            if let Ok(mut result) = result.lock() {
+                *result = Some(outcome);
+            }
+            pending.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn quick_verify_ui(&mut self, ui: &mut egui::Ui, is_running: bool) {
+        let pending = self.quick_pending.load(Ordering::SeqCst);
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Quick verify on date:"));
+            ui.add(egui::TextEdit::singleline(&mut self.quick_date).desired_width(120.0).hint_text("YYYY-MM-DD"));
+            if ui.add_enabled(!is_running && !pending, egui::Button::new(i18n::label("Verify"))).clicked() {
+                self.quick_verify();
+            }
+            if pending {
+                ui.spinner();
+            }
+        });
+
+        let result = self.quick_result.lock().ok();
+        let Some(outcome) = result.as_ref().and_then(|r| r.as_ref()) else { return };
+        let color = match outcome.verdict {
+            verify::Verdict::Verified => egui::Color32::GREEN,
+            verify::Verdict::NotFound | verify::Verdict::Mismatch => egui::Color32::from_rgb(255, 165, 0),
+            verify::Verdict::Error => egui::Color32::RED,
+        };
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(color, i18n::label(outcome.verdict.label()));
+                ui.label(i18n::display(&format!("{} {} - {}", outcome.claim.plate, outcome.claim.date.format("%Y-%m-%d"), outcome.detail)));
+            });
+            egui::Grid::new("quick_verify_fields").num_columns(2).show(ui, |ui| {
+                for (label, value) in &outcome.fields {
+                    ui.strong(i18n::display(label));
+                    ui.label(i18n::display(value));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    // Checks each exact (plate, claimed date) pair from a CSV file one after
+    // the other and writes a verified/not found/mismatch report next to the
+    // results
+    fn verify_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv", "txt"]).pick_file() else {
+            return;
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.log(tr!("Could not read {} - {}", path.display(), e), LogLevel::Error);
+                return;
+            }
+        };
+        let (claims, problems) = verify::parse_csv(&text, &self.config.plate_separator);
+        for problem in problems {
+            self.log(tr!("CSV: {}", problem), LogLevel::Warning);
+        }
+        if claims.is_empty() {
+            self.log(tr!("No plate/date rows found in {}", path.display()), LogLevel::Error);
+            return;
+        }
+        let classifier = match self.classifier() {
+            Ok(c) => c,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+        let Some(transport) = self.build_transport(1) else { return };
+
+        self.log(tr!("Verifying {} plate/date pairs from {}", claims.len(), path.display()), LogLevel::Info);
+        self.is_running.store(true, Ordering::SeqCst);
+        *self.checked_dates.lock().unwrap() = 0;
+        *self.total_dates.lock().unwrap() = claims.len();
+
+        let throttle = throttle::Throttle::with_pacing(std::time::Duration::from_millis(self.request_delay_ms));
+        let report_path = self.results_dir.join(format!("verification_{}.csv", chrono::Local::now().format("%Y%m%d_%H%M%S")));
+        let logs = Arc::clone(&self.logs);
+        let is_running = Arc::clone(&self.is_running);
+        let checked_dates = Arc::clone(&self.checked_dates);
+
+        thread::spawn(move || {
+            let mut outcomes = Vec::new();
+            for claim in claims {
+                if !throttle.pace(&is_running) {
+                    break;
+                }
+                let outcome = verify::check(transport.as_ref(), &classifier, claim);
+                let level = match outcome.verdict {
+                    verify::Verdict::Verified => LogLevel::Success,
+                    verify::Verdict::NotFound | verify::Verdict::Mismatch => LogLevel::Warning,
+                    verify::Verdict::Error => LogLevel::Error,
+                };
+                let msg = tr!("Verify {} {}: {} - {}", outcome.claim.plate, outcome.claim.date.format("%Y-%m-%d"),
+                              tr!(outcome.verdict.label()), outcome.detail);
+                Self::log_static(&logs, msg, level);
+                outcomes.push(outcome);
+                if let Ok(mut count) = checked_dates.lock() {
+                    *count += 1;
+                }
+            }
+
+            let summary = verify::Verdict::ALL
+            .iter()
+            .map(|v| format!("{} {}", tr!(v.label()), outcomes.iter().filter(|o| o.verdict == *v).count()))
+            .collect::<Vec<_>>()
+            .join(", ");
+            Self::log_static(&logs, tr!("Verification finished: {}", summary), LogLevel::Info);
+            match verify::write_report(&report_path, &outcomes) {
+                Ok(_) => Self::log_static(&logs, tr!("Verification report saved to {}", report_path.display()), LogLevel::Success),
+                Err(e) => Self::log_static(&logs, tr!("Could not write {} - {}", report_path.display(), e), LogLevel::Error),
+            }
+            is_running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    // Old non-hit responses past the retention limits
+    fn cleanup_results(&self) {
+        let summary = storage::apply_retention(&self.results_dir, &self.config.retention);
+        let mut removed: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        for (dir, path) in summary.removed_paths {
+            removed.entry(dir).or_default().push(path);
+        }
+        for (dir, paths) in removed {
+            if let Err(e) = db::ResultsDb::open(&dir).and_then(|mut db| db.forget(&paths)) {
+                self.log(tr!("Could not update the database in {} - {}", dir.display(), e), LogLevel::Error);
+            }
+        }
+        if summary.removed > 0 {
+            self.log(tr!("Retention: removed {} old responses ({} KB freed)", summary.removed, summary.freed_bytes / 1024), LogLevel::Info);
+        } else {
+            self.log(tr!("Retention: nothing to remove"), LogLevel::Info);
+        }
+    }
+
+    fn pick_results_dir(&mut self) {
+        let Some(dir) = rfd::FileDialog::new().set_directory(&self.results_dir).pick_folder() else {
+            return;
+        };
+        self.config.results_dir = config::portable_relative(&dir);
+        // Another folder has its own passphrase
+        vault::lock();
+        self.unlock_dismissed = false;
+        self.open_case();
+        self.save_config();
+    }
+
+    fn apply_control_commands(&mut self) {
+        while let Ok(command) = self.control_rx.try_recv() {
+            match command {
+                ControlCommand::Start(request) => {
+                    if self.is_running.load(Ordering::SeqCst) {
+                        self.log(tr!("Remote start ignored, a scan is already running"), LogLevel::Warning);
+                        continue;
+                    }
+                    self.vehicle_no = request.vehicle_no;
+                    if let Some(start) = request.start_date {
+                        self.start_date = start;
+                    }
+                    if let Some(end) = request.end_date {
+                        self.end_date = end;
+                    }
+                    if let Some(threads) = request.threads {
+                        self.num_threads = threads.clamp(1, config::MAX_THREADS);
+                    }
+                    self.log(tr!("Scan requested remotely"), LogLevel::Info);
+                    self.start_checking();
+                }
+                ControlCommand::Stop => {
+                    if self.is_running.load(Ordering::SeqCst) {
+                        self.stop_checking();
+                    }
+                }
+            }
+        }
+    }
+
+    fn load_series_mapping(&mut self) {
+        let path = PathBuf::from(self.series_csv_path.trim());
+        match series::load_mapping(&path) {
+            Ok(mapping) => {
+                self.log(tr!("Loaded {} plate series from {}", mapping.len(), path.display()), LogLevel::Success);
+                self.series_mapping = mapping;
+            }
+            Err(e) => {
+                self.log(tr!("Error loading series mapping - {}", e), LogLevel::Error);
+            }
+        }
+    }
+
+    fn save_config(&mut self) {
+        self.config.threads = self.num_threads;
+        self.config.request_delay_ms = self.request_delay_ms;
+        self.config.adaptive_concurrency = self.adaptive_concurrency;
+        match self.config.save() {
+            Ok(_) => self.log(tr!("Settings saved to {}", AppConfig::path().display()), LogLevel::Success),
+            Err(e) => self.log(tr!("Error saving settings - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn clear_logs(&self) {
+        if let Ok(mut logs) = self.logs.lock() {
+            logs.clear();
+        }
+    }
+
+    fn start_checking(&mut self) {
+        let vehicle_no = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
+        let start_date_str = self.start_date.trim();
+        let end_date_str = self.end_date.trim();
+
+        // Validate inputs
+        if self.config.encrypt_results && !vault::is_unlocked() {
+            self.log(tr!("Results are encrypted - unlock them before scanning, so hits aren't saved in the clear"), LogLevel::Error);
+            self.unlock_dismissed = false;
+            return;
+        }
+        if vehicle_no.is_empty() {
+            self.log(tr!("Please enter a vehicle registration number"), LogLevel::Error);
+            return;
+        }
+
+        let provider = self.config.provider;
+        let search_by = self.config.search_by;
+        if let Err(problem) = search::validate(search_by, &vehicle_no) {
+            if self.plate_warned.as_deref() != Some(vehicle_no.as_str()) {
+                self.log(tr!("{} doesn't look like a {}: {}. Expected something like {}.",
+                             vehicle_no, tr!(search_by.noun()), tr!(problem), search_by.example()), LogLevel::Warning);
+                self.log(tr!("Check the number, or press Start again to scan it anyway."), LogLevel::Warning);
+                self.plate_warned = Some(vehicle_no);
+                return;
+            }
+        }
+        // Searching everywhere, a plate only has to fit one province
+        let fits_somewhere = self.search_everywhere
+        && Provider::ALL.iter().any(|p| plate::validate(&vehicle_no, self.vehicle_type, *p).is_ok());
+        let plate_check = if search_by != SearchBy::Plate || fits_somewhere {
+            Ok(())
+        } else {
+            plate::validate(&vehicle_no, self.vehicle_type, provider)
+        };
+        if let Err(problem) = plate_check {
+            if self.plate_warned.as_deref() != Some(vehicle_no.as_str()) {
+                self.log(tr!("{} doesn't look like a {} plate ({}): {}. Expected something like {}.",
+                             vehicle_no, tr!(provider.region()), tr!(self.vehicle_type.label()), tr!(problem), self.vehicle_type.example(provider)), LogLevel::Warning);
+                self.log(tr!("Check the number, or press Start again to scan it anyway."), LogLevel::Warning);
+                self.plate_warned = Some(vehicle_no);
+                return;
+            }
+        }
+        self.plate_warned = None;
+
+        let start_date = match NaiveDate::parse_from_str(start_date_str, "%Y-%m-%d") {
+            Ok(d) => d,
+            Err(_) => {
+                self.log(tr!("Invalid start date format. Use YYYY-MM-DD"), LogLevel::Error);
+                return;
+            }
+        };
+
+        let end_date = match NaiveDate::parse_from_str(end_date_str, "%Y-%m-%d") {
+            Ok(d) => d,
+            Err(_) => {
+                self.log(tr!("Invalid end date format. Use YYYY-MM-DD"), LogLevel::Error);
+                return;
+            }
+        };
+
+        if start_date > end_date {
+            self.log(tr!("Starting date must be before ending date"), LogLevel::Error);
+            return;
+        }
+
+        let classifier = match self.classifier() {
+            Ok(c) => c,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+
+        // Endpoint, TLS, cookie jar and headers shared by every worker of the run
+        let Some(transport) = self.build_transport(self.num_threads) else { return };
+        let client = VehicleClient::with_transport(transport, classifier).with_field_rules(record::rules());
+
+        let total_days = (end_date - start_date).num_days() + 1;
+        let days_per_thread = partition::longest(start_date, end_date, self.num_threads);
+
+        // Two scans of the same dates double the load on the server and
+        // race each other writing the coverage file
+        let target = run::Target {
+            vehicle_no: &vehicle_no,
+            start: start_date,
+            end: end_date,
+            provider: &self.provider_key(),
+            search_by,
+            threads: self.num_threads,
+            search_order: self.search_order.label(),
+            skip_checked: self.skip_checked,
+        };
+        let logs = Arc::clone(&self.logs);
+        let mut run = match run::Run::begin(&self.results_dir, &target, Arc::clone(&self.coverage), |msg, level| Self::log_static(&logs, msg, level)) {
+            Ok(run) => run,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+        let already_checked = run.already_checked;
+
+        // Reset state
+        self.is_running.store(true, Ordering::SeqCst);
+        self.record_found.store(false, Ordering::SeqCst);
+        *self.found_count.lock().unwrap() = 0;
+        *self.checked_dates.lock().unwrap() = 0;
+        *self.total_dates.lock().unwrap() = run.state.total;
+        let started = chrono::Local::now();
+        self.control.set_job(JobInfo {
+            vehicle_no: vehicle_no.clone(),
+            start_date: start_date_str.to_string(),
+            end_date: end_date_str.to_string(),
+            threads: self.num_threads,
+            started_at: started.format("%Y-%m-%d %H:%M:%S").to_string(),
+            finished_at: None,
+        });
+
+        self.log(tr!("Starting check for vehicle: {}", vehicle_no), LogLevel::Info);
+        self.log(tr!("Date range: {} to {}", start_date_str, end_date_str), LogLevel::Info);
+        self.log(tr!("Total days to check: {}", total_days), LogLevel::Info);
+        if already_checked > 0 {
+            self.log(tr!("Skipping {} dates already checked in earlier sessions", already_checked), LogLevel::Info);
+        }
+        self.log(tr!("Threads: {}, ~{} days per thread", self.num_threads, days_per_thread), LogLevel::Info);
+        self.log(tr!("Search order: {}", i18n::translate(self.search_order.label())), LogLevel::Info);
+        if self.search_everywhere {
+            self.log(tr!("Searching every province: each date goes to {} providers", Provider::ALL.len()), LogLevel::Info);
+        }
+
+        let series_window = if self.search_order == SearchOrder::SeriesWeighted {
+            // An imported issuance period beats one guessed from earlier hits
+            if let Some((from, to)) = series::lookup(&self.series_mapping, &vehicle_no) {
+                self.log(tr!("Series window from imported mapping: {} to {}",
+                                 from.format("%Y-%m-%d"), to.format("%Y-%m-%d")), LogLevel::Info);
+                Some((from, to))
+            } else {
+                let windows = series::learn_from_results(&self.results_dir);
+                let window = series::predict(&windows, &vehicle_no);
+                match window {
+                    Some((from, to)) => self.log(tr!("Series window predicted from earlier hits: {} to {}",
+                                                         from.format("%Y-%m-%d"), to.format("%Y-%m-%d")), LogLevel::Info),
+                    None => self.log(tr!("No earlier hits for this series, falling back to forward scanning"), LogLevel::Warning),
+                }
+                window
+            }
+        } else {
+            None
+        };
+        if self.adaptive_concurrency {
+            self.log(tr!("Adaptive concurrency enabled, up to {} requests in flight", self.num_threads), LogLevel::Info);
+        }
+        if self.request_delay_ms > 0 {
+            self.log(tr!("Each thread waits {} ms between requests", self.request_delay_ms), LogLevel::Info);
+        }
+        if self.config.retention.is_enabled() {
+            self.cleanup_results();
+        }
+        let location = SaveLocation::new(&self.results_dir, &vehicle_no, started, &self.config.filename_template);
+        if let Err(e) = location.create() {
+            self.log(tr!("Could not create results directory {} - {}", location.run_dir.display(), e), LogLevel::Error);
+        }
+        self.log(tr!("Results will be saved to: {}", self.results_dir.join(&location.run_dir).display()), LogLevel::Info);
+        self.log(tr!("Stop conditions: {}", Self::describe_stop_conditions(&self.stop_conditions)), LogLevel::Warning);
+        self.log("-".repeat(80), LogLevel::Info);
+
+        let is_running = Arc::clone(&self.is_running);
+        let control = self.control.clone();
+        let checked_dates = Arc::clone(&self.checked_dates);
+        let coverage = Arc::clone(&self.coverage);
+        let skip_checked = self.skip_checked;
+        let num_threads = self.num_threads;
+        let search_order = self.search_order;
+        self.challenge_alerted = false;
+        self.stats.reset();
+        let stats = Arc::clone(&self.stats);
+        let options = ScanOptions {
+            threads: num_threads,
+            order: search_order,
+            series_window,
+            stop: self.stop_conditions,
+            delay: std::time::Duration::from_millis(self.request_delay_ms),
+            adaptive: self.adaptive_concurrency,
+            breaker_threshold: self.config.breaker_threshold,
+            coverage: Some(Arc::clone(&coverage)),
+            skip_checked,
+            stats: Arc::clone(&stats),
+            ..ScanOptions::default()
+        };
+        let scan_slot = Arc::clone(&self.scan);
+        if let Ok(mut slot) = scan_slot.lock() {
+            *slot = None;
+        }
+        let mut sink = RunSink {
+            vehicle_no: vehicle_no.clone(),
+            logs: Arc::clone(&logs),
+            hits: Arc::clone(&self.hits),
+            found_count: Arc::clone(&self.found_count),
+            checked_dates: Arc::clone(&checked_dates),
+            record_found: Arc::clone(&self.record_found),
+            location: location.clone(),
+            // With every province searched, the log says which one had it
+            provider: self.provider_key(),
+            misses: HashMap::new(),
+        };
+
+        if self.config.accept_invalid_certs {
+            self.log(tr!("TLS certificate validation is DISABLED for this run"), LogLevel::Warning);
+        }
+        run::open_outputs(&self.config, &self.results_dir, |msg, level| Self::log_static(&logs, msg, level));
+        audit::record(audit::Event::new("run_started", &vehicle_no)
+                      .detail(format!("{} to {}, {} threads, {}", run.info.start_date, run.info.end_date, self.num_threads, self.search_order.label())));
+        let bootstrap_url = self.config.session_bootstrap_url.trim().to_string();
+        let cookies_enabled = self.config.cookies_enabled;
+        let health_check = self.config.health_check_enabled;
+        if !bootstrap_url.is_empty() && !self.config.cookies_enabled {
+            self.log(tr!("Session bootstrap URL is set but cookies are disabled, skipping bootstrap"), LogLevel::Warning);
+        }
+
+        let results_dir = self.results_dir.clone();
+        let settings = summary::RunSettings {
+            threads: num_threads,
+            search_order: search_order.label().to_string(),
+            adaptive_concurrency: self.adaptive_concurrency,
+            request_delay_ms: self.request_delay_ms,
+            skip_checked,
+            stop_conditions: self.stop_conditions,
+            offline: !self.config.offline_fixture_dir.trim().is_empty(),
+        };
+
+        thread::spawn(move || {
+            if !bootstrap_url.is_empty() && cookies_enabled {
+                match client.bootstrap(&bootstrap_url) {
+                    Ok(status) => Self::log_static(&logs, tr!("Session bootstrap: HTTP {} from {}", status, bootstrap_url), LogLevel::Info),
+                    Err(e) => Self::log_static(&logs, tr!("Session bootstrap failed - {}", e), LogLevel::Warning),
+                }
+            }
+
+            if health_check {
+                if let Err(problem) = client.health_check() {
+                    Self::log_static(&logs, tr!("Health check failed, run not started: {}", problem), LogLevel::Error);
+                    audit::record(audit::Event::new("health_check_failed", &vehicle_no).detail(problem));
+                    Self::log_static(&logs, tr!("Check the endpoint and classifier rules in Settings, or turn the health check off."), LogLevel::Error);
+                    run.finish(|msg, level| Self::log_static(&logs, msg, level));
+                    control.finish_job();
+                    is_running.store(false, Ordering::SeqCst);
+                    return;
+                }
+                Self::log_static(&logs, tr!("Health check passed: endpoint answered with the no-record page"), LogLevel::Info);
+            }
+
+            let lanes = client::plan(start_date, end_date, &options);
+            let by_range = search_order != SearchOrder::NewestFirstInterleaved && options.series_window.is_none();
+            for (i, dates) in lanes.iter().enumerate() {
+                let (Some(first), Some(from), Some(to)) = (dates.first(), dates.iter().min(), dates.iter().max()) else { continue };
+                let log_msg = if by_range {
+                    tr!("Thread {}: {} to {}", i + 1, from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))
+                } else {
+                    tr!("Thread {}: {} dates, starting at {}", i + 1, dates.len(), first.format("%Y-%m-%d"))
+                };
+                Self::log_static(&logs, log_msg, LogLevel::Info);
+            }
+
+            let mut scan = client.scan_lanes(&vehicle_no, lanes, options);
+            let scan_control = scan.control();
+            if let Ok(mut slot) = scan_slot.lock() {
+                *slot = Some(scan_control.clone());
+            }
+            Self::log_static(&logs, "-".repeat(80), LogLevel::Info);
+
+            // Until every worker is done, saving progress now and then so a
+            // crash loses at most run::CHECKPOINT_SECS of work
+            let mut last_checkpoint = Instant::now();
+            let mut last_progress = Instant::now();
+            loop {
+                while let Some(event) = scan.try_next() {
+                    sink.handle(event, &scan_control);
+                }
+                if scan.is_finished() {
+                    while let Some(event) = scan.try_next() {
+                        sink.handle(event, &scan_control);
+                    }
+                    break;
+                }
+                if !is_running.load(Ordering::SeqCst) {
+                    scan_control.stop();
+                }
+                thread::sleep(std::time::Duration::from_millis(200));
+                if last_progress.elapsed().as_secs() >= 1 {
+                    last_progress = Instant::now();
+                    control.publish_progress();
+                }
+                if let Some(reason) = scan_control.check_limits() {
+                    Self::log_static(&logs, tr!("Stopping all threads: {}", Self::describe_stop_reason(reason)), LogLevel::Warning);
+                }
+                if last_checkpoint.elapsed().as_secs() < run::CHECKPOINT_SECS {
+                    continue;
+                }
+                last_checkpoint = Instant::now();
+                run.checkpoint(checked_dates.lock().map(|c| *c).unwrap_or(0), |msg, level| Self::log_static(&logs, msg, level));
+            }
+
+            let network = stats.snapshot();
+            if network.requests > 0 {
+                Self::log_static(&logs, tr!("Latency: p50 {} ms, p90 {} ms, p99 {} ms",
+                                            network.p50_latency_ms, network.p90_latency_ms, network.p99_latency_ms), LogLevel::Info);
+            }
+            if scan.failed() > 0 {
+                Self::log_static(&logs, tr!("{} dates got no answer and were not checked", scan.failed()), LogLevel::Warning);
+            }
+
+            let finished = chrono::Local::now();
+            let duration_secs = (finished - started).num_seconds().max(0) as u64;
+            let started_at = started.format("%Y-%m-%d %H:%M:%S").to_string();
+            let found: Vec<String> = scan.hits().iter().map(|hit| hit.date.format("%Y-%m-%d").to_string()).collect();
+            let mut errors = std::collections::BTreeMap::new();
+            for event in stats.errors() {
+                *errors.entry(event.kind).or_insert(0) += 1;
+            }
+            let run_summary = summary::RunSummary {
+                vehicle_no: vehicle_no.clone(),
+                start_date: run.state.start_date.clone(),
+                end_date: run.state.end_date.clone(),
+                started_at: started_at.clone(),
+                finished_at: finished.format("%Y-%m-%d %H:%M:%S").to_string(),
+                duration_secs,
+                dates_total: run.state.total,
+                dates_checked: scan.progress().0,
+                hits: found,
+                outcomes: Classification::ALL.iter().map(|c| (c.label().to_string(), stats.outcome_count(*c))).collect(),
+                errors,
+                requests: network.requests,
+                requests_per_sec: network.requests as f64 / duration_secs.max(1) as f64,
+                latency_ms: summary::Latency {
+                    avg: network.avg_latency_ms,
+                    p50: network.p50_latency_ms,
+                    p90: network.p90_latency_ms,
+                    p99: network.p99_latency_ms,
+                },
+                stop_reason: scan.stop_reason(),
+                stopped_by_user: !is_running.load(Ordering::SeqCst),
+                settings,
+            };
+            if let Err(e) = summary::write(&results_dir.join(&location.run_dir), &run_summary) {
+                Self::log_static(&logs, tr!("Could not write run summary - {}", e), LogLevel::Warning);
+            }
+            let outcome = match run_summary.stop_reason {
+                Some(reason) => serde_json::to_string(&reason).unwrap_or_default(),
+                None if run_summary.stopped_by_user => "stopped by user".to_string(),
+                None => "completed".to_string(),
+            };
+            audit::record(audit::Event::new("run_finished", &vehicle_no)
+                          .detail(format!("{}, {} of {} dates checked, {} hits", outcome, run_summary.dates_checked, run_summary.dates_total, run_summary.hits.len())));
+
+            run.finish(|msg, level| Self::log_static(&logs, msg, level));
+            control.finish_job();
+            is_running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn describe_stop_conditions(conditions: &stop::StopConditions) -> String {
+        let mut parts = Vec::new();
+        if conditions.max_hits > 0 {
+            parts.push(tr!("after {} hits", conditions.max_hits));
+        }
+        if conditions.time_limit_mins > 0 {
+            parts.push(tr!("after {} minutes", conditions.time_limit_mins));
+        }
+        if conditions.max_requests > 0 {
+            parts.push(tr!("after {} requests", conditions.max_requests));
+        }
+        if parts.is_empty() {
+            return tr!("run to completion");
+        }
+        tr!("stop {}", parts.join(&tr!(" or ")))
+    }
+
+    fn describe_stop_reason(reason: stop::StopReason) -> String {
+        match reason {
+            stop::StopReason::Hits(hits) => tr!("{} records found", hits),
+            stop::StopReason::TimeLimit(mins) => tr!("time limit of {} minutes reached", mins),
+            stop::StopReason::Requests(requests) => tr!("request limit of {} reached", requests),
+            stop::StopReason::ServerError(status) => tr!("server returned HTTP {}", status),
+        }
+    }
+
+    fn log_static(logs: &Arc<Mutex<Vec<LogEntry>>>, message: String, level: LogLevel) {
+        if !Verbosity::current().shows(level) {
+            return;
+        }
+        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let entry = LogEntry {
+            seq: LOG_SEQ.fetch_add(1, Ordering::SeqCst) + 1,
+            timestamp,
+            message: record::redact_text(&message),
+            level,
+        };
+
+        if let Some(Ok(mut file)) = LOG_FILE.get().map(|f| f.lock()) {
+            use std::io::Write;
+            let _ = writeln!(file, "[{}] {}: {}", entry.timestamp, control::level_name(entry.level), entry.message);
+        }
+        control::publish(EngineEvent::Log(entry.clone()));
+
+        if let Ok(mut logs) = logs.lock() {
+            logs.push(entry);
+            // Keep only last 1000 entries to prevent memory issues
+            if logs.len() > 1000 {
+                logs.drain(0..100);
+            }
+        }
+    }
+
+    // The provider a run goes to, empty when every province is searched
+    fn provider_key(&self) -> String {
+        if self.search_everywhere { String::new() } else { self.config.provider_name().to_string() }
+    }
+
+    // The offline fixture transport when a fixture directory is set, the real
+    // endpoint otherwise. Errors are logged.
+    // The configured rules, behind the script's hook when there is one
+    fn classifier(&self) -> Result<Classifier, String> {
+        Ok(Classifier::new(&self.config.classifier_rules)?.with_hook(self.script_hook.clone()))
+    }
+
+    fn build_transport(&self, pool_size: usize) -> Option<Arc<dyn Transport>> {
+        let fixture_dir = self.config.offline_fixture_dir.trim();
+        if !fixture_dir.is_empty() {
+            self.log(tr!("OFFLINE dry run: answering from fixtures in {}", fixture_dir), LogLevel::Warning);
+            return Some(Arc::new(FixtureTransport::new(PathBuf::from(fixture_dir))));
+        }
+        #[cfg(feature = "plugins")]
+        if !self.config.provider_plugin.is_empty() {
+            let Some(loaded) = plugin::find(&self.config.provider_plugin) else {
+                self.log(tr!("Provider plugin '{}' is not loaded - check {}/", self.config.provider_plugin, plugin::PLUGIN_DIR), LogLevel::Error);
+                return None;
+            };
+            return Some(Arc::new(plugin::PluginTransport(loaded)));
+        }
+        #[cfg(not(feature = "plugins"))]
+        if !self.config.provider_plugin.is_empty() {
+            let name = &self.config.provider_plugin;
+            self.log(tr!("provider plugin '{}' is configured, but this build has no plugin support (--features plugins)", name), LogLevel::Error);
+            return None;
+        }
+        let transport: Result<Arc<dyn Transport>, _> = if self.search_everywhere {
+            let logs = Arc::clone(&self.logs);
+            EverywhereTransport::from_config(&self.config, pool_size, self.script_hook.clone(), move |provider, vehicle_no, date_str| {
+                Self::log_static(&logs, tr!("{} has the record of {} for {}", tr!(provider.label()), vehicle_no, date_str), LogLevel::Success);
+            })
+            .map(|everywhere| Arc::new(everywhere) as Arc<dyn Transport>)
+        } else {
+            HttpTransport::from_config(&self.config, pool_size).map(|http| Arc::new(http) as Arc<dyn Transport>)
+        };
+        match transport {
+            Ok(transport) => Some(transport),
+            Err(e) => {
+                self.log(tr!("Invalid network settings - {}", e), LogLevel::Error);
+                None
+            }
+        }
+    }
+
+    fn scan_control(&self) -> Option<ScanControl> {
+        self.scan.lock().ok().and_then(|scan| scan.clone())
+    }
+
+    fn resume_after_challenge(&mut self) {
+        let Some(scan) = self.scan_control() else { return };
+        if scan.is_breaker_open() {
+            self.log(tr!("Resuming scan after repeated request failures"), LogLevel::Info);
+        } else {
+            self.log(tr!("Resuming scan after challenge"), LogLevel::Info);
+        }
+        scan.resume();
+        self.challenge_alerted = false;
+    }
+
+    fn stop_checking(&mut self) {
+        self.is_running.store(false, Ordering::SeqCst);
+        self.log(tr!("Stopping all threads..."), LogLevel::Warning);
+        if let Some(job) = self.control.job() {
+            audit::record(audit::Event::new("stop_requested", &job.vehicle_no));
+        }
+        // Stopping a queued job means "hold on", not "skip to the next one"
+        if let Some(job) = self.queue.current_mut() {
+            job.status = queue::JobStatus::Stopped;
+            self.queue.paused = true;
+            self.log(tr!("Job queue paused"), LogLevel::Warning);
+        }
+    }
+
+    // The Scan tab's current settings as a queue entry
+    fn enqueue_current(&mut self) {
+        let vehicle_no = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
+        if vehicle_no.is_empty() {
+            self.log(tr!("Please enter a vehicle registration number"), LogLevel::Error);
+            return;
+        }
+        self.queue.push(queue::Job {
+            id: 0,
+            vehicle_no: vehicle_no.clone(),
+            vehicle_type: self.vehicle_type,
+            start_date: self.start_date.trim().to_string(),
+            end_date: self.end_date.trim().to_string(),
+            threads: self.num_threads,
+            search_order: self.search_order,
+            stop_conditions: self.stop_conditions,
+            high_priority: false,
+            status: queue::JobStatus::Pending,
+            started_at: None,
+            finished_at: None,
+            checked: 0,
+            total: 0,
+            hits: Vec::new(),
+        });
+        self.log(tr!("Queued {} ({} to {}), {} jobs waiting", vehicle_no, self.start_date.trim(), self.end_date.trim(), self.queue.pending()), LogLevel::Info);
+    }
+
+    // Called every frame: records the results of a queued run that just
+    // ended and starts the next pending job while the app is idle
+    fn queue_tick(&mut self) {
+        let is_running = self.is_running.load(Ordering::SeqCst);
+        let run_over = !is_running && self.control.job().is_none_or(|job| job.finished_at.is_some());
+
+        if run_over && self.queue.current.is_some() {
+            let job_info = self.control.job();
+            let (checked, total) = self.control.progress();
+            let hits = self.hits.lock().map(|h| h.clone()).unwrap_or_default();
+            if let Some(job) = self.queue.current_mut() {
+                job.finished_at = job_info.and_then(|info| info.finished_at);
+                job.checked = checked;
+                job.total = total;
+                let started_at = job.started_at.clone().unwrap_or_default();
+                job.hits = hits
+                .iter()
+                .filter(|hit| hit.vehicle_no == job.vehicle_no && hit.found_at >= started_at)
+                .map(|hit| hit.date.clone())
+                .collect();
+                if job.status == queue::JobStatus::Running {
+                    job.status = queue::JobStatus::Finished;
+                }
+            }
+            self.queue.current = None;
+        }
+
+        if !run_over || self.queue.paused {
+            return;
+        }
+        let Some(job) = self.queue.next_pending().cloned() else { return };
+
+        self.vehicle_no = job.vehicle_no.clone();
+        self.vehicle_type = job.vehicle_type;
+        self.start_date = job.start_date.clone();
+        self.end_date = job.end_date.clone();
+        self.num_threads = job.threads;
+        self.search_order = job.search_order;
+        self.stop_conditions = job.stop_conditions;
+        // The queue runs unattended, so a plate warning shouldn't hold it up
+        self.plate_warned = Some(job.vehicle_no.clone());
+        self.log(tr!("Starting queued job #{}: {}", job.id, job.vehicle_no), LogLevel::Info);
+        self.start_checking();
+
+        let started = self.is_running.load(Ordering::SeqCst);
+        let started_at = self.control.job().map(|info| info.started_at);
+        if let Some(entry) = self.queue.jobs.iter_mut().find(|entry| entry.id == job.id) {
+            if started {
+                entry.status = queue::JobStatus::Running;
+                entry.started_at = started_at;
+                self.queue.current = Some(entry.id);
+            } else {
+                entry.status = queue::JobStatus::Failed;
+            }
+        }
+    }
+
+    fn queue_ui(&mut self, ui: &mut egui::Ui) {
+        let is_running = self.is_running.load(Ordering::SeqCst);
+        ui.horizontal(|ui| {
+            if ui.button(i18n::label("Add current settings to queue")).clicked() {
+                self.enqueue_current();
+            }
+            if self.queue.paused {
+                if ui.button(i18n::label("Resume queue")).clicked() {
+                    self.queue.paused = false;
+                }
+            } else if ui.button(i18n::label("Pause queue")).clicked() {
+                self.queue.paused = true;
+            }
+            if ui.button(i18n::label("Clear finished")).clicked() {
+                self.queue.clear_done();
+            }
+        });
+        let state = if self.queue.paused {
+            i18n::translate("paused")
+        } else if is_running && self.queue.current.is_some() {
+            i18n::translate("running")
+        } else {
+            i18n::translate("idle")
+        };
+        ui.label(i18n::display(&tr!("{} pending, queue {}", self.queue.pending(), state)));
+        ui.separator();
+
+        if self.queue.jobs.is_empty() {
+            ui.label(i18n::label("No jobs queued. Set up a scan on the Scan tab and add it here."));
+            return;
+        }
+
+        ui.label(i18n::label("Drag a job by its handle to reorder. High-priority jobs run as soon as the current one is done."));
+        let mut remove = None;
+        let mut moved = None;
+        let mut priority = None;
+        egui::ScrollArea::vertical().id_source("job_queue").show(ui, |ui| {
+            egui::Grid::new("queue_grid").striped(true).show(ui, |ui| {
+                for heading in ["", "#", "Vehicle", "Range", "Threads", "High priority", "Status", "Checked", "Found", ""] {
+                    ui.strong(i18n::label(heading));
+                }
+                ui.end_row();
+
+                for job in &self.queue.jobs {
+                    let running = self.queue.current == Some(job.id);
+                    let handle = ui.dnd_drag_source(egui::Id::new(("queued_job", job.id)), job.id, |ui| {
+                        ui.label("☰");
+                    })
+                    .response;
+                    let id_cell = ui.label(job.id.to_string());
+                    let vehicle_cell = ui.label(&job.vehicle_no);
+                    // Dropping on a row puts the dragged job in its place
+                    for cell in [&handle, &id_cell, &vehicle_cell] {
+                        if let Some(dragged) = cell.dnd_release_payload::<u64>() {
+                            moved = Some((*dragged, job.id));
+                        }
+                    }
+                    ui.label(format!("{} - {}", job.start_date, job.end_date));
+                    ui.label(job.threads.to_string());
+                    let mut high = job.high_priority;
+                    if ui.add_enabled(job.status == queue::JobStatus::Pending, egui::Checkbox::without_text(&mut high)).changed() {
+                        priority = Some((job.id, high));
+                    }
+                    let color = match job.status {
+                        queue::JobStatus::Pending => ui.visuals().text_color(),
+                        queue::JobStatus::Running => egui::Color32::LIGHT_BLUE,
+                        queue::JobStatus::Finished if !job.hits.is_empty() => egui::Color32::GREEN,
+                        queue::JobStatus::Finished => ui.visuals().text_color(),
+                        queue::JobStatus::Stopped => egui::Color32::from_rgb(255, 165, 0),
+                        queue::JobStatus::Failed => egui::Color32::RED,
+                    };
+                    ui.colored_label(color, i18n::label(job.status.label()));
+                    if running {
+                        let (checked, total) = self.control.progress();
+                        ui.label(format!("{}/{}", checked, total));
+                    } else if job.started_at.is_some() {
+                        ui.label(format!("{}/{}", job.checked, job.total));
+                    } else {
+                        ui.label("");
+                    }
+                    ui.label(job.hits.join(", "));
+                    if ui.add_enabled(!running, egui::Button::new(i18n::label("Remove"))).clicked() {
+                        remove = Some(job.id);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(id) = remove {
+            self.queue.remove(id);
+        }
+        if let Some((id, target)) = moved {
+            self.queue.move_to(id, target);
+        }
+        if let Some((id, high)) = priority {
+            if let Some(job) = self.queue.jobs.iter_mut().find(|job| job.id == id) {
+                job.high_priority = high;
+            }
+        }
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        let is_running = self.is_running.load(Ordering::SeqCst);
+        let (palette, start, escape, clear, focus, help, next_tab, tab_key) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::P),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::L),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::F),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::F1),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Tab),
+                [egui::Key::Num1, egui::Key::Num2, egui::Key::Num3, egui::Key::Num4, egui::Key::Num5, egui::Key::Num6]
+                .iter()
+                .position(|key| i.consume_key(egui::Modifiers::COMMAND, *key)),
+            )
+        });
+
+        if start && !is_running {
+            self.start_checking();
+        }
+        if palette {
+            self.palette_open = !self.palette_open;
+            self.palette_query.clear();
+            self.palette_selected = 0;
+        }
+        // Esc closes the palette or help first so it doesn't stop a run by accident
+        if escape {
+            if self.palette_open {
+                self.palette_open = false;
+            } else if self.show_shortcuts {
+                self.show_shortcuts = false;
+            } else if is_running {
+                self.stop_checking();
+            }
+        }
+        if clear {
+            self.clear_logs();
+        }
+        if focus {
+            self.tab = Tab::Scan;
+            ctx.memory_mut(|m| m.request_focus(egui::Id::new("vehicle_no_field")));
+        }
+        if help {
+            self.show_shortcuts = !self.show_shortcuts;
+        }
+        if let Some(i) = tab_key {
+            self.tab = Tab::ALL[i];
+        } else if next_tab {
+            let current = Tab::ALL.iter().position(|t| *t == self.tab).unwrap_or(0);
+            self.tab = Tab::ALL[(current + 1) % Tab::ALL.len()];
+        }
+    }
+
+    // Only what makes sense right now, e.g. no Start while a run is going
+    fn palette_actions(&self) -> Vec<(String, Action)> {
+        let is_running = self.is_running.load(Ordering::SeqCst);
+        let has_run = self.control.job().is_some();
+        let mut actions = Vec::new();
+        if is_running {
+            actions.push((i18n::translate("Stop"), Action::Stop));
+        } else {
+            actions.push((i18n::translate("Start"), Action::Start));
+            if has_run {
+                actions.push((i18n::translate("Export PDF Report"), Action::ExportPdf));
+                actions.push((i18n::translate("Export Excel"), Action::ExportExcel));
+                actions.push((i18n::translate("Export Markdown"), Action::ExportMarkdown));
+            }
+            actions.push((i18n::translate("Verify CSV..."), Action::VerifyCsv));
+            for preset in Preset::ALL {
+                actions.push((tr!("Apply preset: {}", i18n::translate(preset.label())), Action::ApplyPreset(preset)));
+            }
+            actions.push((i18n::translate("Clean up now"), Action::CleanUpResults));
+        }
+        actions.push((i18n::translate("Clear Console"), Action::ClearConsole));
+        actions.push((i18n::translate("Open results folder"), Action::OpenResultsFolder));
+        actions.push((i18n::translate("Switch between dark and light theme"), Action::ToggleTheme));
+        for tab in Tab::ALL {
+            actions.push((tr!("Go to tab: {}", i18n::translate(tab.label())), Action::SwitchTab(tab)));
+        }
+        actions.push((i18n::translate("Replay through current rules"), Action::ReplayResponses));
+        actions.push((i18n::translate("Import / re-index results folder"), Action::ImportResults));
+        actions.push((i18n::translate("Save Settings"), Action::SaveSettings));
+        actions.push((i18n::translate("Keyboard Shortcuts"), Action::ShowShortcuts));
+        actions
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::Start => self.start_checking(),
+            Action::Stop => self.stop_checking(),
+            Action::ClearConsole => self.clear_logs(),
+            Action::ExportPdf => self.export_pdf_report(),
+            Action::ExportExcel => self.export_xlsx(),
+            Action::ExportMarkdown => self.export_markdown(),
+            Action::VerifyCsv => self.verify_csv(),
+            Action::OpenResultsFolder => {
+                if let Err(e) = open::that_detached(&self.results_dir) {
+                    self.log(tr!("Could not open {} - {}", self.results_dir.display(), e), LogLevel::Error);
+                }
+            }
+            Action::ToggleTheme => {
+                self.dark_mode = !ctx.style().visuals.dark_mode;
+                ctx.set_visuals(if self.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+            }
+            Action::ApplyPreset(preset) => self.apply_preset(preset),
+            Action::SwitchTab(tab) => self.tab = tab,
+            Action::ReplayResponses => self.replay_saved_responses(),
+            Action::ImportResults => self.import_results(),
+            Action::CleanUpResults => self.cleanup_results(),
+            Action::SaveSettings => self.save_config(),
+            Action::ShowShortcuts => self.show_shortcuts = true,
+        }
+    }
+
+    fn command_palette(&mut self, ctx: &egui::Context) {
+        if !self.palette_open {
+            return;
+        }
+        let actions = self.palette_actions();
+        let labels: Vec<String> = actions.iter().map(|(label, _)| label.clone()).collect();
+        let matches = palette::rank(&self.palette_query, &labels);
+
+        // Arrows and Enter drive the list while the text field keeps focus
+        let (up, down, enter) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+            )
+        });
+        if up {
+            self.palette_selected = self.palette_selected.saturating_sub(1);
+        }
+        if down {
+            self.palette_selected += 1;
+        }
+        self.palette_selected = self.palette_selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = enter.then(|| matches.get(self.palette_selected).copied()).flatten();
+        egui::Window::new(i18n::label("Command palette"))
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_size([420.0, 0.0])
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .show(ctx, |ui| {
+            let field = ui.add(egui::TextEdit::singleline(&mut self.palette_query)
+            .desired_width(f32::INFINITY)
+            .hint_text(i18n::label("Type a command...")));
+            field.request_focus();
+            if field.changed() {
+                self.palette_selected = 0;
+            }
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                if matches.is_empty() {
+                    ui.weak(i18n::label("No matching command"));
+                }
+                for (row, index) in matches.iter().enumerate() {
+                    let selected = row == self.palette_selected;
+                    let response = ui.selectable_label(selected, i18n::display(&labels[*index]));
+                    if selected && (up || down) {
+                        response.scroll_to_me(None);
+                    }
+                    if response.clicked() {
+                        chosen = Some(*index);
+                    }
+                }
+            });
+        });
+
+        if let Some(index) = chosen {
+            self.palette_open = false;
+            self.run_action(ctx, actions[index].1);
+        }
+    }
+
+    #[cfg(feature = "tray")]
+    fn update_tray(&mut self, ctx: &egui::Context, is_running: bool, found: bool) {
+        let Some(tray) = &mut self.tray else {
+            return;
+        };
+
+        tray.set_status(if is_running {
+            tray::TrayStatus::Running
+        } else if found {
+            tray::TrayStatus::Found
+        } else {
+            tray::TrayStatus::Idle
+        });
+
+        for action in tray.poll() {
+            match action {
+                tray::TrayAction::Show => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                tray::TrayAction::Start => {
+                    if !self.is_running.load(Ordering::SeqCst) {
+                        self.start_checking();
+                    }
+                }
+                tray::TrayAction::Stop => {
+                    if self.is_running.load(Ordering::SeqCst) {
+                        self.stop_checking();
+                    }
+                }
+                tray::TrayAction::Quit => {
+                    self.quitting = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+
+        // Closing the window hides it instead; the scan keeps running
+        if ctx.input(|i| i.viewport().close_requested()) && self.config.minimize_to_tray && !self.quitting {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+
+        // Keep reading tray events while the window is hidden
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+    }
+
+    // Narrows Found Records; the provider list has the providers the rows came from
+    fn hit_filter_ui(&mut self, ui: &mut egui::Ui, rows: &[results::Row]) {
+        let mut providers: Vec<&str> = rows.iter().map(|row| row.hit.provider.as_str()).filter(|p| !p.is_empty()).collect();
+        providers.sort_unstable();
+        providers.dedup();
+        let filter = &mut self.hit_filter;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(i18n::label("Vehicle:"));
+            ui.add(egui::TextEdit::singleline(&mut filter.vehicle).desired_width(90.0));
+            ui.label(i18n::label("From:"));
+            ui.add(egui::TextEdit::singleline(&mut filter.from).desired_width(80.0).hint_text("2015-01-01"));
+            ui.label(i18n::label("To:"));
+            ui.add(egui::TextEdit::singleline(&mut filter.to).desired_width(80.0).hint_text("2015-12-31"));
+            ui.label(i18n::label("Provider:"));
+            let any = i18n::label("Any");
+            egui::ComboBox::from_id_source("hit_filter_provider")
+            .selected_text(filter.provider.as_deref().map(i18n::label).unwrap_or_else(|| any.clone()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.provider, None, &any);
+                for provider in providers {
+                    ui.selectable_value(&mut filter.provider, Some(provider.to_string()), i18n::label(provider));
+                }
+            });
+            ui.label(i18n::label("Result:"));
+            egui::ComboBox::from_id_source("hit_filter_outcome")
+            .selected_text(filter.outcome.map(|c| i18n::label(c.label())).unwrap_or_else(|| any.clone()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter.outcome, None, &any);
+                for outcome in Classification::ALL.into_iter().filter(|c| *c != Classification::Miss) {
+                    ui.selectable_value(&mut filter.outcome, Some(outcome), i18n::label(outcome.label()));
+                }
+            });
+            ui.label(i18n::label("Search:"));
+            ui.add(egui::TextEdit::singleline(&mut filter.text).desired_width(120.0));
+            if ui.add_enabled(filter.is_active(), egui::Button::new(i18n::label("Clear"))).clicked() {
+                *filter = results::Filter::default();
+            }
+        });
+    }
+
+    // One row per hit with the fields people compare them by; a heading
+    // click sorts on that column, and ticked rows can be exported on their
+    // own. Returns the file whose details to open.
+    fn hits_table(&mut self, ui: &mut egui::Ui, rows: &[results::Row]) -> Option<String> {
+        let mut details = None;
+        let mut clicked = None;
+        let mut annotate = None;
+        // Taken out so the rows can tick it while borrowing self for the menus
+        let mut selection = std::mem::take(&mut self.hit_selection);
+        egui::ScrollArea::vertical().id_source("found_records").max_height(300.0).show(ui, |ui| {
+            egui::Grid::new("found_records_grid").striped(true).show(ui, |ui| {
+                let mut all = !rows.is_empty() && rows.iter().all(|row| selection.contains(&row.key()));
+                if ui.checkbox(&mut all, "").on_hover_text(i18n::label("Select all shown")).changed() {
+                    for row in rows {
+                        if all {
+                            selection.insert(row.key());
+                        } else {
+                            selection.remove(&row.key());
+                        }
+                    }
+                }
+                for column in results::Column::ALL {
+                    let heading = self.hit_sort.heading(column, &i18n::label(column.label()));
+                    if ui.add(egui::Button::new(egui::RichText::new(heading).strong()).frame(false)).clicked() {
+                        clicked = Some(column);
+                    }
+                }
+                ui.end_row();
+                for row in rows {
+                    let hit = &row.hit;
+                    let key = row.key();
+                    let mut selected = selection.contains(&key);
+                    if ui.checkbox(&mut selected, "").changed() {
+                        if selected {
+                            selection.insert(key);
+                        } else {
+                            selection.remove(&key);
+                        }
+                    }
+                    let color = if row.outcome == Classification::Hit { egui::Color32::GREEN } else { egui::Color32::RED };
+                    ui.colored_label(color, &hit.vehicle_no).context_menu(|ui| {
+                        if self.hit_copy_buttons(ui, hit) {
+                            ui.close_menu();
+                        }
+                    });
+                    for column in &results::Column::ALL[1..] {
+                        let value = column.value(row);
+                        match column {
+                            results::Column::Provider | results::Column::Outcome => ui.label(i18n::label(value)),
+                            // First line only; the whole note on hover
+                            results::Column::Note if !value.is_empty() => {
+                                let first: String = value.lines().next().unwrap_or_default().chars().take(NOTE_PREVIEW_CHARS).collect();
+                                ui.label(i18n::display(&first)).on_hover_text(i18n::display(value))
+                            }
+                            _ => ui.label(i18n::display(value)),
+                        };
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(hit.file_name.is_some(), egui::Button::new(i18n::label("Details"))).clicked() {
+                            details = hit.file_name.clone();
+                        }
+                        if ui.add_enabled(hit.file_name.is_some(), egui::Button::new(i18n::label("Annotate"))).clicked() {
+                            annotate = hit.file_name.clone().map(|name| (name, row.annotation.clone()));
+                        }
+                        ui.menu_button(i18n::label("More"), |ui| {
+                            if self.hit_copy_buttons(ui, hit) {
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(column) = clicked {
+            self.hit_sort.click(column);
+        }
+        if annotate.is_some() {
+            self.annotating = annotate;
+        }
+
+        let chosen: Vec<results::Row> = rows.iter().filter(|row| selection.contains(&row.key())).cloned().collect();
+        let mut export = None;
+        ui.horizontal(|ui| {
+            ui.label(i18n::display(&tr!("{} selected", chosen.len())));
+            for format in results::Format::ALL {
+                if ui.add_enabled(!chosen.is_empty(), egui::Button::new(i18n::label(format.label()))).clicked() {
+                    export = Some(format);
+                }
+            }
+            if ui.add_enabled(!selection.is_empty(), egui::Button::new(i18n::label("Clear selection"))).clicked() {
+                selection.clear();
+            }
+        });
+        self.hit_selection = selection;
+        if let Some(format) = export {
+            let path = results::default_path(&self.results_dir, format);
+            match results::write(format, &chosen, &self.results_dir, &path) {
+                Ok(()) => self.log(tr!("Exported {} records to {}", chosen.len(), path.display()), LogLevel::Success),
+                Err(e) => self.log(tr!("Could not export to {} - {}", path.display(), e), LogLevel::Error),
+            }
+        }
+        details
+    }
+
+    // Returns true when something was copied, so a context menu can close
+    fn hit_copy_buttons(&self, ui: &mut egui::Ui, hit: &HitRecord) -> bool {
+        let mut copied = None;
+        if ui.button(i18n::label("Copy summary")).clicked() {
+            copied = Some(record::summary(&hit.vehicle_no, &hit.date, &hit.fields));
+        }
+        if ui.add_enabled(!hit.fields.is_empty(), egui::Button::new(i18n::label("Copy fields"))).clicked() {
+            copied = Some(record::fields_text(&hit.fields));
+        }
+        let raw = hit.file_name.as_ref().map(|name| self.results_dir.join(name));
+        if ui.add_enabled(raw.is_some(), egui::Button::new(i18n::label("Copy raw response"))).clicked() {
+            match raw.as_deref().map(vault::read_to_string) {
+                Some(Ok(body)) => copied = Some(body),
+                Some(Err(e)) => self.log(tr!("Could not read saved response - {}", e), LogLevel::Error),
+                None => {}
+            }
+        }
+
+        if ui.add_enabled(hit.file_name.is_some(), egui::Button::new(i18n::label("Open in browser"))).clicked() {
+            if let Some(name) = &hit.file_name {
+                self.open_in_browser(name);
+            }
+            return true;
+        }
+        if ui.add_enabled(hit.file_name.is_some(), egui::Button::new(i18n::label("Save as image"))).clicked() {
+            if let Some(name) = &hit.file_name {
+                self.save_image(name);
+            }
+            return true;
+        }
+
+        match copied {
+            Some(text) => {
+                ui.output_mut(|o| o.copied_text = text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The last run started from this window, for the exporters
+    fn run_report(&self) -> Option<RunReport> {
+        let job = self.control.job()?;
+        let (dates_checked, dates_total) = self.control.progress();
+        let hits = self
+        .control
+        .hits_since(0)
+        .into_iter()
+        .filter(|hit| hit.vehicle_no == job.vehicle_no && hit.found_at >= job.started_at)
+        .collect();
+        let gaps = match (NaiveDate::parse_from_str(&job.start_date, "%Y-%m-%d"), NaiveDate::parse_from_str(&job.end_date, "%Y-%m-%d")) {
+            (Ok(start), Ok(end)) => self.coverage.lock().map(|map| map.gaps(start, end)).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        Some(RunReport {
+            job,
+            dates_total,
+            dates_checked,
+            outcomes: Classification::ALL.iter().map(|c| (c.label(), self.stats.outcome_count(*c))).collect(),
+            network: self.stats.snapshot(),
+            errors: self.stats.errors(),
+            hits,
+            gaps,
+            generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        })
+    }
+
+    fn export_pdf_report(&self) {
+        let Some(report) = self.run_report() else {
+            return;
+        };
+        let path = report.default_path(&self.results_dir, "pdf");
+        match report::write_pdf(&report, &path) {
+            Ok(_) => self.log(tr!("PDF report saved to {}", path.display()), LogLevel::Success),
+            Err(e) => self.log(tr!("Error writing PDF report - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn export_markdown(&self) {
+        let Some(report) = self.run_report() else {
+            return;
+        };
+        let path = report.default_path(&self.results_dir, "md");
+        match report::write_markdown(&report, &path) {
+            Ok(_) => self.log(tr!("Markdown report saved to {}", path.display()), LogLevel::Success),
+            Err(e) => self.log(tr!("Error writing Markdown report - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn export_xlsx(&self) {
+        let Some(report) = self.run_report() else {
+            return;
+        };
+        let path = report.default_path(&self.results_dir, "xlsx");
+        match report::write_xlsx(&report, &path) {
+            Ok(_) => self.log(tr!("Excel export saved to {}", path.display()), LogLevel::Success),
+            Err(e) => self.log(tr!("Error writing Excel export - {}", e), LogLevel::Error),
+        }
+    }
+
+    // Hands a saved response to the system's default handler for .html files
+    // Indexes the whole results folder, including anything saved before the
+    // database existed, in the background
+    fn import_results(&self) {
+        let classifier = match self.classifier() {
+            Ok(c) => c,
+            Err(e) => {
+                self.log(e, LogLevel::Error);
+                return;
+            }
+        };
+        let results_dir = self.results_dir.clone();
+        let logs = Arc::clone(&self.logs);
+        let stale = Arc::clone(&self.history_stale);
+        self.log(tr!("Importing results from {}", results_dir.display()), LogLevel::Info);
+
+        thread::spawn(move || {
+            match db::ResultsDb::open(&results_dir).and_then(|mut db| db.import(&results_dir, &classifier)) {
+                Ok(summary) => {
+                    let msg = tr!("Import finished: {} files scanned, {} added, {} updated, {} skipped (unrecognised names)",
+                                  summary.scanned, summary.added, summary.updated, summary.skipped);
+                    Self::log_static(&logs, msg, LogLevel::Success);
+                }
+                Err(e) => Self::log_static(&logs, tr!("Import failed - {}", e), LogLevel::Error),
+            }
+            stale.store(true, Ordering::SeqCst);
+        });
+    }
+
+    fn load_history(&mut self) {
+        match db::ResultsDb::open(&self.results_dir).and_then(|db| db.list(&self.history_filter, HISTORY_LIMIT)) {
+            Ok(rows) => self.history_rows = rows,
+            Err(e) => self.log(tr!("Could not read the results database - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn run_search(&mut self) {
+        if self.history_search.trim().chars().count() < 3 {
+            self.log(tr!("Search needs at least 3 characters"), LogLevel::Warning);
+            return;
+        }
+        match db::ResultsDb::open(&self.results_dir).and_then(|db| db.search(&self.history_search, HISTORY_LIMIT)) {
+            Ok(hits) => self.search_hits = Some(hits),
+            Err(e) => self.log(tr!("Search failed - {}", e), LogLevel::Error),
+        }
+    }
+
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        if self.history_stale.swap(false, Ordering::SeqCst) {
+            self.load_history();
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button(i18n::label("Import / re-index results folder")).clicked() {
+                self.import_results();
+            }
+            ui.label(i18n::label("Vehicle:"));
+            if ui.add(egui::TextEdit::singleline(&mut self.history_filter).desired_width(150.0)).changed() {
+                self.load_history();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Search text:"));
+            let field = ui.add(egui::TextEdit::singleline(&mut self.history_search)
+            .desired_width(250.0)
+            .hint_text(i18n::label("owner, chassis or engine number...")));
+            let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button(i18n::label("Search")).clicked() || entered {
+                self.run_search();
+            }
+            if self.search_hits.is_some() && ui.button(i18n::label("Clear search")).clicked() {
+                self.search_hits = None;
+            }
+        });
+        ui.separator();
+
+        let mut open = None;
+        if let Some(hits) = &self.search_hits {
+            ui.label(i18n::display(&tr!("{} matches (index is updated by Import)", hits.len())));
+            egui::ScrollArea::vertical().id_source("search_hits").show(ui, |ui| {
+                egui::Grid::new("search_grid").striped(true).show(ui, |ui| {
+                    for heading in ["Vehicle", "Date", "Match", "File"] {
+                        ui.strong(i18n::label(heading));
+                    }
+                    ui.end_row();
+                    for hit in hits {
+                        ui.label(&hit.vehicle_no);
+                        ui.label(&hit.date);
+                        ui.label(i18n::display(&hit.snippet));
+                        if ui.link(&hit.path).clicked() {
+                            open = Some(hit.path.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+            if let Some(path) = open {
+                self.open_detail(&path);
+            }
+            return;
+        }
+
+        ui.label(i18n::display(&tr!("{} indexed responses shown (newest dates first, up to {})", self.history_rows.len(), HISTORY_LIMIT)));
+        egui::ScrollArea::vertical().id_source("history").show(ui, |ui| {
+            egui::Grid::new("history_grid").striped(true).show(ui, |ui| {
+                for heading in ["Vehicle", "Date", "Status", "Outcome", "Owner", "File"] {
+                    ui.strong(i18n::label(heading));
+                }
+                ui.end_row();
+                for row in &self.history_rows {
+                    ui.label(&row.vehicle_no);
+                    ui.label(&row.date);
+                    ui.label(row.status.to_string());
+                    ui.label(i18n::label(&row.classification));
+                    ui.label(i18n::display(&row.owner));
+                    if ui.link(&row.path).clicked() {
+                        open = Some(row.path.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        if let Some(path) = open {
+            self.open_detail(&path);
+        }
+    }
+
+    fn open_detail(&mut self, path: &str) {
+        match vault::read_to_string(&self.results_dir.join(path)) {
+            Ok(body) => {
+                self.detail = Some(ResultDetail {
+                    path: path.to_string(),
+                    fields: record::parse_fields(&record::rules(), &body),
+                    raw: highlight::html(&body, self.dark_mode),
+                    text: record::clean_text_lines(&body).join("\n"),
+                    show_text: false,
+                });
+            }
+            Err(e) => self.log(tr!("Could not read saved response - {}", e), LogLevel::Error),
+        }
+    }
+
+    // Parsed fields on top, the page itself below as highlighted HTML or as
+    // plain text
+    fn detail_window(&mut self, ctx: &egui::Context) {
+        let Some(detail) = &mut self.detail else { return };
+        let mut open = true;
+        let mut open_in_browser = false;
+        egui::Window::new(i18n::display(&detail.path))
+        .id(egui::Id::new("result_detail"))
+        .open(&mut open)
+        .default_size([750.0, 600.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut detail.show_text, i18n::label("Show as plain text"));
+                if ui.button(i18n::label("Open in browser")).clicked() {
+                    open_in_browser = true;
+                }
+            });
+            ui.separator();
+            if detail.fields.is_empty() {
+                ui.label(i18n::label("No fields recognised on this page"));
+            } else {
+                egui::ScrollArea::vertical().id_source("detail_fields").max_height(200.0).show(ui, |ui| {
+                    egui::Grid::new("detail_fields_grid").num_columns(2).striped(true).show(ui, |ui| {
+                        for (label, value) in &detail.fields {
+                            ui.strong(i18n::display(label));
+                            ui.label(i18n::display(value));
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+            ui.separator();
+            egui::ScrollArea::both().id_source("detail_source").auto_shrink([false, false]).show(ui, |ui| {
+                if detail.show_text {
+                    ui.label(i18n::display(&detail.text));
+                } else {
+                    ui.label(detail.raw.clone());
+                }
+            });
+        });
+        if open_in_browser {
+            let path = detail.path.clone();
+            self.open_in_browser(&path);
+        }
+        if !open {
+            self.detail = None;
+        }
+    }
+
+    // Requests and errors per second over the last few minutes of the run
+    fn throughput_chart(&self, ui: &mut egui::Ui) {
+        let seconds = self.stats.timeline(THROUGHPUT_WINDOW_SECS);
+        if seconds.is_empty() {
+            return;
+        }
+        let now = seconds.last().map(|s| s.at).unwrap_or(0) as f64;
+        let points = |value: fn(&stats::Second) -> u32| -> egui_plot::PlotPoints {
+            seconds.iter().map(|s| [s.at as f64 - now, value(s) as f64]).collect()
+        };
+        egui_plot::Plot::new("throughput")
+        .height(140.0)
+        .legend(egui_plot::Legend::default())
+        .include_y(0.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .x_axis_label(i18n::label("seconds"))
+        .show(ui, |plot| {
+            plot.line(egui_plot::Line::new(points(|s| s.requests)).name(i18n::label("Requests/s")).color(egui::Color32::from_rgb(0, 150, 255)));
+            plot.line(egui_plot::Line::new(points(|s| s.errors)).name(i18n::label("Errors/s")).color(egui::Color32::RED));
+        });
+    }
+
+    // Checked, errored and hit dates of the current vehicle over the selected
+    // range, one row per month
+    fn coverage_calendar_ui(&mut self, ui: &mut egui::Ui) {
+        let vehicle_no = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
+        let range = (
+            NaiveDate::parse_from_str(self.start_date.trim(), "%Y-%m-%d"),
+            NaiveDate::parse_from_str(self.end_date.trim(), "%Y-%m-%d"),
+        );
+        let (Ok(start), Ok(end)) = range else {
+            ui.label(i18n::label("Enter a valid date range to see the calendar"));
+            return;
+        };
+        if vehicle_no.is_empty() || start > end {
+            ui.label(i18n::label("Enter a vehicle number and date range to see the calendar"));
+            return;
+        }
+
+        let stale = match &self.calendar {
+            Some((built, calendar)) => calendar.vehicle_no != vehicle_no || built.elapsed().as_secs() >= CALENDAR_REFRESH_SECS,
+            None => true,
+        };
+        if stale {
+            let live = self.coverage.lock().map(|map| map.clone()).ok();
+            let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+            let error_dates: Vec<NaiveDate> = self.stats.errors().iter().filter_map(|e| parse(&e.date)).collect();
+            let hit_dates: Vec<NaiveDate> = self
+            .hits
+            .lock()
+            .map(|hits| hits.iter().filter(|h| h.vehicle_no == vehicle_no).filter_map(|h| parse(&h.date)).collect())
+            .unwrap_or_default();
+            let calendar = calendar::CoverageCalendar::build(&self.results_dir, &self.provider_key(), self.config.search_by, &vehicle_no, live.as_ref(), &error_dates, &hit_dates);
+            self.calendar = Some((Instant::now(), calendar));
+        }
+        let Some((_, view)) = &self.calendar else { return };
+
+        let total = (end - start).num_days() + 1;
+        let count = |state| view.count_in(state, start, end);
+        let (hits, errors) = (count(calendar::DayState::Hit), count(calendar::DayState::Error));
+        let checked = count(calendar::DayState::Checked) + hits + errors;
+        ui.label(tr!("{} of {} dates checked, {} errors, {} hits", checked, total, errors, hits));
+        calendar::CoverageCalendar::legend(ui);
+        egui::ScrollArea::vertical().id_source("coverage_calendar").max_height(300.0).show(ui, |ui| {
+            view.show(ui, start, end);
+        });
+    }
+
+    fn open_in_browser(&self, file_name: &str) {
+        let path = self.results_dir.join(file_name);
+        if let Err(e) = vault::plain_copy(&path).and_then(open::that_detached) {
+            self.log(tr!("Could not open {} - {}", path.display(), e), LogLevel::Error);
+        }
+    }
+
+    // Renders a saved response to a PNG beside it, for people who want to see
+    // what the site showed rather than its markup
+    fn save_image(&self, file_name: &str) {
+        let page = self.results_dir.join(file_name);
+        let png = snapshot::image_path(&page);
+        let browser = self.config.browser_path.clone();
+        let logs = Arc::clone(&self.logs);
+        self.log(tr!("Rendering {} to an image...", file_name), LogLevel::Info);
+        thread::spawn(move || {
+            // With encryption on, the browser gets a decrypted copy and draws
+            // into the temp directory; the image is sealed into place after
+            let rendered = vault::plain_copy(&page).map_err(|e| e.to_string()).and_then(|plain| {
+                if !vault::is_encrypting() {
+                    return snapshot::render(&browser, &plain, &png).map(|()| png.clone());
+                }
+                let shown = vault::view_dir().map_err(|e| e.to_string())?.join(png.file_name().unwrap_or_default());
+                snapshot::render(&browser, &plain, &shown)?;
+                fs::read(&shown).and_then(|bytes| vault::write(&png, bytes)).map_err(|e| e.to_string())?;
+                Ok(shown)
+            });
+            match rendered {
+                Ok(shown) => {
+                    Self::log_static(&logs, tr!("Saved image {}", png.display()), LogLevel::Success);
+                    let _ = open::that_detached(&shown);
+                }
+                Err(e) => Self::log_static(&logs, tr!("Could not render {} - {}", page.display(), e), LogLevel::Error),
+            }
+        });
+    }
+
+    // Asked for at startup when the results folder is encrypted, and once
+    // encryption is turned on. "Not now" leaves encrypted results unreadable
+    // and scans off until it is unlocked from Settings.
+    fn unlock_window(&mut self, ctx: &egui::Context) {
+        let root = PathBuf::from(self.config.results_dir.trim());
+        let new_vault = !vault::exists(&root);
+        if self.unlock_dismissed || vault::is_unlocked() || (new_vault && !self.config.encrypt_results) {
+            return;
+        }
+        let mut submit = false;
+        let mut later = false;
+        egui::Window::new(i18n::label("Unlock results"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            if new_vault {
+                ui.label(i18n::label("Choose a passphrase for the results. It can't be recovered: without it, encrypted results can't be read."));
+            } else {
+                ui.label(tr!("The results in {} are encrypted.", root.display()));
+            }
+            ui.add_space(8.0);
+            egui::Grid::new("passphrase_grid").num_columns(2).show(ui, |ui| {
+                ui.label(i18n::label("Passphrase:"));
+                let field = ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+                submit |= field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.end_row();
+                if new_vault {
+                    ui.label(i18n::label("Again:"));
+                    let field = ui.add(egui::TextEdit::singleline(&mut self.passphrase_confirm).password(true));
+                    submit |= field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.end_row();
+                }
+            });
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                submit |= ui.add_enabled(!self.passphrase.is_empty(), egui::Button::new(i18n::label("Unlock"))).clicked();
+                later = ui.button(i18n::label("Not now")).clicked();
+            });
+        });
+
+        if later {
+            self.unlock_dismissed = true;
+            self.log(tr!("Results stay locked - encrypted responses can't be read and scans won't start"), LogLevel::Warning);
+        } else if submit && new_vault && self.passphrase != self.passphrase_confirm {
+            self.log(tr!("The passphrases don't match"), LogLevel::Error);
+        } else if submit {
+            match vault::unlock(&root, &self.passphrase) {
+                Ok(()) => {
+                    self.log(tr!("Results in {} unlocked", root.display()), LogLevel::Success);
+                    // Tags and notes couldn't be read while locked
+                    self.open_case();
+                }
+                Err(e) => self.log(tr!("Could not unlock the results - {}", e), LogLevel::Error),
+            }
+        } else {
+            return;
+        }
+        self.passphrase.clear();
+        self.passphrase_confirm.clear();
+    }
+
+    // Any input counts as activity; none for auto_lock_mins locks the app
+    fn track_activity(&mut self, ctx: &egui::Context) {
+        if self.locked || self.config.lock_passphrase.is_empty() || !applock::AVAILABLE {
+            return;
+        }
+        if ctx.input(|i| !i.events.is_empty() || i.pointer.is_moving()) {
+            self.last_activity = Instant::now();
+        }
+        if self.config.auto_lock_mins == 0 {
+            return;
+        }
+        let limit = std::time::Duration::from_secs(self.config.auto_lock_mins * 60);
+        let idle = self.last_activity.elapsed();
+        if idle >= limit {
+            self.lock_app();
+        } else {
+            // An idle window isn't repainted by itself
+            ctx.request_repaint_after(limit - idle);
+        }
+    }
+
+    fn lock_app(&mut self) {
+        // Nothing could unlock it again
+        if !applock::AVAILABLE {
+            return;
+        }
+        self.locked = true;
+        self.lock_failed = false;
+        self.lock_input.clear();
+        self.detail = None;
+        self.annotating = None;
+        self.palette_open = false;
+        self.log(tr!("Locked"), LogLevel::Info);
+    }
+
+    fn lock_screen(&mut self, ctx: &egui::Context) {
+        let mut submit = false;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(120.0);
+                ui.heading(i18n::label("Vehicle Registration Checker"));
+                ui.label(i18n::label("Locked - enter the passphrase to continue"));
+                if self.is_running.load(Ordering::SeqCst) {
+                    ui.label(i18n::label("A scan is running in the background."));
+                }
+                ui.add_space(10.0);
+                let field = ui.add(egui::TextEdit::singleline(&mut self.lock_input).password(true).desired_width(240.0));
+                field.request_focus();
+                submit = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                submit |= ui.add_enabled(!self.lock_input.is_empty(), egui::Button::new(i18n::label("Unlock"))).clicked();
+                if self.lock_failed {
+                    ui.colored_label(egui::Color32::RED, i18n::label("Wrong passphrase"));
+                }
+            });
+        });
+        if !submit {
+            return;
+        }
+        if applock::verify(&self.config.lock_passphrase, &self.lock_input) {
+            self.locked = false;
+            self.last_activity = Instant::now();
+            self.log(tr!("Unlocked"), LogLevel::Info);
+        } else {
+            self.lock_failed = true;
+            self.log(tr!("Wrong passphrase on the lock screen"), LogLevel::Warning);
+        }
+        self.lock_input.clear();
+    }
+
+    fn lock_settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Startup passphrase:"));
+            if self.config.lock_passphrase.is_empty() {
+                ui.add(egui::TextEdit::singleline(&mut self.new_lock).password(true).desired_width(140.0));
+                ui.label(i18n::label("Again:"));
+                ui.add(egui::TextEdit::singleline(&mut self.new_lock_confirm).password(true).desired_width(140.0));
+                if ui.add_enabled(!self.new_lock.is_empty(), egui::Button::new(i18n::label("Set"))).clicked() {
+                    self.set_lock_passphrase();
+                }
+            } else {
+                ui.label(i18n::label("set"));
+                if ui.button(i18n::label("Lock now")).clicked() {
+                    self.lock_app();
+                }
+                if ui.button(i18n::label("Remove")).clicked() {
+                    self.config.lock_passphrase.clear();
+                    self.save_config();
+                    self.log(tr!("Startup passphrase removed"), LogLevel::Info);
+                }
+            }
+        })
+        .response
+        .on_hover_text(i18n::label("Asked for at startup before history, results and settings are shown. It doesn't encrypt anything."));
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Lock after"));
+            ui.add_enabled(!self.config.lock_passphrase.is_empty(), egui::DragValue::new(&mut self.config.auto_lock_mins).range(0..=480));
+            ui.label(i18n::label("minutes without input (0 = never)"));
+        });
+    }
+
+    fn set_lock_passphrase(&mut self) {
+        if self.new_lock != self.new_lock_confirm {
+            self.log(tr!("The passphrases don't match"), LogLevel::Error);
+            return;
+        }
+        match applock::hash(&self.new_lock) {
+            Ok(hash) => {
+                self.config.lock_passphrase = hash;
+                self.last_activity = Instant::now();
+                self.save_config();
+                self.log(tr!("Startup passphrase set"), LogLevel::Success);
+            }
+            Err(e) => self.log(tr!("Could not set the startup passphrase - {}", e), LogLevel::Error),
+        }
+        self.new_lock.clear();
+        self.new_lock_confirm.clear();
+    }
+
+    // Seals the responses and the database of the results folder and every
+    // case in it that were saved before encryption was turned on
+    fn encrypt_existing(&self) {
+        let root = PathBuf::from(self.config.results_dir.trim());
+        let logs = Arc::clone(&self.logs);
+        let history_stale = Arc::clone(&self.history_stale);
+        self.log(tr!("Encrypting existing results in {}...", root.display()), LogLevel::Info);
+        thread::spawn(move || {
+            let mut dirs = vec![root.clone()];
+            dirs.extend(case::list(&root).iter().map(|listed| case::dir(&root, &listed.name)));
+            let mut sealed = 0;
+            for dir in dirs {
+                // The database first, so it is encrypted before it takes the
+                // hashed names
+                if let Err(e) = db::ResultsDb::encrypt(&dir) {
+                    Self::log_static(&logs, tr!("Could not encrypt the database in {} - {}", dir.display(), e), LogLevel::Error);
+                }
+                let results = match vault::seal_results(&dir) {
+                    Ok(results) => results,
+                    Err(e) => {
+                        Self::log_static(&logs, tr!("Could not encrypt results in {} - {}", dir.display(), e), LogLevel::Error);
+                        continue;
+                    }
+                };
+                sealed += results.files;
+                if let Err(e) = db::ResultsDb::open(&dir).and_then(|mut db| db.rename(&results.renamed)) {
+                    Self::log_static(&logs, tr!("Could not update the database in {} - {}", dir.display(), e), LogLevel::Error);
+                }
+            }
+            Self::log_static(&logs, tr!("Encrypted {} files", sealed), LogLevel::Success);
+            history_stale.store(true, Ordering::SeqCst);
+        });
+    }
+
+    // Offered once per unfinished run found at startup. Resuming restarts the
+    // same scan with "skip checked" on, so it picks up from the last checkpoint.
+    fn resume_prompt(&mut self, ctx: &egui::Context) {
+        let Some(run) = self.orphaned_run.clone() else { return };
+        let mut choice = None;
+        egui::Window::new(i18n::label("Resume interrupted scan?"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(tr!("The scan of {} from {} to {} started {} did not finish.", run.vehicle_no, run.start_date, run.end_date, run.started_at));
+            ui.label(tr!("Last checkpoint {}: {} of {} dates checked.", run.checkpoint_at, run.checked, run.total));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                let idle = !self.is_running.load(Ordering::SeqCst);
+                if ui.add_enabled(idle, egui::Button::new(i18n::label("Resume"))).clicked() {
+                    choice = Some(true);
+                }
+                if ui.button(i18n::label("Discard")).clicked() {
+                    choice = Some(false);
+                }
+            });
+        });
+
+        let Some(resume) = choice else { return };
+        recovery::remove(&self.results_dir, &run.id);
+        self.orphaned_run = recovery::orphaned(&self.results_dir).into_iter().next();
+        if !resume {
+            self.log(tr!("Discarded the unfinished scan of {}", run.vehicle_no), LogLevel::Info);
+            return;
+        }
+
+        self.vehicle_no = run.vehicle_no.clone();
+        self.start_date = run.start_date.clone();
+        self.end_date = run.end_date.clone();
+        self.num_threads = run.threads.clamp(1, config::MAX_THREADS);
+        if let Some(order) = SearchOrder::ALL.into_iter().find(|o| o.label() == run.search_order) {
+            self.search_order = order;
+        }
+        self.skip_checked = true;
+        self.tab = Tab::Scan;
+        self.log(tr!("Resuming the unfinished scan of {}", run.vehicle_no), LogLevel::Info);
+        self.start_checking();
+    }
+
+    fn shortcuts_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new(i18n::label("Keyboard Shortcuts"))
+        .open(&mut self.show_shortcuts)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            egui::Grid::new("shortcuts").num_columns(2).spacing([20.0, 6.0]).show(ui, |ui| {
+                for (keys, action) in SHORTCUTS {
+                    ui.monospace(keys);
+                    ui.label(i18n::label(action));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    // Which province the plate goes to. A new plate with a known prefix
+    // switches the provider on its own; picking one here wins until the
+    // plate changes.
+    fn provider_name(&self) -> String {
+        i18n::label(self.config.provider_name())
+    }
+
+    // The built-in providers and, in builds with the `plugins` feature, the
+    // loaded plugins. Returns whether the choice changed.
+    fn provider_combo(&mut self, ui: &mut egui::Ui, id: &str) -> bool {
+        let current = self.config.provider_plugin.is_empty().then_some(self.config.provider);
+        let mut selected = current;
+        #[cfg(feature = "plugins")]
+        let mut selected_plugin = None;
+        egui::ComboBox::from_id_source(id).selected_text(self.provider_name()).show_ui(ui, |ui| {
+            for provider in Provider::ALL {
+                ui.selectable_value(&mut selected, Some(provider), i18n::label(provider.label()));
+            }
+            #[cfg(feature = "plugins")]
+            for loaded in plugin::all() {
+                if ui.selectable_label(self.config.provider_plugin == loaded.name, &loaded.name).clicked() {
+                    selected_plugin = Some(loaded);
+                }
+            }
+        });
+        #[cfg(feature = "plugins")]
+        if let Some(loaded) = selected_plugin.filter(|loaded| loaded.name != self.config.provider_plugin) {
+            let logs = Arc::clone(&self.logs);
+            loaded.select(&mut self.config, |message, level| Self::log_static(&logs, message, level));
+            return true;
+        }
+        match selected {
+            Some(provider) if selected != current => {
+                self.config.switch_provider(provider);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn provider_ui(&mut self, ui: &mut egui::Ui, is_running: bool) {
+        let normalized = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
+        // Chassis numbers say nothing about the province
+        let detected = (self.config.search_by == SearchBy::Plate).then(|| provider::detect(&normalized)).flatten();
+        if normalized != self.provider_detected_for && !is_running {
+            self.provider_detected_for = normalized;
+            self.provider_manual = false;
+            // A plugin was picked on purpose; the plate says nothing about it
+            if let Some(guess) = detected.filter(|p| *p != self.config.provider && self.config.provider_plugin.is_empty()) {
+                self.config.switch_provider(guess);
+                self.plate_warned = None;
+                self.log(tr!("Plate looks like it was issued in {} - switched provider", tr!(guess.region())), LogLevel::Info);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Provider:"));
+            let changed = ui.add_enabled_ui(!is_running, |ui| self.provider_combo(ui, "scan_provider")).inner;
+            if changed {
+                self.provider_manual = true;
+                self.plate_warned = None;
+            }
+            match detected {
+                Some(guess) if guess == self.config.provider && !self.provider_manual && self.config.provider_plugin.is_empty() => {
+                    ui.weak(i18n::label("detected from the plate"));
+                }
+                Some(guess) if guess != self.config.provider => {
+                    ui.weak(i18n::display(&tr!("plate looks like {}", tr!(guess.region()))));
+                }
+                _ => {}
+            }
+            ui.add_enabled(!is_running, egui::Checkbox::new(&mut self.search_everywhere, i18n::label("Search every province")))
+            .on_hover_text(i18n::label("Sends each date to all providers at once and reports which one has the record"));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Search by:"));
+            let provider = self.config.provider;
+            ui.add_enabled_ui(!is_running, |ui| {
+                egui::ComboBox::from_id_source("search_by")
+                .selected_text(i18n::label(self.config.search_by.label()))
+                .show_ui(ui, |ui| {
+                    for by in SearchBy::ALL.into_iter().filter(|by| provider.supports(*by)) {
+                        ui.selectable_value(&mut self.config.search_by, by, i18n::label(by.label()));
+                    }
+                });
+            });
+            if SearchBy::ALL.iter().filter(|by| provider.supports(**by)).count() == 1 {
+                ui.weak(i18n::display(&tr!("{} only searches by registration number", tr!(provider.label()))));
+            }
+        });
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Language:"));
+            let previous = self.config.language;
+            egui::ComboBox::from_id_source("language")
+            .selected_text(i18n::display(self.config.language.label()))
+            .show_ui(ui, |ui| {
+                for language in Language::ALL {
+                    ui.selectable_value(&mut self.config.language, language, i18n::display(language.label()));
+                }
+            });
+            if self.config.language != previous {
+                self.apply_language(ctx);
+            }
+
+            ui.label(i18n::label("Font:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.ui_font_path)
+            .desired_width(200.0)
+            .hint_text(i18n::label("optional .ttf with Urdu glyphs")));
+            if ui.button(i18n::label("Apply")).clicked() {
+                self.apply_language(ctx);
+            }
+        });
+
+        ui.checkbox(&mut self.config.check_for_updates, i18n::label("Check GitHub for a newer version at startup"));
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Console verbosity:"));
+            let previous = self.config.verbosity;
+            egui::ComboBox::from_id_source("verbosity")
+            .selected_text(i18n::label(self.config.verbosity.label()))
+            .show_ui(ui, |ui| {
+                for verbosity in Verbosity::ALL {
+                    ui.selectable_value(&mut self.config.verbosity, verbosity, i18n::label(verbosity.label()));
+                }
+            });
+            if self.config.verbosity != previous {
+                self.config.verbosity.set();
+            }
+        })
+        .response
+        .on_hover_text(i18n::label("Quiet shows only hits and errors; Debug adds every classification decision; Trace adds request timings"));
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Results directory:"));
+            ui.monospace(self.results_dir.display().to_string());
+            let is_running = self.is_running.load(Ordering::SeqCst);
+            if ui.add_enabled(!is_running, egui::Button::new(i18n::label("Browse..."))).clicked() {
+                self.pick_results_dir();
+            }
+            if ui.add_enabled(!is_running, egui::Button::new(i18n::label("Reset"))).clicked() {
+                self.config.results_dir = config::DEFAULT_RESULTS_DIR.to_string();
+                vault::lock();
+                self.unlock_dismissed = false;
+                self.open_case();
+                self.save_config();
+            }
+        });
+        ui.horizontal(|ui| {
+            let encrypt = ui
+            .checkbox(&mut self.config.encrypt_results, i18n::label("Encrypt saved responses and results.db with a passphrase"))
+            .on_hover_text(i18n::label("AES-256-GCM, with the key derived from the passphrase. A lost passphrase can't be recovered."));
+            if encrypt.changed() {
+                vault::set_sealing(self.config.encrypt_results);
+                self.unlock_dismissed = false;
+            }
+            let is_running = self.is_running.load(Ordering::SeqCst);
+            if vault::is_unlocked() {
+                if ui.add_enabled(!is_running && vault::is_sealing(), egui::Button::new(i18n::label("Encrypt existing results"))).clicked() {
+                    self.encrypt_existing();
+                }
+            } else if vault::exists(std::path::Path::new(self.config.results_dir.trim())) && ui.button(i18n::label("Unlock...")).clicked() {
+                self.unlock_dismissed = false;
+            }
+        });
+        let redact = ui
+        .checkbox(&mut self.config.redact_personal, i18n::label("Mask owner names, CNICs and addresses in the console, log files, HTTP transcripts, MQTT, scripts and the found command"))
+        .on_hover_text(i18n::label("The dashboard and gRPC log streams carry the masked lines too. Saved responses and the results database keep everything; encrypt those to protect them too"));
+        if redact.changed() {
+            record::set_redaction(self.config.redact_personal);
+        }
+        self.lock_settings_ui(ui);
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("File name template:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.filename_template)
+            .desired_width(300.0)
+            .hint_text(storage::DEFAULT_FILENAME_TEMPLATE));
+        });
+        ui.label(i18n::label("Placeholders: {vehicle} {date} {status} {thread}. Each run is saved under <vehicle>/<run time>/."));
+
+        ui.add_space(5.0);
+        ui.label(i18n::label("Retention (0 = no limit, applied before each run):"));
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Max age (days):"));
+            ui.add(egui::DragValue::new(&mut self.config.retention.max_age_days));
+            ui.label(i18n::label("Max size (MB):"));
+            ui.add(egui::DragValue::new(&mut self.config.retention.max_size_mb));
+            ui.label(i18n::label("Max files:"));
+            ui.add(egui::DragValue::new(&mut self.config.retention.max_files));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.config.retention.keep_hits, i18n::label("Keep found records forever"));
+            let is_running = self.is_running.load(Ordering::SeqCst);
+            if ui.add_enabled(!is_running && self.config.retention.is_enabled(), egui::Button::new(i18n::label("Clean up now"))).clicked() {
+                self.cleanup_results();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Plate separator:"));
+            let selected = plate::SEPARATORS
+            .iter()
+            .find(|(sep, _)| *sep == self.config.plate_separator)
+            .map(|(_, label)| i18n::label(label))
+            .unwrap_or_else(|| self.config.plate_separator.clone());
+            egui::ComboBox::from_id_source("plate_separator")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (sep, label) in plate::SEPARATORS {
+                    ui.selectable_value(&mut self.config.plate_separator, sep.to_string(), i18n::label(label));
+                }
+            });
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Provider:"));
+            if self.provider_combo(ui, "provider") {
+                self.provider_manual = true;
+                self.log(tr!("Switched to {} - endpoint and no-record rule updated", self.provider_name()), LogLevel::Info);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Endpoint URL:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.endpoint_url).desired_width(400.0));
+            if ui.button(i18n::label("Reset")).clicked() {
+                self.config.endpoint_url = self.config.provider.endpoint().to_string();
+            }
+        });
+        ui.label(i18n::label("Fallback endpoints (used in order when the current one keeps failing):"));
+        let mut remove_endpoint = None;
+        egui::Grid::new("fallback_endpoints").show(ui, |ui| {
+            for (i, url) in self.config.fallback_endpoints.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(url).desired_width(400.0));
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove_endpoint = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove_endpoint {
+            self.config.fallback_endpoints.remove(i);
+        }
+        if ui.button(i18n::label("Add Fallback Endpoint")).clicked() {
+            self.config.fallback_endpoints.push(String::new());
+        }
+
+        ui.add_space(5.0);
+        self.request_template_settings(ui);
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Timeouts in seconds (0 = none) - connect:"));
+            ui.add(egui::DragValue::new(&mut self.config.connect_timeout_secs));
+            ui.label(i18n::label("total:"));
+            ui.add(egui::DragValue::new(&mut self.config.request_timeout_secs));
+        });
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Pause the run after this many failed requests in a row (0 = never):"));
+            ui.add(egui::DragValue::new(&mut self.config.breaker_threshold));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Custom CA certificate (PEM):"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.ca_cert_path)
+            .desired_width(300.0)
+            .hint_text(i18n::label("optional path")));
+        });
+        ui.checkbox(&mut self.config.accept_invalid_certs, i18n::label("Accept invalid TLS certificates (insecure)"));
+        ui.checkbox(&mut self.config.transcript_enabled, i18n::label("Debug: write a full HTTP transcript (headers and bodies) of every request"));
+        ui.checkbox(&mut self.config.audit_log_enabled, i18n::label("Append hits, errors and run events to audit.jsonl in the results folder"));
+        if self.config.accept_invalid_certs {
+            ui.colored_label(egui::Color32::from_rgb(255, 165, 0),
+                             i18n::label("Certificate errors will be ignored - only use this for a known self-signed server."));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Offline fixture directory:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.offline_fixture_dir)
+            .desired_width(300.0)
+            .hint_text(i18n::label("empty = live endpoint")));
+        });
+        ui.checkbox(&mut self.config.cookies_enabled, i18n::label("Keep cookies between requests"));
+        ui.checkbox(&mut self.config.health_check_enabled, i18n::label("Check the endpoint with a test lookup before each run"));
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Session bootstrap URL:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.session_bootstrap_url)
+            .desired_width(300.0)
+            .hint_text(i18n::label("optional, fetched once before each run")));
+        });
+
+        ui.add_space(5.0);
+        ui.label(i18n::label("Extra request headers:"));
+        let mut remove_header = None;
+        egui::Grid::new("extra_headers").show(ui, |ui| {
+            for (i, (name, value)) in self.config.extra_headers.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(name).desired_width(150.0).hint_text(i18n::label("Header")));
+                ui.add(egui::TextEdit::singleline(value).desired_width(300.0).hint_text(i18n::label("Value")));
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove_header = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove_header {
+            self.config.extra_headers.remove(i);
+        }
+        if ui.button(i18n::label("Add Header")).clicked() {
+            self.config.extra_headers.push((String::new(), String::new()));
+        }
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.config.metrics_enabled, i18n::label("Prometheus /metrics on"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.metrics_bind).desired_width(150.0));
+            ui.label(i18n::label("(applies on restart)"));
+        });
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.config.server_enabled, i18n::label("Server mode (web dashboard + WebSocket) on"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.server_bind).desired_width(150.0));
+            ui.label(i18n::label("(applies on restart)"));
+        });
+        self.api_token_settings(ui);
+        self.mqtt_settings(ui);
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Run on every hit:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.found_command).desired_width(300.0).hint_text("notify.sh \"$VEH_REG_RESULT\""))
+            .on_hover_text(i18n::label("Shell command; gets VEH_REG_VEHICLE, VEH_REG_DATE, VEH_REG_RESULT, VEH_REG_FOUND_AT and VEH_REG_FIELDS"));
+            ui.label(i18n::label("(applies on restart)"));
+        });
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Browser for result images:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.browser_path).desired_width(300.0).hint_text(i18n::label("Chrome, Edge or Chromium if empty")));
+        });
+
+        #[cfg(feature = "tray")]
+        ui.checkbox(&mut self.config.minimize_to_tray, i18n::label("Closing the window minimizes to the tray"));
+
+        #[cfg(feature = "scripting")]
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Script:"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.script_path).desired_width(250.0).hint_text("hooks.rhai"));
+            if ui.button(i18n::label("Browse...")).clicked() {
+                if let Some(path) = rfd::FileDialog::new().add_filter("Rhai", &["rhai"]).pick_file() {
+                    self.config.script_path = path.display().to_string();
+                }
+            }
+            ui.label(i18n::label("(applies on restart)"));
+        });
+
+        #[cfg(feature = "grpc")]
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.config.grpc_enabled, i18n::label("gRPC control API on"));
+            ui.add(egui::TextEdit::singleline(&mut self.config.grpc_bind).desired_width(150.0));
+            ui.label(i18n::label("(applies on restart)"));
+        });
+
+        ui.add_space(5.0);
+        if ui.button(i18n::label("Save Settings")).clicked() {
+            self.save_config();
+        }
+    }
+
+    fn read_for_compare(&self, name: &str) -> Result<String, String> {
+        let body = vault::read_to_string(&self.results_dir.join(name)).map_err(|e| format!("{} - {}", name, e))?;
+        if self.compare_text_only {
+            Ok(record::clean_text_lines(&body).join("\n"))
+        } else {
+            Ok(body)
+        }
+    }
+
+    fn compare_ui(&mut self, ui: &mut egui::Ui) {
+        let files: Vec<String> = self
+        .control
+        .list_results()
+        .into_iter()
+        .map(|f| f.name)
+        .filter(|name| name.ends_with(".html"))
+        .collect();
+
+        ui.horizontal(|ui| {
+            for (id, selected) in [("compare_left", &mut self.compare_left), ("compare_right", &mut self.compare_right)] {
+                egui::ComboBox::from_id_source(id)
+                .width(250.0)
+                .selected_text(if selected.is_empty() { i18n::label("Choose a response") } else { selected.clone() })
+                .show_ui(ui, |ui| {
+                    for name in &files {
+                        ui.selectable_value(selected, name.clone(), name);
+                    }
+                });
+            }
+            ui.checkbox(&mut self.compare_text_only, i18n::label("Visible text only"));
+        });
+        ui.separator();
+
+        if self.compare_left.is_empty() || self.compare_right.is_empty() {
+            ui.label(i18n::label("Pick two saved responses to see what changed between them."));
+            return;
+        }
+
+        let key = (self.compare_left.clone(), self.compare_right.clone(), self.compare_text_only);
+        if self.compare_cache.as_ref().is_none_or(|(cached, _)| *cached != key) {
+            let result = self
+            .read_for_compare(&key.0)
+            .and_then(|old| self.read_for_compare(&key.1).map(|new| diff::diff(&old, &new, 3)));
+            self.compare_cache = Some((key, result));
+        }
+
+        let Some((_, result)) = &self.compare_cache else {
+            return;
+        };
+        match result {
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            Ok(lines) if lines.is_empty() => {
+                ui.label(i18n::label("The two responses are identical."));
+            }
+            Ok(lines) => {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    for line in lines {
+                        let (prefix, color) = match line.kind {
+                            diff::LineKind::Same => ("  ", egui::Color32::GRAY),
+                            diff::LineKind::Removed => ("- ", egui::Color32::from_rgb(255, 100, 100)),
+                            diff::LineKind::Added => ("+ ", egui::Color32::from_rgb(100, 220, 100)),
+                        };
+                        let plain = egui::TextFormat {
+                            font_id: egui::FontId::monospace(12.0),
+                            color,
+                            ..Default::default()
+                        };
+                        // Changed words get a tinted background on top of the line colour
+                        let highlighted = egui::TextFormat {
+                            background: color.gamma_multiply(0.35),
+                            ..plain.clone()
+                        };
+
+                        let mut job = egui::text::LayoutJob::default();
+                        job.append(prefix, 0.0, plain.clone());
+                        for (emphasized, text) in &line.segments {
+                            let format = if *emphasized { highlighted.clone() } else { plain.clone() };
+                            job.append(text, 0.0, format);
+                        }
+                        ui.label(job);
+                    }
+                });
+            }
+        }
+    }
+
+    // Tokens for the server's API; the token itself is masked like a password
+    fn api_token_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(i18n::label("API tokens (none = no authentication):"));
+        let mut remove = None;
+        egui::Grid::new("api_tokens").show(ui, |ui| {
+            for (i, token) in self.config.api_tokens.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut token.name).desired_width(120.0).hint_text(i18n::label("Name")));
+                ui.add(egui::TextEdit::singleline(&mut token.token).desired_width(220.0).password(true));
+                egui::ComboBox::from_id_source(("token_scope", i))
+                .selected_text(i18n::label(token.scope.label()))
+                .show_ui(ui, |ui| {
+                    for scope in TokenScope::ALL {
+                        ui.selectable_value(&mut token.scope, scope, i18n::label(scope.label()));
+                    }
+                });
+                if ui.button(i18n::label("Copy")).clicked() {
+                    ui.output_mut(|o| o.copied_text = token.token.clone());
+                }
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.config.api_tokens.remove(i);
+        }
+        if ui.button(i18n::label("Add Token")).clicked() {
+            self.config.api_tokens.push(ApiToken {
+                name: format!("token {}", self.config.api_tokens.len() + 1),
+                token: auth::generate_token(),
+                scope: TokenScope::ReadOnly,
+            });
+        }
+    }
+
+    fn request_template_settings(&mut self, ui: &mut egui::Ui) {
+        let (provider, search_by) = (self.config.provider, self.config.search_by);
+        let template = &mut self.config.request_template;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut template.enabled, i18n::label("Send a custom request template instead of the provider's form"));
+            if ui.button(i18n::label("Start from the provider's form")).clicked() {
+                *template = RequestTemplate { enabled: true, ..RequestTemplate::from_provider(provider, search_by) };
+            }
+            if ui.button(i18n::label("Import cURL command...")).clicked() {
+                self.curl_import = Some(String::new());
+            }
+        });
+        if !template.enabled {
             return;
         }
-
-        let start_date = match NaiveDate::parse_from_str(start_date_str, "%Y-%m-%d") {
-            Ok(d) => d,
-            Err(_) => {
-                self.log("Invalid start date format. Use YYYY-MM-DD".to_string(), LogLevel::Error);
-                return;
+        ui.horizontal(|ui| {
+            ui.label(i18n::label("Encoding:"));
+            egui::ComboBox::from_id_source("template_encoding")
+            .selected_text(i18n::label(template.encoding.label()))
+            .show_ui(ui, |ui| {
+                for encoding in Encoding::ALL {
+                    ui.selectable_value(&mut template.encoding, encoding, i18n::label(encoding.label()));
+                }
+            });
+            if template.encoding == Encoding::Multipart {
+                ui.label(i18n::label("Boundary:"));
+                ui.add(egui::TextEdit::singleline(&mut template.boundary).desired_width(250.0));
             }
-        };
-
-        let end_date = match NaiveDate::parse_from_str(end_date_str, "%Y-%m-%d") {
-            Ok(d) => d,
-            Err(_) => {
-                self.log("Invalid end date format. Use YYYY-MM-DD".to_string(), LogLevel::Error);
-                return;
+        });
+        ui.label(i18n::label("Fields, in order. Values can use {vehicle}, {date}, {day}, {month} and {year}; others are sent as typed:"));
+        let mut remove_field = None;
+        egui::Grid::new("template_fields").show(ui, |ui| {
+            for (i, (name, value)) in template.fields.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(name).desired_width(150.0).hint_text(i18n::label("Field")));
+                ui.add(egui::TextEdit::singleline(value).desired_width(300.0).hint_text(i18n::label("Value")));
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove_field = Some(i);
+                }
+                ui.end_row();
             }
-        };
+        });
+        if let Some(i) = remove_field {
+            template.fields.remove(i);
+        }
+        if ui.button(i18n::label("Add Field")).clicked() {
+            template.fields.push((String::new(), String::new()));
+        }
+        let (content_type, body) = template.render(TEMPLATE_PREVIEW_VEHICLE, TEMPLATE_PREVIEW_DATE);
+        ui.collapsing(i18n::label("Preview"), |ui| {
+            ui.monospace(format!("Content-Type: {}\n\n{}", content_type, String::from_utf8_lossy(&body)));
+        });
+    }
 
-        if start_date > end_date {
-            self.log("Starting date must be before ending date".to_string(), LogLevel::Error);
+    fn mqtt_settings(&mut self, ui: &mut egui::Ui) {
+        let mqtt = &mut self.config.mqtt;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut mqtt.enabled, i18n::label("Publish hits to MQTT"));
+            ui.add(egui::TextEdit::singleline(&mut mqtt.broker).desired_width(150.0).hint_text("host:1883"));
+            ui.label(i18n::label("(applies on restart)"));
+        });
+        if !mqtt.enabled {
             return;
         }
+        egui::Grid::new("mqtt_settings").show(ui, |ui| {
+            ui.label(i18n::label("Topic:"));
+            ui.add(egui::TextEdit::singleline(&mut mqtt.topic).desired_width(150.0));
+            ui.label(i18n::label("QoS:"));
+            egui::ComboBox::from_id_source("mqtt_qos").selected_text(mqtt.qos.to_string()).show_ui(ui, |ui| {
+                for qos in 0..=2u8 {
+                    ui.selectable_value(&mut mqtt.qos, qos, qos.to_string());
+                }
+            });
+            ui.end_row();
+            ui.label(i18n::label("Client ID:"));
+            ui.add(egui::TextEdit::singleline(&mut mqtt.client_id).desired_width(150.0));
+            ui.end_row();
+            ui.label(i18n::label("Username:"));
+            ui.add(egui::TextEdit::singleline(&mut mqtt.username).desired_width(150.0));
+            ui.label(i18n::label("Password:"));
+            ui.add(egui::TextEdit::singleline(&mut mqtt.password).desired_width(150.0).password(true));
+            ui.end_row();
+        });
+    }
 
-        let total_days = (end_date - start_date).num_days() + 1;
-        let days_per_thread = total_days / self.num_threads as i64;
-        let remainder_days = total_days % self.num_threads as i64;
-
-        // Reset state
-        self.is_running.store(true, Ordering::SeqCst);
-        self.record_found.store(false, Ordering::SeqCst);
-        *self.found_count.lock().unwrap() = 0;
-        *self.checked_dates.lock().unwrap() = 0;
-        *self.total_dates.lock().unwrap() = total_days as usize;
-
-        self.log(format!("Starting check for vehicle: {}", vehicle_no), LogLevel::Info);
-        self.log(format!("Date range: {} to {}", start_date_str, end_date_str), LogLevel::Info);
-        self.log(format!("Total days to check: {}", total_days), LogLevel::Info);
-        self.log(format!("Threads: {}, ~{} days per thread", self.num_threads, days_per_thread), LogLevel::Info);
-        self.log(format!("Results will be saved to: {:?}", self.results_dir), LogLevel::Info);
-        self.log("Program will STOP automatically when a record is found!".to_string(), LogLevel::Warning);
-        self.log("-".repeat(80), LogLevel::Info);
-
-        let results_dir = self.results_dir.clone();
-        let logs = Arc::clone(&self.logs);
-        let is_running = Arc::clone(&self.is_running);
-        let record_found = Arc::clone(&self.record_found);
-        let found_count = Arc::clone(&self.found_count);
-        let checked_dates = Arc::clone(&self.checked_dates);
-        let num_threads = self.num_threads;
+    fn rules_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(i18n::label("Rules are tried top to bottom, first match wins. Unmatched 200 responses are hits. Join substrings with && to require all of them."));
+        let mut remove = None;
 
-        // Spawn threads
-        thread::spawn(move || {
-            let mut handles = vec![];
-            let mut current_start = start_date;
+        egui::Grid::new("classifier_rules").striped(true).show(ui, |ui| {
+            ui.label(i18n::label("On"));
+            ui.label(i18n::label("Name"));
+            ui.label(i18n::label("Match"));
+            ui.label(i18n::label("Pattern"));
+            ui.label(i18n::label("Status"));
+            ui.label(i18n::label("Outcome"));
+            ui.end_row();
 
-            for i in 0..num_threads {
-                let thread_days = days_per_thread + if i < remainder_days as usize { 1 } else { 0 };
-                let thread_end = current_start + Duration::days(thread_days - 1);
-                let thread_end = if thread_end > end_date { end_date } else { thread_end };
+            for (i, rule) in self.config.classifier_rules.iter_mut().enumerate() {
+                ui.checkbox(&mut rule.enabled, "");
+                ui.add(egui::TextEdit::singleline(&mut rule.name).desired_width(120.0));
+                egui::ComboBox::from_id_source(("rule_kind", i))
+                .selected_text(i18n::label(rule.kind.label()))
+                .show_ui(ui, |ui| {
+                    for kind in [MatchKind::Substring, MatchKind::Regex] {
+                        ui.selectable_value(&mut rule.kind, kind, i18n::label(kind.label()));
+                    }
+                });
+                ui.add(egui::TextEdit::singleline(&mut rule.pattern).desired_width(220.0));
 
-                let log_msg = format!("Thread {}: {} to {}", i + 1,
-                                      current_start.format("%Y-%m-%d"), thread_end.format("%Y-%m-%d"));
-                Self::log_static(&logs, log_msg, LogLevel::Info);
+                let mut status_text = rule.status.map(|s| s.to_string()).unwrap_or_default();
+                if ui.add(egui::TextEdit::singleline(&mut status_text).desired_width(40.0).hint_text(i18n::label("any"))).changed() {
+                    rule.status = status_text.trim().parse().ok();
+                }
 
-                let vehicle = vehicle_no.clone();
-                let logs_clone = Arc::clone(&logs);
-                let is_running_clone = Arc::clone(&is_running);
-                let record_found_clone = Arc::clone(&record_found);
-                let found_count_clone = Arc::clone(&found_count);
-                let checked_dates_clone = Arc::clone(&checked_dates);
-                let results_dir_clone = results_dir.clone();
-                let thread_id = i + 1;
-
-                let handle = thread::spawn(move || {
-                    Self::check_vehicle_thread(
-                        vehicle,
-                        current_start,
-                        thread_end,
-                        thread_id,
-                        logs_clone,
-                        is_running_clone,
-                        record_found_clone,
-                        found_count_clone,
-                        checked_dates_clone,
-                        results_dir_clone,
-                    );
+                egui::ComboBox::from_id_source(("rule_outcome", i))
+                .selected_text(i18n::label(rule.outcome.label()))
+                .show_ui(ui, |ui| {
+                    for outcome in Classification::ALL {
+                        ui.selectable_value(&mut rule.outcome, outcome, i18n::label(outcome.label()));
+                    }
                 });
 
-                handles.push(handle);
-                current_start = thread_end + Duration::days(1);
-
-                if current_start > end_date {
-                    break;
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove = Some(i);
                 }
+                ui.end_row();
             }
+        });
 
-            Self::log_static(&logs, "-".repeat(80), LogLevel::Info);
+        if let Some(i) = remove {
+            self.config.classifier_rules.remove(i);
+        }
 
-            // Wait for all threads
-            for handle in handles {
-                let _ = handle.join();
+        ui.horizontal(|ui| {
+            if ui.button(i18n::label("Add Rule")).clicked() {
+                self.config.classifier_rules.push(ClassifierRule {
+                    name: "New rule".to_string(),
+                    enabled: true,
+                    kind: MatchKind::Substring,
+                    pattern: String::new(),
+                    status: None,
+                    outcome: Classification::Miss,
+                });
             }
 
-            is_running.store(false, Ordering::SeqCst);
+            if ui.button(i18n::label("Restore Defaults")).clicked() {
+                self.config.classifier_rules = classify::default_rules();
+            }
+
+            if ui.button(i18n::label("Save Rules")).clicked() {
+                match self.classifier() {
+                    Ok(_) => self.save_config(),
+                    Err(e) => self.log(e, LogLevel::Error),
+                }
+            }
         });
+
+        ui.add_space(10.0);
+        ui.separator();
+        self.extraction_rules_ui(ui);
     }
 
-    fn log_static(logs: &Arc<Mutex<Vec<LogEntry>>>, message: String, level: LogLevel) {
-        let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        let entry = LogEntry {
-            timestamp,
-            message,
-            level,
-        };
+    // Fields the generic parser misses, with a live preview against a saved
+    // response so a pattern can be fixed before it is saved
+    fn extraction_rules_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading(i18n::label("Field extraction"));
+        ui.label(i18n::label("Pull extra fields out of hit pages. A regex gives its first capture group, a CSS selector the text of the first element it matches. These come first and replace generic fields of the same name."));
+        let mut remove = None;
+        egui::Grid::new("extraction_rules").striped(true).show(ui, |ui| {
+            ui.label(i18n::label("On"));
+            ui.label(i18n::label("Field"));
+            ui.label(i18n::label("Match"));
+            ui.label(i18n::label("Pattern"));
+            ui.end_row();
 
-        if let Ok(mut logs) = logs.lock() {
-            logs.push(entry);
-            if logs.len() > 1000 {
-                logs.drain(0..100);
+            for (i, rule) in self.config.extraction_rules.iter_mut().enumerate() {
+                ui.checkbox(&mut rule.enabled, "");
+                ui.add(egui::TextEdit::singleline(&mut rule.name).desired_width(120.0));
+                egui::ComboBox::from_id_source(("extract_kind", i))
+                .selected_text(i18n::label(rule.kind.label()))
+                .show_ui(ui, |ui| {
+                    for kind in ExtractKind::ALL {
+                        ui.selectable_value(&mut rule.kind, kind, i18n::label(kind.label()));
+                    }
+                });
+                ui.add(egui::TextEdit::singleline(&mut rule.pattern).desired_width(300.0).code_editor());
+                if ui.button(i18n::label("Remove")).clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
             }
+        });
+        if let Some(i) = remove {
+            self.config.extraction_rules.remove(i);
         }
-    }
 
-    fn check_vehicle_thread(
-        vehicle_no: String,
-        start_date: NaiveDate,
-        end_date: NaiveDate,
-        thread_id: usize,
-        logs: Arc<Mutex<Vec<LogEntry>>>,
-        is_running: Arc<AtomicBool>,
-        record_found: Arc<AtomicBool>,
-        found_count: Arc<Mutex<usize>>,
-        checked_dates: Arc<Mutex<usize>>,
-        results_dir: PathBuf,
-    ) {
-        let mut current_date = start_date;
-        let mut checked_count = 0;
-
-        while current_date <= end_date && is_running.load(Ordering::SeqCst) && !record_found.load(Ordering::SeqCst) {
-            let date_str = current_date.format("%Y-%m-%d").to_string();
-
-            match Self::make_request(&vehicle_no, &date_str) {
-                Ok((status, response)) => {
-                    checked_count += 1;
+        ui.horizontal(|ui| {
+            if ui.button(i18n::label("Add Field")).clicked() {
+                self.config.extraction_rules.push(ExtractionRule {
+                    name: String::new(),
+                    enabled: true,
+                    kind: ExtractKind::Regex,
+                    pattern: String::new(),
+                });
+            }
+            if ui.button(i18n::label("Load Sample Response...")).clicked() {
+                if let Some(path) = rfd::FileDialog::new().set_directory(&self.results_dir).add_filter("HTML", &["html", "htm", "json", "txt"]).pick_file() {
+                    match vault::read_to_string(&path) {
+                        Ok(body) => self.extraction_sample = Some((path, body)),
+                        Err(e) => self.log(tr!("Could not read {} - {}", path.display(), e), LogLevel::Error),
+                    }
+                }
+            }
+            if ui.button(i18n::label("Save Fields")).clicked() {
+                match record::set_rules(&self.config.extraction_rules) {
+                    Ok(()) => self.save_config(),
+                    Err(e) => self.log(e, LogLevel::Error),
+                }
+            }
+        });
 
-                    // Increment global checked dates counter
-                    if let Ok(mut count) = checked_dates.lock() {
-                        *count += 1;
+        let Some((path, body)) = &self.extraction_sample else { return };
+        ui.label(i18n::display(&tr!("Preview against {}", path.display())));
+        match record::preview(&self.config.extraction_rules, body) {
+            Ok(fields) if fields.is_empty() => {
+                ui.weak(i18n::label("No fields found"));
+            }
+            Ok(fields) => {
+                let custom: Vec<&str> = self.config.extraction_rules.iter().filter(|r| r.enabled).map(|r| r.name.trim()).collect();
+                egui::Grid::new("extraction_preview").striped(true).show(ui, |ui| {
+                    for (label, value) in &fields {
+                        if custom.iter().any(|name| name.eq_ignore_ascii_case(label)) {
+                            ui.strong(label);
+                        } else {
+                            ui.label(label);
+                        }
+                        ui.label(value);
+                        ui.end_row();
                     }
+                });
+            }
+            Err(e) => {
+                ui.colored_label(egui::Color32::from_rgb(255, 80, 80), e);
+            }
+        }
+    }
+}
 
-                    if status != 200 {
-                        let msg = format!("Thread {}: HTTP {} Error - Vehicle: {}, Date: {}",
-                                          thread_id, status, vehicle_no, date_str);
-                        Self::log_static(&logs, msg, LogLevel::Error);
+// What the GUI does with the events of a running scan: logs them, saves the
+// pages worth keeping and feeds the hit list and counters the window shows
+struct RunSink {
+    vehicle_no: String,
+    logs: Arc<Mutex<Vec<LogEntry>>>,
+    hits: Arc<Mutex<Vec<HitRecord>>>,
+    found_count: Arc<Mutex<usize>>,
+    checked_dates: Arc<Mutex<usize>>,
+    record_found: Arc<AtomicBool>,
+    location: SaveLocation,
+    // Label of the provider asked, empty when every province is
+    provider: String,
+    // Misses per thread, for the every-tenth progress line
+    misses: HashMap<usize, usize>,
+}
 
-                        Self::save_response(&vehicle_no, &date_str, &response, thread_id, status, &results_dir, &logs, &found_count);
+impl RunSink {
+    fn log(&self, message: String, level: LogLevel) {
+        VehicleChecker::log_static(&self.logs, message, level);
+    }
 
-                        let preview = response.chars().take(300).collect::<String>()
-                        .replace('\n', " ").replace('\t', " ");
-                        Self::log_static(&logs, format!("Response preview: {}...", preview), LogLevel::Error);
+    fn classified(&self, thread: usize, outcome: &CheckOutcome) {
+        self.log(tr!("Thread {}: {} classified as {} by rule '{}'", thread, outcome.date.format("%Y-%m-%d"),
+                     i18n::translate(outcome.classification.label()), outcome.rule), LogLevel::Debug);
+    }
 
-                        record_found.store(true, Ordering::SeqCst);
-                        Self::log_static(&logs, format!("Thread {}: Stopping all threads due to HTTP {} error", thread_id, status), LogLevel::Warning);
-                        break;
-                    } else if response.to_uppercase().contains("NO RECORD FOUND") &&
-                        response.to_uppercase().contains("PLEASE CONTACT EXCISE") {
-                            if checked_count % 10 == 0 {
-                                let msg = format!("Thread {}: Checked {} dates, currently at {} - No records",
-                                                  thread_id, checked_count, date_str);
-                                Self::log_static(&logs, msg, LogLevel::Info);
-                            }
-                        } else {
-                            let msg = format!("Thread {}: *** RECORD FOUND *** - Vehicle: {}, Date: {}",
-                                              thread_id, vehicle_no, date_str);
-                            Self::log_static(&logs, msg, LogLevel::Success);
-                            Self::log_static(&logs, "=".repeat(80), LogLevel::Success);
-                            Self::log_static(&logs, "RECORD FOUND! STOPPING ALL THREADS".to_string(), LogLevel::Success);
-                            Self::log_static(&logs, "=".repeat(80), LogLevel::Success);
+    // The start of a page that needs a human to look at it, masked as a
+    // transcript would be
+    fn preview(body: &str) -> String {
+        record::redact_body(body).chars().take(300).collect::<String>().replace(['\n', '\t'], " ")
+    }
 
-                            Self::save_response(&vehicle_no, &date_str, &response, thread_id, status, &results_dir, &logs, &found_count);
+    fn handle(&mut self, event: ScanEvent, scan: &ScanControl) {
+        let vehicle_no = self.vehicle_no.clone();
+        match event {
+            ScanEvent::Progress { checked, .. } => {
+                if let Ok(mut count) = self.checked_dates.lock() {
+                    *count = checked;
+                }
+            }
+            ScanEvent::Response { thread, date, latency, result } => {
+                if Verbosity::current().shows(LogLevel::Trace) {
+                    let msg = match result {
+                        Ok((status, bytes)) => tr!("Thread {}: {} - HTTP {} in {} ms, {}",
+                                                   thread, date.format("%Y-%m-%d"), status, latency.as_millis(), stats::format_bytes(bytes as u64)),
+                        Err(e) => tr!("Thread {}: {} - failed after {} ms: {}", thread, date.format("%Y-%m-%d"), latency.as_millis(), e),
+                    };
+                    self.log(msg, LogLevel::Trace);
+                }
+            }
+            ScanEvent::Skipped { thread, date } => {
+                self.log(tr!("Thread {}: {} already checked, skipping", thread, date.format("%Y-%m-%d")), LogLevel::Debug);
+            }
+            ScanEvent::Miss { thread, outcome } => {
+                self.classified(thread, &outcome);
+                let misses = self.misses.entry(thread).or_default();
+                *misses += 1;
+                if *misses % 10 == 0 {
+                    let msg = tr!("Thread {}: Checked {} dates, currently at {} - No records", thread, *misses, outcome.date.format("%Y-%m-%d"));
+                    self.log(msg, LogLevel::Info);
+                }
+            }
+            ScanEvent::Hit { thread, outcome, hits, stopping } => {
+                self.classified(thread, &outcome);
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                let shared_date = record::shareable_date(&date_str);
+                self.log(tr!("Thread {}: *** RECORD FOUND *** - Vehicle: {}, Date: {}", thread, vehicle_no, shared_date), LogLevel::Success);
+                audit::record(audit::Event::new("hit", &vehicle_no).thread(thread).date(&shared_date).status(outcome.status));
+                self.record_found.store(true, Ordering::SeqCst);
+                self.log("=".repeat(80), LogLevel::Success);
+                if stopping {
+                    self.log(tr!("RECORD FOUND! STOPPING ALL THREADS"), LogLevel::Success);
+                } else {
+                    self.log(tr!("RECORD FOUND! ({} so far, continuing)", hits), LogLevel::Success);
+                }
+                self.log("=".repeat(80), LogLevel::Success);
 
-                            let preview = response.chars().take(300).collect::<String>()
-                            .replace('\n', " ").replace('\t', " ");
-                            Self::log_static(&logs, format!("Preview: {}...", preview), LogLevel::Success);
+                let file_name = self.save_response(thread, &outcome);
+                let key_fields = record::key_fields(&outcome.fields);
+                if key_fields.is_empty() && !record::redacting() {
+                    // Nothing recognisable on the page, show the raw start instead
+                    self.log(tr!("Preview: {}...", Self::preview(&outcome.body)), LogLevel::Success);
+                }
+                for (name, value) in key_fields {
+                    self.log(format!("{}: {}", i18n::translate(name), record::shareable(name, value)), LogLevel::Success);
+                }
 
-                            record_found.store(true, Ordering::SeqCst);
-                            break;
-                        }
+                let hit = HitRecord {
+                    vehicle_no,
+                    date: date_str,
+                    file_name,
+                    found_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    fields: outcome.fields,
+                    provider: self.provider.clone(),
+                };
+                if let Ok(mut hits) = self.hits.lock() {
+                    hits.push(hit.clone());
                 }
-                Err(e) => {
-                    let msg = format!("Thread {}: Error checking {} - {}", thread_id, date_str, e);
-                    Self::log_static(&logs, msg, LogLevel::Error);
+                control::publish(EngineEvent::Hit(hit));
+            }
+            ScanEvent::RateLimited { thread, outcome, cooloff } => {
+                self.classified(thread, &outcome);
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                let msg = tr!("Thread {}: Rate limited at {} (HTTP {}, {}) - pausing all threads for {}s, date will be retried",
+                              thread, date_str, outcome.status, outcome.rule, cooloff.as_secs());
+                self.log(msg, LogLevel::Warning);
+                audit::record(audit::Event::new("rate_limited", &vehicle_no).thread(thread).date(&date_str).status(outcome.status).detail(outcome.rule));
+            }
+            ScanEvent::Challenge { thread, outcome, paused } => {
+                self.classified(thread, &outcome);
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                audit::record(audit::Event::new("challenge", &vehicle_no).thread(thread).date(&date_str).status(outcome.status).detail(outcome.rule.clone()));
+                if paused {
+                    self.log(tr!("Thread {}: Challenge page at {} ({}) - scan PAUSED until resumed", thread, date_str, outcome.rule), LogLevel::Error);
+                    self.log(tr!("Response preview: {}...", Self::preview(&outcome.body)), LogLevel::Error);
                 }
             }
-
-            current_date = current_date + Duration::days(1);
+            ScanEvent::BreakerOpen { thread, date, failures } => {
+                let msg = tr!("Thread {}: {} failed requests in a row - circuit breaker tripped, scan PAUSED until resumed", thread, failures);
+                self.log(msg, LogLevel::Error);
+                audit::record(audit::Event::new("circuit_breaker_open", &vehicle_no).thread(thread).date(&date.format("%Y-%m-%d").to_string()));
+            }
+            ScanEvent::Deferred { thread, date } => {
+                self.log(tr!("Thread {}: {} will be retried after the pause", thread, date.format("%Y-%m-%d")), LogLevel::Debug);
+            }
+            ScanEvent::Retrying { thread, date, reason } => {
+                let date_str = date.format("%Y-%m-%d").to_string();
+                self.log(tr!("Thread {}: Error checking {} - {}, will retry", thread, date_str, reason), LogLevel::Warning);
+                audit::record(audit::Event::new("endpoint_failure", &vehicle_no).thread(thread).date(&date_str).detail(reason));
+            }
+            ScanEvent::FailedOver { thread, from, to } => {
+                self.log(tr!("Thread {}: {} keeps failing, failing over to {}", thread, from, to), LogLevel::Warning);
+            }
+            ScanEvent::ConcurrencyChanged { thread, change: LimitChange::Increased(limit) } => {
+                self.log(tr!("Thread {}: Responses healthy, raising concurrency to {}", thread, limit), LogLevel::Info);
+            }
+            ScanEvent::ConcurrencyChanged { thread, change: LimitChange::Decreased(limit) } => {
+                self.log(tr!("Thread {}: Error rate spiking, lowering concurrency to {}", thread, limit), LogLevel::Warning);
+            }
+            ScanEvent::Error { thread, outcome, stopping } => {
+                self.classified(thread, &outcome);
+                let date_str = outcome.date.format("%Y-%m-%d").to_string();
+                let msg = tr!("Thread {}: HTTP {} Error ({}) - Vehicle: {}, Date: {}", thread, outcome.status, outcome.rule, vehicle_no, date_str);
+                self.log(msg, LogLevel::Error);
+                audit::record(audit::Event::new("server_error", &vehicle_no).thread(thread).date(&date_str).status(outcome.status).detail(outcome.rule.clone()));
+                self.save_response(thread, &outcome);
+                self.log(tr!("Response preview: {}...", Self::preview(&outcome.body)), LogLevel::Error);
+                if stopping {
+                    self.log(tr!("Thread {}: Stopping all threads due to HTTP {} error", thread, outcome.status), LogLevel::Warning);
+                }
+            }
+            ScanEvent::Failed { thread, date, error } => {
+                let date_str = date.format("%Y-%m-%d").to_string();
+                self.log(tr!("Thread {}: Error checking {} - {}", thread, date_str, error), LogLevel::Error);
+                audit::record(audit::Event::new("request_failed", &vehicle_no).thread(thread).date(&date_str).detail(error));
+            }
+            ScanEvent::Stopped { reason } => {
+                self.log(tr!("Stopping all threads: {}", VehicleChecker::describe_stop_reason(reason)), LogLevel::Warning);
+            }
+            ScanEvent::Finished { thread, checked } => match scan.stop_reason() {
+                Some(reason) => self.log(tr!("Thread {}: Stopped - {}", thread, VehicleChecker::describe_stop_reason(reason)), LogLevel::Warning),
+                None => self.log(tr!("Thread {}: Completed - Checked {} dates", thread, checked), LogLevel::Warning),
+            },
         }
+    }
 
-        if record_found.load(Ordering::SeqCst) {
-            Self::log_static(&logs, format!("Thread {}: Stopped due to record found", thread_id), LogLevel::Warning);
-        } else {
-            Self::log_static(&logs, format!("Thread {}: Completed - Checked {} dates", thread_id, checked_count), LogLevel::Warning);
-        }
-    }
-
-    fn make_request(vehicle_no: &str, date_str: &str) -> Result<(u16, String), Box<dyn std::error::Error>> {
-        let boundary = "wL36Yn8afVp8Ag7AmP8qZ0SA4n1v9T";
-
-        let mut body = Vec::new();
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Disposition: form-data; name=registrationNo;\r\n");
-        body.extend_from_slice(b"Content-Type: text/plain\r\n\r\n");
-        body.extend_from_slice(vehicle_no.as_bytes());
-        body.extend_from_slice(b"\r\n");
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Disposition: form-data; name=registrationDate;\r\n");
-        body.extend_from_slice(b"Content-Type: text/plain\r\n\r\n");
-        body.extend_from_slice(date_str.as_bytes());
-        body.extend_from_slice(b"\r\n");
-        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
-
-        let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-
-        let response = client
-        .post("http://58.65.189.226:8080/ovd/API_FOR_VEH_REG_DATA/VEHDATA.php")
-        .header("Content-Type", format!("multipart/form-data; boundary={}", boundary))
-        .body(body)
-        .send()?;
-
-        let status = response.status().as_u16();
-        let text = response.text()?;
-
-        Ok((status, text))
-    }
-
-    fn save_response(
-        vehicle_no: &str,
-        date_str: &str,
-        response: &str,
-        thread_id: usize,
-        status: u16,
-        results_dir: &PathBuf,
-        logs: &Arc<Mutex<Vec<LogEntry>>>,
-        found_count: &Arc<Mutex<usize>>,
-    ) {
-        if let Ok(mut count) = found_count.lock() {
+    fn save_response(&self, thread: usize, outcome: &CheckOutcome) -> Option<String> {
+        if let Ok(mut count) = self.found_count.lock() {
             *count += 1;
         }
 
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let status_prefix = if status != 200 {
-            format!("HTTP{}_", status)
-        } else {
-            String::new()
-        };
-        let filename = format!("{}{}_{}.html", status_prefix, vehicle_no, date_str);
-        let filepath = results_dir.join(filename.clone());
-
-        match fs::write(&filepath, response) {
+        // Relative to the results directory, e.g. ABC-123/20240101_120000/ABC-123_2015-06-01.html
+        let date_str = outcome.date.format("%Y-%m-%d").to_string();
+        let filename = self.location.relative_path(&self.vehicle_no, &date_str, outcome.status, thread);
+        match vault::write(&self.location.root.join(&filename), &outcome.body) {
             Ok(_) => {
-                let msg = format!("Thread {}: Response saved to: {}", thread_id, filename);
-                Self::log_static(logs, msg, LogLevel::Success);
+                self.log(tr!("Thread {}: Response saved to: {}", thread, record::without_date(&filename, &date_str)), LogLevel::Success);
+                Some(filename)
             }
             Err(e) => {
-                let msg = format!("Thread {}: Error saving file - {}", thread_id, e);
-                Self::log_static(logs, msg, LogLevel::Error);
+                self.log(tr!("Thread {}: Error saving file - {}", thread, e), LogLevel::Error);
+                None
             }
         }
     }
-
-    fn stop_checking(&mut self) {
-        self.is_running.store(false, Ordering::SeqCst);
-        self.log("Stopping all threads...".to_string(), LogLevel::Warning);
-    }
 }
 
 impl eframe::App for VehicleChecker {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let state = UiState {
+            tab: self.tab,
+            console_height: self.console_height,
+            dark_mode: self.dark_mode,
+        };
+        eframe::set_value(storage, eframe::APP_KEY, &state);
+    }
+
+    // Drops the key and the decrypted copies made for the browser
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        vault::lock();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.apply_control_commands();
+        self.queue_tick();
+        if self.queue.current.is_some() || (self.queue.pending() > 0 && !self.queue.paused) {
+            // A queued run can end, or the next one become due, while the window is idle
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+        // Locked, only the lock screen is drawn; runs and the queue go on
+        self.track_activity(ctx);
+        if self.locked {
+            self.lock_screen(ctx);
+            #[cfg(feature = "tray")]
+            {
+                let found = self.record_found.load(Ordering::SeqCst) && *self.found_count.lock().unwrap() > 0;
+                self.update_tray(ctx, self.is_running.load(Ordering::SeqCst), found);
+            }
+            return;
+        }
+        self.handle_shortcuts(ctx);
+        self.dark_mode = ctx.style().visuals.dark_mode;
+        if self.config.grpc_enabled || self.config.server_enabled {
+            // Keep polling for remote commands even when the window is idle
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+
         // Update status
         let is_running = self.is_running.load(Ordering::SeqCst);
         let record_found = self.record_found.load(Ordering::SeqCst);
@@ -387,149 +3900,540 @@ impl eframe::App for VehicleChecker {
             0.0
         };
 
-        if !is_running && record_found && found_count > 0 {
-            self.status_text = "RECORD FOUND!".to_string();
+        let scan = self.scan_control();
+        let challenge_paused = is_running && scan.as_ref().is_some_and(|s| s.is_challenge_paused());
+        let breaker_open = is_running && scan.as_ref().is_some_and(|s| s.is_breaker_open());
+        if (challenge_paused || breaker_open) && !self.challenge_alerted {
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Critical));
+            self.challenge_alerted = true;
+        }
+
+        if challenge_paused {
+            self.status_text = tr!("PAUSED - captcha/challenge detected");
+        } else if breaker_open {
+            self.status_text = tr!("PAUSED - requests keep failing");
+        } else if !is_running && record_found && found_count > 0 {
+            self.status_text = tr!("RECORD FOUND!");
+        } else if let (false, Some(reason)) = (is_running, scan.as_ref().and_then(|s| s.stop_reason())) {
+            self.status_text = tr!("Stopped: {}", Self::describe_stop_reason(reason));
         } else if !is_running {
-            self.status_text = "Ready".to_string();
+            self.status_text = tr!("Ready");
         } else {
-            self.status_text = format!("Running... ({}/{})", checked_dates, total_dates);
+            self.status_text = tr!("Running... ({}/{})", checked_dates, total_dates);
+            if self.adaptive_concurrency {
+                if let Some(scan) = &scan {
+                    self.status_text.push_str(&tr!(" - {} in flight max", scan.concurrency()));
+                }
+            }
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Vehicle Registration Checker");
-            ui.add_space(10.0);
+            ui.heading(i18n::label("Vehicle Registration Checker"));
+            ui.add_space(5.0);
+            if let Some(other) = &self.other_instance {
+                ui.colored_label(
+                    egui::Color32::from_rgb(255, 165, 0),
+                    tr!("Another copy of the app (PID {}, started {}) is using this results folder. Scans of dates it is already checking will be refused.", other.pid, other.started_at),
+                );
+            }
+            ui.horizontal(|ui| {
+                for tab in Tab::ALL {
+                    ui.selectable_value(&mut self.tab, tab, i18n::label(tab.label()));
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    egui::widgets::global_dark_light_mode_switch(ui);
+                    if ui.button(i18n::label("Shortcuts (F1)")).clicked() {
+                        self.show_shortcuts = !self.show_shortcuts;
+                    }
+                    if !self.config.lock_passphrase.is_empty() && ui.button(i18n::label("Lock")).clicked() {
+                        self.lock_app();
+                    }
+                });
+            });
+            ui.separator();
 
-            // Configuration - Centered and Full Width
-            ui.vertical_centered(|ui| {
-                egui::Frame::group(ui.style())
-                .inner_margin(10.0)
-                .show(ui, |ui| {
-                    ui.set_width(ui.available_width());
+            match self.tab {
+                Tab::Settings => {
+                    egui::ScrollArea::vertical().show(ui, |ui| self.settings_ui(ui, ctx));
+                }
+                Tab::Rules => {
+                    egui::ScrollArea::vertical().show(ui, |ui| self.rules_ui(ui));
+                }
+                Tab::Compare => self.compare_ui(ui),
+                Tab::History => self.history_ui(ui),
+                Tab::Queue => self.queue_ui(ui),
+                Tab::Scan => {
+                    // Configuration - Centered and Full Width
                     ui.vertical_centered(|ui| {
-                        ui.heading("Configuration");
-                    });
-                    ui.add_space(10.0);
+                        egui::Frame::group(ui.style())
+                        .inner_margin(10.0)
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.vertical_centered(|ui| {
+                                ui.heading(i18n::label("Configuration"));
+                            });
+                            ui.add_space(10.0);
 
-                    ui.horizontal(|ui| {
-                        ui.label("Vehicle Registration No:");
-                        ui.add(egui::TextEdit::singleline(&mut self.vehicle_no).desired_width(200.0));
-                    });
+                            self.case_ui(ui, is_running);
 
-                    ui.horizontal(|ui| {
-                        ui.label("Starting Date (YYYY-MM-DD):");
-                        ui.add(egui::TextEdit::singleline(&mut self.start_date).desired_width(200.0));
-                    });
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Vehicle Registration No:"));
+                                ui.add(egui::TextEdit::singleline(&mut self.vehicle_no)
+                                .id(egui::Id::new("vehicle_no_field"))
+                                .desired_width(200.0));
+                                let normalized = self.config.search_by.normalize(&self.vehicle_no, &self.config.plate_separator);
+                                if !normalized.is_empty() {
+                                    ui.label(i18n::display(&tr!("Will be sent as: {}", normalized)));
+                                }
+                            });
 
-                    ui.horizontal(|ui| {
-                        ui.label("Ending Date (YYYY-MM-DD):");
-                        ui.add(egui::TextEdit::singleline(&mut self.end_date).desired_width(200.0));
-                    });
+                            self.provider_ui(ui, is_running);
 
-                    ui.horizontal(|ui| {
-                        ui.label("Number of Threads:");
-                        ui.add(egui::Slider::new(&mut self.num_threads, 1..=20));
-                    });
+                            if self.config.search_by == SearchBy::Plate {
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n::label("Vehicle type:"));
+                                    let previous = self.vehicle_type;
+                                    ui.add_enabled_ui(!is_running, |ui| {
+                                        egui::ComboBox::from_id_source("vehicle_type")
+                                        .selected_text(i18n::label(self.vehicle_type.label()))
+                                        .show_ui(ui, |ui| {
+                                            for kind in plate::VehicleType::ALL {
+                                                ui.selectable_value(&mut self.vehicle_type, kind, i18n::label(kind.label()));
+                                            }
+                                        });
+                                    });
+                                    // Picking a type moves the range to where that series starts
+                                    if self.vehicle_type != previous {
+                                        self.start_date = self.vehicle_type.suggested_start().format("%Y-%m-%d").to_string();
+                                        self.end_date = chrono::Local::now().format("%Y-%m-%d").to_string();
+                                        self.plate_warned = None;
+                                    }
+                                    ui.weak(i18n::display(&tr!("e.g. {}", self.vehicle_type.example(self.config.provider))));
+                                });
+                            } else {
+                                ui.weak(i18n::display(&tr!("e.g. {}", self.config.search_by.example())));
+                            }
 
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        if ui.add_enabled(!is_running, egui::Button::new("Start")).clicked() {
-                            self.start_checking();
-                        }
+                            if let Some((from, to)) = series::lookup(&self.series_mapping, &self.vehicle_no).filter(|_| self.config.search_by == SearchBy::Plate) {
+                                ui.horizontal(|ui| {
+                                    ui.label(i18n::display(&tr!("Series issued {} to {}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))));
+                                    if ui.add_enabled(!is_running, egui::Button::new(i18n::label("Use suggested dates"))).clicked() {
+                                        self.start_date = from.format("%Y-%m-%d").to_string();
+                                        self.end_date = to.format("%Y-%m-%d").to_string();
+                                    }
+                                });
+                            }
 
-                        if ui.add_enabled(is_running, egui::Button::new("Stop")).clicked() {
-                            self.stop_checking();
-                        }
+                            self.quick_verify_ui(ui, is_running);
 
-                        if ui.button("Clear Console").clicked() {
-                            self.clear_logs();
-                        }
-                    });
-                });
-            });
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Starting Date (YYYY-MM-DD):"));
+                                ui.add(egui::TextEdit::singleline(&mut self.start_date).desired_width(200.0));
+                            });
 
-            ui.add_space(10.0);
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Ending Date (YYYY-MM-DD):"));
+                                ui.add(egui::TextEdit::singleline(&mut self.end_date).desired_width(200.0));
+                            });
 
-            // Status - Centered and Full Width
-            ui.vertical_centered(|ui| {
-                egui::Frame::group(ui.style())
-                .inner_margin(10.0)
-                .show(ui, |ui| {
-                    ui.set_width(ui.available_width());
-                    ui.vertical_centered(|ui| {
-                        ui.heading("Status");
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Preset:"));
+                                for preset in Preset::ALL {
+                                    if ui.add_enabled(!is_running, egui::Button::new(i18n::label(preset.label())))
+                                    .on_hover_text(i18n::label(preset.description()))
+                                    .clicked() {
+                                        self.apply_preset(preset);
+                                    }
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Number of Threads:"));
+                                ui.add(egui::Slider::new(&mut self.num_threads, 1..=config::MAX_THREADS));
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Search Order:"));
+                                egui::ComboBox::from_id_source("search_order")
+                                .selected_text(i18n::label(self.search_order.label()))
+                                .show_ui(ui, |ui| {
+                                    for order in SearchOrder::ALL {
+                                        ui.selectable_value(&mut self.search_order, order, i18n::label(order.label()));
+                                    }
+                                });
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Series Mapping CSV:"));
+                                ui.add(egui::TextEdit::singleline(&mut self.series_csv_path).desired_width(200.0));
+                                if ui.button(i18n::label("Load")).clicked() {
+                                    self.load_series_mapping();
+                                }
+                            });
+
+                            ui.checkbox(&mut self.adaptive_concurrency, i18n::label("Adaptive concurrency (thread count becomes the maximum)"));
+
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Delay between requests (ms):"));
+                                ui.add(egui::DragValue::new(&mut self.request_delay_ms).speed(10.0));
+                            });
+
+                            ui.checkbox(&mut self.skip_checked, i18n::label("Skip dates already checked in earlier sessions"));
+
+                            ui.horizontal(|ui| {
+                                ui.label(i18n::label("Stop after hits:"));
+                                ui.add(egui::DragValue::new(&mut self.stop_conditions.max_hits));
+                                ui.label(i18n::label("Time limit (min):"));
+                                ui.add(egui::DragValue::new(&mut self.stop_conditions.time_limit_mins));
+                                ui.label(i18n::label("Max requests:"));
+                                ui.add(egui::DragValue::new(&mut self.stop_conditions.max_requests).speed(10.0));
+                                if ui.button(i18n::label("Run to completion")).clicked() {
+                                    self.stop_conditions = stop::StopConditions { max_hits: 0, time_limit_mins: 0, max_requests: 0 };
+                                }
+                            });
+                            ui.label(i18n::display(&tr!("0 = no limit. Currently: {}", Self::describe_stop_conditions(&self.stop_conditions))));
+
+                            ui.add_space(10.0);
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(!is_running, egui::Button::new(i18n::label("Start"))).clicked() {
+                                    self.start_checking();
+                                }
+
+                                if ui.add_enabled(is_running, egui::Button::new(i18n::label("Stop"))).clicked() {
+                                    self.stop_checking();
+                                }
+
+                                if ui.button(i18n::label("Add to queue")).clicked() {
+                                    self.enqueue_current();
+                                }
+
+                                if ui.button(i18n::label("Clear Console")).clicked() {
+                                    self.clear_logs();
+                                }
+
+                                let has_run = self.control.job().is_some();
+                                if ui.add_enabled(!is_running && has_run, egui::Button::new(i18n::label("Export PDF Report"))).clicked() {
+                                    self.export_pdf_report();
+                                }
+                                if ui.add_enabled(!is_running && has_run, egui::Button::new(i18n::label("Export Excel"))).clicked() {
+                                    self.export_xlsx();
+                                }
+                                if ui.add_enabled(!is_running && has_run, egui::Button::new(i18n::label("Export Markdown")))
+                                .on_hover_text(i18n::label("A plain-text report to paste into a ticket or case notes"))
+                                .clicked() {
+                                    self.export_markdown();
+                                }
+                                if ui.add_enabled(!is_running, egui::Button::new(i18n::label("Verify CSV...")))
+                                .on_hover_text(i18n::label("Check exact plate/date pairs from a CSV file and write a verification report"))
+                                .clicked() {
+                                    self.verify_csv();
+                                }
+                            });
+                        });
                     });
-                    ui.add_space(5.0);
 
-                    let color = if record_found && found_count > 0 {
-                        egui::Color32::GREEN
-                    } else if is_running {
-                        egui::Color32::from_rgb(255, 165, 0)
-                    } else {
-                        egui::Color32::BLUE
-                    };
+                    ui.add_space(10.0);
 
+                    // Status - Centered and Full Width
                     ui.vertical_centered(|ui| {
-                        ui.colored_label(color, &self.status_text);
+                        egui::Frame::group(ui.style())
+                        .inner_margin(10.0)
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.vertical_centered(|ui| {
+                                ui.heading(i18n::label("Status"));
+                            });
+                            ui.add_space(5.0);
+
+                            let color = if challenge_paused || breaker_open {
+                                egui::Color32::RED
+                            } else if record_found && found_count > 0 {
+                                egui::Color32::GREEN
+                            } else if is_running {
+                                egui::Color32::from_rgb(255, 165, 0)
+                            } else {
+                                egui::Color32::BLUE
+                            };
+
+                            ui.vertical_centered(|ui| {
+                                ui.colored_label(color, i18n::display(&self.status_text));
+                            });
+
+                            ui.add_space(5.0);
+
+                            if challenge_paused {
+                                ui.vertical_centered(|ui| {
+                                    ui.label(i18n::label("The server returned a captcha or challenge page. Wait a while for it to clear, then resume; the date is tried again."));
+                                    if ui.button(i18n::label("Resume")).clicked() {
+                                        self.resume_after_challenge();
+                                    }
+                                });
+                                ui.add_space(5.0);
+                            } else if breaker_open {
+                                ui.vertical_centered(|ui| {
+                                    ui.label(i18n::label("Too many requests failed in a row. The server may be down or your connection lost; check it, then resume."));
+                                    if ui.button(i18n::label("Resume")).clicked() {
+                                        self.resume_after_challenge();
+                                    }
+                                });
+                                ui.add_space(5.0);
+                            }
+
+                            ui.collapsing(i18n::label("Network Statistics"), |ui| {
+                                let snapshot = self.stats.snapshot();
+                                egui::Grid::new("network_stats").num_columns(4).show(ui, |ui| {
+                                    ui.label(i18n::label("Requests:"));
+                                    ui.label(snapshot.requests.to_string());
+                                    ui.label(i18n::label("In flight:"));
+                                    ui.label(snapshot.in_flight.to_string());
+                                    ui.end_row();
+
+                                    ui.label(i18n::label("Responses:"));
+                                    ui.label(snapshot.successes.to_string());
+                                    ui.label(i18n::label("Failed:"));
+                                    ui.label(snapshot.errors.to_string());
+                                    ui.end_row();
+
+                                    ui.label(i18n::label("Sent:"));
+                                    ui.label(stats::format_bytes(snapshot.bytes_sent));
+                                    ui.label(i18n::label("Received:"));
+                                    ui.label(stats::format_bytes(snapshot.bytes_received));
+                                    ui.end_row();
+
+                                    ui.label(i18n::label("Avg latency:"));
+                                    ui.label(format!("{} ms", snapshot.avg_latency_ms));
+                                    ui.label(i18n::label("p50 latency:"));
+                                    ui.label(format!("{} ms", snapshot.p50_latency_ms));
+                                    ui.end_row();
+
+                                    ui.label(i18n::label("p90 latency:"));
+                                    ui.label(format!("{} ms", snapshot.p90_latency_ms));
+                                    ui.label(i18n::label("p99 latency:"));
+                                    ui.label(format!("{} ms", snapshot.p99_latency_ms));
+                                    ui.end_row();
+                                });
+                                self.throughput_chart(ui);
+                            });
+
+                            if is_running || progress > 0.0 {
+                                let progress_text = format!("{:.1}% ({}/{})",
+                                                            progress * 100.0, checked_dates, total_dates);
+                                ui.add(
+                                    egui::ProgressBar::new(progress)
+                                    .show_percentage()
+                                    .text(progress_text)
+                                );
+                            }
+                        });
                     });
 
-                    ui.add_space(5.0);
+                    ui.add_space(10.0);
+                    let calendar_open = egui::CollapsingHeader::new(i18n::label("Coverage calendar"))
+                    .id_source("coverage_calendar_header")
+                    .show(ui, |ui| self.coverage_calendar_ui(ui))
+                    .body_returned
+                    .is_some();
+                    if !calendar_open {
+                        self.calendar = None;
+                    }
 
-                    if is_running || progress > 0.0 {
-                        let progress_text = format!("{:.1}% ({}/{})",
-                                                    progress * 100.0, checked_dates, total_dates);
-                        ui.add(
-                            egui::ProgressBar::new(progress)
-                            .show_percentage()
-                            .text(progress_text)
-                        );
+                    let mut rows: Vec<results::Row> = self
+                    .hits
+                    .lock()
+                    .map(|h| h.clone())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|hit| {
+                        let annotation = hit.file_name.as_ref().and_then(|name| self.annotations.get(name)).cloned().unwrap_or_default();
+                        results::Row { annotation, ..results::Row::hit(hit) }
+                    })
+                    .collect();
+                    // Failed lookups only when the filter asks for more than hits
+                    if self.hit_filter.outcome != Some(Classification::Hit) {
+                        let vehicle_no = self.control.job().map(|job| job.vehicle_no).unwrap_or_default();
+                        let provider = self.config.provider_name().to_string();
+                        rows.extend(self.stats.errors().iter().map(|event| results::Row::error(event, &vehicle_no, &provider)));
+                    }
+                    let mut details = None;
+                    if !rows.is_empty() || self.hit_filter.is_active() {
+                        ui.add_space(10.0);
+                        egui::Frame::group(ui.style()).show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.label(i18n::label("Found Records"));
+                            ui.add_space(5.0);
+                            self.hit_filter_ui(ui, &rows);
+                            let total = rows.len();
+                            rows.retain(|row| self.hit_filter.matches(row));
+                            if rows.len() < total {
+                                ui.weak(i18n::display(&tr!("{} of {} shown", rows.len(), total)));
+                            }
+                            self.hit_sort.apply(&mut rows);
+                            details = self.hits_table(ui, &rows);
+                        });
+                    }
+                    if let Some(path) = details {
+                        self.open_detail(&path);
                     }
-                });
-            });
 
-            ui.add_space(10.0);
-
-            // Console
-            egui::Frame::group(ui.style()).show(ui, |ui| {
-                ui.label("Console Output");
-                ui.add_space(5.0);
-
-                egui::ScrollArea::vertical()
-                .max_height(400.0)
-                .stick_to_bottom(true)
-                .show(ui, |ui| {
-                    if let Ok(logs) = self.logs.lock() {
-                        for entry in logs.iter() {
-                            let color = match entry.level {
-                                LogLevel::Info => egui::Color32::LIGHT_BLUE,
-                                LogLevel::Success => egui::Color32::GREEN,
-                                LogLevel::Error => egui::Color32::RED,
-                                LogLevel::Warning => egui::Color32::from_rgb(255, 165, 0),
-                            };
-                            ui.colored_label(color, format!("[{}] {}", entry.timestamp, entry.message));
+                    ui.collapsing(i18n::label("Saved Responses"), |ui| {
+                        if ui.button(i18n::label("Replay through current rules"))
+                        .on_hover_text(i18n::label("Re-classify and re-parse every saved response without querying the server"))
+                        .clicked() {
+                            self.replay_saved_responses();
                         }
-                    }
-                });
-            });
+                        let files = self.control.list_results();
+                        if files.is_empty() {
+                            ui.label(i18n::label("No saved responses yet"));
+                        }
+                        egui::ScrollArea::vertical().id_source("saved_responses").max_height(200.0).show(ui, |ui| {
+                            egui::Grid::new("saved_responses_grid").striped(true).show(ui, |ui| {
+                                for file in files.iter().filter(|f| f.name.ends_with(".html")) {
+                                    ui.label(&file.name);
+                                    ui.label(&file.modified);
+                                    if ui.button(i18n::label("Details")).clicked() {
+                                        self.open_detail(&file.name);
+                                    }
+                                    if ui.button(i18n::label("Open in browser")).clicked() {
+                                        self.open_in_browser(&file.name);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    });
+
+                    ui.add_space(10.0);
+
+                    // Console
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.label(i18n::label("Console Output"));
+                        ui.add_space(5.0);
+
+                        egui::ScrollArea::vertical()
+                        .max_height(self.console_height)
+                        .min_scrolled_height(self.console_height)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            if let Ok(logs) = self.logs.lock() {
+                                for entry in logs.iter() {
+                                    let color = match entry.level {
+                                        LogLevel::Info => egui::Color32::LIGHT_BLUE,
+                                        LogLevel::Success => egui::Color32::GREEN,
+                                        LogLevel::Error => egui::Color32::RED,
+                                        LogLevel::Warning => egui::Color32::from_rgb(255, 165, 0),
+                                        LogLevel::Debug => egui::Color32::GRAY,
+                                        LogLevel::Trace => egui::Color32::DARK_GRAY,
+                                    };
+                                    ui.colored_label(color, format!("[{}] {}", entry.timestamp, i18n::display(&entry.message)));
+                                }
+                            }
+                        });
+
+                        // Drag handle under the console to change its height
+                        let handle = ui.add(egui::Separator::default().spacing(8.0)).interact(egui::Sense::drag());
+                        if handle.hovered() || handle.dragged() {
+                            ctx.set_cursor_icon(egui::CursorIcon::ResizeVertical);
+                        }
+                        self.console_height = (self.console_height + handle.drag_delta().y).clamp(100.0, 2000.0);
+                    });
+                }
+            }
         });
 
-        // Request repaint if running
+        self.shortcuts_window(ctx);
+        self.detail_window(ctx);
+        self.unlock_window(ctx);
+        self.resume_prompt(ctx);
+        self.update_window(ctx);
+        self.curl_import_window(ctx);
+        self.annotation_window(ctx);
+        #[cfg(feature = "ocr")]
+        {
+            self.read_dropped_photos(ctx);
+            self.photo_plate_window(ctx);
+        }
+        self.command_palette(ctx);
+
+        #[cfg(feature = "tray")]
+        self.update_tray(ctx, is_running, record_found && found_count > 0);
+
+        // Engine events wake the window (see main); this keeps the charts
+        // moving through quiet stretches of a run
         if is_running {
-            ctx.request_repaint();
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+    }
+}
+
+// Everything the app reads or writes by relative path then lands in the
+// portable folder, whatever directory it was started from
+fn enter_portable_mode(dir: &std::path::Path) {
+    if let Err(e) = std::env::set_current_dir(dir) {
+        eprintln!("Portable mode: could not switch to {} - {}", dir.display(), e);
+        return;
+    }
+    let file = fs::create_dir_all("logs").and_then(|_| {
+        fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(format!("logs/{}.log", chrono::Local::now().format("%Y-%m-%d")))
+    });
+    match file {
+        Ok(file) => {
+            let _ = LOG_FILE.set(Mutex::new(file));
         }
+        Err(e) => eprintln!("Portable mode: could not open log file - {}", e),
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
+    let portable = config::portable_dir();
+    if let Some(dir) = portable {
+        enter_portable_mode(dir);
+    }
+
+    if std::env::args().skip(1).any(|arg| arg == "--daemon") {
+        if let Err(e) = daemon::run() {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // The app creator outlives main's borrows, so it gets its own copy
+    let portable_path: Option<PathBuf> = portable.map(|dir| dir.to_path_buf());
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
         .with_inner_size([800.0, 600.0])
         .with_title("Vehicle Registration Checker"),
+        // Window state and tab choice too, instead of the user's profile
+        persistence_path: portable.map(|dir| dir.join("window_state.ron")),
         ..Default::default()
     };
 
     eframe::run_native(
         "Vehicle Registration Checker",
         options,
-        Box::new(|_cc| Ok(Box::new(VehicleChecker::default()))),
+        Box::new(move |cc| {
+            let mut checker = VehicleChecker::default();
+            let state: UiState = cc.storage.and_then(|s| eframe::get_value(s, eframe::APP_KEY)).unwrap_or_default();
+            checker.tab = state.tab;
+            checker.console_height = state.console_height;
+            checker.dark_mode = state.dark_mode;
+            cc.egui_ctx.set_visuals(if state.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+            checker.apply_language(&cc.egui_ctx);
+            let ctx = cc.egui_ctx.clone();
+            control::on_event(move |_| ctx.request_repaint());
+            if let Some(dir) = &portable_path {
+                checker.log(tr!("Portable mode: settings, results and logs are kept in {}", dir.display()), LogLevel::Info);
+            }
+            #[cfg(feature = "tray")]
+            match tray::Tray::new() {
+                Ok(tray) => checker.tray = Some(tray),
+                Err(e) => checker.log(tr!("No tray icon - {}", e), LogLevel::Warning),
+            }
+            Ok(Box::new(checker))
+        }),
     )
 }
@@ -0,0 +1,106 @@
+use crate::auth::{self, ApiToken, TokenScope};
+use crate::classify::Classification;
+use crate::daemon;
+use crate::i18n::tr;
+use crate::server;
+use crate::stats::{NetworkStats, LATENCY_BUCKETS_MS};
+use std::fmt::Write as _;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// Requests are served one at a time, so a scraper that connects and goes
+// quiet may hold the exporter up for this long and no longer
+const READ_TIMEOUT_SECS: u64 = 5;
+
+// Serves the current run's counters in the Prometheus text format on
+// GET /metrics. Counters reset when a new run starts, which Prometheus
+// treats as an ordinary counter reset. Takes the server mode's API tokens,
+// any scope, as `Authorization: Bearer` (Prometheus' `authorization`
+// setting).
+pub fn spawn_exporter(
+    bind: &str,
+    stats: Arc<NetworkStats>,
+    is_running: Arc<AtomicBool>,
+    tokens: Vec<ApiToken>,
+) -> std::io::Result<()> {
+    // The run state and statistics are nobody else's business
+    if !daemon::is_loopback(bind) && !tokens.iter().any(|t| !t.token.is_empty()) {
+        let message = tr!("refusing to listen on {} without API tokens; add one or bind to 127.0.0.1", bind);
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, message));
+    }
+    let listener = TcpListener::bind(bind)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECS)));
+            let _ = handle(stream, &stats, &is_running, &tokens);
+        }
+    });
+
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, stats: &NetworkStats, is_running: &AtomicBool, tokens: &[ApiToken]) -> std::io::Result<()> {
+    let request = server::read_request(&stream)?;
+    if request.path != "/metrics" {
+        return server::respond(&mut stream, "404 Not Found", "text/plain", b"");
+    }
+    // Every scope may read them, so a token is only ever missing or unknown
+    if auth::authorize(tokens, server::presented_token(&request).as_deref(), TokenScope::ReadOnly).is_err() {
+        return server::respond(&mut stream, "401 Unauthorized", "text/plain", b"missing or unknown token");
+    }
+
+    let body = render(stats, is_running.load(Ordering::SeqCst));
+    server::respond(&mut stream, "200 OK", "text/plain; version=0.0.4", body.as_bytes())
+}
+
+fn render(stats: &NetworkStats, running: bool) -> String {
+    let snapshot = stats.snapshot();
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP vehreg_running Whether a scan is currently running");
+    let _ = writeln!(out, "# TYPE vehreg_running gauge");
+    let _ = writeln!(out, "vehreg_running {}", running as u8);
+
+    let _ = writeln!(out, "# HELP vehreg_requests_total Requests sent to the endpoint");
+    let _ = writeln!(out, "# TYPE vehreg_requests_total counter");
+    let _ = writeln!(out, "vehreg_requests_total {}", snapshot.requests);
+
+    let _ = writeln!(out, "# HELP vehreg_request_errors_total Requests that failed without a response");
+    let _ = writeln!(out, "# TYPE vehreg_request_errors_total counter");
+    let _ = writeln!(out, "vehreg_request_errors_total {}", snapshot.errors);
+
+    let _ = writeln!(out, "# HELP vehreg_in_flight_requests Requests currently waiting for a response");
+    let _ = writeln!(out, "# TYPE vehreg_in_flight_requests gauge");
+    let _ = writeln!(out, "vehreg_in_flight_requests {}", snapshot.in_flight);
+
+    let _ = writeln!(out, "# HELP vehreg_bytes_sent_total Request bytes sent");
+    let _ = writeln!(out, "# TYPE vehreg_bytes_sent_total counter");
+    let _ = writeln!(out, "vehreg_bytes_sent_total {}", snapshot.bytes_sent);
+
+    let _ = writeln!(out, "# HELP vehreg_bytes_received_total Response bytes received");
+    let _ = writeln!(out, "# TYPE vehreg_bytes_received_total counter");
+    let _ = writeln!(out, "vehreg_bytes_received_total {}", snapshot.bytes_received);
+
+    let _ = writeln!(out, "# HELP vehreg_responses_total Classified responses by outcome");
+    let _ = writeln!(out, "# TYPE vehreg_responses_total counter");
+    for classification in Classification::ALL {
+        let outcome = format!("{:?}", classification).to_lowercase();
+        let _ = writeln!(out, "vehreg_responses_total{{outcome=\"{}\"}} {}", outcome, stats.outcome_count(classification));
+    }
+
+    let (buckets, count, sum_ms) = stats.latency_histogram();
+    let _ = writeln!(out, "# HELP vehreg_request_duration_seconds Request latency");
+    let _ = writeln!(out, "# TYPE vehreg_request_duration_seconds histogram");
+    for (bound, cumulative) in LATENCY_BUCKETS_MS.iter().zip(buckets) {
+        let _ = writeln!(out, "vehreg_request_duration_seconds_bucket{{le=\"{}\"}} {}", *bound as f64 / 1000.0, cumulative);
+    }
+    let _ = writeln!(out, "vehreg_request_duration_seconds_bucket{{le=\"+Inf\"}} {}", count);
+    let _ = writeln!(out, "vehreg_request_duration_seconds_sum {}", sum_ms as f64 / 1000.0);
+    let _ = writeln!(out, "vehreg_request_duration_seconds_count {}", count);
+
+    out
+}
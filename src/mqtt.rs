@@ -0,0 +1,101 @@
+use crate::config::MqttSettings;
+use crate::control::{self, EngineEvent, JobInfo};
+use crate::i18n::tr;
+use crate::record;
+use crate::{HitRecord, LogLevel};
+use rumqttc::{Client, MqttOptions, QoS};
+use serde_json::json;
+use std::thread;
+use std::time::Duration;
+
+// Pending publishes held while the broker is unreachable
+const QUEUE_CAPACITY: usize = 100;
+
+// Between reconnect attempts
+const RETRY_SECS: u64 = 10;
+
+// Publishes every hit to <topic>/hit and every finished run to
+// <topic>/finished as JSON, for home-automation and ops setups that already
+// listen on MQTT. Reconnects on its own when the broker goes away; `log` is
+// told when it does, once per outage.
+pub fn spawn_publisher(settings: &MqttSettings, log: impl Fn(String, LogLevel) + Send + 'static) -> Result<(), String> {
+    let broker = settings.broker.trim();
+    let (host, port) = match broker.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().map_err(|_| tr!("invalid MQTT broker port in '{}'", broker))?),
+        None => (broker, 1883),
+    };
+    if host.is_empty() {
+        return Err(tr!("no MQTT broker set"));
+    }
+    let qos = match settings.qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        2 => QoS::ExactlyOnce,
+        other => return Err(tr!("MQTT QoS must be 0, 1 or 2, not {}", other)),
+    };
+
+    let mut options = MqttOptions::new(settings.client_id.trim(), host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if !settings.username.is_empty() {
+        options.set_credentials(settings.username.clone(), settings.password.clone());
+    }
+    let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+
+    // Nothing goes out unless the connection is polled
+    let broker = broker.to_string();
+    thread::spawn(move || {
+        let mut connected = true;
+        for notification in connection.iter() {
+            match notification {
+                Ok(_) if !connected => {
+                    connected = true;
+                    log(tr!("Reconnected to MQTT broker {}", broker), LogLevel::Info);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if connected {
+                        connected = false;
+                        log(tr!("MQTT broker {} unreachable - {}", broker, e), LogLevel::Warning);
+                    }
+                    thread::sleep(Duration::from_secs(RETRY_SECS));
+                }
+            }
+        }
+    });
+
+    let topic = settings.topic.trim().trim_end_matches('/').to_string();
+    control::on_event(move |event| {
+        let (suffix, payload) = match event {
+            EngineEvent::Hit(hit) => ("hit", hit_json(hit)),
+            EngineEvent::RunStarted(job) => ("started", job_json(job)),
+            EngineEvent::RunFinished(job) => ("finished", job_json(job)),
+            _ => return,
+        };
+        // try_publish: a full queue during an outage drops the event instead
+        // of holding up everyone else's events
+        let _ = client.try_publish(format!("{}/{}", topic, suffix), qos, false, payload.to_string());
+    });
+    Ok(())
+}
+
+fn hit_json(hit: &HitRecord) -> serde_json::Value {
+    let fields: serde_json::Map<String, serde_json::Value> = record::shareable_fields(&hit.fields).into_iter().map(|(label, value)| (label, json!(value))).collect();
+    json!({
+        "vehicle_no": hit.vehicle_no,
+        "date": record::shareable_date(&hit.date),
+        "file": hit.file_name.as_ref().map(|name| record::without_date(name, &hit.date)),
+        "found_at": hit.found_at,
+        "fields": fields,
+    })
+}
+
+fn job_json(job: &JobInfo) -> serde_json::Value {
+    json!({
+        "vehicle_no": job.vehicle_no,
+        "start_date": job.start_date,
+        "end_date": job.end_date,
+        "threads": job.threads,
+        "started_at": job.started_at,
+        "finished_at": job.finished_at,
+    })
+}
@@ -0,0 +1,44 @@
+use crate::i18n::tr;
+use crate::plate;
+use crate::provider::Provider;
+use std::path::Path;
+use tesseract::Tesseract;
+
+// Characters a plate can have; anything else Tesseract sees is noise from
+// the frame, the city name or the green strip
+const PLATE_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789- ";
+
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "tif", "tiff"];
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+// The registration number in a photo of a plate, normalized with
+// `separator`. Of the lines Tesseract reads, the first that looks like a
+// plate of any province wins, otherwise the longest one with both letters
+// and digits. Slow enough to keep off the UI thread.
+pub fn read_plate(path: &Path, separator: &str) -> Result<String, String> {
+    let path = path.to_str().ok_or_else(|| tr!("the image path is not valid UTF-8"))?;
+    let mut tesseract = Tesseract::new(None, Some("eng"))
+    .map_err(|e| e.to_string())?
+    .set_variable("tessedit_char_whitelist", PLATE_CHARS)
+    .map_err(|e| e.to_string())?
+    .set_image(path)
+    .map_err(|e| e.to_string())?;
+    let text = tesseract.get_text().map_err(|e| e.to_string())?;
+
+    let candidates: Vec<String> = text
+    .lines()
+    .map(|line| plate::normalize(line, separator))
+    .filter(|line| line.chars().any(|c| c.is_ascii_digit()) && line.chars().any(|c| c.is_ascii_alphabetic()))
+    .collect();
+    candidates
+    .iter()
+    .find(|candidate| Provider::ALL.iter().any(|p| plate::validate(candidate, plate::VehicleType::Any, *p).is_ok()))
+    .or_else(|| candidates.iter().max_by_key(|candidate| candidate.len()))
+    .cloned()
+    .ok_or_else(|| tr!("no registration number found in the image"))
+}
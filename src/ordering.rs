@@ -0,0 +1,98 @@
+use chrono::NaiveDate;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchOrder {
+    // Each thread walks its own contiguous chunk oldest to newest
+    Forward,
+    // Each thread walks its own contiguous chunk newest to oldest
+    Backward,
+    // The whole range newest to oldest, dealt round-robin to the threads so
+    // every thread works on recent dates first
+    NewestFirstInterleaved,
+    // Dates in the window predicted from the plate's series first, working
+    // outwards from its middle, then the rest newest to oldest. Falls back to
+    // Forward when nothing is known about the series.
+    SeriesWeighted,
+}
+
+impl SearchOrder {
+    pub const ALL: [SearchOrder; 4] = [
+        SearchOrder::Forward,
+        SearchOrder::Backward,
+        SearchOrder::NewestFirstInterleaved,
+        SearchOrder::SeriesWeighted,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchOrder::Forward => "Forward (oldest first)",
+            SearchOrder::Backward => "Backward (newest first per thread)",
+            SearchOrder::NewestFirstInterleaved => "Newest first, interleaved",
+            SearchOrder::SeriesWeighted => "Series-weighted (likely window first)",
+        }
+    }
+}
+
+pub fn chunk_dates(start: NaiveDate, end: NaiveDate, order: SearchOrder) -> Vec<NaiveDate> {
+    let mut dates: Vec<NaiveDate> = start.iter_days().take_while(|d| *d <= end).collect();
+    if order == SearchOrder::Backward {
+        dates.reverse();
+    }
+    dates
+}
+
+fn deal(dates: impl IntoIterator<Item = NaiveDate>, threads: usize) -> Vec<Vec<NaiveDate>> {
+    let mut lanes = vec![Vec::new(); threads.max(1)];
+    let count = lanes.len();
+    for (i, date) in dates.into_iter().enumerate() {
+        lanes[i % count].push(date);
+    }
+    lanes.retain(|lane| !lane.is_empty());
+    lanes
+}
+
+pub fn interleave_newest_first(start: NaiveDate, end: NaiveDate, threads: usize) -> Vec<Vec<NaiveDate>> {
+    let mut dates = chunk_dates(start, end, SearchOrder::Forward);
+    dates.reverse();
+    deal(dates, threads)
+}
+
+pub fn prioritize_window(
+    start: NaiveDate,
+    end: NaiveDate,
+    window: (NaiveDate, NaiveDate),
+    threads: usize,
+) -> Vec<Vec<NaiveDate>> {
+    let from = window.0.max(start);
+    let to = window.1.min(end);
+
+    let mut ordered = Vec::new();
+    if from <= to {
+        // Middle of the window outwards, alternating later and earlier
+        let mid = from + (to - from) / 2;
+        ordered.push(mid);
+        let mut offset = 1;
+        loop {
+            let later = mid + chrono::Duration::days(offset);
+            let earlier = mid - chrono::Duration::days(offset);
+            if later > to && earlier < from {
+                break;
+            }
+            if later <= to {
+                ordered.push(later);
+            }
+            if earlier >= from {
+                ordered.push(earlier);
+            }
+            offset += 1;
+        }
+    }
+
+    let rest = chunk_dates(start, end, SearchOrder::Forward)
+    .into_iter()
+    .rev()
+    .filter(|d| *d < from || *d > to);
+    ordered.extend(rest);
+
+    deal(ordered, threads)
+}
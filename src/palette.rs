@@ -0,0 +1,36 @@
+// Fuzzy matching for the command palette: the query's characters must all
+// appear in the label, in order, ignoring case. "exx" finds "Export Excel".
+fn score(query: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.chars().flat_map(|c| c.to_lowercase()).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.chars().flat_map(|c| c.to_lowercase()).filter(|c| !c.is_whitespace()) {
+        let found = pos + label[pos..].iter().position(|c| *c == q)?;
+        // Runs of matched characters and matches at the start of a word count
+        // for more than scattered letters
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - pos) as i32;
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+// Indexes of the labels matching `query`, best match first. An empty query
+// keeps them all in their original order.
+pub fn rank(query: &str, labels: &[String]) -> Vec<usize> {
+    let mut matches: Vec<(usize, i32)> = labels
+    .iter()
+    .enumerate()
+    .filter_map(|(i, label)| score(query, label).map(|s| (i, s)))
+    .collect();
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    matches.into_iter().map(|(i, _)| i).collect()
+}
@@ -0,0 +1,127 @@
+use chrono::NaiveDate;
+
+// Splits start..=end into contiguous ranges, one per thread, whose lengths
+// differ by at most a day; the longer ones come first. There are never more
+// ranges than days, so a short range with many threads leaves some threads
+// idle rather than handing them empty ranges. Nothing comes back when `end`
+// is before `start` or there are no threads.
+pub fn split(start: NaiveDate, end: NaiveDate, threads: usize) -> Vec<(NaiveDate, NaiveDate)> {
+    if end < start || threads == 0 {
+        return Vec::new();
+    }
+    let total_days = (end - start).num_days() as u64 + 1;
+    let parts = total_days.min(threads as u64);
+    let base = total_days / parts;
+    let remainder = total_days % parts;
+
+    let mut ranges = Vec::with_capacity(parts as usize);
+    let mut from = start;
+    for i in 0..parts {
+        let days = base + if i < remainder { 1 } else { 0 };
+        let to = from + chrono::Days::new(days - 1);
+        ranges.push((from, to));
+        // `to` is `end` on the last range, which may be the last representable date
+        match to.succ_opt() {
+            Some(next) => from = next,
+            None => break,
+        }
+    }
+    ranges
+}
+
+// The most days any one thread gets
+pub fn longest(start: NaiveDate, end: NaiveDate, threads: usize) -> u64 {
+    split(start, end, threads)
+    .first()
+    .map(|(from, to)| (*to - *from).num_days() as u64 + 1)
+    .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(offset: u64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2020, 1, 1).unwrap() + chrono::Days::new(offset)
+    }
+
+    fn length(range: &(NaiveDate, NaiveDate)) -> i64 {
+        (range.1 - range.0).num_days() + 1
+    }
+
+    // Every range length up to two months against every thread count up to
+    // a few more than that, in place of a property test
+    fn each_case(check: impl Fn(NaiveDate, NaiveDate, usize, &[(NaiveDate, NaiveDate)])) {
+        for days in 1..=60 {
+            for threads in 1..=70 {
+                let (start, end) = (day(0), day(days - 1));
+                check(start, end, threads, &split(start, end, threads));
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_are_contiguous_and_cover_the_whole_span() {
+        each_case(|start, end, _, ranges| {
+            assert_eq!(ranges.first().unwrap().0, start);
+            assert_eq!(ranges.last().unwrap().1, end);
+            for pair in ranges.windows(2) {
+                assert_eq!(pair[0].1.succ_opt(), Some(pair[1].0));
+            }
+            assert!(ranges.iter().all(|range| range.0 <= range.1));
+        });
+    }
+
+    #[test]
+    fn lengths_differ_by_at_most_a_day_with_the_longer_first() {
+        each_case(|_, _, _, ranges| {
+            let lengths: Vec<i64> = ranges.iter().map(length).collect();
+            assert!(lengths.iter().max().unwrap() - lengths.iter().min().unwrap() <= 1);
+            assert!(lengths.windows(2).all(|pair| pair[0] >= pair[1]));
+        });
+    }
+
+    #[test]
+    fn one_range_per_thread_up_to_one_per_day() {
+        each_case(|start, end, threads, ranges| {
+            let days = (end - start).num_days() as usize + 1;
+            assert_eq!(ranges.len(), threads.min(days));
+        });
+    }
+
+    #[test]
+    fn more_threads_than_days_gives_single_days() {
+        let ranges = split(day(0), day(2), 8);
+        assert_eq!(ranges, vec![(day(0), day(0)), (day(1), day(1)), (day(2), day(2))]);
+        assert_eq!(longest(day(0), day(2), 8), 1);
+    }
+
+    #[test]
+    fn no_threads_gives_nothing() {
+        assert!(split(day(0), day(10), 0).is_empty());
+        assert_eq!(longest(day(0), day(10), 0), 0);
+    }
+
+    #[test]
+    fn end_before_start_gives_nothing() {
+        assert!(split(day(5), day(4), 3).is_empty());
+        assert_eq!(longest(day(5), day(4), 3), 0);
+    }
+
+    #[test]
+    fn single_day() {
+        assert_eq!(split(day(3), day(3), 4), vec![(day(3), day(3))]);
+    }
+
+    #[test]
+    fn ends_on_the_last_representable_date() {
+        let start = NaiveDate::MAX - chrono::Days::new(9);
+        let ranges = split(start, NaiveDate::MAX, 3);
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges.first().unwrap().0, start);
+        assert_eq!(ranges.last().unwrap().1, NaiveDate::MAX);
+        assert_eq!(ranges.iter().map(length).collect::<Vec<_>>(), vec![4, 3, 3]);
+
+        assert_eq!(split(NaiveDate::MAX, NaiveDate::MAX, 2), vec![(NaiveDate::MAX, NaiveDate::MAX)]);
+    }
+}
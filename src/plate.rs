@@ -0,0 +1,146 @@
+use crate::provider::Provider;
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Motorcycles, private cars and commercial vehicles are registered in
+// separate series with their own plate formats and issuance histories. The
+// type narrows what counts as a well-formed plate and where a search should
+// start.
+#[derive(Clone, Copy, PartialEq)]
+pub enum VehicleType {
+    Any,
+    Motorcycle,
+    PrivateCar,
+    Commercial,
+}
+
+impl VehicleType {
+    pub const ALL: [VehicleType; 4] = [VehicleType::Any, VehicleType::Motorcycle, VehicleType::PrivateCar, VehicleType::Commercial];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VehicleType::Any => "Any vehicle",
+            VehicleType::Motorcycle => "Motorcycle",
+            VehicleType::PrivateCar => "Private car",
+            VehicleType::Commercial => "Commercial",
+        }
+    }
+
+    pub fn example(&self, provider: Provider) -> &'static str {
+        match (provider, self) {
+            (Provider::Sindh, VehicleType::Any) => "BKA-123",
+            (Provider::Sindh, VehicleType::Motorcycle) => "KFA-1234",
+            (Provider::Sindh, VehicleType::PrivateCar) => "AFR-786",
+            (Provider::Sindh, VehicleType::Commercial) => "JU-1234",
+            (_, VehicleType::Any) => "ABC-123",
+            (_, VehicleType::Motorcycle) => "ICT-AB-12345",
+            (_, VehicleType::PrivateCar) => "ABC-1234",
+            (_, VehicleType::Commercial) => "ICT-A-1234",
+        }
+    }
+
+    fn pattern(&self, provider: Provider) -> &'static str {
+        match (provider, self) {
+            // Sindh series are letters and a number of at most four digits,
+            // with no prefix
+            (Provider::Sindh, VehicleType::Any) => r"^[A-Z]{1,3}[-\s]?[0-9]{1,4}$",
+            // Motorcycle series are three letters starting with K
+            (Provider::Sindh, VehicleType::Motorcycle) => r"^K[A-Z]{2}[-\s]?[0-9]{1,4}$",
+            (Provider::Sindh, VehicleType::PrivateCar) => r"^[A-Z]{2,3}[-\s]?[0-9]{1,4}$",
+            // Goods and passenger carriers are on one- or two-letter series
+            (Provider::Sindh, VehicleType::Commercial) => r"^[A-Z]{1,2}[-\s]?[0-9]{1,4}$",
+            // Anything an Islamabad/ICT plate has looked like. Punjab and KPK
+            // series have the same shape.
+            (_, VehicleType::Any) => r"^(ICT[-\s]?)?[A-Z]{1,3}[-\s]?[0-9]{1,5}$",
+            // Two-wheeler series are short and run past 9999
+            (_, VehicleType::Motorcycle) => r"^(ICT[-\s]?)?[A-Z]{1,2}[-\s]?[0-9]{1,5}$",
+            // Car series are two or three letters, never the ICT prefix
+            (_, VehicleType::PrivateCar) => r"^[A-Z]{2,3}[-\s]?[0-9]{1,4}$",
+            // Goods and passenger carriers keep the ICT prefix on one- or two-letter series
+            (_, VehicleType::Commercial) => r"^(ICT[-\s]?)?[A-Z]{1,2}[-\s]?[0-9]{1,4}$",
+        }
+    }
+
+    // Earliest date worth scanning by default: when the series in use today
+    // started being issued
+    pub fn suggested_start(&self) -> NaiveDate {
+        let year = match self {
+            VehicleType::Any => 2000,
+            VehicleType::Motorcycle => 2008,
+            VehicleType::PrivateCar => 2000,
+            VehicleType::Commercial => 1995,
+        };
+        NaiveDate::from_ymd_opt(year, 1, 1).unwrap()
+    }
+}
+
+// Islamabad/ICT plates are a series of letters followed by a number, e.g.
+// "ABC-123", "AB 1234" or "IDB1234". Older plates can carry an "ICT" prefix
+// in front of the series; Sindh plates never do. The error says what looks
+// wrong, in words the catalog can translate.
+pub fn validate(vehicle_no: &str, kind: VehicleType, provider: Provider) -> Result<(), &'static str> {
+    // One row of vehicle types per provider
+    static FORMATS: OnceLock<Vec<Vec<Regex>>> = OnceLock::new();
+    let formats = FORMATS.get_or_init(|| {
+        Provider::ALL
+        .iter()
+        .map(|p| VehicleType::ALL.iter().map(|t| Regex::new(t.pattern(*p)).unwrap()).collect())
+        .collect()
+    });
+    let row = Provider::ALL.iter().position(|p| *p == provider).unwrap_or(0);
+    let index = VehicleType::ALL.iter().position(|t| *t == kind).unwrap_or(0);
+
+    if formats[row][index].is_match(vehicle_no) {
+        return Ok(());
+    }
+    Err(if !vehicle_no.chars().any(|c| c.is_ascii_digit()) {
+        "there is no number"
+    } else if !vehicle_no.chars().any(|c| c.is_ascii_alphabetic()) {
+        "there are no series letters"
+    } else if vehicle_no.chars().any(|c| !c.is_ascii_alphanumeric() && c != '-' && c != ' ') {
+        "it contains characters other than letters, digits, spaces and dashes"
+    } else {
+        "the letters and digits are not in the usual order or length"
+    })
+}
+
+// Separators the portal might expect between the series and the number
+pub const SEPARATORS: [(&str, &str); 3] = [("-", "Dash (ABC-123)"), (" ", "Space (ABC 123)"), ("", "None (ABC123)")];
+
+// What actually gets sent for whatever was typed: Urdu and Arabic-Indic
+// digits become ASCII, case is folded, spaces, dashes and other punctuation
+// are dropped, and the letter and digit groups are joined with `separator`.
+// "ict ab ۱۲۳" becomes "ICT-AB-123".
+pub fn normalize(input: &str, separator: &str) -> String {
+    let cleaned: String = input
+    .chars()
+    .map(ascii_digit)
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(|c| c.to_uppercase())
+    .collect();
+
+    let mut groups: Vec<String> = Vec::new();
+    for c in cleaned.chars() {
+        match groups.last_mut() {
+            Some(group) if group.chars().last().is_some_and(|last| last.is_ascii_digit() == c.is_ascii_digit()) => group.push(c),
+            _ => groups.push(c.to_string()),
+        }
+    }
+    // The old ICT prefix is its own group
+    let ict_prefix = |group: &&mut String| group.len() > 3 && group.starts_with("ICT") && !group.chars().any(|c| c.is_ascii_digit());
+    if let Some(first) = groups.first_mut().filter(ict_prefix) {
+        let series = first.split_off(3);
+        groups.insert(1, series);
+    }
+    groups.join(separator)
+}
+
+// U+06F0..U+06F9 (Urdu/Persian) and U+0660..U+0669 (Arabic-Indic)
+fn ascii_digit(c: char) -> char {
+    match c {
+        '\u{06F0}'..='\u{06F9}' => char::from(b'0' + (c as u32 - 0x06F0) as u8),
+        '\u{0660}'..='\u{0669}' => char::from(b'0' + (c as u32 - 0x0660) as u8),
+        _ => c,
+    }
+}
@@ -0,0 +1,152 @@
+use crate::classify::{self, ClassifierRule, MatchKind};
+use crate::config::AppConfig;
+use crate::LogLevel;
+use crate::i18n::tr;
+use crate::transport::{Transport, TransportResult};
+use libloading::{Library, Symbol};
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+// Where plugins are picked up from at startup, next to config.json
+pub const PLUGIN_DIR: &str = "plugins";
+
+// Bumped whenever the functions below change shape
+const ABI_VERSION: u32 = 1;
+
+type CheckFn = unsafe extern "C" fn(*const c_char, *const c_char, *mut u16, *mut *mut c_char) -> c_int;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+type TextFn = unsafe extern "C" fn() -> *const c_char;
+
+// A provider for a service this app doesn't know, in a shared library
+// (.dll, .so, .dylib) built separately. It exports, with C linkage:
+//
+//   uint32_t veh_reg_plugin_abi(void);            returns 1
+//   const char *veh_reg_plugin_name(void);        shown in the provider list
+//   const char *veh_reg_plugin_no_record(void);   optional: text only the
+//                                                 not-found answer contains
+//   int veh_reg_plugin_check(const char *vehicle_no, const char *date,
+//                            uint16_t *status, char **body);
+//   void veh_reg_plugin_free(char *body);
+//
+// check does one lookup (date as YYYY-MM-DD) and returns 0 with the HTTP
+// status and an allocated UTF-8 body, which is handed back to free; anything
+// else is a failed request. It is called from several threads at once.
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+    pub no_record: Option<String>,
+    check: CheckFn,
+    free: FreeFn,
+    // Keeps the code behind `check` and `free` loaded
+    _library: Library,
+}
+
+static PLUGINS: OnceLock<Vec<Arc<Plugin>>> = OnceLock::new();
+
+// Loads every library in `dir` once, returning the ones that failed with
+// why. A missing directory just means no plugins.
+pub fn load_all(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut plugins = Vec::new();
+    let mut failed = Vec::new();
+    let entries = fs::read_dir(dir).into_iter().flatten().flatten();
+    let mut paths: Vec<PathBuf> = entries
+    .map(|entry| entry.path())
+    .filter(|path| path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ["dll", "so", "dylib"].contains(&ext)))
+    .collect();
+    paths.sort();
+    for path in paths {
+        match load(&path) {
+            Ok(plugin) if plugins.iter().any(|p: &Arc<Plugin>| p.name == plugin.name) => {
+                failed.push((path, tr!("another plugin is already called '{}'", plugin.name)));
+            }
+            Ok(plugin) => plugins.push(Arc::new(plugin)),
+            Err(e) => failed.push((path, e)),
+        }
+    }
+    let _ = PLUGINS.set(plugins);
+    failed
+}
+
+pub fn all() -> &'static [Arc<Plugin>] {
+    PLUGINS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+pub fn find(name: &str) -> Option<Arc<Plugin>> {
+    all().iter().find(|plugin| plugin.name == name).cloned()
+}
+
+fn load(path: &Path) -> Result<Plugin, String> {
+    // SAFETY: loading runs the library's initializers; plugins are trusted
+    // the way the app itself is, by whoever put them in the directory
+    let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+    let (name, no_record, check, free) = unsafe {
+        let abi: Symbol<unsafe extern "C" fn() -> u32> = library.get(b"veh_reg_plugin_abi").map_err(|e| e.to_string())?;
+        if abi() != ABI_VERSION {
+            return Err(tr!("built for plugin interface {}, this version has {}", abi(), ABI_VERSION));
+        }
+        let name: Symbol<TextFn> = library.get(b"veh_reg_plugin_name").map_err(|e| e.to_string())?;
+        let no_record = library.get::<TextFn>(b"veh_reg_plugin_no_record").ok().and_then(|no_record| text(no_record()));
+        let check: Symbol<CheckFn> = library.get(b"veh_reg_plugin_check").map_err(|e| e.to_string())?;
+        let free: Symbol<FreeFn> = library.get(b"veh_reg_plugin_free").map_err(|e| e.to_string())?;
+        (text(name()), no_record, *check, *free)
+    };
+    let name = name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).ok_or_else(|| tr!("the plugin has no name"))?;
+    let no_record = no_record.filter(|pattern| !pattern.trim().is_empty());
+    Ok(Plugin { name, path: path.to_path_buf(), no_record, check, free, _library: library })
+}
+
+unsafe fn text(ptr: *const c_char) -> Option<String> {
+    // SAFETY: the plugin returns NUL-terminated strings that live as long as it
+    (!ptr.is_null()).then(|| unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned())
+}
+
+impl Plugin {
+    // Points the "No record page" rule at the plugin's not-found text, like
+    // AppConfig::switch_provider does for a built-in provider. A plugin
+    // without one gets the rule turned off rather than the last provider's,
+    // which would never match its pages; switching back turns it on again.
+    pub fn select(&self, config: &mut AppConfig, log: impl Fn(String, LogLevel)) {
+        config.provider_plugin = self.name.clone();
+        let rule = match &self.no_record {
+            Some(pattern) => ClassifierRule { kind: MatchKind::Substring, pattern: pattern.clone(), ..classify::no_record_rule(config.provider) },
+            None => {
+                log(tr!("Provider plugin {} doesn't say what its not-found page looks like, so those pages count as hits until a \"{}\" rule matches them.",
+                        self.name, classify::NO_RECORD_RULE), LogLevel::Warning);
+                ClassifierRule { enabled: false, ..classify::no_record_rule(config.provider) }
+            }
+        };
+        match config.classifier_rules.iter_mut().find(|r| r.name == classify::NO_RECORD_RULE) {
+            Some(existing) => *existing = rule,
+            None => config.classifier_rules.push(rule),
+        }
+    }
+}
+
+// The lookups of a run, done by the plugin instead of over HTTP here
+pub struct PluginTransport(pub Arc<Plugin>);
+
+impl Transport for PluginTransport {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        let vehicle_no = CString::new(vehicle_no)?;
+        let date = CString::new(date_str)?;
+        let mut status = 0u16;
+        let mut body: *mut c_char = std::ptr::null_mut();
+        // SAFETY: the plugin keeps to the contract on Plugin
+        let code = unsafe { (self.0.check)(vehicle_no.as_ptr(), date.as_ptr(), &mut status, &mut body) };
+        let text = unsafe { text(body) }.unwrap_or_default();
+        if !body.is_null() {
+            unsafe { (self.0.free)(body) };
+        }
+        if code != 0 {
+            return Err(tr!("plugin {} failed with code {}: {}", self.0.name, code, text).into());
+        }
+        Ok((status, text))
+    }
+
+    // The plugin keeps its own session, if it needs one
+    fn bootstrap(&self, _url: &str) -> TransportResult<u16> {
+        Ok(200)
+    }
+}
@@ -0,0 +1,156 @@
+use crate::classify::MatchKind;
+use crate::search::SearchBy;
+use serde::{Deserialize, Serialize};
+
+// Which province's registration service a run talks to. Each one has its own
+// endpoint, lookup form and "no record" page; the scan engine, classifier
+// and everything downstream of a response are shared.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Provider {
+    #[default]
+    Islamabad,
+    Punjab,
+    Sindh,
+    Kpk,
+}
+
+// How the lookup form is sent
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Encoding {
+    Multipart,
+    UrlEncoded,
+    // A flat JSON object of the fields
+    Json,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Multipart, Encoding::UrlEncoded, Encoding::Json];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Multipart => "Multipart form",
+            Encoding::UrlEncoded => "URL-encoded form",
+            Encoding::Json => "JSON",
+        }
+    }
+}
+
+impl Provider {
+    pub const ALL: [Provider; 4] = [Provider::Islamabad, Provider::Punjab, Provider::Sindh, Provider::Kpk];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Provider::Islamabad => "Islamabad (ICT Excise)",
+            Provider::Punjab => "Punjab (MTMIS)",
+            Provider::Sindh => "Sindh (Excise & Taxation)",
+            Provider::Kpk => "Khyber Pakhtunkhwa (Excise & Taxation)",
+        }
+    }
+
+    // Where its plates are issued, for messages about plate formats
+    pub fn region(&self) -> &'static str {
+        match self {
+            Provider::Islamabad => "Islamabad",
+            Provider::Punjab => "Punjab",
+            Provider::Sindh => "Sindh",
+            Provider::Kpk => "Khyber Pakhtunkhwa",
+        }
+    }
+
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            Provider::Islamabad => crate::config::DEFAULT_ENDPOINT,
+            Provider::Punjab => "https://mtmis.excise.punjab.gov.pk/vehicle-verification",
+            Provider::Sindh => "https://excise.gos.pk/vehicle/vehicle_search",
+            Provider::Kpk => "https://kpexcise.gov.pk/mvrecords/api/verify",
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        match self {
+            Provider::Islamabad => Encoding::Multipart,
+            Provider::Punjab | Provider::Sindh => Encoding::UrlEncoded,
+            Provider::Kpk => Encoding::Json,
+        }
+    }
+
+    // Form field the number goes in, if the form can be searched by it
+    pub fn field(&self, by: SearchBy) -> Option<&'static str> {
+        match (self, by) {
+            (Provider::Islamabad, SearchBy::Plate) => Some("registrationNo"),
+            (Provider::Punjab, SearchBy::Plate) => Some("vhlno"),
+            (Provider::Punjab, SearchBy::Chassis) => Some("chassisno"),
+            (Provider::Punjab, SearchBy::Engine) => Some("engineno"),
+            (Provider::Sindh, SearchBy::Plate) => Some("reg_no"),
+            (Provider::Sindh, SearchBy::Chassis) => Some("chassis_no"),
+            (Provider::Kpk, SearchBy::Plate) => Some("registration_no"),
+            (Provider::Kpk, SearchBy::Chassis) => Some("chassis_no"),
+            (Provider::Kpk, SearchBy::Engine) => Some("engine_no"),
+            _ => None,
+        }
+    }
+
+    pub fn supports(&self, by: SearchBy) -> bool {
+        self.field(by).is_some()
+    }
+
+    // Form field name for the registration date
+    pub fn date_field(&self) -> &'static str {
+        match self {
+            Provider::Islamabad => "registrationDate",
+            Provider::Punjab => "regdate",
+            Provider::Sindh => "reg_date",
+            Provider::Kpk => "registration_date",
+        }
+    }
+
+    // The date as the form expects it, in request template placeholders
+    pub fn date_value(&self) -> &'static str {
+        match self {
+            Provider::Islamabad | Provider::Kpk => "{date}",
+            Provider::Punjab => "{day}-{month}-{year}",
+            Provider::Sindh => "{day}/{month}/{year}",
+        }
+    }
+
+    // Kind and pattern of the "No record page" classifier rule: what only
+    // the not-found answer has
+    pub fn no_record_match(&self) -> (MatchKind, &'static str) {
+        match self {
+            Provider::Islamabad => (MatchKind::Substring, "NO RECORD FOUND && PLEASE CONTACT EXCISE"),
+            Provider::Punjab => (MatchKind::Substring, "NO RECORD FOUND && MTMIS"),
+            // The Sindh service answers in JSON either way
+            Provider::Sindh => (MatchKind::Substring, "\"STATUS\" && RECORD NOT FOUND"),
+            // KPK answers {"success": false, "message": ...} for unknown
+            // plates and for failures alike; only the message tells them apart
+            Provider::Kpk => (MatchKind::Regex, r#"(?is)"success"\s*:\s*false.*not (found|registered)"#),
+        }
+    }
+}
+
+// Series prefixes only one authority issues: the ICT prefix, and district
+// codes that open the series of the bigger cities. Letters further into a
+// series overlap between provinces and say nothing.
+const PREFIXES: [(Provider, &[&str]); 4] = [
+    (Provider::Islamabad, &["ICT", "ID"]),
+    (Provider::Punjab, &["LE", "RI", "FD", "MN", "GRW", "SKT"]),
+    (Provider::Sindh, &["KHI", "HD", "SKR", "LRK"]),
+    (Provider::Kpk, &["PR", "PSH", "MRD", "SWT", "ABT"]),
+];
+
+// The authority a plate most likely belongs to, from the longest known prefix
+// of its series letters; None when the prefix isn't specific to one
+pub fn detect(vehicle_no: &str) -> Option<Provider> {
+    let series: String = vehicle_no
+    .trim()
+    .chars()
+    .take_while(|c| c.is_ascii_alphabetic())
+    .collect::<String>()
+    .to_uppercase();
+    PREFIXES
+    .iter()
+    .flat_map(|(provider, prefixes)| prefixes.iter().map(move |prefix| (*provider, *prefix)))
+    .filter(|(_, prefix)| series.starts_with(prefix))
+    .max_by_key(|(_, prefix)| prefix.len())
+    .map(|(provider, _)| provider)
+}
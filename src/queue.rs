@@ -0,0 +1,107 @@
+use crate::ordering::SearchOrder;
+use crate::plate::VehicleType;
+use crate::stop::StopConditions;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Running,
+    // Ran to the end of its range or stopped on a hit
+    Finished,
+    // Stopped by the user; the queue pauses behind it
+    Stopped,
+    // Never got going, e.g. a bad date range; the console says why
+    Failed,
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "Pending",
+            JobStatus::Running => "Running",
+            JobStatus::Finished => "Finished",
+            JobStatus::Stopped => "Stopped",
+            JobStatus::Failed => "Failed",
+        }
+    }
+}
+
+// One scan waiting in, or done by, the queue. The settings are copied from
+// the Scan tab when the job is added.
+#[derive(Clone)]
+pub struct Job {
+    pub id: u64,
+    pub vehicle_no: String,
+    pub vehicle_type: VehicleType,
+    pub start_date: String,
+    pub end_date: String,
+    pub threads: usize,
+    pub search_order: SearchOrder,
+    pub stop_conditions: StopConditions,
+    // Runs next once the current job is done, ahead of everything else pending
+    pub high_priority: bool,
+    pub status: JobStatus,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub checked: usize,
+    pub total: usize,
+    // Registration dates found for the plate
+    pub hits: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct JobQueue {
+    pub jobs: Vec<Job>,
+    // Set when the user stops a queued job; nothing new starts until resumed
+    pub paused: bool,
+    // The job whose run is in progress, if the run came from the queue
+    pub current: Option<u64>,
+    next_id: u64,
+}
+
+impl JobQueue {
+    pub fn push(&mut self, mut job: Job) {
+        self.next_id += 1;
+        job.id = self.next_id;
+        job.status = JobStatus::Pending;
+        self.jobs.push(job);
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Job> {
+        let id = self.current?;
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    // High-priority jobs first, otherwise in queue order
+    pub fn next_pending(&mut self) -> Option<&mut Job> {
+        let index = self
+        .jobs
+        .iter()
+        .position(|job| job.status == JobStatus::Pending && job.high_priority)
+        .or_else(|| self.jobs.iter().position(|job| job.status == JobStatus::Pending))?;
+        self.jobs.get_mut(index)
+    }
+
+    // Moves a job to where `target` is, shifting the rest down
+    pub fn move_to(&mut self, id: u64, target: u64) {
+        let Some(from) = self.jobs.iter().position(|job| job.id == id) else { return };
+        let Some(to) = self.jobs.iter().position(|job| job.id == target) else { return };
+        let job = self.jobs.remove(from);
+        self.jobs.insert(to, job);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.jobs.iter().filter(|job| job.status == JobStatus::Pending).count()
+    }
+
+    // The job of the run in progress stays
+    pub fn remove(&mut self, id: u64) {
+        let current = self.current;
+        self.jobs.retain(|job| job.id != id || current == Some(id));
+    }
+
+    pub fn clear_done(&mut self) {
+        let current = self.current;
+        self.jobs.retain(|job| job.status == JobStatus::Pending || current == Some(job.id));
+    }
+}
@@ -0,0 +1,588 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ExtractKind {
+    // The first capture group, or the whole match without one
+    Regex,
+    // The text of the first matching element
+    Selector,
+}
+
+impl ExtractKind {
+    pub const ALL: [ExtractKind; 2] = [ExtractKind::Regex, ExtractKind::Selector];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExtractKind::Regex => "Regex",
+            ExtractKind::Selector => "CSS selector",
+        }
+    }
+}
+
+// A field the generic parser misses or gets wrong, pulled out by the user's
+// own pattern. Found values come before the generic ones and replace a
+// generic field of the same name.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ExtractionRule {
+    pub name: String,
+    pub enabled: bool,
+    pub kind: ExtractKind,
+    pub pattern: String,
+}
+
+enum Extractor {
+    Pattern(Regex),
+    Selector(scraper::Selector),
+}
+
+// Extraction rules ready to apply, with the list they came from. Each
+// VehicleClient keeps its own; the app's are also what masking looks at.
+#[derive(Default)]
+pub struct FieldRules {
+    rules: Vec<ExtractionRule>,
+    extractors: Vec<(String, Extractor)>,
+}
+
+impl FieldRules {
+    pub fn new(rules: &[ExtractionRule]) -> Result<Self, String> {
+        Ok(Self { rules: rules.to_vec(), extractors: compile(rules)? })
+    }
+}
+
+// The app's rules, see set_rules
+static RULES: RwLock<Option<Arc<FieldRules>>> = RwLock::new(None);
+
+fn compile(rules: &[ExtractionRule]) -> Result<Vec<(String, Extractor)>, String> {
+    rules
+    .iter()
+    .filter(|rule| rule.enabled && !rule.name.trim().is_empty() && !rule.pattern.trim().is_empty())
+    .map(|rule| {
+        let extractor = match rule.kind {
+            ExtractKind::Regex => Extractor::Pattern(Regex::new(&rule.pattern).map_err(|e| format!("field '{}': {}", rule.name, e))?),
+            ExtractKind::Selector => Extractor::Selector(
+                scraper::Selector::parse(&rule.pattern).map_err(|e| format!("field '{}': {}", rule.name, e))?,
+            ),
+        };
+        Ok((rule.name.trim().to_string(), extractor))
+    })
+    .collect()
+}
+
+// Makes `rules` the app's, like i18n::set_language does for the language:
+// what its own parsing uses and what personal fields masking covers. An
+// invalid rule keeps the previous set in place.
+pub fn set_rules(rules: &[ExtractionRule]) -> Result<(), String> {
+    if RULES.read().is_ok_and(|current| current.as_ref().is_some_and(|current| current.rules == rules)) {
+        return Ok(());
+    }
+    let compiled = FieldRules::new(rules)?;
+    if let Ok(mut current) = RULES.write() {
+        *current = Some(Arc::new(compiled));
+    }
+    Ok(())
+}
+
+// The app's rules as set_rules last left them
+pub fn rules() -> Arc<FieldRules> {
+    RULES.read().ok().and_then(|current| current.clone()).unwrap_or_default()
+}
+
+// What `rules` find in `body`, for trying rules out before they are saved
+pub fn preview(rules: &[ExtractionRule], body: &str) -> Result<Vec<(String, String)>, String> {
+    Ok(merge(extract(&compile(rules)?, body), body))
+}
+
+fn extract(extractors: &[(String, Extractor)], body: &str) -> Vec<(String, String)> {
+    if extractors.is_empty() {
+        return Vec::new();
+    }
+    // Parsed once, and only when a selector needs it
+    let mut document = None;
+    let mut fields = Vec::new();
+    for (name, extractor) in extractors {
+        let value = match extractor {
+            Extractor::Pattern(re) => re.captures(body).and_then(|c| c.get(1).or_else(|| c.get(0))).map(|m| clean_text(m.as_str())),
+            Extractor::Selector(selector) => document
+            .get_or_insert_with(|| scraper::Html::parse_document(body))
+            .select(selector)
+            .next()
+            .map(|element| element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")),
+        };
+        if let Some(value) = value {
+            push_field(&mut fields, name, &value);
+        }
+    }
+    fields
+}
+
+// The rule fields first, then the generic ones they don't replace
+fn merge(mut fields: Vec<(String, String)>, body: &str) -> Vec<(String, String)> {
+    for (label, value) in parse_generic(body) {
+        if !fields.iter().any(|(name, _)| name.eq_ignore_ascii_case(&label)) {
+            fields.push((label, value));
+        }
+    }
+    fields
+}
+
+// Label/value pairs pulled out of a hit page: the fields of the user's
+// extraction rules, then whatever the generic parser finds
+pub fn parse_fields(rules: &FieldRules, body: &str) -> Vec<(String, String)> {
+    merge(extract(&rules.extractors, body), body)
+}
+
+// The excise site renders the record as a table with the label in one cell
+// and the value in the next, so rows are paired cell by cell; pages without
+// a table fall back to "Label: Value" lines. Providers that answer in JSON
+// (Sindh) are read as JSON instead.
+fn parse_generic(html: &str) -> Vec<(String, String)> {
+    if let Some(fields) = parse_json_fields(html) {
+        return fields;
+    }
+
+    static ROW: OnceLock<Regex> = OnceLock::new();
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    static NOISE: OnceLock<Regex> = OnceLock::new();
+    let row_re = ROW.get_or_init(|| Regex::new(r"(?is)<tr[^>]*>(.*?)</tr>").unwrap());
+    let cell_re = CELL.get_or_init(|| Regex::new(r"(?is)<t[dh][^>]*>(.*?)</t[dh]>").unwrap());
+    let noise_re = NOISE.get_or_init(|| Regex::new(r"(?is)<(script|style)[^>]*>.*?</(script|style)>").unwrap());
+
+    let html = noise_re.replace_all(html, "");
+    let mut fields = Vec::new();
+
+    for row in row_re.captures_iter(&html) {
+        let cells: Vec<String> = cell_re.captures_iter(&row[1]).map(|c| clean_text(&c[1])).collect();
+        for pair in cells.chunks(2) {
+            if let [label, value] = pair {
+                push_field(&mut fields, label, value);
+            }
+        }
+    }
+
+    if fields.is_empty() {
+        for line in clean_text_lines(&html) {
+            if let Some((label, value)) = line.split_once(':') {
+                push_field(&mut fields, label, value);
+            }
+        }
+    }
+
+    fields
+}
+
+// The record object of a JSON answer, under "data" or at the top level,
+// with keys like "owner_name" turned into labels like "Owner Name" so the
+// key fields are found the same way as on HTML pages
+fn parse_json_fields(body: &str) -> Option<Vec<(String, String)>> {
+    let json: serde_json::Value = serde_json::from_str(body.trim()).ok()?;
+    let record = match json.get("data") {
+        Some(serde_json::Value::Array(rows)) => rows.first()?,
+        Some(data @ serde_json::Value::Object(_)) => data,
+        _ => &json,
+    };
+
+    let mut fields = Vec::new();
+    for (key, value) in record.as_object()? {
+        let value = match value {
+            serde_json::Value::String(text) => decode_entities(text),
+            serde_json::Value::Number(number) => number.to_string(),
+            serde_json::Value::Bool(flag) => flag.to_string(),
+            _ => continue,
+        };
+        push_field(&mut fields, &json_label(key), &value);
+    }
+    Some(fields)
+}
+
+// "owner_name" -> "Owner Name"
+fn json_label(key: &str) -> String {
+    key
+    .split(['_', '-'])
+    .filter(|word| !word.is_empty())
+    .map(|word| {
+        let mut chars = word.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn push_field(fields: &mut Vec<(String, String)>, label: &str, value: &str) {
+    let label = label.trim().trim_end_matches(':').trim();
+    let value = value.trim();
+    if !label.is_empty() && !value.is_empty() && label.len() <= 60 {
+        fields.push((label.to_string(), value.to_string()));
+    }
+}
+
+// The owner's name if the page has one
+pub fn owner(fields: &[(String, String)]) -> Option<&str> {
+    let find = |needle: &str| {
+        fields
+        .iter()
+        .find(|(label, _)| label.to_lowercase().contains(needle))
+        .map(|(_, value)| value.as_str())
+    };
+    find("owner").or_else(|| find("name"))
+}
+
+// Process-wide like the verbosity, so every thread's output follows it
+static REDACT: AtomicBool = AtomicBool::new(false);
+
+// Masks owner names, CNICs and addresses, and the registration dates of
+// hits, in what leaves the app (console, log files, the audit log, MQTT, the
+// found command and scripts); saved responses keep them
+pub fn set_redaction(on: bool) {
+    REDACT.store(on, Ordering::Relaxed);
+}
+
+pub fn redacting() -> bool {
+    REDACT.load(Ordering::Relaxed)
+}
+
+// Labels of fields that say who someone is or where they live
+const PERSONAL_LABELS: [&str; 7] = ["owner", "father", "cnic", "address", "phone", "mobile", "contact"];
+
+// "Name" alone is personal; "Maker Name" or "Model Name" isn't
+pub fn is_personal(label: &str) -> bool {
+    let label = label.to_lowercase();
+    let words: Vec<&str> = label.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    PERSONAL_LABELS.iter().any(|keyword| label.contains(keyword))
+    || words.contains(&"nic")
+    || (words.contains(&"name") && !["make", "maker", "model", "manufacturer", "company"].iter().any(|w| words.contains(w)))
+}
+
+// "Muhammad Ali" -> "M******* A**", so the shape of the value still shows
+pub fn mask(value: &str) -> String {
+    value
+    .split(' ')
+    .map(|word| {
+        let mut chars = word.chars();
+        chars.next().map(|first| format!("{}{}", first, "*".repeat(chars.count()))).unwrap_or_default()
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+// A hit's registration date as it may leave the app. With the plate it looks
+// the record up again, so while redacting only the year is given:
+// "2015-06-01" -> "2015-**-**".
+pub fn shareable_date(date: &str) -> String {
+    if !redacting() {
+        return date.to_string();
+    }
+    match date.split_once('-') {
+        Some((year, rest)) => format!("{}-{}", year, rest.replace(|c: char| c.is_ascii_digit(), "*")),
+        None => mask(date),
+    }
+}
+
+// `text` with a hit's date masked wherever it appears, e.g. in the name of
+// its saved response
+pub fn without_date(text: &str, date: &str) -> String {
+    if !redacting() || date.is_empty() {
+        return text.to_string();
+    }
+    text.replace(date, &shareable_date(date))
+}
+
+// A field's value as it may leave the app
+pub fn shareable(label: &str, value: &str) -> String {
+    if redacting() && is_personal(label) { mask(value) } else { value.to_string() }
+}
+
+pub fn shareable_fields(fields: &[(String, String)]) -> Vec<(String, String)> {
+    fields.iter().map(|(label, value)| (label.clone(), shareable(label, value))).collect()
+}
+
+// Free text as it may leave the app: CNICs (12345-1234567-1, with or
+// without the dashes) are masked while redacting. Names can't be told apart
+// in free text, so fields are masked where they are written out instead.
+pub fn redact_text(text: &str) -> String {
+    if redacting() { mask_cnics(text) } else { text.to_string() }
+}
+
+fn mask_cnics(text: &str) -> String {
+    static CNIC: OnceLock<Regex> = OnceLock::new();
+    let cnic = CNIC.get_or_init(|| Regex::new(r"\b\d{5}-?\d{7}-?\d\b").unwrap());
+    cnic.replace_all(text, "*****-*******-*").into_owned()
+}
+
+// A whole response as it may leave the app, e.g. in an HTTP transcript.
+// Personal values are masked where they sit in the page (the value cell,
+// the text after "Label:", the JSON string, the rule's match) rather than
+// searched for, so entities and line breaks in the source don't let them
+// through. Any CNIC left over is masked after.
+pub fn redact_body(body: &str) -> String {
+    if redacting() { masked_body(body) } else { body.to_string() }
+}
+
+fn masked_body(body: &str) -> String {
+    let text = redact_json(body).unwrap_or_else(|| {
+        let mut text = replace_spans(body, personal_spans(body));
+        // Selector matches have no position in the source, so their values
+        // are searched for
+        for value in selector_values(body) {
+            text = text.replace(&value, &mask(&value));
+        }
+        text
+    });
+    mask_cnics(&text)
+}
+
+// The same record parse_json_fields reads, every row of it, with the
+// personal values masked
+fn redact_json(body: &str) -> Option<String> {
+    let mut json: serde_json::Value = serde_json::from_str(body.trim()).ok()?;
+    let in_data = matches!(json.get("data"), Some(serde_json::Value::Array(_) | serde_json::Value::Object(_)));
+    let records: Vec<&mut serde_json::Value> = match if in_data { json.get_mut("data")? } else { &mut json } {
+        serde_json::Value::Array(rows) => rows.iter_mut().collect(),
+        record => vec![record],
+    };
+    for record in records {
+        let Some(object) = record.as_object_mut() else { continue };
+        for (key, value) in object.iter_mut() {
+            if !is_personal(&json_label(key)) {
+                continue;
+            }
+            let masked = match value {
+                serde_json::Value::String(text) => mask(&decode_entities(text)),
+                serde_json::Value::Number(number) => mask(&number.to_string()),
+                _ => continue,
+            };
+            *value = serde_json::Value::String(masked);
+        }
+    }
+    serde_json::to_string(&json).ok()
+}
+
+// Byte ranges of personal values in an HTML page, with what goes in their
+// place: value cells paired the way parse_generic pairs them, "Label: Value"
+// text and the matches of the user's regex rules
+fn personal_spans(html: &str) -> Vec<(Range<usize>, String)> {
+    static ROW: OnceLock<Regex> = OnceLock::new();
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    static TEXT: OnceLock<Regex> = OnceLock::new();
+    static LABELLED: OnceLock<Regex> = OnceLock::new();
+    let row_re = ROW.get_or_init(|| Regex::new(r"(?is)<tr[^>]*>(.*?)</tr>").unwrap());
+    let cell_re = CELL.get_or_init(|| Regex::new(r"(?is)<t[dh][^>]*>(.*?)</t[dh]>").unwrap());
+    let text_re = TEXT.get_or_init(|| Regex::new(r"(?:^|>)([^<]+)").unwrap());
+    let labelled_re = LABELLED.get_or_init(|| Regex::new(r"([^:\n]+):([^\n]+)").unwrap());
+
+    let mut spans = Vec::new();
+    let mut push = |range: Range<usize>, raw: &str| {
+        let value = clean_text(raw);
+        if !value.is_empty() {
+            spans.push((range, mask(&value)));
+        }
+    };
+
+    for row in row_re.captures_iter(html) {
+        let row = row.get(1).unwrap();
+        let cells: Vec<_> = cell_re.captures_iter(row.as_str()).filter_map(|c| c.get(1)).collect();
+        for pair in cells.chunks(2) {
+            if let [label, value] = pair {
+                if is_personal(&clean_text(label.as_str())) {
+                    push(row.start() + value.start()..row.start() + value.end(), value.as_str());
+                }
+            }
+        }
+    }
+
+    for node in text_re.captures_iter(html).filter_map(|c| c.get(1)) {
+        for line in labelled_re.captures_iter(node.as_str()) {
+            let (label, value) = (&line[1], line.get(2).unwrap());
+            if is_personal(&clean_text(label)) {
+                push(node.start() + value.start()..node.start() + value.end(), value.as_str());
+            }
+        }
+    }
+
+    for (name, extractor) in &rules().extractors {
+        if let Extractor::Pattern(re) = extractor {
+            if !is_personal(name) {
+                continue;
+            }
+            for found in re.captures_iter(html) {
+                if let Some(value) = found.get(1).or_else(|| found.get(0)) {
+                    push(value.range(), value.as_str());
+                }
+            }
+        }
+    }
+    spans
+}
+
+// The values of personal fields found by the user's selector rules
+fn selector_values(html: &str) -> Vec<String> {
+    let rules = rules();
+    let personal: Vec<&(String, Extractor)> = rules
+    .extractors
+    .iter()
+    .filter(|(name, extractor)| matches!(extractor, Extractor::Selector(_)) && is_personal(name))
+    .collect();
+    if personal.is_empty() {
+        return Vec::new();
+    }
+    let document = scraper::Html::parse_document(html);
+    personal
+    .iter()
+    .filter_map(|(_, extractor)| match extractor {
+        Extractor::Selector(selector) => document.select(selector).next(),
+        Extractor::Pattern(_) => None,
+    })
+    .flat_map(|element| element.text().map(str::trim).filter(|text| !text.is_empty()).map(str::to_string).collect::<Vec<_>>())
+    .collect()
+}
+
+// Puts each replacement in place of its range; a range overlapping an
+// earlier one is dropped
+fn replace_spans(text: &str, mut spans: Vec<(Range<usize>, String)>) -> String {
+    spans.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+    let mut out = String::with_capacity(text.len());
+    let mut at = 0;
+    for (range, replacement) in spans {
+        if range.start < at {
+            continue;
+        }
+        out.push_str(&text[at..range.start]);
+        out.push_str(&replacement);
+        at = range.end;
+    }
+    out.push_str(&text[at..]);
+    out
+}
+
+// The fields people actually look for, by label keyword, in display order
+const KEY_FIELDS: [(&str, &[&str]); 6] = [
+    ("Owner", &["owner", "name"]),
+    ("Chassis", &["chassis"]),
+    ("Engine", &["engine"]),
+    ("Make", &["make", "maker", "manufacturer"]),
+    ("Model", &["model"]),
+    ("Registration date", &["registration date", "reg date", "date of reg", "reg. date"]),
+];
+
+pub fn key_fields(fields: &[(String, String)]) -> Vec<(&'static str, &str)> {
+    KEY_FIELDS
+    .iter()
+    .filter_map(|(name, keywords)| {
+        keywords.iter().find_map(|keyword| {
+            fields
+            .iter()
+            .find(|(label, _)| label.to_lowercase().contains(keyword))
+            .map(|(_, value)| (*name, value.as_str()))
+        })
+    })
+    .collect()
+}
+
+// "ABC-123 2015-06-01 John Doe", without the owner if the page has none
+pub fn summary(vehicle_no: &str, date: &str, fields: &[(String, String)]) -> String {
+    match owner(fields) {
+        Some(owner) => format!("{} {} {}", vehicle_no, date, owner),
+        None => format!("{} {}", vehicle_no, date),
+    }
+}
+
+pub fn fields_text(fields: &[(String, String)]) -> String {
+    fields
+    .iter()
+    .map(|(label, value)| format!("{}: {}", label, value))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&amp;", "&")
+}
+
+fn clean_text(html: &str) -> String {
+    clean_text_lines(html).join(" ")
+}
+
+// Visible text, one entry per line break or block element
+pub fn clean_text_lines(html: &str) -> Vec<String> {
+    static BREAK: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let break_re = BREAK.get_or_init(|| Regex::new(r"(?i)<br\s*/?>|</(p|div|tr|li|h\d)>").unwrap());
+    let tag_re = TAG.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+    let text = break_re.replace_all(html, "\n");
+    let text = decode_entities(&tag_re.replace_all(&text, " "));
+    text.lines()
+    .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+    .filter(|line| !line.is_empty())
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Straight to the masking, so no test flips the process-wide switch
+    // under another one running in parallel
+    fn redacted(body: &str) -> String {
+        masked_body(body)
+    }
+
+    #[test]
+    fn each_rule_set_only_applies_where_it_is_passed() {
+        let body = "<table><tr><td>Owner Name</td><td>Ali</td></tr></table><p>Ref#A-77</p>";
+        let rule = ExtractionRule { name: "Reference".into(), enabled: true, kind: ExtractKind::Regex, pattern: r"Ref#(\S+)</p>".into() };
+        let with_rule = parse_fields(&FieldRules::new(&[rule]).unwrap(), body);
+        assert_eq!(with_rule[0], ("Reference".to_string(), "A-77".to_string()));
+        let generic = parse_fields(&FieldRules::default(), body);
+        assert!(generic.iter().all(|(label, _)| label != "Reference"), "{:?}", generic);
+        assert!(generic.contains(&("Owner Name".to_string(), "Ali".to_string())), "{:?}", generic);
+    }
+
+    #[test]
+    fn masks_value_cells_written_with_entities() {
+        let body = "<table><tr><td>Owner Name</td><td>Muhammad&nbsp;Ali &amp; Sons</td></tr>\
+                    <tr><td>Maker Name</td><td>Toyota</td></tr></table>";
+        let out = redacted(body);
+        assert!(out.contains("<td>M******* A** & S***</td>"), "{}", out);
+        assert!(out.contains("<td>Toyota</td>"), "{}", out);
+        assert!(!out.contains("Ali"), "{}", out);
+    }
+
+    #[test]
+    fn masks_value_cells_spread_over_lines() {
+        let body = "<tr>\n  <td>Father Name</td>\n  <td>\n    Ahmed\n    Khan\n  </td>\n</tr>";
+        let out = redacted(body);
+        assert!(out.contains("<td>A**** K***</td>"), "{}", out);
+        assert!(!out.contains("Ahmed") && !out.contains("Khan"), "{}", out);
+    }
+
+    #[test]
+    fn masks_labelled_text_outside_tables() {
+        let body = "<div>Owner:  Zainab&#39;s   Motors<br>Model: Corolla</div>";
+        let out = redacted(body);
+        assert!(!out.contains("Zainab"), "{}", out);
+        assert!(out.contains("Model: Corolla"), "{}", out);
+    }
+
+    #[test]
+    fn masks_json_records() {
+        let body = r#"{"data": [{"owner_name": "Sara Bibi", "model": "Civic", "cnic": 3520212345671}]}"#;
+        let out = redacted(body);
+        assert!(!out.contains("Sara") && !out.contains("3520212345671"), "{}", out);
+        assert!(out.contains("Civic"), "{}", out);
+    }
+
+    #[test]
+    fn masks_cnics_left_in_free_text() {
+        let out = redacted("<p>Ref 35202-1234567-1</p>");
+        assert_eq!(out, "<p>Ref *****-*******-*</p>");
+    }
+}
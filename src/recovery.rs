@@ -0,0 +1,68 @@
+use crate::{lock, vault};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// One file per running scan under <results>/runs/, rewritten at every
+// checkpoint and removed when the run ends. A file still there when the app
+// starts belongs to a run that never got to finish. Sealed like the
+// responses while the results are encrypted.
+pub const RUNS_DIR: &str = "runs";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunState {
+    // Start time and PID, e.g. 20240105_143000123_4242. Also the file name.
+    pub id: String,
+    pub pid: u32,
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub threads: usize,
+    // SearchOrder label
+    pub search_order: String,
+    pub started_at: String,
+    pub checkpoint_at: String,
+    pub checked: usize,
+    pub total: usize,
+}
+
+pub fn run_id(started: DateTime<Local>, pid: u32) -> String {
+    format!("{}_{}", started.format("%Y%m%d_%H%M%S%3f"), pid)
+}
+
+fn path_for(results_dir: &Path, id: &str) -> PathBuf {
+    results_dir.join(RUNS_DIR).join(format!("{}.json", id))
+}
+
+// Written to a temp file and renamed over the old one, so a crash mid-write
+// leaves the previous checkpoint intact
+pub fn save(results_dir: &Path, state: &RunState) -> io::Result<()> {
+    let path = path_for(results_dir, &state.id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    vault::write(&tmp, serde_json::to_string_pretty(state).map_err(io::Error::other)?)?;
+    fs::rename(&tmp, &path)
+}
+
+pub fn remove(results_dir: &Path, id: &str) {
+    let _ = fs::remove_file(path_for(results_dir, id));
+}
+
+// Runs nobody is keeping the run lock of alive, whichever process they were
+// started by. The oldest comes first.
+pub fn orphaned(results_dir: &Path) -> Vec<RunState> {
+    let Ok(entries) = fs::read_dir(results_dir.join(RUNS_DIR)) else { return Vec::new() };
+    let mut runs: Vec<RunState> = entries
+    .flatten()
+    .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+    .filter_map(|entry| vault::read_to_string(&entry.path()).ok())
+    .filter_map(|text| serde_json::from_str::<RunState>(&text).ok())
+    .filter(|state| !lock::run_is_live(results_dir, &state.id))
+    .collect();
+    runs.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    runs
+}
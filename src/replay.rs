@@ -0,0 +1,42 @@
+use crate::classify::{Classification, Classifier};
+use crate::record;
+use crate::storage;
+use crate::vault;
+use std::path::Path;
+
+pub struct ReplayEntry {
+    // Relative to the results directory
+    pub path: String,
+    pub vehicle_no: String,
+    pub date: String,
+    pub classification: Classification,
+    pub rule: String,
+    // Only parsed for hits
+    pub fields: Vec<(String, String)>,
+}
+
+// Runs every saved response back through the current classifier rules and
+// record parser, without touching the network. Files whose names don't say
+// which vehicle and date they are for are skipped.
+pub fn replay(root: &Path, classifier: &Classifier) -> Vec<ReplayEntry> {
+    let rules = record::rules();
+    let mut entries: Vec<ReplayEntry> = storage::walk_results(root)
+    .into_iter()
+    .filter_map(|path| {
+        let name = storage::parse_result_path(&path)?;
+        let body = vault::read_to_string(&root.join(&path)).ok()?;
+        let (classification, rule) = classifier.classify(name.status, &body);
+        let fields = if classification == Classification::Hit { record::parse_fields(&rules, &body) } else { Vec::new() };
+        Some(ReplayEntry {
+            path,
+            vehicle_no: name.vehicle_no,
+            date: name.date.format("%Y-%m-%d").to_string(),
+            classification,
+            rule,
+            fields,
+        })
+    })
+    .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
@@ -0,0 +1,360 @@
+use crate::HitRecord;
+use crate::control::JobInfo;
+use crate::record;
+use crate::stats::{self, ErrorEvent, StatsSnapshot};
+use crate::vault;
+use chrono::NaiveDate;
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+// Everything known about a finished run, gathered once and handed to the
+// report writers
+pub struct RunReport {
+    pub job: JobInfo,
+    pub dates_total: usize,
+    pub dates_checked: usize,
+    // Classification label and count, in Classification::ALL order
+    pub outcomes: Vec<(&'static str, u64)>,
+    pub network: StatsSnapshot,
+    pub errors: Vec<ErrorEvent>,
+    pub hits: Vec<HitRecord>,
+    // Unchecked stretches of the range, first and last date
+    pub gaps: Vec<(NaiveDate, NaiveDate)>,
+    pub generated_at: String,
+}
+
+impl RunReport {
+    // results/report_<VEHICLE>_<timestamp>.<extension>
+    pub fn default_path(&self, results_dir: &Path, extension: &str) -> PathBuf {
+        let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        results_dir.join(format!("report_{}_{}.{}", self.job.vehicle_no, stamp, extension))
+    }
+}
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+// Characters per line at 10pt Helvetica across the printable width
+const WRAP_AT: usize = 95;
+
+// Writes lines top to bottom, starting new A4 pages as needed
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page 1");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self { doc, layer, regular, bold, y: PAGE_HEIGHT - MARGIN })
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Page");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn text(&mut self, text: &str, size: f32, bold: bool) {
+        let line_height = size * 0.5;
+        for line in wrap(text, WRAP_AT * 10 / size as usize) {
+            self.ensure_space(line_height);
+            let font = if bold { &self.bold } else { &self.regular };
+            self.layer.use_text(line, size, Mm(MARGIN), Mm(self.y), font);
+            self.y -= line_height;
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.gap(4.0);
+        self.text(text, 13.0, true);
+        self.gap(1.0);
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        self.text(&format!("{}: {}", label, value), 10.0, false);
+    }
+
+    fn gap(&mut self, mm: f32) {
+        self.y -= mm;
+    }
+
+    fn save(self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.doc.save(&mut out)?;
+        Ok(())
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+// The built-in PDF fonts only cover Latin-1, anything else would come out as garbage
+fn latin1(text: &str) -> String {
+    text.chars().map(|c| if (c as u32) < 256 { c } else { '?' }).collect()
+}
+
+// Reports are for reading in other programs, so none are written while the
+// results are encrypted
+pub fn write_pdf(report: &RunReport, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    vault::allow_plain()?;
+    let job = &report.job;
+    let mut pdf = PdfWriter::new(&format!("Registration check {}", job.vehicle_no))?;
+
+    pdf.text("Vehicle Registration Check Report", 18.0, true);
+    pdf.gap(3.0);
+    pdf.field("Generated", &report.generated_at);
+
+    pdf.heading("Run");
+    pdf.field("Vehicle", &job.vehicle_no);
+    pdf.field("Date range", &format!("{} to {}", job.start_date, job.end_date));
+    pdf.field("Threads", &job.threads.to_string());
+    pdf.field("Started", &job.started_at);
+    pdf.field("Finished", job.finished_at.as_deref().unwrap_or("not finished"));
+    pdf.field("Dates checked", &format!("{} of {}", report.dates_checked, report.dates_total));
+
+    pdf.heading("Outcomes");
+    for (label, count) in &report.outcomes {
+        pdf.field(label, &count.to_string());
+    }
+
+    pdf.heading("Network");
+    let net = &report.network;
+    pdf.field("Requests", &net.requests.to_string());
+    pdf.field("Failed requests", &net.errors.to_string());
+    pdf.field("Sent", &stats::format_bytes(net.bytes_sent));
+    pdf.field("Received", &stats::format_bytes(net.bytes_received));
+    pdf.field("Latency", &format!("avg {} ms, p50 {} ms, p90 {} ms, p99 {} ms",
+                                  net.avg_latency_ms, net.p50_latency_ms, net.p90_latency_ms, net.p99_latency_ms));
+
+    pdf.heading(&format!("Errors ({})", report.errors.len()));
+    // The full list is in the Excel export; a blocked run can have thousands
+    for error in report.errors.iter().take(50) {
+        pdf.text(&latin1(&format!("{}  {}  {} - {}", error.at, error.date, error.kind, error.detail)), 9.0, false);
+    }
+    if report.errors.len() > 50 {
+        pdf.text(&format!("... and {} more", report.errors.len() - 50), 9.0, false);
+    }
+
+    pdf.heading(&format!("Records found ({})", report.hits.len()));
+    if report.hits.is_empty() {
+        pdf.text("No record was found in the checked range.", 10.0, false);
+    }
+    for hit in &report.hits {
+        pdf.gap(2.0);
+        pdf.text(&latin1(&record::summary(&hit.vehicle_no, &hit.date, &hit.fields)), 11.0, true);
+        pdf.field("Found at", &hit.found_at);
+        if let Some(file) = &hit.file_name {
+            pdf.field("Saved response", file);
+        }
+        for (label, value) in &hit.fields {
+            pdf.field(&latin1(label), &latin1(value));
+        }
+    }
+
+    pdf.save(path)
+}
+
+// Plain Markdown for pasting into a ticket or case notes
+pub fn write_markdown(report: &RunReport, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    vault::allow_plain()?;
+    fs::write(path, markdown(report))?;
+    Ok(())
+}
+
+fn markdown(report: &RunReport) -> String {
+    let job = &report.job;
+    let net = &report.network;
+    let mut md = String::new();
+
+    let _ = writeln!(md, "# Registration check: {}\n", job.vehicle_no);
+    let _ = writeln!(md, "_Report generated {}_\n", report.generated_at);
+
+    let _ = writeln!(md, "## Search\n");
+    let _ = writeln!(md, "| | |\n|---|---|");
+    let _ = writeln!(md, "| Vehicle | {} |", md_cell(&job.vehicle_no));
+    let _ = writeln!(md, "| Date range | {} to {} |", job.start_date, job.end_date);
+    let _ = writeln!(md, "| Threads | {} |", job.threads);
+    let _ = writeln!(md, "| Started | {} |", job.started_at);
+    let _ = writeln!(md, "| Finished | {} |", job.finished_at.as_deref().unwrap_or("not finished"));
+    let _ = writeln!(md, "| Dates checked | {} of {} |", report.dates_checked, report.dates_total);
+    let _ = writeln!(md, "| Requests | {} ({} failed) |", net.requests, net.errors);
+    let _ = writeln!(md, "| Latency | avg {} ms, p50 {} ms, p90 {} ms, p99 {} ms |\n",
+                     net.avg_latency_ms, net.p50_latency_ms, net.p90_latency_ms, net.p99_latency_ms);
+
+    let _ = writeln!(md, "## Records found ({})\n", report.hits.len());
+    if report.hits.is_empty() {
+        let _ = writeln!(md, "No record was found in the checked range.\n");
+    }
+    for hit in &report.hits {
+        let _ = writeln!(md, "### {}\n", record::summary(&hit.vehicle_no, &hit.date, &hit.fields));
+        let _ = writeln!(md, "- Found at: {}", hit.found_at);
+        if let Some(file) = &hit.file_name {
+            let _ = writeln!(md, "- Saved response: `{}`", file);
+        }
+        for (label, value) in &hit.fields {
+            let _ = writeln!(md, "- {}: {}", label, value);
+        }
+        md.push('\n');
+    }
+
+    let _ = writeln!(md, "## Coverage gaps\n");
+    if report.gaps.is_empty() {
+        let _ = writeln!(md, "Every date in the range was checked.\n");
+    }
+    for (from, to) in &report.gaps {
+        let days = (*to - *from).num_days() + 1;
+        if from == to {
+            let _ = writeln!(md, "- {}", from.format("%Y-%m-%d"));
+        } else {
+            let _ = writeln!(md, "- {} to {} ({} days)", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"), days);
+        }
+    }
+    if !report.gaps.is_empty() {
+        md.push('\n');
+    }
+
+    let _ = writeln!(md, "## Errors ({})\n", report.errors.len());
+    if report.errors.is_empty() {
+        let _ = writeln!(md, "None.");
+        return md;
+    }
+    let mut kinds: BTreeMap<&str, usize> = BTreeMap::new();
+    for error in &report.errors {
+        *kinds.entry(error.kind.as_str()).or_insert(0) += 1;
+    }
+    let _ = writeln!(md, "| Kind | Count |\n|---|---|");
+    for (kind, count) in kinds {
+        let _ = writeln!(md, "| {} | {} |", md_cell(kind), count);
+    }
+    let _ = writeln!(md, "\n| Time | Date checked | Kind | Detail |\n|---|---|---|---|");
+    // Same cut-off as the PDF; the Excel export has them all
+    for error in report.errors.iter().take(50) {
+        let _ = writeln!(md, "| {} | {} | {} | {} |", error.at, error.date, md_cell(&error.kind), md_cell(&error.detail));
+    }
+    if report.errors.len() > 50 {
+        let _ = writeln!(md, "\n... and {} more", report.errors.len() - 50);
+    }
+    md
+}
+
+// Keeps a value from breaking out of its table cell
+fn md_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+// Hits, errors and run metadata on separate sheets
+pub fn write_xlsx(report: &RunReport, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    vault::allow_plain()?;
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+
+    // Every page can label its fields differently, so each distinct label gets a column
+    let mut labels: Vec<&str> = Vec::new();
+    for hit in &report.hits {
+        for (label, _) in &hit.fields {
+            if !labels.contains(&label.as_str()) {
+                labels.push(label);
+            }
+        }
+    }
+
+    let sheet = workbook.add_worksheet().set_name("Hits")?;
+    let fixed = ["Vehicle", "Registration date", "Found at", "Saved response"];
+    for (col, title) in fixed.iter().chain(labels.iter()).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &bold)?;
+    }
+    for (i, hit) in report.hits.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &hit.vehicle_no)?;
+        sheet.write_string(row, 1, &hit.date)?;
+        sheet.write_string(row, 2, &hit.found_at)?;
+        sheet.write_string(row, 3, hit.file_name.as_deref().unwrap_or(""))?;
+        for (label, value) in &hit.fields {
+            if let Some(j) = labels.iter().position(|l| *l == label.as_str()) {
+                sheet.write_string(row, (fixed.len() + j) as u16, value)?;
+            }
+        }
+    }
+    sheet.autofit();
+
+    let sheet = workbook.add_worksheet().set_name("Errors")?;
+    for (col, title) in ["Time", "Date checked", "Kind", "Detail"].iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &bold)?;
+    }
+    for (i, error) in report.errors.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &error.at)?;
+        sheet.write_string(row, 1, &error.date)?;
+        sheet.write_string(row, 2, &error.kind)?;
+        sheet.write_string(row, 3, &error.detail)?;
+    }
+    sheet.autofit();
+
+    let job = &report.job;
+    let net = &report.network;
+    let mut rows: Vec<(String, String)> = vec![
+        ("Vehicle".to_string(), job.vehicle_no.clone()),
+        ("Start date".to_string(), job.start_date.clone()),
+        ("End date".to_string(), job.end_date.clone()),
+        ("Threads".to_string(), job.threads.to_string()),
+        ("Started".to_string(), job.started_at.clone()),
+        ("Finished".to_string(), job.finished_at.clone().unwrap_or_default()),
+        ("Dates to check".to_string(), report.dates_total.to_string()),
+        ("Dates checked".to_string(), report.dates_checked.to_string()),
+        ("Requests".to_string(), net.requests.to_string()),
+        ("Failed requests".to_string(), net.errors.to_string()),
+        ("Bytes sent".to_string(), net.bytes_sent.to_string()),
+        ("Bytes received".to_string(), net.bytes_received.to_string()),
+        ("Average latency (ms)".to_string(), net.avg_latency_ms.to_string()),
+        ("p50 latency (ms)".to_string(), net.p50_latency_ms.to_string()),
+        ("p90 latency (ms)".to_string(), net.p90_latency_ms.to_string()),
+        ("p99 latency (ms)".to_string(), net.p99_latency_ms.to_string()),
+        ("Report generated".to_string(), report.generated_at.clone()),
+    ];
+    for (label, count) in &report.outcomes {
+        rows.push((format!("Outcome: {}", label), count.to_string()));
+    }
+
+    let sheet = workbook.add_worksheet().set_name("Run")?;
+    for (i, (label, value)) in rows.iter().enumerate() {
+        sheet.write_string_with_format(i as u32, 0, label, &bold)?;
+        // Counts as numbers so they can be summed
+        match value.parse::<f64>() {
+            Ok(number) => sheet.write_number(i as u32, 1, number)?,
+            Err(_) => sheet.write_string(i as u32, 1, value)?,
+        };
+    }
+    sheet.autofit();
+
+    workbook.save(path)?;
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use crate::HitRecord;
+use crate::classify::Classification;
+use crate::db::Annotation;
+use crate::record;
+use crate::stats::ErrorEvent;
+use crate::vault;
+use crate::verify::csv_cell;
+use serde_json::json;
+use std::cmp::Ordering;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+// A line of the Found Records table: a hit, or a lookup of the run that
+// failed, shown as a record with the error in its fields
+#[derive(Clone)]
+pub struct Row {
+    pub outcome: Classification,
+    pub hit: HitRecord,
+    // Tags and note from the results database, for rows with a saved response
+    pub annotation: Annotation,
+}
+
+impl Row {
+    pub fn hit(hit: HitRecord) -> Self {
+        Self { outcome: Classification::Hit, hit, annotation: Annotation::default() }
+    }
+
+    pub fn error(event: &ErrorEvent, vehicle_no: &str, provider: &str) -> Self {
+        Self {
+            // "Rate limited" and "Challenge" are outcomes of their own; HTTP
+            // errors and failed requests are plain errors
+            outcome: Classification::parse(&event.kind).unwrap_or(Classification::Error),
+            hit: HitRecord {
+                vehicle_no: vehicle_no.to_string(),
+                date: event.date.clone(),
+                file_name: None,
+                found_at: event.at.clone(),
+                fields: vec![("Error".to_string(), event.kind.clone()), ("Detail".to_string(), event.detail.clone())],
+                provider: provider.to_string(),
+            },
+            annotation: Annotation::default(),
+        }
+    }
+
+    // Tells rows apart across frames: the saved response where there is one
+    pub fn key(&self) -> String {
+        match &self.hit.file_name {
+            Some(name) => name.clone(),
+            None => format!("{}|{}|{}", self.hit.vehicle_no, self.hit.date, self.hit.found_at),
+        }
+    }
+}
+
+// Columns of the Found Records table
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Column {
+    Vehicle,
+    Date,
+    Owner,
+    Make,
+    Status,
+    Provider,
+    Outcome,
+    FoundAt,
+    Tags,
+    Note,
+}
+
+impl Column {
+    pub const ALL: [Column; 10] = [
+        Column::Vehicle,
+        Column::Date,
+        Column::Owner,
+        Column::Make,
+        Column::Status,
+        Column::Provider,
+        Column::Outcome,
+        Column::FoundAt,
+        Column::Tags,
+        Column::Note,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Column::Vehicle => "Vehicle",
+            Column::Date => "Date",
+            Column::Owner => "Owner",
+            Column::Make => "Make",
+            Column::Status => "Status",
+            Column::Provider => "Provider",
+            Column::Outcome => "Result",
+            Column::FoundAt => "Found",
+            Column::Tags => "Tags",
+            Column::Note => "Note",
+        }
+    }
+
+    // What the cell shows, untranslated; empty when the page didn't have it
+    pub fn value<'a>(&self, row: &'a Row) -> &'a str {
+        let hit = &row.hit;
+        match self {
+            Column::Vehicle => &hit.vehicle_no,
+            Column::Date => &hit.date,
+            Column::Owner => record::owner(&hit.fields).unwrap_or_default(),
+            Column::Make => key_field(hit, "Make"),
+            // The vehicle's status on the record (active, blocked, ...), not
+            // how the lookup was classified
+            Column::Status => hit
+            .fields
+            .iter()
+            .find(|(label, _)| label.to_lowercase().contains("status"))
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default(),
+            Column::Provider => &hit.provider,
+            Column::Outcome => row.outcome.label(),
+            Column::FoundAt => &hit.found_at,
+            Column::Tags => &row.annotation.tags,
+            Column::Note => &row.annotation.note,
+        }
+    }
+}
+
+fn key_field<'a>(hit: &'a HitRecord, name: &str) -> &'a str {
+    record::key_fields(&hit.fields).into_iter().find(|(key, _)| *key == name).map(|(_, value)| value).unwrap_or_default()
+}
+
+// Which column the table is sorted on, and which way
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sort {
+    pub column: Column,
+    pub ascending: bool,
+}
+
+impl Default for Sort {
+    // The order the hits came in
+    fn default() -> Self {
+        Self { column: Column::FoundAt, ascending: true }
+    }
+}
+
+impl Sort {
+    // Clicking the sorted column's heading flips it, another one sorts on
+    // that column ascending
+    pub fn click(&mut self, column: Column) {
+        if self.column == column {
+            self.ascending = !self.ascending;
+        } else {
+            *self = Self { column, ascending: true };
+        }
+    }
+
+    // The heading with an arrow on the sorted column
+    pub fn heading(&self, column: Column, label: &str) -> String {
+        match (self.column == column, self.ascending) {
+            (true, true) => format!("{} ⏶", label),
+            (true, false) => format!("{} ⏷", label),
+            (false, _) => label.to_string(),
+        }
+    }
+
+    // Stable, so rows that tie keep the order they were found in. Dates and
+    // times are ISO strings and sort as text; empty cells go last either way.
+    pub fn apply(&self, rows: &mut [Row]) {
+        rows.sort_by(|a, b| {
+            let (a, b) = (self.column.value(a), self.column.value(b));
+            match (a.is_empty(), b.is_empty()) {
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                _ if self.ascending => a.to_lowercase().cmp(&b.to_lowercase()),
+                _ => b.to_lowercase().cmp(&a.to_lowercase()),
+            }
+        });
+    }
+}
+
+// What the table is narrowed to; empty parts don't narrow anything
+#[derive(Clone, PartialEq)]
+pub struct Filter {
+    pub vehicle: String,
+    // YYYY-MM-DD, or a shorter prefix like 2015 or 2015-06
+    pub from: String,
+    pub to: String,
+    pub provider: Option<String>,
+    pub outcome: Option<Classification>,
+    // Anywhere in the record: vehicle, date, provider, field labels and
+    // values, tags and note
+    pub text: String,
+}
+
+impl Default for Filter {
+    // Hits only: failed lookups are there when asked for
+    fn default() -> Self {
+        Self {
+            vehicle: String::new(),
+            from: String::new(),
+            to: String::new(),
+            provider: None,
+            outcome: Some(Classification::Hit),
+            text: String::new(),
+        }
+    }
+}
+
+impl Filter {
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+
+    pub fn matches(&self, row: &Row) -> bool {
+        let hit = &row.hit;
+        let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.trim().to_lowercase());
+        // Compared on the filter's own length, so "2015" takes in all of 2015
+        let from = self.from.trim();
+        let to = self.to.trim();
+
+        (self.vehicle.trim().is_empty() || contains(&hit.vehicle_no, &self.vehicle))
+        && (from.is_empty() || hit.date.get(..from.len()).unwrap_or(&hit.date) >= from)
+        && (to.is_empty() || hit.date.get(..to.len()).unwrap_or(&hit.date) <= to)
+        && self.provider.as_ref().is_none_or(|provider| *provider == hit.provider)
+        && self.outcome.is_none_or(|outcome| outcome == row.outcome)
+        && (self.text.trim().is_empty()
+            || [&hit.vehicle_no, &hit.date, &hit.provider, &row.annotation.tags, &row.annotation.note]
+            .iter()
+            .any(|value| contains(value, &self.text))
+            || hit.fields.iter().any(|(label, value)| contains(label, &self.text) || contains(value, &self.text)))
+    }
+}
+
+// Export formats for a selection of rows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Csv,
+    Json,
+    // The CSV, the JSON and the saved responses of the rows
+    Zip,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Csv, Format::Json, Format::Zip];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Format::Csv => "Export CSV",
+            Format::Json => "Export JSON",
+            Format::Zip => "Export ZIP",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Zip => "zip",
+        }
+    }
+}
+
+// results/selection_<timestamp>.<extension>
+pub fn default_path(results_dir: &Path, format: Format) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    results_dir.join(format!("selection_{}.{}", stamp, format.extension()))
+}
+
+pub fn write(format: Format, rows: &[Row], results_dir: &Path, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        Format::Csv => fs::write(path, csv(rows))?,
+        Format::Json => fs::write(path, serde_json::to_string_pretty(&json(rows))?)?,
+        Format::Zip => write_zip(rows, results_dir, path)?,
+    }
+    Ok(())
+}
+
+// The table's columns, then every field of the record as label: value
+// lines in one cell, as records differ in which fields they have
+fn csv(rows: &[Row]) -> String {
+    let mut headings: Vec<&str> = Column::ALL.iter().map(|c| c.label()).collect();
+    headings.extend(["File", "Fields"]);
+    let mut out = headings.join(",");
+    out.push('\n');
+    for row in rows {
+        let mut cells: Vec<String> = Column::ALL.iter().map(|c| csv_cell(c.value(row))).collect();
+        cells.push(csv_cell(row.hit.file_name.as_deref().unwrap_or_default()));
+        cells.push(csv_cell(&record::fields_text(&row.hit.fields)));
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn json(rows: &[Row]) -> serde_json::Value {
+    let rows: Vec<serde_json::Value> = rows
+    .iter()
+    .map(|row| {
+        let fields: serde_json::Map<String, serde_json::Value> = row.hit.fields.iter().map(|(label, value)| (label.clone(), json!(value))).collect();
+        json!({
+            "vehicle_no": row.hit.vehicle_no,
+            "date": row.hit.date,
+            "provider": row.hit.provider,
+            "result": row.outcome.label(),
+            "file": row.hit.file_name,
+            "found_at": row.hit.found_at,
+            "fields": fields,
+            "tags": row.annotation.tag_list(),
+            "note": row.annotation.note,
+        })
+    })
+    .collect();
+    json!(rows)
+}
+
+fn write_zip(rows: &[Row], results_dir: &Path, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = ZipWriter::new(fs::File::create(path)?);
+    let options = SimpleFileOptions::default();
+    zip.start_file("selection.csv", options)?;
+    zip.write_all(csv(rows).as_bytes())?;
+    zip.start_file("selection.json", options)?;
+    zip.write_all(serde_json::to_string_pretty(&json(rows))?.as_bytes())?;
+    for name in rows.iter().filter_map(|row| row.hit.file_name.as_deref()) {
+        // Under the same relative path as in the results folder; a response
+        // deleted since is left out rather than failing the export
+        let Ok(body) = vault::read(&results_dir.join(name)) else { continue };
+        zip.start_file(format!("responses/{}", name.replace('\\', "/")), options)?;
+        zip.write_all(&body)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
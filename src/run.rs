@@ -0,0 +1,145 @@
+use crate::config::AppConfig;
+use crate::coverage::CoverageMap;
+use crate::i18n::tr;
+use crate::lock::{LockFile, LockInfo};
+use crate::recovery::RunState;
+use crate::search::SearchBy;
+use crate::{LogLevel, audit, http, lock, recovery, vault};
+use chrono::NaiveDate;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// How often a running scan saves its coverage and run state
+pub const CHECKPOINT_SECS: u64 = 10;
+
+// The bookkeeping around one scan, the same for the app and the daemon: the
+// run lock that keeps two scans off the same dates, the run state a crash
+// leaves behind to resume from, and the vehicle's coverage file. Dropping it
+// without finish() still gives the lock up.
+pub struct Run {
+    pub info: LockInfo,
+    pub state: RunState,
+    // Dates in the range that earlier sessions already checked
+    pub already_checked: usize,
+    results_dir: PathBuf,
+    coverage: Arc<Mutex<CoverageMap>>,
+    coverage_path: PathBuf,
+    _lock: LockFile,
+}
+
+// What a run scans, and how
+pub struct Target<'a> {
+    pub vehicle_no: &'a str,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    // Empty when every province is searched
+    pub provider: &'a str,
+    pub search_by: SearchBy,
+    pub threads: usize,
+    // SearchOrder label
+    pub search_order: &'a str,
+    // Dates in the coverage file don't count towards the total
+    pub skip_checked: bool,
+}
+
+impl Run {
+    // Takes the run lock and loads the vehicle's coverage into `coverage`,
+    // for the scan to add to. Fails, with the run not started, when there is
+    // no lock to be had or another scan has some of the same dates.
+    pub fn begin(results_dir: &Path, target: &Target, coverage: Arc<Mutex<CoverageMap>>, log: impl Fn(String, LogLevel)) -> Result<Run, String> {
+        let now = chrono::Local::now();
+        let info = LockInfo {
+            pid: std::process::id(),
+            run_id: recovery::run_id(now, std::process::id()),
+            vehicle_no: target.vehicle_no.to_string(),
+            start_date: target.start.format("%Y-%m-%d").to_string(),
+            end_date: target.end.format("%Y-%m-%d").to_string(),
+            started_at: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        // Without the lock nobody else could see this run, so it doesn't start
+        let held = lock::hold_run(results_dir, &info)
+        .map_err(|e| tr!("Could not create lock file in {} - {}. Without it another scan of the same dates could not be detected, so the run was not started.",
+                         results_dir.display(), e))?;
+        // Checked after taking our own lock, see lock::conflicting_run
+        if let Some(other) = lock::conflicting_run(results_dir, &info) {
+            let holder = if other.pid == info.pid { tr!("this copy of the app") } else { tr!("another copy of the app (PID {})", other.pid) };
+            return Err(tr!("{} is already being scanned from {} to {} by {}, started {}. Stop that scan or choose dates outside its range.",
+                           other.vehicle_no, other.start_date, other.end_date, holder, other.started_at));
+        }
+
+        let coverage_path = CoverageMap::path_for(results_dir, target.provider, target.search_by, target.vehicle_no);
+        let map = CoverageMap::load(&coverage_path).unwrap_or_else(|e| {
+            log(tr!("Could not read coverage file, starting fresh - {}", e), LogLevel::Warning);
+            CoverageMap::default()
+        });
+        let already_checked = if target.skip_checked { map.count_in(target.start, target.end) } else { 0 };
+        if let Ok(mut shared) = coverage.lock() {
+            *shared = map;
+        }
+
+        // Saved again at every checkpoint; left behind only if the process
+        // dies mid-run
+        let state = RunState {
+            id: info.run_id.clone(),
+            pid: info.pid,
+            vehicle_no: info.vehicle_no.clone(),
+            start_date: info.start_date.clone(),
+            end_date: info.end_date.clone(),
+            threads: target.threads,
+            search_order: target.search_order.to_string(),
+            started_at: info.started_at.clone(),
+            checkpoint_at: info.started_at.clone(),
+            checked: 0,
+            total: (target.end - target.start).num_days() as usize + 1 - already_checked,
+        };
+        if let Err(e) = recovery::save(results_dir, &state) {
+            log(tr!("Could not save run state, this run can't be resumed after a crash - {}", e), LogLevel::Warning);
+        }
+
+        Ok(Run { info, state, already_checked, results_dir: results_dir.to_path_buf(), coverage, coverage_path, _lock: held })
+    }
+
+    // Saves the coverage so far and how far the scan has got
+    pub fn checkpoint(&mut self, checked: usize, log: impl Fn(String, LogLevel)) {
+        self.save_coverage(&log);
+        self.state.checked = checked;
+        self.state.checkpoint_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let _ = recovery::save(&self.results_dir, &self.state);
+    }
+
+    // The run ended, however it ended: the coverage is saved and the run
+    // state and lock go
+    pub fn finish(self, log: impl Fn(String, LogLevel)) {
+        self.save_coverage(&log);
+        recovery::remove(&self.results_dir, &self.state.id);
+    }
+
+    fn save_coverage(&self, log: &impl Fn(String, LogLevel)) {
+        if let Ok(mut map) = self.coverage.lock() {
+            if let Err(e) = map.merge_and_save(&self.coverage_path) {
+                log(tr!("Error saving coverage - {}", e), LogLevel::Error);
+            }
+        }
+    }
+}
+
+// Opens or closes the audit log as the config says and tells whether the
+// HTTP transcript is being written. Neither is while the results are
+// encrypted.
+pub fn open_outputs(config: &AppConfig, results_dir: &Path, log: impl Fn(String, LogLevel)) {
+    if config.transcript_enabled && config.offline_fixture_dir.trim().is_empty() {
+        if vault::is_encrypting() {
+            log(tr!("The results are encrypted, so no HTTP transcript is written"), LogLevel::Warning);
+        } else {
+            log(tr!("Full HTTP transcript is being written to {}", results_dir.join(http::TRANSCRIPT_DIR).display()), LogLevel::Warning);
+        }
+    }
+    if !config.audit_log_enabled {
+        audit::close();
+        return;
+    }
+    match audit::open(results_dir) {
+        Ok(_) => log(tr!("Audit events are being appended to {}", results_dir.join(audit::AUDIT_FILE).display()), LogLevel::Info),
+        Err(e) => log(tr!("Could not open audit log - {}", e), LogLevel::Warning),
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+
+// A five-field cron expression in local time: minute, hour, day of month,
+// month, day of week. Fields take *, numbers, a-b ranges, /steps and comma
+// lists; months and weekdays also take names (JAN, SUN). @hourly, @daily,
+// @weekly and @monthly are shorthands. As in cron, when both day fields are
+// restricted a day matching either one runs; a field starting with * (like
+// */2) counts as unrestricted, as in Vixie cron.
+pub struct Schedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Vec<u32>,
+    months: Vec<u32>,
+    // 0 = Sunday
+    weekdays: Vec<u32>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+const MONTHS: [&str; 12] = ["JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC"];
+const WEEKDAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+// A date that matches at all comes round within this many days; 29 February
+// on a given weekday can take decades, which isn't worth waiting for
+const SEARCH_DAYS: u64 = 366 * 8;
+
+impl Schedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields, got {}", fields.len()));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, &WEEKDAYS, 0)?;
+        // 7 is Sunday too
+        if weekdays.contains(&7) {
+            weekdays.retain(|&d| d != 7);
+            if !weekdays.contains(&0) {
+                weekdays.insert(0, 0);
+            }
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0)?,
+            hours: parse_field(hour, 0, 23, &[], 0)?,
+            days: parse_field(day, 1, 31, &[], 0)?,
+            months: parse_field(month, 1, 12, &MONTHS, 1)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    // The first time after `after`, to the minute
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut date = after.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.runs_on(date) {
+                for &hour in &self.hours {
+                    for &minute in &self.minutes {
+                        let time = date.and_time(NaiveTime::from_hms_opt(hour, minute, 0)?);
+                        if time > after {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn runs_on(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(&date.month()) {
+            return false;
+        }
+        let day = self.days.contains(&date.day());
+        let weekday = self.weekdays.contains(&date.weekday().num_days_from_sunday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+// Sorted values; `names[i]` stands for `first_name + i`
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str], first_name: u32) -> Result<Vec<u32>, String> {
+    let value = |text: &str| -> Result<u32, String> {
+        if let Some(i) = names.iter().position(|name| name.eq_ignore_ascii_case(text)) {
+            return Ok(first_name + i as u32);
+        }
+        match text.parse::<u32>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(format!("'{}' is not a value from {} to {}", text, min, max)),
+        }
+    };
+
+    let mut values = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("'{}' is not a valid step", step)),
+            },
+            None => (part, None),
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((from, to)) => (value(from)?, value(to)?),
+                // "5/15" means from 5 to the end in steps of 15
+                None if step.is_some() => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if from > to {
+            return Err(format!("'{}' is an empty range", range));
+        }
+        values.extend((from..=to).step_by(step.unwrap_or(1) as usize));
+    }
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn parses_fields() {
+        // As minutes
+        let cases: [(&str, &[u32]); 9] = [
+            ("3", &[3]),
+            ("1-4", &[1, 2, 3, 4]),
+            ("*/15", &[0, 15, 30, 45]),
+            ("10-20/5", &[10, 15, 20]),
+            ("50/1", &[50, 51, 52, 53, 54, 55, 56, 57, 58, 59]),
+            ("45/5", &[45, 50, 55]),
+            ("5,1,3,1", &[1, 3, 5]),
+            ("1-3,10/20", &[1, 2, 3, 10, 30, 50]),
+            ("58-59,*/30", &[0, 30, 58, 59]),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(parse_field(field, 0, 59, &[], 0).as_deref(), Ok(expected), "{}", field);
+        }
+        assert_eq!(parse_field("jan-MAR,dec", 1, 12, &MONTHS, 1), Ok(vec![1, 2, 3, 12]));
+        assert_eq!(parse_field("*", 1, 12, &MONTHS, 1), Ok((1..=12).collect()));
+    }
+
+    #[test]
+    fn rejects_bad_fields() {
+        for field in ["60", "5-2", "*/0", "a", "1-", "*/x"] {
+            assert!(parse_field(field, 0, 59, &[], 0).is_err(), "{}", field);
+        }
+        assert!(Schedule::parse("* * * *").is_err());
+    }
+
+    #[test]
+    fn sunday_is_0_or_7() {
+        assert_eq!(Schedule::parse("0 0 * * 7").unwrap().weekdays, [0]);
+        assert_eq!(Schedule::parse("0 0 * * 5-7").unwrap().weekdays, [0, 5, 6]);
+    }
+
+    #[test]
+    fn next_runs() {
+        // 2026-10-16 is a Friday
+        let cases = [
+            ("@hourly", "2026-10-16 09:30", "2026-10-16 10:00"),
+            ("@daily", "2026-10-16 09:30", "2026-10-17 00:00"),
+            ("@weekly", "2026-10-16 09:30", "2026-10-18 00:00"),
+            ("@monthly", "2026-10-16 09:30", "2026-11-01 00:00"),
+            ("*/20 9 * * *", "2026-10-16 09:30", "2026-10-16 09:40"),
+            ("0 0 29 2 *", "2026-10-16 09:30", "2028-02-29 00:00"),
+            // Both day fields restricted: either one matching runs
+            ("0 0 1 * MON", "2026-10-16 09:30", "2026-10-19 00:00"),
+            ("0 0 17 * MON", "2026-10-16 09:30", "2026-10-17 00:00"),
+            // A * field with a step leaves the other in charge
+            ("0 0 */2 * MON", "2026-10-16 09:30", "2026-10-19 00:00"),
+            ("0 0 13 * */3", "2026-10-16 09:30", "2026-12-13 00:00"),
+            ("0 0 * * MON", "2026-10-16 09:30", "2026-10-19 00:00"),
+        ];
+        for (expression, after, expected) in cases {
+            let (after_date, after_time) = after.split_once(' ').unwrap();
+            let (date, time) = expected.split_once(' ').unwrap();
+            let next = Schedule::parse(expression).unwrap().next_after(at(after_date, after_time));
+            assert_eq!(next, Some(at(date, time)), "{}", expression);
+        }
+    }
+}
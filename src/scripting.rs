@@ -0,0 +1,141 @@
+use crate::classify::{Classification, ClassifyHook};
+use crate::control::{self, EngineEvent, JobInfo};
+use crate::i18n::tr;
+use crate::record;
+use crate::{HitRecord, LogLevel};
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Hook functions a script may define; any of them can be left out
+const CLASSIFY_RESPONSE: &str = "classify_response";
+const ON_HIT: &str = "on_hit";
+const ON_RUN_END: &str = "on_run_end";
+
+// Per hook call, so a runaway loop in a script can't hang a lookup thread
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// What a script brought
+pub struct Script {
+    // The hooks it defines
+    pub hooks: Vec<&'static str>,
+    // Its classify_response, for the classifiers to consult
+    pub classify: Option<ClassifyHook>,
+}
+
+// Loads the Rhai script at `path` and wires up the hooks it defines:
+//
+//   classify_response(status, body, run) -> "hit", "miss", "error",
+//       "rate_limited", "challenge", or () to leave it to the rules
+//   on_hit(hit)      - vehicle_no, date, file, found_at and fields
+//   on_run_end(run)  - vehicle_no, start_date, end_date, threads,
+//       started_at and finished_at
+//
+// `run` in classify_response is the run in progress, empty outside one.
+// print() and script errors go to `log`; a classify_response that fails
+// leaves the response to the rules. The events hooks are wired up here; the
+// classify hook is returned, see Classifier::with_hook.
+pub fn load(path: &Path, log: impl Fn(String, LogLevel) + Send + Sync + 'static) -> Result<Script, String> {
+    let log = Arc::new(log);
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let print_log = Arc::clone(&log);
+    engine.on_print(move |text| print_log(tr!("Script: {}", text), LogLevel::Info));
+    let debug_log = Arc::clone(&log);
+    engine.on_debug(move |text, _, position| debug_log(tr!("Script ({}): {}", position, text), LogLevel::Debug));
+
+    let script = std::fs::read_to_string(path).map_err(|e| tr!("cannot read {} - {}", path.display(), e))?;
+    let ast = engine.compile(&script).map_err(|e| tr!("{} doesn't compile - {}", path.display(), e))?;
+    let hooks: Vec<&'static str> = [CLASSIFY_RESPONSE, ON_HIT, ON_RUN_END]
+    .into_iter()
+    .filter(|hook| ast.iter_functions().any(|f| f.name == *hook))
+    .collect();
+    if hooks.is_empty() {
+        return Err(tr!("{} defines none of {}, {} or {}", path.display(), CLASSIFY_RESPONSE, ON_HIT, ON_RUN_END));
+    }
+
+    let engine = Arc::new(engine);
+    let ast = Arc::new(ast);
+    let run = Arc::new(Mutex::new(Map::new()));
+
+    let mut classify = None;
+    if hooks.contains(&CLASSIFY_RESPONSE) {
+        let (engine, ast, run, log) = (Arc::clone(&engine), Arc::clone(&ast), Arc::clone(&run), Arc::clone(&log));
+        // Every response of a run goes through the hook; one report of a
+        // failing script is enough
+        let reported = AtomicBool::new(false);
+        let hook: ClassifyHook = Arc::new(move |status, body| {
+            let run = run.lock().map(|run| run.clone()).unwrap_or_default();
+            let answer = call(&engine, &ast, CLASSIFY_RESPONSE, (status as rhai::INT, body.to_string(), run));
+            let outcome = answer.and_then(|answer| {
+                if answer.is_unit() {
+                    return Ok(None);
+                }
+                let name = answer.into_string().map_err(|kind| tr!("returned a {} instead of a string", kind))?;
+                Classification::parse(&name).map(Some).ok_or_else(|| tr!("returned unknown outcome '{}'", name))
+            });
+            outcome.unwrap_or_else(|e| {
+                if !reported.swap(true, Ordering::Relaxed) {
+                    log(tr!("Script {} failed, using the rules instead - {}", CLASSIFY_RESPONSE, e), LogLevel::Warning);
+                }
+                None
+            })
+        });
+        classify = Some(hook);
+    }
+
+    let wants_hits = hooks.contains(&ON_HIT);
+    let wants_run_end = hooks.contains(&ON_RUN_END);
+    control::on_event(move |event| {
+        let result = match event {
+            EngineEvent::RunStarted(job) => {
+                if let Ok(mut run) = run.lock() {
+                    *run = job_map(job);
+                }
+                return;
+            }
+            EngineEvent::Hit(hit) if wants_hits => call(&engine, &ast, ON_HIT, (hit_map(hit),)).map(|_| ()).map_err(|e| (ON_HIT, e)),
+            EngineEvent::RunFinished(job) => {
+                if let Ok(mut run) = run.lock() {
+                    run.clear();
+                }
+                if !wants_run_end {
+                    return;
+                }
+                call(&engine, &ast, ON_RUN_END, (job_map(job),)).map(|_| ()).map_err(|e| (ON_RUN_END, e))
+            }
+            _ => return,
+        };
+        if let Err((hook, e)) = result {
+            log(tr!("Script {} failed - {}", hook, e), LogLevel::Warning);
+        }
+    });
+    Ok(Script { hooks, classify })
+}
+
+fn call(engine: &Engine, ast: &AST, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, String> {
+    engine.call_fn::<Dynamic>(&mut Scope::new(), ast, name, args).map_err(|e| e.to_string())
+}
+
+fn hit_map(hit: &HitRecord) -> Map {
+    let fields: Map = record::shareable_fields(&hit.fields).into_iter().map(|(label, value)| (label.into(), Dynamic::from(value))).collect();
+    let mut map = Map::new();
+    map.insert("vehicle_no".into(), Dynamic::from(hit.vehicle_no.clone()));
+    map.insert("date".into(), Dynamic::from(record::shareable_date(&hit.date)));
+    map.insert("file".into(), hit.file_name.as_ref().map(|name| Dynamic::from(record::without_date(name, &hit.date))).unwrap_or(Dynamic::UNIT));
+    map.insert("found_at".into(), Dynamic::from(hit.found_at.clone()));
+    map.insert("fields".into(), Dynamic::from_map(fields));
+    map
+}
+
+fn job_map(job: &JobInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("vehicle_no".into(), Dynamic::from(job.vehicle_no.clone()));
+    map.insert("start_date".into(), Dynamic::from(job.start_date.clone()));
+    map.insert("end_date".into(), Dynamic::from(job.end_date.clone()));
+    map.insert("threads".into(), Dynamic::from(job.threads as rhai::INT));
+    map.insert("started_at".into(), Dynamic::from(job.started_at.clone()));
+    map.insert("finished_at".into(), job.finished_at.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT));
+    map
+}
@@ -0,0 +1,106 @@
+use crate::plate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+// What the number in the vehicle field is. Providers that have it on their
+// lookup form can be searched by chassis or engine number instead of the
+// plate, with the same date-range scan.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SearchBy {
+    #[default]
+    Plate,
+    Chassis,
+    Engine,
+}
+
+impl SearchBy {
+    pub const ALL: [SearchBy; 3] = [SearchBy::Plate, SearchBy::Chassis, SearchBy::Engine];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchBy::Plate => "Registration number",
+            SearchBy::Chassis => "Chassis number",
+            SearchBy::Engine => "Engine number",
+        }
+    }
+
+    // For the middle of a sentence
+    pub fn noun(&self) -> &'static str {
+        match self {
+            SearchBy::Plate => "registration number",
+            SearchBy::Chassis => "chassis number",
+            SearchBy::Engine => "engine number",
+        }
+    }
+
+    pub fn example(&self) -> &'static str {
+        match self {
+            SearchBy::Plate => "ABC-123",
+            SearchBy::Chassis => "NZE141-6012345",
+            SearchBy::Engine => "1NZ-1234567",
+        }
+    }
+
+    // What gets sent for whatever was typed. Plates are regrouped with
+    // `separator`; chassis and engine numbers keep their own dash and lose
+    // the rest.
+    pub fn normalize(&self, input: &str, separator: &str) -> String {
+        match self {
+            SearchBy::Plate => plate::normalize(input, separator),
+            SearchBy::Chassis | SearchBy::Engine => input
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .flat_map(|c| c.to_uppercase())
+            .collect(),
+        }
+    }
+}
+
+// Whether the number looks like what `by` expects. Plates are left to
+// plate::validate, which also needs the vehicle type and province.
+pub fn validate(by: SearchBy, number: &str) -> Result<(), &'static str> {
+    match by {
+        SearchBy::Plate => Ok(()),
+        SearchBy::Chassis => validate_chassis(number),
+        SearchBy::Engine => validate_engine(number),
+    }
+}
+
+// Chassis numbers come in two shapes: the 17-character VIN of locally
+// assembled cars (no I, O or Q), and the model code and serial of Japanese
+// imports, e.g. "NZE141-6012345". The error is in words the catalog can
+// translate, like plate::validate's.
+pub fn validate_chassis(chassis_no: &str) -> Result<(), &'static str> {
+    static VIN: OnceLock<Regex> = OnceLock::new();
+    static FRAME: OnceLock<Regex> = OnceLock::new();
+    let vin = VIN.get_or_init(|| Regex::new(r"^[A-HJ-NPR-Z0-9]{17}$").unwrap());
+    let frame = FRAME.get_or_init(|| Regex::new(r"^[A-Z0-9]{2,7}-[0-9]{5,8}$").unwrap());
+
+    if vin.is_match(chassis_no) || frame.is_match(chassis_no) {
+        return Ok(());
+    }
+    Err(if chassis_no.len() == 17 && !chassis_no.contains('-') {
+        "a 17-character VIN never has the letters I, O or Q"
+    } else if !chassis_no.contains('-') {
+        "it is neither a 17-character VIN nor a model code and serial"
+    } else {
+        "the model code or the serial after the dash has the wrong length"
+    })
+}
+
+// Engine numbers are the engine code and a serial, e.g. "1NZ-1234567",
+// "K12B 1234567" (sent as "K12B1234567") or "L15Z1-1234567"
+pub fn validate_engine(engine_no: &str) -> Result<(), &'static str> {
+    static ENGINE: OnceLock<Regex> = OnceLock::new();
+    let engine = ENGINE.get_or_init(|| Regex::new(r"^[A-Z0-9]{2,6}-?[0-9]{5,8}$").unwrap());
+
+    if engine.is_match(engine_no) {
+        return Ok(());
+    }
+    Err(if !engine_no.chars().any(|c| c.is_ascii_digit()) {
+        "there is no serial number"
+    } else {
+        "the engine code or the serial has the wrong length"
+    })
+}
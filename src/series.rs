@@ -0,0 +1,114 @@
+use crate::storage;
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// How far either side of the known issuance dates a prediction reaches
+const WINDOW_MARGIN_DAYS: i64 = 365;
+
+// The series of a plate is its leading letters, e.g. "ABC" for "ABC-123"
+pub fn series_of(vehicle_no: &str) -> Option<String> {
+    let series: String = vehicle_no
+    .chars()
+    .filter(|c| c.is_ascii_alphanumeric())
+    .take_while(|c| c.is_ascii_alphabetic())
+    .collect::<String>()
+    .to_uppercase();
+
+    if series.is_empty() { None } else { Some(series) }
+}
+
+// Series are issued in batches over time, so earlier hits for the same series
+// tell us roughly when a plate was registered. Collect the earliest and latest
+// registration date seen per series from saved hit files (VEHICLE_DATE.html).
+pub fn learn_from_results(results_dir: &Path) -> HashMap<String, (NaiveDate, NaiveDate)> {
+    let mut windows: HashMap<String, (NaiveDate, NaiveDate)> = HashMap::new();
+
+    for path in storage::walk_results(results_dir) {
+        let Some(result) = storage::parse_result_path(&path) else { continue };
+        if result.status != 200 {
+            continue;
+        }
+        let date = result.date;
+        let Some(series) = series_of(&result.vehicle_no) else { continue };
+
+        windows
+        .entry(series)
+        .and_modify(|(first, last)| {
+            *first = (*first).min(date);
+            *last = (*last).max(date);
+        })
+        .or_insert((date, date));
+    }
+
+    windows
+}
+
+pub fn predict(
+    windows: &HashMap<String, (NaiveDate, NaiveDate)>,
+    vehicle_no: &str,
+) -> Option<(NaiveDate, NaiveDate)> {
+    let series = series_of(vehicle_no)?;
+    let (first, last) = windows.get(&series)?;
+    Some((
+        *first - Duration::days(WINDOW_MARGIN_DAYS),
+        *last + Duration::days(WINDOW_MARGIN_DAYS),
+    ))
+}
+
+fn parse_bound(value: &str, end_of_year: bool) -> Option<NaiveDate> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let year: i32 = value.parse().ok()?;
+    if end_of_year {
+        NaiveDate::from_ymd_opt(year, 12, 31)
+    } else {
+        NaiveDate::from_ymd_opt(year, 1, 1)
+    }
+}
+
+// Loads a user-supplied CSV of known issuance periods, one series per line:
+//   series,start,end
+// where start/end are either YYYY-MM-DD dates or bare years. A header line and
+// lines starting with '#' are ignored.
+pub fn load_mapping(path: &Path) -> Result<HashMap<String, (NaiveDate, NaiveDate)>, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)?;
+    let mut mapping = HashMap::new();
+
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 {
+            return Err(format!("line {}: expected series,start,end", line_no + 1).into());
+        }
+
+        let (Some(start), Some(end)) = (parse_bound(fields[1], false), parse_bound(fields[2], true)) else {
+            if line_no == 0 {
+                continue;
+            }
+            return Err(format!("line {}: invalid start or end date", line_no + 1).into());
+        };
+
+        let Some(series) = series_of(fields[0]) else {
+            return Err(format!("line {}: missing series", line_no + 1).into());
+        };
+
+        mapping.insert(series, (start.min(end), start.max(end)));
+    }
+
+    Ok(mapping)
+}
+
+pub fn lookup(
+    mapping: &HashMap<String, (NaiveDate, NaiveDate)>,
+    vehicle_no: &str,
+) -> Option<(NaiveDate, NaiveDate)> {
+    mapping.get(&series_of(vehicle_no)?).copied()
+}
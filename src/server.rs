@@ -0,0 +1,418 @@
+use crate::auth::{self, ApiToken, AuthError, TokenScope};
+use crate::control::{self, ControlHandle, EngineEvent, ScanRequest};
+use crate::i18n::tr;
+use crate::{HitRecord, LogEntry, daemon};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+use tungstenite::protocol::{Role, WebSocket};
+use tungstenite::Message;
+
+pub struct HttpRequest {
+    pub method: String,
+    // Without the query string
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+// Requests bigger than this are cut off; a job submission is a few hundred bytes
+const MAX_BODY: usize = 64 * 1024;
+
+// The request line and each header are cut off at this length, and a request
+// with more headers than MAX_HEADERS is dropped, so a client can't make us
+// buffer without end before the body limit ever applies
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+// Connections served at once; more are turned away with a 503. Live
+// dashboards each keep one open.
+pub const MAX_CONNECTIONS: usize = 32;
+
+// A client that stops sending mid-request gives up its thread after this long
+pub const READ_TIMEOUT_SECS: u64 = 10;
+
+const DASHBOARD_HTML: &str = include_str!("dashboard.html");
+
+// Set by the dashboard from the token in its URL fragment
+const TOKEN_COOKIE: &str = "veh_reg_token";
+
+// How long a live socket may sit without events before it is pinged
+const PING_SECS: u64 = 15;
+
+// Server mode: a small embedded HTTP server. / serves the bundled dashboard,
+// /api/* the JSON it polls, and /ws upgrades to a WebSocket that pushes log
+// entries, progress and hits as JSON messages. POST /api/jobs and
+// /api/stop start and stop runs. When tokens are configured everything but
+// the dashboard page itself needs one, as an `Authorization: Bearer` header
+// or, for links and the browser's WebSocket, the dashboard's TOKEN_COOKIE.
+// Without tokens only requests addressed to this machine are served; see
+// `cross_site`.
+pub fn spawn_server(bind: &str, control: ControlHandle, tokens: Vec<ApiToken>) -> std::io::Result<()> {
+    // Without tokens anyone who can reach the port could start and stop runs
+    if !daemon::is_loopback(bind) && !tokens.iter().any(|t| !t.token.is_empty()) {
+        let message = tr!("refusing to listen on {} without API tokens; add one or bind to 127.0.0.1", bind);
+        return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied, message));
+    }
+    let listener = TcpListener::bind(bind)?;
+    let tokens = Arc::new(tokens);
+    let open = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECS)));
+            if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                open.fetch_sub(1, Ordering::SeqCst);
+                let _ = respond(&mut stream, "503 Service Unavailable", "text/plain", b"too many connections");
+                continue;
+            }
+            let control = control.clone();
+            let tokens = Arc::clone(&tokens);
+            let open = Arc::clone(&open);
+            thread::spawn(move || {
+                let _ = handle_connection(stream, control, &tokens);
+                open.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+// One line of at most MAX_LINE bytes; longer ones are an error
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> std::io::Result<usize> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    if read > MAX_LINE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "request line or header too long"));
+    }
+    Ok(read)
+}
+
+pub fn read_request(stream: &TcpStream) -> std::io::Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("");
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+
+    let mut headers = HashMap::new();
+    for count in 0.. {
+        let mut line = String::new();
+        if read_line(&mut reader, &mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if count == MAX_HEADERS {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "too many headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = headers.get("content-length").and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+    let mut body = vec![0; length.min(MAX_BODY)];
+    reader.read_exact(&mut body)?;
+
+    Ok(HttpRequest { method, path: path.to_string(), headers, body })
+}
+
+pub fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> std::io::Result<()> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)
+}
+
+// Saved pages come from someone else's server. Sandboxed, they run no
+// script and get an origin of their own, so nothing in them can act as the
+// dashboard or read its token.
+fn respond_saved(stream: &mut TcpStream, name: &str, body: &[u8]) -> std::io::Result<()> {
+    let content_type = if name.ends_with(".png") { "image/png" } else { "text/html; charset=utf-8" };
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nContent-Security-Policy: sandbox\r\nX-Content-Type-Options: nosniff\r\nConnection: close\r\n\r\n",
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes())?;
+    stream.write_all(body)
+}
+
+// From the Authorization header, or the dashboard's cookie where a header
+// can't be set (links, the browser's WebSocket). Never from the query
+// string, which ends up in history, logs and Referer headers.
+pub fn presented_token(request: &HttpRequest) -> Option<String> {
+    let from_header = request.headers.get("authorization").and_then(|v| v.strip_prefix("Bearer "));
+    let from_cookie = || {
+        request
+        .headers
+        .get("cookie")?
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == TOKEN_COOKIE)
+        .map(|(_, value)| percent_decode(value))
+    };
+    from_header.map(str::to_string).or_else(from_cookie).map(|token| token.trim().to_string())
+}
+
+// localhost, 127.x.x.x or [::1], with or without a port
+fn is_local_authority(authority: &str) -> bool {
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        None => authority.rsplit_once(':').map_or(authority, |(host, _)| host),
+    };
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+// Why a request has to be turned away even though it reached us, if it does.
+// Without tokens the loopback bind is all that keeps others out, yet any web
+// page open in the user's browser can still send requests to 127.0.0.1. Those
+// carry the page's Origin, or, after DNS rebinding, its host name in Host, so
+// both have to name this machine. A POST also has to say it is JSON: a page
+// can't send that content type to another origin without a CORS preflight,
+// which we never answer.
+pub fn cross_site(request: &HttpRequest, tokens: &[ApiToken]) -> Option<&'static str> {
+    if request.method == "POST" {
+        let content_type = request.headers.get("content-type").map(|v| v.split(';').next().unwrap_or("").trim());
+        if !content_type.is_some_and(|v| v.eq_ignore_ascii_case("application/json")) {
+            return Some("POST requests need Content-Type: application/json");
+        }
+    }
+    if !tokens.is_empty() {
+        return None;
+    }
+    if !request.headers.get("host").is_some_and(|host| is_local_authority(host)) {
+        return Some("Host has to be this machine when no API tokens are configured");
+    }
+    let origin_is_local = |origin: &str| origin.split_once("://").is_some_and(|(_, authority)| is_local_authority(authority));
+    if request.headers.get("origin").is_some_and(|origin| !origin_is_local(origin)) {
+        return Some("requests from other sites need an API token");
+    }
+    None
+}
+
+fn handle_connection(mut stream: TcpStream, control: ControlHandle, tokens: &[ApiToken]) -> std::io::Result<()> {
+    let request = read_request(&stream)?;
+
+    if let Some(reason) = cross_site(&request, tokens) {
+        return respond(&mut stream, "403 Forbidden", "text/plain", reason.as_bytes());
+    }
+
+    // The page holds no data, and has to load to ask for a token at all
+    if !(request.method == "GET" && request.path == "/") {
+        let needed = if request.method == "POST" { TokenScope::Submit } else { TokenScope::ReadOnly };
+        match auth::authorize(tokens, presented_token(&request).as_deref(), needed) {
+            Ok(_) => {}
+            Err(AuthError::Unauthorized) => return respond(&mut stream, "401 Unauthorized", "text/plain", b"missing or unknown token"),
+            Err(AuthError::Forbidden) => return respond(&mut stream, "403 Forbidden", "text/plain", b"token is read-only"),
+        }
+    }
+
+    let is_upgrade = request
+    .headers
+    .get("upgrade")
+    .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/ws") if is_upgrade => {
+            let Some(key) = request.headers.get("sec-websocket-key") else {
+                return respond(&mut stream, "400 Bad Request", "text/plain", b"missing Sec-WebSocket-Key");
+            };
+            let accept = tungstenite::handshake::derive_accept_key(key.as_bytes());
+            let handshake = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept
+            );
+            stream.write_all(handshake.as_bytes())?;
+            stream_events(WebSocket::from_raw_socket(stream, Role::Server, None), control);
+            Ok(())
+        }
+        ("GET", "/") => respond(&mut stream, "200 OK", "text/html; charset=utf-8", DASHBOARD_HTML.as_bytes()),
+        ("GET", "/api/status") => {
+            let body = status_json(&control).to_string();
+            respond(&mut stream, "200 OK", "application/json", body.as_bytes())
+        }
+        ("GET", "/api/results") => {
+            let files: Vec<_> = control
+            .list_results()
+            .into_iter()
+            .map(|f| json!({ "name": f.name, "size": f.size, "modified": f.modified }))
+            .collect();
+            let body = serde_json::Value::from(files).to_string();
+            respond(&mut stream, "200 OK", "application/json", body.as_bytes())
+        }
+        ("GET", path) if path.starts_with("/results/") => {
+            let name = percent_decode(&path["/results/".len()..]);
+            match control.read_result(&name) {
+                Some(body) => respond_saved(&mut stream, &name, &body),
+                None => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+            }
+        }
+        ("POST", "/api/jobs") => {
+            let Some(request) = scan_request(&request.body) else {
+                return respond(&mut stream, "400 Bad Request", "text/plain", b"expected JSON with a vehicle_no");
+            };
+            if control.start(request) {
+                respond(&mut stream, "202 Accepted", "application/json", b"{\"accepted\":true}")
+            } else {
+                respond(&mut stream, "409 Conflict", "text/plain", b"a run is already going")
+            }
+        }
+        ("POST", "/api/stop") => {
+            control.stop();
+            respond(&mut stream, "202 Accepted", "application/json", b"{\"accepted\":true}")
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+// {"vehicle_no": "ABC-123", "start_date": "2020-01-01", "end_date": "2020-12-31", "threads": 4};
+// everything but the vehicle is optional and falls back to the GUI's values
+fn scan_request(body: &[u8]) -> Option<ScanRequest> {
+    let value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let text = |name: &str| value.get(name).and_then(|v| v.as_str()).map(|s| s.trim().to_string());
+    let vehicle_no = text("vehicle_no").filter(|v| !v.is_empty())?;
+    Some(ScanRequest {
+        vehicle_no,
+        start_date: text("start_date"),
+        end_date: text("end_date"),
+        threads: value.get("threads").and_then(|v| v.as_u64()).map(|n| n as usize),
+    })
+}
+
+// Result names are nested paths, so the dashboard URL-encodes them
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn status_json(control: &ControlHandle) -> serde_json::Value {
+    let (checked, total) = control.progress();
+    let job = control.job().map(|job| {
+        json!({
+            "vehicle_no": job.vehicle_no,
+            "start_date": job.start_date,
+            "end_date": job.end_date,
+            "threads": job.threads,
+            "started_at": job.started_at,
+            "finished_at": job.finished_at,
+        })
+    });
+    let hits: Vec<_> = control
+    .hits_since(0)
+    .into_iter()
+    .map(|hit| json!({ "vehicle_no": hit.vehicle_no, "date": hit.date, "file": hit.file_name, "found_at": hit.found_at }))
+    .collect();
+
+    json!({
+        "running": control.is_running(),
+        "checked": checked,
+        "total": total,
+        "job": job,
+        "hits": hits,
+    })
+}
+
+fn send_json(socket: &mut WebSocket<TcpStream>, value: serde_json::Value) -> bool {
+    socket.send(Message::Text(value.to_string())).is_ok()
+}
+
+fn log_json(entry: &LogEntry) -> serde_json::Value {
+    json!({
+        "type": "log",
+        "seq": entry.seq,
+        "timestamp": entry.timestamp,
+        "level": control::level_name(entry.level),
+        "message": entry.message,
+    })
+}
+
+fn hit_json(hit: &HitRecord) -> serde_json::Value {
+    json!({
+        "type": "hit",
+        "vehicle_no": hit.vehicle_no,
+        "date": hit.date,
+        "file": hit.file_name,
+        "found_at": hit.found_at,
+    })
+}
+
+fn progress_json(running: bool, checked: usize, total: usize) -> serde_json::Value {
+    json!({
+        "type": "progress",
+        "running": running,
+        "checked": checked,
+        "total": total,
+    })
+}
+
+// Sends the session so far, then engine events as they happen until the
+// client goes away
+fn stream_events(mut socket: WebSocket<TcpStream>, control: ControlHandle) {
+    // Subscribed before reading the backlog so nothing falls in between
+    let events = control::subscribe();
+
+    let mut last_seq = 0;
+    let mut backlog = Vec::new();
+    for entry in control.events_since(0) {
+        last_seq = entry.seq;
+        backlog.push(log_json(&entry));
+    }
+    backlog.extend(control.hits_since(0).iter().map(hit_json));
+    let (checked, total) = control.progress();
+    backlog.push(progress_json(control.is_running(), checked, total));
+    for message in backlog {
+        if !send_json(&mut socket, message) {
+            return;
+        }
+    }
+
+    loop {
+        let message = match events.recv_timeout(Duration::from_secs(PING_SECS)) {
+            Ok(EngineEvent::Log(entry)) if entry.seq <= last_seq => continue,
+            Ok(EngineEvent::Log(entry)) => log_json(&entry),
+            Ok(EngineEvent::Hit(hit)) => hit_json(&hit),
+            Ok(EngineEvent::Progress { checked, total }) => progress_json(true, checked, total),
+            Ok(EngineEvent::RunStarted(_)) => progress_json(true, 0, control.progress().1),
+            Ok(EngineEvent::RunFinished(_)) => {
+                let (checked, total) = control.progress();
+                progress_json(false, checked, total)
+            }
+            // Nothing happening; a ping finds out whether the client is still there
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if socket.send(Message::Ping(Vec::new())).is_err() {
+                    return;
+                }
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        };
+        if !send_json(&mut socket, message) {
+            return;
+        }
+    }
+}
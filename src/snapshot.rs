@@ -0,0 +1,66 @@
+use crate::i18n::tr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Page size of the picture; long result pages are cut off below this
+const WINDOW_SIZE: &str = "1280,1800";
+
+// Where Chrome, Edge and Chromium usually are, in order of preference
+#[cfg(windows)]
+const BROWSERS: &[&str] = &[
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+    r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+];
+#[cfg(target_os = "macos")]
+const BROWSERS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+];
+#[cfg(not(any(windows, target_os = "macos")))]
+const BROWSERS: &[&str] = &["google-chrome", "google-chrome-stable", "chromium", "chromium-browser", "microsoft-edge"];
+
+fn find_browser() -> Option<PathBuf> {
+    BROWSERS.iter().map(PathBuf::from).find(|browser| {
+        if browser.is_absolute() {
+            return browser.exists();
+        }
+        std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(browser).is_file()))
+    })
+}
+
+// The PNG saved next to a response, e.g. ABC-123_2020-01-01.png
+pub fn image_path(page: &Path) -> PathBuf {
+    page.with_extension("png")
+}
+
+// Screenshots `page` the way a browser shows it, with a headless Chrome,
+// Edge or Chromium: `browser` if set, otherwise whichever is installed.
+// Takes a few seconds, so keep it off the UI thread.
+pub fn render(browser: &str, page: &Path, png: &Path) -> Result<(), String> {
+    let browser = match browser.trim() {
+        "" => find_browser().ok_or_else(|| tr!("no Chrome, Edge or Chromium found - set the browser in Settings"))?,
+        set => PathBuf::from(set),
+    };
+    let page = std::path::absolute(page).map_err(|e| e.to_string())?;
+    let png = std::path::absolute(png).map_err(|e| e.to_string())?;
+    // Its own profile, so an open browser window doesn't take the job over
+    let profile = std::env::temp_dir().join("veh-reg-snapshot");
+    let output = Command::new(&browser)
+    .arg("--headless")
+    .arg("--disable-gpu")
+    .arg("--hide-scrollbars")
+    .arg(format!("--window-size={}", WINDOW_SIZE))
+    .arg(format!("--user-data-dir={}", profile.display()))
+    .arg(format!("--screenshot={}", png.display()))
+    .arg(&page)
+    .output()
+    .map_err(|e| tr!("cannot start {} - {}", browser.display(), e))?;
+    if !png.exists() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(tr!("{} made no image ({}): {}", browser.display(), output.status, stderr.trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use crate::classify::Classification;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Upper bounds of the latency histogram buckets, in milliseconds
+pub const LATENCY_BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+// How much per-second history the throughput chart keeps
+const TIMELINE_SECS: u64 = 600;
+
+// Requests and errors in one second of the run
+#[derive(Clone, Copy)]
+pub struct Second {
+    pub at: u64,
+    pub requests: u32,
+    pub errors: u32,
+}
+
+// Live counters for the current run, written by the workers and read by the
+// GUI and the metrics exporter. Reset when a new run starts.
+#[derive(Default)]
+pub struct NetworkStats {
+    requests: AtomicU64,
+    successes: AtomicU64,
+    errors: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    in_flight: AtomicU64,
+    latencies_ms: Mutex<Vec<u64>>,
+    // Indexed like Classification::ALL
+    outcomes: [AtomicU64; 5],
+    error_events: Mutex<Vec<ErrorEvent>>,
+    // Seconds since the run started, oldest first
+    timeline: Mutex<VecDeque<Second>>,
+    started: Mutex<Option<Instant>>,
+}
+
+// A check that didn't produce a usable answer, kept for the run reports
+#[derive(Clone)]
+pub struct ErrorEvent {
+    pub at: String,
+    pub date: String,
+    pub kind: String,
+    pub detail: String,
+}
+
+pub struct StatsSnapshot {
+    pub requests: u64,
+    pub successes: u64,
+    pub errors: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub in_flight: u64,
+    pub avg_latency_ms: u64,
+    pub p50_latency_ms: u64,
+    pub p90_latency_ms: u64,
+    pub p99_latency_ms: u64,
+}
+
+impl NetworkStats {
+    pub fn reset(&self) {
+        for counter in [&self.requests, &self.successes, &self.errors, &self.bytes_sent, &self.bytes_received, &self.in_flight] {
+            counter.store(0, Ordering::Relaxed);
+        }
+        for counter in &self.outcomes {
+            counter.store(0, Ordering::Relaxed);
+        }
+        if let Ok(mut latencies) = self.latencies_ms.lock() {
+            latencies.clear();
+        }
+        if let Ok(mut events) = self.error_events.lock() {
+            events.clear();
+        }
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.clear();
+        }
+        if let Ok(mut started) = self.started.lock() {
+            *started = Some(Instant::now());
+        }
+    }
+
+    fn tick(&self, requests: u32, errors: u32) {
+        let Some(at) = self.started.lock().ok().and_then(|s| s.map(|s| s.elapsed().as_secs())) else { return };
+        let Ok(mut timeline) = self.timeline.lock() else { return };
+        match timeline.back_mut() {
+            Some(last) if last.at == at => {
+                last.requests += requests;
+                last.errors += errors;
+            }
+            _ => timeline.push_back(Second { at, requests, errors }),
+        }
+        while timeline.front().is_some_and(|first| first.at + TIMELINE_SECS < at) {
+            timeline.pop_front();
+        }
+    }
+
+    // Per-second requests and errors for the last `window` seconds, with the
+    // quiet seconds filled in as zero
+    pub fn timeline(&self, window: u64) -> Vec<Second> {
+        let now = match self.started.lock().ok().and_then(|s| *s) {
+            Some(started) => started.elapsed().as_secs(),
+            None => return Vec::new(),
+        };
+        let from = now.saturating_sub(window);
+        let mut seconds: Vec<Second> = (from..=now).map(|at| Second { at, requests: 0, errors: 0 }).collect();
+        if let Ok(timeline) = self.timeline.lock() {
+            for second in timeline.iter().filter(|s| s.at >= from && s.at <= now) {
+                seconds[(second.at - from) as usize] = *second;
+            }
+        }
+        seconds
+    }
+
+    pub fn record_error(&self, date: &str, kind: &str, detail: String) {
+        self.tick(0, 1);
+        if let Ok(mut events) = self.error_events.lock() {
+            events.push(ErrorEvent {
+                at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                date: date.to_string(),
+                kind: kind.to_string(),
+                detail,
+            });
+        }
+    }
+
+    pub fn errors(&self) -> Vec<ErrorEvent> {
+        self.error_events.lock().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    pub fn record_outcome(&self, classification: Classification) {
+        if let Some(i) = Classification::ALL.iter().position(|c| *c == classification) {
+            self.outcomes[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn outcome_count(&self, classification: Classification) -> u64 {
+        Classification::ALL
+        .iter()
+        .position(|c| *c == classification)
+        .map(|i| self.outcomes[i].load(Ordering::Relaxed))
+        .unwrap_or(0)
+    }
+
+    // Cumulative bucket counts (matching LATENCY_BUCKETS_MS), total count and sum in ms
+    pub fn latency_histogram(&self) -> (Vec<u64>, u64, u64) {
+        let latencies = match self.latencies_ms.lock() {
+            Ok(l) => l.clone(),
+            Err(_) => Vec::new(),
+        };
+        let buckets = LATENCY_BUCKETS_MS
+        .iter()
+        .map(|bound| latencies.iter().filter(|l| **l <= *bound).count() as u64)
+        .collect();
+        (buckets, latencies.len() as u64, latencies.iter().sum())
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn begin(&self, bytes_sent: u64) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes_sent, Ordering::Relaxed);
+    }
+
+    // `bytes_received` is None when the request failed before a response arrived
+    pub fn finish(&self, latency: Duration, bytes_received: Option<u64>) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.tick(1, 0);
+        match bytes_received {
+            Some(bytes) => {
+                self.successes.fetch_add(1, Ordering::Relaxed);
+                self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+            }
+            None => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        if let Ok(mut latencies) = self.latencies_ms.lock() {
+            latencies.push(latency.as_millis() as u64);
+        }
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let mut sorted = self.latencies_ms.lock().map(|l| l.clone()).unwrap_or_default();
+        sorted.sort_unstable();
+        let avg_latency_ms = if sorted.is_empty() { 0 } else { sorted.iter().sum::<u64>() / sorted.len() as u64 };
+
+        StatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            successes: self.successes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            avg_latency_ms,
+            p50_latency_ms: percentile(&sorted, 50),
+            p90_latency_ms: percentile(&sorted, 90),
+            p99_latency_ms: percentile(&sorted, 99),
+        }
+    }
+}
+
+// Nearest-rank percentile of an ascending list, 0 when it's empty
+fn percentile(sorted: &[u64], pct: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (sorted.len() * pct).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
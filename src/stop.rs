@@ -0,0 +1,85 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
+
+// When a run ends early. Zero means no limit, so all zeros runs the whole
+// range. The default stops on the first hit, which is what a plate lookup
+// normally wants.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+pub struct StopConditions {
+    pub max_hits: u32,
+    pub time_limit_mins: u64,
+    pub max_requests: u64,
+}
+
+impl Default for StopConditions {
+    fn default() -> Self {
+        Self { max_hits: 1, time_limit_mins: 0, max_requests: 0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum StopReason {
+    Hits(u32),
+    TimeLimit(u64),
+    Requests(u64),
+    // The server answered with an error page; not configurable
+    ServerError(u16),
+}
+
+// The stop conditions of one run, checked by every worker
+pub struct RunLimits {
+    conditions: StopConditions,
+    started: Instant,
+    hits: AtomicU32,
+    reason: Mutex<Option<StopReason>>,
+}
+
+impl RunLimits {
+    pub fn new(conditions: StopConditions) -> Self {
+        Self { conditions, started: Instant::now(), hits: AtomicU32::new(0), reason: Mutex::new(None) }
+    }
+
+    // True for the call that stopped the run, so only one thread reports it
+    pub fn trip(&self, reason: StopReason) -> bool {
+        let Ok(mut current) = self.reason.lock() else { return false };
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(reason);
+        true
+    }
+
+    pub fn reason(&self) -> Option<StopReason> {
+        self.reason.lock().ok().and_then(|r| *r)
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    // Counts a hit; true when it is the one that reaches the limit
+    pub fn record_hit(&self) -> bool {
+        let hits = self.hits.fetch_add(1, Ordering::SeqCst) + 1;
+        self.conditions.max_hits > 0 && hits >= self.conditions.max_hits && self.trip(StopReason::Hits(hits))
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits.load(Ordering::SeqCst)
+    }
+
+    // Checks the time and request limits, returning the reason if this call
+    // is the one that stopped the run
+    pub fn check(&self, requests: u64) -> Option<StopReason> {
+        let limit = self.conditions.time_limit_mins;
+        let reason = if limit > 0 && self.started.elapsed().as_secs() >= limit * 60 {
+            StopReason::TimeLimit(limit)
+        } else if self.conditions.max_requests > 0 && requests >= self.conditions.max_requests {
+            StopReason::Requests(self.conditions.max_requests)
+        } else {
+            return None;
+        };
+        self.trip(reason).then_some(reason)
+    }
+}
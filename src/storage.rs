@@ -0,0 +1,402 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{vehicle}_{date}.html";
+
+// Where each case keeps its own results, under the results root
+pub const CASES_DIR: &str = "cases";
+
+// Directories under the results root that don't hold responses of its own
+const SKIP_DIRS: [&str; 5] = ["coverage", "locks", "runs", "transcripts", CASES_DIR];
+
+// How what the library writes into the results folder is sealed. The app
+// sets one when its results are encrypted (see its vault.rs); the library
+// has no key of its own, so without one everything is written as it is.
+#[derive(Clone, Copy)]
+pub struct Sealer {
+    // Whether new files are sealed right now
+    pub sealing: fn() -> bool,
+    // Fails rather than hand back plain text when it can't seal
+    pub seal: fn(&[u8]) -> Result<Vec<u8>, String>,
+    pub unseal: fn(&[u8]) -> Result<Vec<u8>, String>,
+    // A stand-in for a file name that doesn't give it away
+    pub name: fn(&str) -> String,
+}
+
+static SEALER: RwLock<Option<Sealer>> = RwLock::new(None);
+
+pub fn set_sealer(sealer: Sealer) {
+    if let Ok(mut current) = SEALER.write() {
+        *current = Some(sealer);
+    }
+}
+
+fn sealer() -> Option<Sealer> {
+    SEALER.read().ok().and_then(|current| *current)
+}
+
+pub fn sealing() -> bool {
+    sealer().is_some_and(|sealer| (sealer.sealing)())
+}
+
+// Encrypted while sealing, as it is otherwise
+pub fn seal(data: &[u8]) -> io::Result<Vec<u8>> {
+    match sealer() {
+        Some(sealer) => (sealer.seal)(data).map_err(io::Error::other),
+        None => Ok(data.to_vec()),
+    }
+}
+
+// Files that were never sealed come back as they are
+pub fn unseal(data: &[u8]) -> io::Result<Vec<u8>> {
+    match sealer() {
+        Some(sealer) => (sealer.unseal)(data).map_err(io::Error::other),
+        None => Ok(data.to_vec()),
+    }
+}
+
+// Where one run saves its responses: <root>/<VEHICLE>/<RUN_TIMESTAMP>/, with
+// file names from the user's template
+#[derive(Clone)]
+pub struct SaveLocation {
+    pub root: PathBuf,
+    // Relative to root
+    pub run_dir: PathBuf,
+    template: String,
+}
+
+impl SaveLocation {
+    pub fn new(root: &Path, vehicle_no: &str, started: chrono::DateTime<chrono::Local>, template: &str) -> Self {
+        let template = template.trim();
+        Self {
+            root: root.to_path_buf(),
+            run_dir: PathBuf::from(sanitize(vehicle_no)).join(started.format("%Y%m%d_%H%M%S").to_string()),
+            template: if template.is_empty() { DEFAULT_FILENAME_TEMPLATE.to_string() } else { template.to_string() },
+        }
+    }
+
+    pub fn create(&self) -> std::io::Result<()> {
+        fs::create_dir_all(self.root.join(&self.run_dir))
+    }
+
+    // Path relative to the results root, with '/' separators so it can be
+    // used in URLs as well. Error responses keep an HTTP<status>_ prefix so
+    // they're never mistaken for hits. While sealing, the name from the
+    // template is swapped for the sealer's stand-in, so a listing of the
+    // folder doesn't say which plate was registered on which date.
+    pub fn relative_path(&self, vehicle_no: &str, date_str: &str, status: u16, thread_id: usize) -> String {
+        let mut name = render_template(&self.template, vehicle_no, date_str, status, thread_id);
+        if let Some(sealer) = sealer().filter(|sealer| (sealer.sealing)()) {
+            name = format!("{}.html", (sealer.name)(&name));
+        }
+        if status != 200 {
+            name = format!("HTTP{}_{}", status, name);
+        }
+        let dir = self.run_dir.to_string_lossy().replace('\\', "/");
+        format!("{}/{}", dir, name)
+    }
+}
+
+// Placeholders: {vehicle}, {date}, {status}, {thread}. Always ends in .html.
+pub fn render_template(template: &str, vehicle_no: &str, date_str: &str, status: u16, thread_id: usize) -> String {
+    let name = template
+    .replace("{vehicle}", vehicle_no)
+    .replace("{date}", date_str)
+    .replace("{status}", &status.to_string())
+    .replace("{thread}", &thread_id.to_string());
+    let name = sanitize(&name);
+    if name.to_lowercase().ends_with(".html") { name } else { format!("{}.html", name) }
+}
+
+// Keeps a single path component: separators and other characters Windows
+// refuses in file names become '_'
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+    .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+    .collect()
+}
+
+// Every file under the results root, as '/'-separated paths relative to it.
+// Coverage bitmaps are left out.
+pub fn walk_results(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    walk(root, "", &mut files);
+    files
+}
+
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+        match entry.file_type() {
+            Ok(t) if t.is_dir() && !(prefix.is_empty() && SKIP_DIRS.contains(&name.as_str())) => {
+                walk(&entry.path(), &relative, files);
+            }
+            Ok(t) if t.is_file() => files.push(relative),
+            _ => {}
+        }
+    }
+}
+
+// What a saved response's path says about it
+pub struct ResultName {
+    pub vehicle_no: String,
+    pub date: NaiveDate,
+    // 200 unless the file carries an HTTP<status>_ prefix
+    pub status: u16,
+}
+
+// Runs are saved as <vehicle>/<run time>/<file>, where the file name comes
+// from a template; older results sit flat as <vehicle>_<date>.html
+pub fn parse_result_path(path: &str) -> Option<ResultName> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let stem = name.strip_suffix(".html")?;
+    let (status, stem) = match stem.strip_prefix("HTTP") {
+        Some(rest) => {
+            let (code, rest) = rest.split_once('_')?;
+            (code.parse().ok()?, rest)
+        }
+        None => (200, stem),
+    };
+    let vehicle_no = match path.split_once('/') {
+        Some((dir, _)) => dir,
+        None => stem.rsplit_once('_')?.0,
+    };
+    Some(ResultName {
+        vehicle_no: vehicle_no.to_string(),
+        date: find_date(stem)?,
+        status,
+    })
+}
+
+// The first YYYY-MM-DD anywhere in a file name
+fn find_date(name: &str) -> Option<NaiveDate> {
+    name.char_indices()
+    .filter_map(|(i, _)| name.get(i..i + 10))
+    .find_map(|candidate| NaiveDate::parse_from_str(candidate, "%Y-%m-%d").ok())
+}
+
+// A relative path from a remote client that stays inside the results root
+pub fn is_safe_relative(path: &str) -> bool {
+    !path.is_empty()
+    && !path.contains('\\')
+    && !path.starts_with('/')
+    && path.split('/').all(|part| !part.is_empty() && part != "." && part != ".." && !part.contains(':'))
+}
+
+// Where relative_path would have put a response, or the PNG of one, had it
+// been saved while sealing: the same folder, under a hashed name. None when
+// not sealing, for other files and for names that already are hashed.
+pub fn sealed_path(path: &str) -> Option<String> {
+    let sealer = sealer().filter(|sealer| (sealer.sealing)())?;
+    let (page, image) = match path.strip_suffix(".png") {
+        Some(stem) => (format!("{}.html", stem), true),
+        None => (path.to_string(), false),
+    };
+    let status = parse_result_path(&page)?.status;
+    let (dir, file) = page.rsplit_once('/').unwrap_or(("", &page));
+    let rendered = if status == 200 { file } else { file.strip_prefix(&format!("HTTP{}_", status))? };
+    let mut name = (sealer.name)(rendered);
+    if status != 200 {
+        name = format!("HTTP{}_{}", status, name);
+    }
+    let name = format!("{}.{}", name, if image { "png" } else { "html" });
+    Some(if dir.is_empty() { name } else { format!("{}/{}", dir, name) })
+}
+
+// A saved response, or the PNG of one next to it, as opposed to the other
+// files in the results folder
+pub fn is_saved_response(path: &str) -> bool {
+    if !is_safe_relative(path) {
+        return false;
+    }
+    let page = match path.strip_suffix(".png") {
+        Some(stem) => format!("{}.html", stem),
+        None => path.to_string(),
+    };
+    parse_result_path(&page).is_some() || is_sealed_name(&page)
+}
+
+// HTTP<status>_ and a hex hash, the name a response gets while sealing
+fn is_sealed_name(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let Some(stem) = name.strip_suffix(".html") else { return false };
+    let hash = stem.rsplit('_').next().unwrap_or(stem);
+    hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Limits on how many saved responses are kept. Zero turns a limit off.
+// Size and file limits count everything under the results directory but
+// only ever delete responses that are allowed to go, oldest first.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub max_age_days: u32,
+    pub max_size_mb: u64,
+    pub max_files: usize,
+    // Found records are what the whole scan is for
+    pub keep_hits: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: 0,
+            max_size_mb: 0,
+            max_files: 0,
+            keep_hits: true,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days > 0 || self.max_size_mb > 0 || self.max_files > 0
+    }
+}
+
+pub struct CleanupSummary {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    // What was removed, as the results folder or case folder that held it and
+    // the path relative to that, for its results.db to forget
+    pub removed_paths: Vec<(PathBuf, String)>,
+}
+
+struct SavedResponse {
+    // The results root or the case folder the path is relative to
+    root: PathBuf,
+    path: String,
+    size: u64,
+    modified: SystemTime,
+    // Error pages are saved with an HTTP<status>_ prefix, everything else is a hit
+    is_hit: bool,
+}
+
+// The results root and each case folder under it. A case keeps its runs,
+// coverage and results.db the way the root does.
+fn result_roots(root: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![root.to_path_buf()];
+    if let Ok(cases) = fs::read_dir(root.join(CASES_DIR)) {
+        roots.extend(cases.flatten().filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir())).map(|entry| entry.path()));
+    }
+    roots
+}
+
+// Limits apply to the root and its cases together
+pub fn apply_retention(root: &Path, policy: &RetentionPolicy) -> CleanupSummary {
+    let mut summary = CleanupSummary { removed: 0, freed_bytes: 0, removed_paths: Vec::new() };
+    if !policy.is_enabled() {
+        return summary;
+    }
+
+    let roots = result_roots(root);
+    let mut responses: Vec<SavedResponse> = roots
+    .iter()
+    .flat_map(|root| walk_results(root).into_iter().map(move |path| (root, path)))
+    .filter(|(_, path)| path.ends_with(".html"))
+    .filter_map(|(root, path)| {
+        let meta = fs::metadata(root.join(&path)).ok()?;
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        let is_hit = !name.starts_with("HTTP");
+        Some(SavedResponse { root: root.clone(), size: meta.len(), modified: meta.modified().ok()?, is_hit, path })
+    })
+    .collect();
+    responses.sort_by_key(|r| r.modified);
+
+    let mut total_size: u64 = responses.iter().map(|r| r.size).sum();
+    let mut total_files = responses.len();
+    let max_size = policy.max_size_mb * 1024 * 1024;
+    let max_age = Duration::from_secs(policy.max_age_days as u64 * 24 * 60 * 60);
+    let now = SystemTime::now();
+
+    for response in responses {
+        if response.is_hit && policy.keep_hits {
+            continue;
+        }
+        let too_old = policy.max_age_days > 0 && now.duration_since(response.modified).is_ok_and(|age| age > max_age);
+        let too_big = policy.max_size_mb > 0 && total_size > max_size;
+        let too_many = policy.max_files > 0 && total_files > policy.max_files;
+        if !(too_old || too_big || too_many) {
+            continue;
+        }
+        if fs::remove_file(response.root.join(&response.path)).is_ok() {
+            summary.removed += 1;
+            summary.freed_bytes += response.size;
+            total_size -= response.size;
+            total_files -= 1;
+            summary.removed_paths.push((response.root, response.path));
+        }
+    }
+
+    for root in &roots {
+        remove_empty_dirs(root, true);
+    }
+    summary
+}
+
+// Run folders left empty by a cleanup. The results root itself stays.
+fn remove_empty_dirs(dir: &Path, is_root: bool) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else { return false };
+    let mut empty = true;
+    for entry in entries.flatten() {
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        let skipped = is_root && SKIP_DIRS.contains(&entry.file_name().to_string_lossy().as_ref());
+        if !is_dir || skipped || !remove_empty_dirs(&entry.path(), false) {
+            empty = false;
+        }
+    }
+    if empty && !is_root {
+        return fs::remove_dir(dir).is_ok();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_responses_and_their_pictures_are_saved_responses() {
+        for served in ["ABC-123/2020-01-01_120000/ABC-123_2020-01-05.html", "ABC-123_2020-01-05.html",
+                       "ABC-123/run/HTTP500_ABC-123_2020-01-05.html", "ABC-123/run/ABC-123_2020-01-05.png",
+                       "ABC-123/run/0123456789abcdef0123456789abcdef.html"] {
+            assert!(is_saved_response(served), "{}", served);
+        }
+        for refused in ["results.db", "daemon.db", "audit.jsonl", "vault.json", "ABC-123/run/summary.json",
+                        "locks/run_1.json", "cases/x/case.json", "../ABC-123_2020-01-05.html", "ABC-123/run/notes.html"] {
+            assert!(!is_saved_response(refused), "{}", refused);
+        }
+    }
+
+    #[test]
+    fn retention_counts_and_cleans_cases_too() {
+        let root = tempfile::tempdir().unwrap();
+        let saved = ["ABC-123/run/HTTP500_ABC-123_2020-01-05.html", "ABC-123/run/ABC-123_2020-01-06.html",
+                     "cases/stolen/XYZ-9/run/HTTP500_XYZ-9_2021-03-04.html", "cases/stolen/coverage/everywhere/plate/HTTP500_x.html"];
+        for path in saved {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "<html></html>").unwrap();
+        }
+
+        let policy = RetentionPolicy { max_files: 1, ..Default::default() };
+        let summary = apply_retention(root.path(), &policy);
+        // Both error pages go, the hit stays and the case's coverage isn't a response
+        assert_eq!(summary.removed, 2);
+        let mut removed = summary.removed_paths;
+        removed.sort();
+        assert_eq!(removed, vec![(root.path().to_path_buf(), saved[0].to_string()),
+                                 (root.path().join("cases/stolen"), "XYZ-9/run/HTTP500_XYZ-9_2021-03-04.html".to_string())]);
+        assert!(root.path().join(saved[1]).exists() && root.path().join(saved[3]).exists());
+        // Emptied run folders go, the case folder itself stays
+        assert!(!root.path().join("cases/stolen/XYZ-9").exists());
+        assert!(root.path().join("cases/stolen").exists());
+    }
+}
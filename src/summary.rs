@@ -0,0 +1,60 @@
+use crate::stop::{StopConditions, StopReason};
+use crate::vault;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Written into the run's results folder when it ends, so the folder says
+// what was scanned, how, and with what outcome without the app. Sealed like
+// the responses while the results are encrypted.
+pub const SUMMARY_FILE: &str = "summary.json";
+
+#[derive(Serialize)]
+pub struct RunSummary {
+    pub vehicle_no: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub duration_secs: u64,
+    pub dates_total: usize,
+    pub dates_checked: usize,
+    // Registration dates found
+    pub hits: Vec<String>,
+    // Classification label -> responses
+    pub outcomes: BTreeMap<String, u64>,
+    // Error kind -> occurrences, e.g. "Request failed" or "HTTP 503"
+    pub errors: BTreeMap<String, u64>,
+    pub requests: u64,
+    pub requests_per_sec: f64,
+    pub latency_ms: Latency,
+    // None when the run went through its whole range or was stopped by hand
+    pub stop_reason: Option<StopReason>,
+    pub stopped_by_user: bool,
+    pub settings: RunSettings,
+}
+
+#[derive(Serialize)]
+pub struct Latency {
+    pub avg: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+#[derive(Serialize)]
+pub struct RunSettings {
+    pub threads: usize,
+    pub search_order: String,
+    pub adaptive_concurrency: bool,
+    pub request_delay_ms: u64,
+    pub skip_checked: bool,
+    pub stop_conditions: StopConditions,
+    pub offline: bool,
+}
+
+pub fn write(run_dir: &Path, summary: &RunSummary) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = run_dir.join(SUMMARY_FILE);
+    vault::write(&path, serde_json::to_string_pretty(summary)?)?;
+    Ok(path)
+}
@@ -0,0 +1,229 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const BASE_COOLOFF: Duration = Duration::from_secs(30);
+const MAX_COOLOFF: Duration = Duration::from_secs(600);
+
+// Suspicious bodies remembered for the generic-page check; the oldest is
+// forgotten first. A generic page comes back again soon, so recent ones are
+// the ones that matter.
+const BODY_HISTORY: usize = 256;
+
+struct ThrottleState {
+    paused_until: Option<Instant>,
+    cooloff: Duration,
+    // What `cooloff` goes back to once requests get through again
+    base_cooloff: Duration,
+    // Hash of each suspicious (hit or error) body and the date it came back
+    // for, at most BODY_HISTORY of them, with the hashes oldest first
+    bodies: HashMap<u64, String>,
+    body_order: VecDeque<u64>,
+}
+
+// Shared by all workers of a run. When any worker sees a rate-limit or ban
+// response every worker pauses; consecutive trips double the cool-off.
+pub struct Throttle {
+    state: Mutex<ThrottleState>,
+    // Set when a challenge page needs manual attention; only `resume` clears it
+    challenge_paused: AtomicBool,
+    // Circuit breaker: after this many failed requests in a row (0 = never)
+    // the run pauses like it does for a challenge, instead of burning through
+    // dates while the server is down
+    breaker_threshold: u32,
+    consecutive_failures: AtomicU32,
+    breaker_open: AtomicBool,
+    // Each worker waits `delay` between requests
+    delay: Duration,
+}
+
+pub fn body_hash(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(ThrottleState {
+                paused_until: None,
+                cooloff: BASE_COOLOFF,
+                base_cooloff: BASE_COOLOFF,
+                bodies: HashMap::new(),
+                body_order: VecDeque::new(),
+            }),
+            challenge_paused: AtomicBool::new(false),
+            breaker_threshold: 0,
+            consecutive_failures: AtomicU32::new(0),
+            breaker_open: AtomicBool::new(false),
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+impl Throttle {
+    pub fn with_pacing(delay: Duration) -> Self {
+        Self { delay, ..Self::default() }
+    }
+
+    // The first cool-off after a rate limit; consecutive trips double it
+    pub fn with_cooloff(self, base: Duration) -> Self {
+        if let Ok(mut state) = self.state.lock() {
+            state.cooloff = base;
+            state.base_cooloff = base;
+        }
+        self
+    }
+
+    pub fn with_breaker(self, threshold: u32) -> Self {
+        Self { breaker_threshold: threshold, ..self }
+    }
+
+    // The per-request delay, so each worker keeps its requests spaced out.
+    // Returns false if the run was stopped while waiting.
+    pub fn pace(&self, is_running: &AtomicBool) -> bool {
+        let mut remaining = self.delay;
+        while !remaining.is_zero() {
+            if !is_running.load(Ordering::SeqCst) {
+                return false;
+            }
+            let step = remaining.min(Duration::from_millis(200));
+            thread::sleep(step);
+            remaining -= step;
+        }
+        true
+    }
+
+    // Sleeps while a cool-off is active. Returns false if the run was stopped.
+    pub fn wait(&self, is_running: &AtomicBool) -> bool {
+        loop {
+            if !is_running.load(Ordering::SeqCst) {
+                return false;
+            }
+            if self.challenge_paused.load(Ordering::SeqCst) || self.breaker_open.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            let paused_until = self.state.lock().ok().and_then(|s| s.paused_until);
+            match paused_until {
+                Some(until) if until > Instant::now() => thread::sleep(Duration::from_millis(200)),
+                _ => return true,
+            }
+        }
+    }
+
+    // Starts (or extends) a cool-off and returns how long it lasts. Workers
+    // that trip while already paused don't stack further backoff.
+    pub fn trip(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if let Some(until) = state.paused_until {
+            if until > now {
+                return until - now;
+            }
+        }
+        let cooloff = state.cooloff;
+        state.paused_until = Some(now + cooloff);
+        state.cooloff = (cooloff * 2).min(MAX_COOLOFF);
+        cooloff
+    }
+
+    // Returns true for the worker that actually paused the run
+    pub fn pause_for_challenge(&self) -> bool {
+        !self.challenge_paused.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_challenge_paused(&self) -> bool {
+        self.challenge_paused.load(Ordering::SeqCst)
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.paused_until = None;
+            state.cooloff = state.base_cooloff;
+        }
+        self.challenge_paused.store(false, Ordering::SeqCst);
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        self.breaker_open.store(false, Ordering::SeqCst);
+    }
+
+    // A request that failed outright or got a 5xx, counted across all
+    // workers. Returns true for the call that opened the breaker.
+    pub fn record_failure(&self) -> bool {
+        if self.breaker_threshold == 0 {
+            return false;
+        }
+        let streak = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if streak < self.breaker_threshold {
+            return false;
+        }
+        !self.breaker_open.swap(true, Ordering::SeqCst)
+    }
+
+    // Any answer from the server closes the run-wide streak
+    pub fn record_reachable(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+    }
+
+    pub fn is_breaker_open(&self) -> bool {
+        self.breaker_open.load(Ordering::SeqCst)
+    }
+
+    pub fn breaker_threshold(&self) -> u32 {
+        self.breaker_threshold
+    }
+
+    pub fn record_success(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            let paused = state.paused_until.is_some_and(|until| until > Instant::now());
+            if !paused {
+                state.cooloff = state.base_cooloff;
+            }
+        }
+    }
+
+    // A real record page is specific to its date. The same body coming back
+    // for two different dates is a generic page (block notice, maintenance).
+    pub fn seen_for_other_date(&self, hash: u64, date_str: &str) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.bodies.get(&hash) {
+            Some(seen) => seen != date_str,
+            None => {
+                if state.body_order.len() >= BODY_HISTORY {
+                    if let Some(oldest) = state.body_order.pop_front() {
+                        state.bodies.remove(&oldest);
+                    }
+                }
+                state.bodies.insert(hash, date_str.to_string());
+                state.body_order.push_back(hash);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembers_a_bounded_number_of_bodies() {
+        let throttle = Throttle::default();
+        assert!(!throttle.seen_for_other_date(0, "2020-01-01"));
+        assert!(throttle.seen_for_other_date(0, "2020-01-02"));
+        assert!(!throttle.seen_for_other_date(0, "2020-01-01"));
+
+        for hash in 1..=BODY_HISTORY as u64 {
+            throttle.seen_for_other_date(hash, "2020-01-03");
+        }
+        assert_eq!(throttle.state.lock().unwrap().bodies.len(), BODY_HISTORY);
+        // The first body has been forgotten, the latest is still known
+        assert!(!throttle.seen_for_other_date(0, "2020-01-02"));
+        assert!(throttle.seen_for_other_date(BODY_HISTORY as u64, "2020-01-04"));
+    }
+}
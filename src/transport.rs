@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::PathBuf;
+
+pub type TransportResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+// Everything the scan engine needs from the network. The HTTP implementation
+// lives in `http`; `FixtureTransport` answers from files on disk instead.
+pub trait Transport: Send + Sync {
+    // Queries one vehicle/date pair, returning the HTTP status and body
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)>;
+
+    // Fetches an arbitrary page, used to pick up session cookies
+    fn bootstrap(&self, url: &str) -> TransportResult<u16>;
+
+    // Bytes put on the wire for one check, for the network statistics
+    fn request_size(&self, _vehicle_no: &str, _date_str: &str) -> u64 {
+        0
+    }
+
+    // Told after every check whether it failed. Returns the old and new
+    // endpoint when repeated failures made it switch to a fallback.
+    fn report_outcome(&self, _failed: bool) -> Option<(String, String)> {
+        None
+    }
+
+    // Whether a failed check is worth retrying because a fallback endpoint
+    // is still left to switch to
+    fn can_fail_over(&self) -> bool {
+        false
+    }
+}
+
+pub const FIXTURE_MISS_BODY: &str = "<html><body>NO RECORD FOUND. PLEASE CONTACT EXCISE OFFICE.</body></html>";
+
+// Serves `<VEHICLE>_<DATE>.html` from a directory as a 200 response, or
+// `HTTP<status>_<VEHICLE>_<DATE>.html` with that status, and the standard
+// no-record page for everything else. Lets a full run be exercised offline.
+pub struct FixtureTransport {
+    dir: PathBuf,
+}
+
+impl FixtureTransport {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        let name = format!("{}_{}.html", vehicle_no, date_str);
+        if let Ok(body) = fs::read_to_string(self.dir.join(&name)) {
+            return Ok((200, body));
+        }
+
+        for status in [403u16, 429, 500, 502, 503] {
+            let path = self.dir.join(format!("HTTP{}_{}", status, name));
+            if let Ok(body) = fs::read_to_string(path) {
+                return Ok((status, body));
+            }
+        }
+
+        Ok((200, FIXTURE_MISS_BODY.to_string()))
+    }
+
+    fn bootstrap(&self, _url: &str) -> TransportResult<u16> {
+        Ok(200)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::{self, Classification, Classifier};
+
+    // A record on Jan 5, a rate-limit page on Jan 3 and a server error on
+    // Jan 8; every other date gets the no-record page
+    fn fixtures() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("ABC-123_2020-01-05.html"), "<html><body>Owner Name: MUHAMMAD ALI</body></html>").unwrap();
+        fs::write(dir.path().join("HTTP429_ABC-123_2020-01-03.html"), "slow down").unwrap();
+        fs::write(dir.path().join("HTTP500_ABC-123_2020-01-08.html"), "Internal Server Error").unwrap();
+        dir
+    }
+
+    fn outcome(transport: &FixtureTransport, date: &str) -> (u16, Classification) {
+        let classifier = Classifier::new(&classify::default_rules()).unwrap();
+        let (status, body) = transport.check("ABC-123", date).unwrap();
+        (status, classifier.classify(status, &body).0)
+    }
+
+    #[test]
+    fn answers_from_fixture_files() {
+        let dir = fixtures();
+        let transport = FixtureTransport::new(dir.path().to_path_buf());
+        assert_eq!(outcome(&transport, "2020-01-05"), (200, Classification::Hit));
+        assert_eq!(outcome(&transport, "2020-01-03"), (429, Classification::RateLimited));
+        assert_eq!(outcome(&transport, "2020-01-08"), (500, Classification::Error));
+        assert_eq!(outcome(&transport, "2020-01-01"), (200, Classification::Miss));
+        assert_eq!(transport.bootstrap("https://example.invalid/").unwrap(), 200);
+    }
+
+    // Fixtures are per vehicle, so another plate on a hit date is a miss
+    #[test]
+    fn other_vehicles_get_the_no_record_page() {
+        let dir = fixtures();
+        let transport = FixtureTransport::new(dir.path().to_path_buf());
+        let (status, body) = transport.check("XYZ-999", "2020-01-05").unwrap();
+        assert_eq!((status, body.as_str()), (200, FIXTURE_MISS_BODY));
+    }
+}
@@ -0,0 +1,134 @@
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrayStatus {
+    Idle,
+    Running,
+    Found,
+}
+
+impl TrayStatus {
+    fn tooltip(&self) -> &'static str {
+        match self {
+            TrayStatus::Idle => "Vehicle Registration Checker - idle",
+            TrayStatus::Running => "Vehicle Registration Checker - running",
+            TrayStatus::Found => "Vehicle Registration Checker - RECORD FOUND",
+        }
+    }
+
+    // Same colours as the status line in the window
+    fn color(&self) -> [u8; 3] {
+        match self {
+            TrayStatus::Idle => [0, 0, 255],
+            TrayStatus::Running => [255, 165, 0],
+            TrayStatus::Found => [0, 255, 0],
+        }
+    }
+}
+
+pub enum TrayAction {
+    Show,
+    Start,
+    Stop,
+    Quit,
+}
+
+pub struct Tray {
+    icon: TrayIcon,
+    status: TrayStatus,
+    show: MenuItem,
+    start: MenuItem,
+    stop: MenuItem,
+    quit: MenuItem,
+}
+
+// A filled circle in the status colour
+fn status_icon(status: TrayStatus) -> Result<Icon, String> {
+    const SIZE: u32 = 32;
+    let [r, g, b] = status.color();
+    let center = SIZE as f32 / 2.0 - 0.5;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let dx = x as f32 - center;
+            let dy = y as f32 - center;
+            let alpha = if dx * dx + dy * dy <= center * center { 255 } else { 0 };
+            rgba.extend_from_slice(&[r, g, b, alpha]);
+        }
+    }
+    Icon::from_rgba(rgba, SIZE, SIZE).map_err(|e| e.to_string())
+}
+
+impl Tray {
+    pub fn new() -> Result<Self, String> {
+        // tray-icon needs a running GTK main loop on Linux, which eframe doesn't provide
+        if cfg!(target_os = "linux") {
+            return Err("the system tray is not supported on Linux builds".to_string());
+        }
+
+        let show = MenuItem::new("Show", true, None);
+        let start = MenuItem::new("Start", true, None);
+        let stop = MenuItem::new("Stop", false, None);
+        let quit = MenuItem::new("Quit", true, None);
+
+        let menu = Menu::new();
+        menu.append_items(&[
+            &show,
+            &PredefinedMenuItem::separator(),
+            &start,
+            &stop,
+            &PredefinedMenuItem::separator(),
+            &quit,
+        ])
+        .map_err(|e| e.to_string())?;
+
+        let status = TrayStatus::Idle;
+        let icon = TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip(status.tooltip())
+        .with_icon(status_icon(status)?)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+        Ok(Self { icon, status, show, start, stop, quit })
+    }
+
+    pub fn set_status(&mut self, status: TrayStatus) {
+        if status == self.status {
+            return;
+        }
+        self.status = status;
+        if let Ok(icon) = status_icon(status) {
+            let _ = self.icon.set_icon(Some(icon));
+        }
+        let _ = self.icon.set_tooltip(Some(status.tooltip()));
+        self.start.set_enabled(status != TrayStatus::Running);
+        self.stop.set_enabled(status == TrayStatus::Running);
+    }
+
+    // Menu clicks and left clicks on the icon since the last call
+    pub fn poll(&self) -> Vec<TrayAction> {
+        let mut actions = Vec::new();
+
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            if event.id == *self.show.id() {
+                actions.push(TrayAction::Show);
+            } else if event.id == *self.start.id() {
+                actions.push(TrayAction::Start);
+            } else if event.id == *self.stop.id() {
+                actions.push(TrayAction::Stop);
+            } else if event.id == *self.quit.id() {
+                actions.push(TrayAction::Quit);
+            }
+        }
+
+        while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                actions.push(TrayAction::Show);
+            }
+        }
+
+        actions
+    }
+}
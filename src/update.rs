@@ -0,0 +1,49 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::time::Duration;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/nerdrogue/ISL-Veh-Reg-Hacking/releases/latest";
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// The parts of a GitHub release we show
+#[derive(Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+    // Changelog, as written on the release page
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+// "v1.2.10" -> [1, 2, 10]; anything after the digits of a part ("0-beta") is ignored
+fn parse_version(text: &str) -> Vec<u64> {
+    text.trim()
+    .trim_start_matches(['v', 'V'])
+    .split('.')
+    .map(|part| part.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0))
+    .collect()
+}
+
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    let (mut tag, mut current) = (parse_version(tag), parse_version(current));
+    let len = tag.len().max(current.len());
+    tag.resize(len, 0);
+    current.resize(len, 0);
+    tag > current
+}
+
+// The latest release, if it is newer than this build
+pub fn check() -> Result<Option<Release>, Box<dyn Error>> {
+    let client = reqwest::blocking::Client::builder()
+    // GitHub's API refuses requests without a User-Agent
+    .user_agent(format!("ISL-Veh-Reg-Hacking/{}", CURRENT_VERSION))
+    .timeout(Duration::from_secs(15))
+    .build()?;
+    let response = client
+    .get(RELEASES_URL)
+    .header("Accept", "application/vnd.github+json")
+    .send()?
+    .error_for_status()?;
+    let release: Release = serde_json::from_str(&response.text()?)?;
+    Ok(is_newer(&release.tag_name, CURRENT_VERSION).then_some(release))
+}
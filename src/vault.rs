@@ -0,0 +1,466 @@
+use crate::i18n::tr;
+use crate::{case, daemon, recovery, storage, summary};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tempfile::TempDir;
+use zeroize::Zeroizing;
+
+// Encryption at rest of the results folder, for results folders on shared
+// or field machines: saved responses, results.db, run summaries, run state
+// and coverage (the last two through storage::set_sealer). Saved responses
+// get hashed names. The key comes from a passphrase (Argon2id with a salt
+// kept in vault.json at the top of the results folder) and lives only in
+// memory while unlocked; it's wiped when locked.
+//
+// A sealed file is MAGIC, a 12-byte nonce and the AES-256-GCM ciphertext.
+// Files without MAGIC are read as they are, so results saved before
+// encryption was turned on stay readable until they are encrypted too.
+pub const VAULT_FILE: &str = "vault.json";
+const MAGIC: &[u8] = b"VEHREG-AESGCM1\n";
+
+// Sealed into vault.json, to tell a wrong passphrase from the right one
+const CHECK_TEXT: &[u8] = b"veh-reg vault";
+
+// Decrypted copies handed to the browser go in a folder under the system
+// temp directory named with this and a random suffix
+const VIEW_DIR_PREFIX: &str = "veh-reg-view-";
+
+// Where coverage bitmaps are kept, see CoverageMap::path_for
+const COVERAGE_DIR: &str = "coverage";
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    // Hex
+    salt: String,
+    check: String,
+}
+
+static KEY: RwLock<Option<[u8; 32]>> = RwLock::new(None);
+
+// This process's own view folder, made on first use. Dropping it removes it.
+static VIEW_DIR: Mutex<Option<TempDir>> = Mutex::new(None);
+
+// Whether new files are sealed. Turning encryption off stops that, but files
+// sealed before still need the key to be read.
+static SEALING: AtomicBool = AtomicBool::new(false);
+
+pub fn set_sealing(on: bool) {
+    SEALING.store(on, Ordering::Relaxed);
+    storage::set_sealer(storage::Sealer { sealing: is_encrypting, seal, unseal, name: opaque_name });
+}
+
+// Sealing is on and the key is there to do it
+pub fn is_sealing() -> bool {
+    is_encrypting() && is_unlocked()
+}
+
+// Sealing is on, whether or not the key is there. Nothing that would have
+// been sealed is written in the clear instead while it is.
+pub fn is_encrypting() -> bool {
+    SEALING.load(Ordering::Relaxed)
+}
+
+pub fn exists(results_root: &Path) -> bool {
+    results_root.join(VAULT_FILE).exists()
+}
+
+// Derives the key and keeps it for this session. The first unlock of a
+// results folder sets its passphrase; later ones must match it.
+pub fn unlock(results_root: &Path, passphrase: &str) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err(tr!("the passphrase is empty"));
+    }
+    let path = results_root.join(VAULT_FILE);
+    let mut key = match fs::read_to_string(&path) {
+        Ok(content) => {
+            let vault: VaultFile = serde_json::from_str(&content).map_err(|e| tr!("cannot read {} - {}", path.display(), e))?;
+            let salt = from_hex(&vault.salt).ok_or_else(|| tr!("{} is damaged", path.display()))?;
+            let mut key = derive_key(passphrase, &salt)?;
+            let check = from_hex(&vault.check).and_then(|check| decrypt(&key, &check).ok());
+            if check.as_deref() != Some(CHECK_TEXT) {
+                wipe(&mut key);
+                return Err(tr!("wrong passphrase"));
+            }
+            key
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let salt = random_salt()?;
+            let key = derive_key(passphrase, &salt)?;
+            let vault = VaultFile { salt: to_hex(&salt), check: to_hex(&encrypt(&key, CHECK_TEXT)) };
+            fs::create_dir_all(results_root).map_err(|e| tr!("cannot create {} - {}", results_root.display(), e))?;
+            let content = serde_json::to_string_pretty(&vault).map_err(|e| e.to_string())?;
+            fs::write(&path, content).map_err(|e| tr!("cannot write {} - {}", path.display(), e))?;
+            key
+        }
+        Err(e) => return Err(tr!("cannot read {} - {}", path.display(), e)),
+    };
+    if let Ok(mut held) = KEY.write() {
+        if let Some(old) = held.as_mut() {
+            wipe(old);
+        }
+        *held = Some(key);
+    }
+    wipe(&mut key);
+    Ok(())
+}
+
+// Forgets the key; sealed files can't be read until the next unlock
+pub fn lock() {
+    if let Ok(mut held) = KEY.write() {
+        if let Some(key) = held.as_mut() {
+            wipe(key);
+        }
+        *held = None;
+    }
+    if let Ok(mut view) = VIEW_DIR.lock() {
+        view.take();
+    }
+}
+
+pub fn is_unlocked() -> bool {
+    with_key(|_| ()).is_some()
+}
+
+// Borrows the key where it's held rather than copying it out, so no copies
+// are left behind on the stack
+fn with_key<T>(f: impl FnOnce(&[u8; 32]) -> T) -> Option<T> {
+    KEY.read().ok().and_then(|held| held.as_ref().map(f))
+}
+
+// The raw key in the form SQLCipher's PRAGMA key takes it, wiped when
+// dropped. Written straight into its final buffer so no copy is left behind
+// by reallocating.
+pub fn sqlcipher_key() -> Option<Zeroizing<String>> {
+    with_key(|key| {
+        let mut text = Zeroizing::new(String::with_capacity(3 + key.len() * 2));
+        text.push_str("x'");
+        for byte in key {
+            text.push(char::from_digit(u32::from(byte >> 4), 16).unwrap_or('0'));
+            text.push(char::from_digit(u32::from(byte & 0xf), 16).unwrap_or('0'));
+        }
+        text.push('\'');
+        text
+    })
+}
+
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+// Encrypted while sealing, as it is otherwise. Refused while sealing with
+// the results locked, so the writer saves nothing rather than plain text.
+pub fn seal(plain: &[u8]) -> Result<Vec<u8>, String> {
+    if !is_encrypting() {
+        return Ok(plain.to_vec());
+    }
+    with_key(|key| [MAGIC, &encrypt(key, plain)].concat()).ok_or_else(|| tr!("the results are encrypted and locked - unlock them first"))
+}
+
+pub fn unseal(data: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(sealed) = data.strip_prefix(MAGIC) else { return Ok(data.to_vec()) };
+    with_key(|key| decrypt(key, sealed)).unwrap_or_else(|| Err(tr!("the results are encrypted - unlock them first")))
+}
+
+// A saved file's name while sealing: a keyed hash of the name it would have
+// had, 32 hex characters. The same name gives the same hash for as long as
+// the passphrase stays the same; without the key it says nothing.
+pub fn opaque_name(name: &str) -> String {
+    with_key(|key| to_hex(&name_hash(key, name))).unwrap_or_else(|| name.to_string())
+}
+
+// For files that can't be sealed, being appended to as they go or read by
+// other programs: the transcript, the audit log and reports. While sealing
+// they aren't written, rather than left in the clear beside sealed results.
+pub fn allow_plain() -> io::Result<()> {
+    if is_encrypting() {
+        return Err(io::Error::other(tr!("the results are encrypted, and this file can't be - not written")));
+    }
+    Ok(())
+}
+
+pub fn write(path: &Path, data: impl AsRef<[u8]>) -> io::Result<()> {
+    fs::write(path, seal(data.as_ref()).map_err(io::Error::other)?)
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    unseal(&fs::read(path)?).map_err(io::Error::other)
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(io::Error::other)
+}
+
+// Encrypts a file saved before encryption was turned on, in place. False
+// when it already was.
+pub fn seal_file(path: &Path) -> io::Result<bool> {
+    let data = fs::read(path)?;
+    if is_sealed(&data) || !is_sealing() {
+        return Ok(false);
+    }
+    // Beside it and renamed over it, so a crash can't leave half a file
+    let tmp = path.with_extension("sealing");
+    fs::write(&tmp, seal(&data).map_err(io::Error::other)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(true)
+}
+
+// What seal_results did
+#[derive(Default)]
+pub struct SealedResults {
+    // Files sealed
+    pub files: usize,
+    // Responses moved to their hashed names, as (old, new) paths relative to
+    // the results folder, for results.db to follow
+    pub renamed: Vec<(String, String)>,
+}
+
+// Seals what was saved under `dir` before encryption was turned on, as it
+// would have been saved since: responses and their images under hashed
+// names, and run summaries, run state, coverage, the daemon's state and the
+// case file under their own.
+pub fn seal_results(dir: &Path) -> io::Result<SealedResults> {
+    let mut sealed = SealedResults::default();
+    if !is_sealing() {
+        return Ok(sealed);
+    }
+    for path in storage::walk_results(dir) {
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        if name == summary::SUMMARY_FILE || path == daemon::STATE_FILE || path == case::CASE_FILE {
+            sealed.files += usize::from(seal_file(&dir.join(&path))?);
+            continue;
+        }
+        let Some(hashed) = storage::sealed_path(&path) else { continue };
+        sealed.files += usize::from(seal_file(&dir.join(&path))?);
+        fs::rename(dir.join(&path), dir.join(&hashed))?;
+        if !path.ends_with(".png") {
+            sealed.renamed.push((path, hashed));
+        }
+    }
+    for kept in [COVERAGE_DIR, recovery::RUNS_DIR] {
+        let kept = dir.join(kept);
+        for path in storage::walk_results(&kept) {
+            sealed.files += usize::from(seal_file(&kept.join(&path))?);
+        }
+    }
+    Ok(sealed)
+}
+
+// For things that need the file itself, like a browser: the path as it is
+// when it isn't sealed, otherwise a decrypted copy in the temp directory,
+// removed again on lock()
+pub fn plain_copy(path: &Path) -> io::Result<PathBuf> {
+    let data = fs::read(path)?;
+    if !is_sealed(&data) {
+        return Ok(path.to_path_buf());
+    }
+    let copy = view_dir()?.join(path.file_name().unwrap_or_default());
+    fs::write(&copy, unseal(&data).map_err(io::Error::other)?)?;
+    Ok(copy)
+}
+
+// Where decrypted copies go, for things made from them too. A new folder
+// only this user can open, so other users of the machine can't read the
+// copies or plant links in it; lock(), and so quitting the app, removes it.
+pub fn view_dir() -> io::Result<PathBuf> {
+    let mut view = VIEW_DIR.lock().map_err(|_| io::Error::other("view folder lock poisoned"))?;
+    if let Some(dir) = view.as_ref() {
+        return Ok(dir.path().to_path_buf());
+    }
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(VIEW_DIR_PREFIX);
+    #[cfg(unix)]
+    builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+    Ok(view.insert(builder.tempdir()?).path().to_path_buf())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn wipe(key: &mut [u8; 32]) {
+    zeroize::Zeroize::zeroize(key);
+}
+
+#[cfg(feature = "encryption")]
+fn name_hash(key: &[u8; 32], name: &str) -> [u8; 16] {
+    use blake2::digest::{Mac, consts::U16};
+    let mut mac = blake2::Blake2bMac::<U16>::new_from_slice(key).expect("32-byte keys are within BLAKE2b's limit");
+    mac.update(name.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(feature = "encryption")]
+fn random_salt() -> Result<[u8; 16], String> {
+    use aes_gcm::aead::{OsRng, rand_core::RngCore};
+    let mut salt = [0u8; 16];
+    OsRng.try_fill_bytes(&mut salt).map_err(|e| e.to_string())?;
+    Ok(salt)
+}
+
+#[cfg(feature = "encryption")]
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key).map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+// Nonce, then ciphertext
+#[cfg(feature = "encryption")]
+fn encrypt(key: &[u8; 32], plain: &[u8]) -> Vec<u8> {
+    use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+    let cipher = aes_gcm::Aes256Gcm::new(key.into());
+    let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+    // Only fails for inputs far beyond anything saved here
+    let sealed = cipher.encrypt(&nonce, plain).expect("AES-GCM input too long");
+    [nonce.as_slice(), &sealed].concat()
+}
+
+#[cfg(feature = "encryption")]
+fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    if data.len() < 12 {
+        return Err(tr!("the encrypted file is cut short"));
+    }
+    let (nonce, sealed) = data.split_at(12);
+    let cipher = aes_gcm::Aes256Gcm::new(key.into());
+    cipher.decrypt(aes_gcm::Nonce::from_slice(nonce), sealed).map_err(|_| tr!("cannot decrypt - wrong key or damaged file"))
+}
+
+// Without the feature no key can be set, so nothing is ever sealed
+#[cfg(not(feature = "encryption"))]
+fn name_hash(_key: &[u8; 32], _name: &str) -> [u8; 16] {
+    [0; 16]
+}
+
+#[cfg(not(feature = "encryption"))]
+fn random_salt() -> Result<[u8; 16], String> {
+    Err(tr!("this build has no encryption support (--features encryption)"))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn derive_key(_passphrase: &str, _salt: &[u8]) -> Result<[u8; 32], String> {
+    Err(tr!("this build has no encryption support (--features encryption)"))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt(_key: &[u8; 32], plain: &[u8]) -> Vec<u8> {
+    plain.to_vec()
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt(_key: &[u8; 32], _data: &[u8]) -> Result<Vec<u8>, String> {
+    Err(tr!("this build has no encryption support (--features encryption)"))
+}
+
+// Uses the real key derivation and cipher, so only with the feature. The
+// unlock test is the only one touching the process-wide key.
+#[cfg(all(test, feature = "encryption"))]
+mod tests {
+    use super::*;
+
+    const SALT: [u8; 16] = *b"0123456789abcdef";
+
+    #[test]
+    fn encrypt_then_decrypt_gives_the_plain_text_back() {
+        let key = derive_key("correct horse", &SALT).unwrap();
+        for plain in [&b""[..], b"x", b"<html>MUHAMMAD ALI</html>", &[0u8; 5000]] {
+            let sealed = encrypt(&key, plain);
+            assert_ne!(&sealed[12..], plain);
+            assert_eq!(decrypt(&key, &sealed).unwrap(), plain);
+        }
+        // A fresh nonce every time
+        assert_ne!(encrypt(&key, b"same"), encrypt(&key, b"same"));
+    }
+
+    #[test]
+    fn the_wrong_key_or_a_damaged_file_does_not_decrypt() {
+        let key = derive_key("correct horse", &SALT).unwrap();
+        let sealed = encrypt(&key, b"record page");
+        assert!(decrypt(&derive_key("battery staple", &SALT).unwrap(), &sealed).is_err());
+        assert!(decrypt(&derive_key("correct horse", b"fedcba9876543210").unwrap(), &sealed).is_err());
+
+        let mut damaged = sealed.clone();
+        *damaged.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&key, &damaged).is_err());
+        assert!(decrypt(&key, &sealed[..11]).is_err());
+    }
+
+    #[test]
+    fn unlock_checks_the_passphrase_and_lock_removes_the_view_folder() {
+        let results = tempfile::tempdir().unwrap();
+        assert!(unlock(results.path(), "").is_err());
+
+        // The first unlock sets the passphrase
+        unlock(results.path(), "correct horse").unwrap();
+        assert!(exists(results.path()));
+        set_sealing(true);
+        let page = results.path().join("ABC-123_2020-01-05.html");
+        write(&page, "<html>MUHAMMAD ALI</html>").unwrap();
+        set_sealing(false);
+        assert!(is_sealed(&fs::read(&page).unwrap()));
+        assert_eq!(read_to_string(&page).unwrap(), "<html>MUHAMMAD ALI</html>");
+
+        // Names hash the same way every time and don't show the original
+        let name = opaque_name("ABC-123_2020-01-05.html");
+        assert_eq!(name, opaque_name("ABC-123_2020-01-05.html"));
+        assert_ne!(name, opaque_name("ABC-123_2020-01-06.html"));
+        assert_eq!(name.len(), 32);
+        assert!(!name.contains("ABC"));
+
+        let copy = plain_copy(&page).unwrap();
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "<html>MUHAMMAD ALI</html>");
+        let view = copy.parent().unwrap().to_path_buf();
+        assert!(view.file_name().unwrap().to_string_lossy().starts_with(VIEW_DIR_PREFIX));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&view).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+
+        lock();
+        assert!(!view.exists());
+        assert!(read(&page).is_err());
+        // Locked while sealing, nothing is written rather than plain text
+        set_sealing(true);
+        assert!(write(&results.path().join("summary.json"), "{}").is_err());
+        assert!(!results.path().join("summary.json").exists());
+        set_sealing(false);
+        assert_eq!(unlock(results.path(), "battery staple"), Err(tr!("wrong passphrase")));
+        assert!(!is_unlocked());
+        unlock(results.path(), "correct horse").unwrap();
+        assert_eq!(read_to_string(&page).unwrap(), "<html>MUHAMMAD ALI</html>");
+
+        // Results saved before encryption are sealed under the names they
+        // would have had, and the rest under their own
+        let found = results.path().join("Punjab").join("HTTP500_XYZ-9_2021-03-04.html");
+        fs::create_dir_all(found.parent().unwrap()).unwrap();
+        fs::write(&found, "<html>error</html>").unwrap();
+        fs::write(found.with_extension("png"), b"png").unwrap();
+        fs::write(results.path().join("summary.json"), "{}").unwrap();
+        set_sealing(true);
+        let sealed = seal_results(results.path()).unwrap();
+        set_sealing(false);
+        let hashed = format!("Punjab/HTTP500_{}.html", opaque_name("XYZ-9_2021-03-04.html"));
+        assert_eq!(sealed.files, 3);
+        // The page sealed above already was, but still gets its hashed name
+        assert_eq!(sealed.renamed, vec![("ABC-123_2020-01-05.html".to_string(), format!("{}.html", name)),
+                                        ("Punjab/HTTP500_XYZ-9_2021-03-04.html".to_string(), hashed.clone())]);
+        assert!(!found.exists());
+        assert_eq!(read_to_string(&results.path().join(&hashed)).unwrap(), "<html>error</html>");
+        assert_eq!(read(&results.path().join(&hashed).with_extension("png")).unwrap(), b"png");
+        assert_eq!(read_to_string(&results.path().join("summary.json")).unwrap(), "{}");
+        assert!(is_sealed(&fs::read(results.path().join("summary.json")).unwrap()));
+        lock();
+    }
+}
@@ -0,0 +1,148 @@
+use crate::classify::{Classification, Classifier};
+use crate::plate;
+use crate::record;
+use crate::transport::Transport;
+use crate::vault;
+use chrono::NaiveDate;
+use std::fs;
+use std::path::Path;
+
+// Formats seen in fleet spreadsheets and on the excise pages
+const DATE_FORMATS: [&str; 6] = ["%Y-%m-%d", "%d-%m-%Y", "%d/%m/%Y", "%d.%m.%Y", "%d-%b-%Y", "%d %b %Y"];
+
+pub fn parse_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim();
+    DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+}
+
+// One row of the imported CSV: a plate and the registration date its
+// paperwork claims
+pub struct Claim {
+    pub line: usize,
+    pub plate: String,
+    pub date: NaiveDate,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Verified,
+    NotFound,
+    // The server has a record for the pair but its registration date differs
+    Mismatch,
+    Error,
+}
+
+impl Verdict {
+    pub const ALL: [Verdict; 4] = [Verdict::Verified, Verdict::NotFound, Verdict::Mismatch, Verdict::Error];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verdict::Verified => "Verified",
+            Verdict::NotFound => "Not found",
+            Verdict::Mismatch => "Mismatch",
+            Verdict::Error => "Error",
+        }
+    }
+}
+
+pub struct Outcome {
+    pub claim: Claim,
+    pub verdict: Verdict,
+    pub found_date: String,
+    pub owner: String,
+    pub detail: String,
+    // Everything parsed off the page, for showing a single result in full
+    pub fields: Vec<(String, String)>,
+}
+
+// "plate,date" per line, comma or semicolon separated. A header row and
+// blank lines are skipped; rows that can't be read are returned as messages.
+pub fn parse_csv(text: &str, separator: &str) -> (Vec<Claim>, Vec<String>) {
+    let mut claims = Vec::new();
+    let mut problems = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cells: Vec<&str> = line.split([',', ';']).map(|c| c.trim().trim_matches('"')).collect();
+        let (Some(plate_cell), Some(date_cell)) = (cells.first(), cells.get(1)) else {
+            problems.push(format!("line {}: expected plate and date", line_no));
+            continue;
+        };
+        match parse_date(date_cell) {
+            Some(date) => claims.push(Claim { line: line_no, plate: plate::normalize(plate_cell, separator), date }),
+            None if line_no == 1 => {}
+            None => problems.push(format!("line {}: unreadable date '{}'", line_no, date_cell)),
+        }
+    }
+
+    (claims, problems)
+}
+
+// Queries the exact pair once
+pub fn check(transport: &dyn Transport, classifier: &Classifier, claim: Claim) -> Outcome {
+    let date_str = claim.date.format("%Y-%m-%d").to_string();
+    let (status, body) = match transport.check(&claim.plate, &date_str) {
+        Ok(response) => response,
+        Err(e) => {
+            return Outcome {
+                claim,
+                verdict: Verdict::Error,
+                found_date: String::new(),
+                owner: String::new(),
+                detail: e.to_string(),
+                fields: Vec::new(),
+            };
+        }
+    };
+
+    let (classification, rule) = classifier.classify(status, &body);
+    let fields = record::parse_fields(&record::rules(), &body);
+    let owner = record::owner(&fields).unwrap_or_default().to_string();
+    let found_date = record::key_fields(&fields)
+    .into_iter()
+    .find(|(name, _)| *name == "Registration date")
+    .map(|(_, value)| value.to_string())
+    .unwrap_or_default();
+
+    let verdict = match classification {
+        Classification::Miss => Verdict::NotFound,
+        Classification::Hit => match parse_date(&found_date) {
+            Some(date) if date != claim.date => Verdict::Mismatch,
+            _ => Verdict::Verified,
+        },
+        _ => Verdict::Error,
+    };
+    let detail = format!("HTTP {}, {} ({})", status, classification.label(), rule);
+    Outcome { claim, verdict, found_date, owner, detail, fields }
+}
+
+pub fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Not written while the results are encrypted, like the run reports
+pub fn write_report(path: &Path, outcomes: &[Outcome]) -> std::io::Result<()> {
+    vault::allow_plain()?;
+    let mut out = String::from("line,plate,claimed_date,verdict,registration_date_on_record,owner,detail\n");
+    for outcome in outcomes {
+        let row = [
+            outcome.claim.line.to_string(),
+            outcome.claim.plate.clone(),
+            outcome.claim.date.format("%Y-%m-%d").to_string(),
+            outcome.verdict.label().to_string(),
+            outcome.found_date.clone(),
+            outcome.owner.clone(),
+            outcome.detail.clone(),
+        ];
+        out.push_str(&row.iter().map(|cell| csv_cell(cell)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    fs::write(path, out)
+}
@@ -0,0 +1,248 @@
+// Whole scans against FixtureTransport: no network, the answers come from
+// files in a temporary directory.
+use chrono::NaiveDate;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use veh_reg::classify::{self, Classification, Classifier};
+use veh_reg::coverage::CoverageMap;
+use veh_reg::stop::{StopConditions, StopReason};
+use veh_reg::storage::{self, SaveLocation};
+use veh_reg::transport::{FixtureTransport, Transport, TransportResult};
+use veh_reg::{ScanEvent, ScanHandle, ScanOptions, VehicleClient};
+
+const VEHICLE: &str = "ABC-123";
+
+const RECORD_PAGE: &str = "<html><body><table>\
+<tr><td>Owner Name</td><td>MUHAMMAD ALI</td></tr>\
+<tr><td>Chassis No</td><td>NZE141-1234567</td></tr>\
+</table></body></html>";
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2020, 1, day).unwrap()
+}
+
+// A record on Jan 5, a rate-limit page on Jan 3 and a server error on
+// Jan 8; every other date gets the no-record page
+fn fixtures(dir: &Path) {
+    fs::write(dir.join("ABC-123_2020-01-05.html"), RECORD_PAGE).unwrap();
+    fs::write(dir.join("HTTP429_ABC-123_2020-01-03.html"), "slow down").unwrap();
+    fs::write(dir.join("HTTP500_ABC-123_2020-01-08.html"), "Internal Server Error").unwrap();
+}
+
+fn client(dir: &Path) -> VehicleClient {
+    let classifier = Classifier::new(&classify::default_rules()).unwrap();
+    VehicleClient::with_transport(Arc::new(FixtureTransport::new(dir.to_path_buf())), classifier)
+}
+
+// Serves the fixtures, but a rate-limit page only once: the server lets
+// the date through after the cool-off. Keeps when each date was asked for.
+struct RateLimitClears {
+    fixtures: FixtureTransport,
+    dir: PathBuf,
+    requests: Mutex<Vec<(String, Instant)>>,
+}
+
+impl Transport for RateLimitClears {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        self.requests.lock().unwrap().push((date_str.to_string(), Instant::now()));
+        let answer = self.fixtures.check(vehicle_no, date_str)?;
+        if answer.0 == 429 {
+            fs::remove_file(self.dir.join(format!("HTTP429_{}_{}.html", vehicle_no, date_str)))?;
+        }
+        Ok(answer)
+    }
+
+    fn bootstrap(&self, _url: &str) -> TransportResult<u16> {
+        Ok(200)
+    }
+}
+
+const COOLOFF: Duration = Duration::from_millis(300);
+
+// One thread, so dates are checked in order, no hit limit and a short cool-off
+fn options() -> ScanOptions {
+    ScanOptions { threads: 1, stop: StopConditions { max_hits: 0, ..StopConditions::default() }, cooloff: COOLOFF, ..ScanOptions::default() }
+}
+
+fn events(mut scan: ScanHandle) -> (ScanHandle, Vec<ScanEvent>) {
+    let mut events = Vec::new();
+    while let Some(event) = scan.next_blocking() {
+        events.push(event);
+    }
+    (scan, events)
+}
+
+#[test]
+fn hits_misses_and_rate_limits_are_told_apart() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path());
+
+    let transport = Arc::new(RateLimitClears {
+        fixtures: FixtureTransport::new(dir.path().to_path_buf()),
+        dir: dir.path().to_path_buf(),
+        requests: Mutex::new(Vec::new()),
+    });
+    let classifier = Classifier::new(&classify::default_rules()).unwrap();
+    let client = VehicleClient::with_transport(transport.clone(), classifier);
+
+    let (scan, events) = events(client.scan(VEHICLE, date(1), date(6), options()));
+
+    let hits: Vec<_> = events.iter().filter_map(|e| if let ScanEvent::Hit { outcome, .. } = e { Some(outcome) } else { None }).collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].date, date(5));
+    assert_eq!(hits[0].status, 200);
+    assert!(hits[0].fields.iter().any(|(label, value)| label == "Owner Name" && value == "MUHAMMAD ALI"));
+
+    let limited: Vec<_> = events.iter().filter_map(|e| if let ScanEvent::RateLimited { outcome, cooloff, .. } = e { Some((outcome.date, outcome.status, *cooloff)) } else { None }).collect();
+    assert_eq!(limited, vec![(date(3), 429, COOLOFF)]);
+    assert!(!events.iter().any(|e| matches!(e, ScanEvent::Error { .. })));
+
+    // Jan 3 is asked for again once the cool-off is over, and then misses
+    let misses: Vec<_> = events.iter().filter_map(|e| if let ScanEvent::Miss { outcome, .. } = e { Some(outcome.date) } else { None }).collect();
+    assert_eq!(misses, vec![date(1), date(2), date(3), date(4), date(6)]);
+    let requests = transport.requests.lock().unwrap();
+    let jan3: Vec<_> = requests.iter().filter(|(day, _)| day == "2020-01-03").map(|(_, at)| *at).collect();
+    assert_eq!(jan3.len(), 2);
+    assert!(jan3[1] - jan3[0] >= COOLOFF);
+    // Six dates, Jan 3 again, and Jan 4 once more as the control date of the hit
+    assert_eq!(requests.len(), 8);
+
+    // Each date counts once, the rate limit as an error
+    assert!(scan.stop_reason().is_none());
+    assert_eq!(scan.progress(), (6, 6));
+    assert_eq!(scan.errors(), 1);
+}
+
+#[test]
+fn a_server_error_stops_the_scan() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path());
+
+    let (scan, events) = events(client(dir.path()).scan(VEHICLE, date(7), date(10), options()));
+
+    assert!(matches!(
+        events.iter().find(|e| matches!(e, ScanEvent::Error { .. })),
+        Some(ScanEvent::Error { outcome, stopping: true, .. }) if outcome.status == 500
+    ));
+    assert!(scan.stop_reason() == Some(StopReason::ServerError(500)));
+    // Jan 7 was checked; the error page is no answer for Jan 8 and nothing comes after it
+    assert_eq!(scan.progress(), (1, 4));
+}
+
+// Dates in the coverage map from an earlier session are skipped, and the
+// ones answered now are added to it
+#[test]
+fn coverage_is_skipped_and_recorded() {
+    // No fixtures: every date misses
+    let dir = tempfile::tempdir().unwrap();
+    let mut earlier = CoverageMap::default();
+    earlier.insert(date(1));
+    earlier.insert(date(2));
+    let coverage = Arc::new(Mutex::new(earlier));
+
+    let options = ScanOptions { coverage: Some(Arc::clone(&coverage)), skip_checked: true, ..options() };
+    let (scan, events) = events(client(dir.path()).scan(VEHICLE, date(1), date(6), options));
+
+    let skipped: Vec<_> = events.iter().filter_map(|e| if let ScanEvent::Skipped { date, .. } = e { Some(*date) } else { None }).collect();
+    assert_eq!(skipped, vec![date(1), date(2)]);
+    assert_eq!(scan.progress(), (4, 4));
+    let coverage = coverage.lock().unwrap();
+    assert_eq!(coverage.count_in(date(1), date(6)), 6);
+}
+
+// Fails every request for one date, answers the rest from the fixtures
+struct Unreachable {
+    fixtures: FixtureTransport,
+    date: &'static str,
+}
+
+impl Transport for Unreachable {
+    fn check(&self, vehicle_no: &str, date_str: &str) -> TransportResult<(u16, String)> {
+        if date_str == self.date {
+            return Err("connection reset".into());
+        }
+        self.fixtures.check(vehicle_no, date_str)
+    }
+
+    fn bootstrap(&self, _url: &str) -> TransportResult<u16> {
+        Ok(200)
+    }
+}
+
+#[test]
+fn dates_without_an_answer_are_not_counted_as_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = Arc::new(Unreachable { fixtures: FixtureTransport::new(dir.path().to_path_buf()), date: "2020-01-02" });
+    let client = VehicleClient::with_transport(transport, Classifier::new(&classify::default_rules()).unwrap());
+    let coverage = Arc::new(Mutex::new(CoverageMap::default()));
+
+    let options = ScanOptions { coverage: Some(Arc::clone(&coverage)), ..options() };
+    let (scan, events) = events(client.scan(VEHICLE, date(1), date(3), options));
+
+    assert!(events.iter().any(|e| matches!(e, ScanEvent::Failed { date: failed, .. } if *failed == date(2))));
+    assert_eq!(scan.progress(), (2, 3));
+    assert_eq!(scan.failed(), 1);
+    assert!(!coverage.lock().unwrap().contains(date(2)));
+}
+
+// A "record" that comes back the same for the control date next to it is a
+// generic page, waited out like a rate limit instead of saved as a hit
+#[test]
+fn the_same_page_for_two_dates_is_not_a_hit() {
+    let dir = tempfile::tempdir().unwrap();
+    for day in ["2020-01-01", "2020-01-02"] {
+        fs::write(dir.path().join(format!("ABC-123_{}.html", day)), RECORD_PAGE).unwrap();
+    }
+    // It keeps coming back, so the request limit ends the scan
+    let stop = StopConditions { max_hits: 0, max_requests: 4, ..StopConditions::default() };
+    let options = ScanOptions { cooloff: Duration::from_millis(50), stop, ..options() };
+    let (scan, events) = events(client(dir.path()).scan(VEHICLE, date(2), date(2), options));
+
+    assert!(!events.iter().any(|e| matches!(e, ScanEvent::Hit { .. })));
+    assert!(events.iter().any(|e| matches!(e, ScanEvent::RateLimited { outcome, .. } if outcome.fields.is_empty() && outcome.rule.contains("2020-01-01"))));
+    assert!(scan.stop_reason() == Some(StopReason::Requests(4)));
+    assert!(scan.hits().is_empty());
+}
+
+#[test]
+fn single_checks_classify_each_fixture() {
+    let dir = tempfile::tempdir().unwrap();
+    fixtures(dir.path());
+    let client = client(dir.path());
+
+    let classification = |day| client.check_blocking(VEHICLE, date(day)).unwrap().classification;
+    assert_eq!(classification(5), Classification::Hit);
+    assert_eq!(classification(1), Classification::Miss);
+    assert_eq!(classification(3), Classification::RateLimited);
+    assert_eq!(classification(8), Classification::Error);
+}
+
+// Saved the way the daemon saves hits, the files are found again by the
+// results view with the right vehicle, date and status
+#[test]
+fn saved_responses_are_read_back() {
+    let fixture_dir = tempfile::tempdir().unwrap();
+    fixtures(fixture_dir.path());
+    let results = tempfile::tempdir().unwrap();
+    let location = SaveLocation::new(results.path(), VEHICLE, chrono::Local::now(), storage::DEFAULT_FILENAME_TEMPLATE);
+    location.create().unwrap();
+
+    let client = client(fixture_dir.path());
+    for day in [3, 5] {
+        let outcome = client.check_blocking(VEHICLE, date(day)).unwrap();
+        let relative = location.relative_path(VEHICLE, &outcome.date.format("%Y-%m-%d").to_string(), outcome.status, 1);
+        fs::write(results.path().join(&relative), &outcome.body).unwrap();
+    }
+
+    let saved = storage::walk_results(results.path());
+    assert_eq!(saved.len(), 2);
+    let mut names: Vec<_> = saved.iter().map(|path| storage::parse_result_path(path).unwrap()).collect();
+    names.sort_by_key(|name| name.date);
+    assert!(names.iter().all(|name| name.vehicle_no == VEHICLE));
+    assert_eq!(names.iter().map(|name| (name.date, name.status)).collect::<Vec<_>>(), vec![(date(3), 429), (date(5), 200)]);
+
+    let hit = saved.iter().find(|path| !path.contains("HTTP429_")).unwrap();
+    assert_eq!(fs::read_to_string(results.path().join(hit)).unwrap(), RECORD_PAGE);
+}
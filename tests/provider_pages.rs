@@ -0,0 +1,63 @@
+// Each province's not-found and record pages, as its site sends them, run
+// through the rules the app switches to when that province is picked
+use std::fs;
+use std::path::Path;
+use veh_reg::classify::{self, Classification, Classifier};
+use veh_reg::config::AppConfig;
+use veh_reg::provider::Provider;
+use veh_reg::record::{self, FieldRules};
+
+fn page(name: &str) -> String {
+    fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)).unwrap()
+}
+
+fn classify(provider: Provider, body: &str) -> (Classification, String) {
+    let mut config = AppConfig::default();
+    config.switch_provider(provider);
+    Classifier::new(&config.classifier_rules).unwrap().classify(200, body)
+}
+
+fn field<'a>(fields: &'a [(String, String)], label: &str) -> Option<&'a str> {
+    fields.iter().find(|(name, _)| name == label).map(|(_, value)| value.as_str())
+}
+
+#[test]
+fn punjab_pages() {
+    let not_found = page("punjab/not_found.html");
+    assert_eq!(classify(Provider::Punjab, &not_found), (Classification::Miss, classify::NO_RECORD_RULE.to_string()));
+    // Only Punjab's own rule knows this page
+    assert_eq!(classify(Provider::Islamabad, &not_found).0, Classification::Hit);
+
+    let found = page("punjab/found.html");
+    assert_eq!(classify(Provider::Punjab, &found).0, Classification::Hit);
+    let fields = record::parse_fields(&FieldRules::default(), &found);
+    assert_eq!(field(&fields, "Owner Name"), Some("MUHAMMAD ALI"));
+    assert_eq!(field(&fields, "Chassis No"), Some("NZE141-1234567"));
+}
+
+#[test]
+fn sindh_pages() {
+    let not_found = page("sindh/not_found.json");
+    assert_eq!(classify(Provider::Sindh, &not_found), (Classification::Miss, classify::NO_RECORD_RULE.to_string()));
+    assert_eq!(classify(Provider::Islamabad, &not_found).0, Classification::Hit);
+
+    // The service answers in JSON for records too, with a status of its own
+    let found = page("sindh/found.json");
+    assert_eq!(classify(Provider::Sindh, &found).0, Classification::Hit);
+    let fields = record::parse_fields(&FieldRules::default(), &found);
+    assert_eq!(field(&fields, "Owner Name"), Some("SARA BIBI"));
+    assert_eq!(field(&fields, "Chassis No"), Some("ZRE142-7654321"));
+}
+
+#[test]
+fn kpk_pages() {
+    let not_found = page("kpk/not_found.json");
+    assert_eq!(classify(Provider::Kpk, &not_found), (Classification::Miss, classify::NO_RECORD_RULE.to_string()));
+    assert_eq!(classify(Provider::Islamabad, &not_found).0, Classification::Hit);
+
+    let found = page("kpk/found.json");
+    assert_eq!(classify(Provider::Kpk, &found).0, Classification::Hit);
+    let fields = record::parse_fields(&FieldRules::default(), &found);
+    assert_eq!(field(&fields, "Owner Name"), Some("AHMED KHAN"));
+    assert_eq!(field(&fields, "Registration Date"), Some("2018-07-21"));
+}
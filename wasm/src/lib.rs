@@ -0,0 +1,66 @@
+use serde::Serialize;
+use veh_reg::classify::{self, Classification, Classifier, ClassifierRule};
+use veh_reg::{form, record};
+use wasm_bindgen::prelude::*;
+
+// Browser bindings for single lookups:
+//
+//     import init, { Client } from "./pkg/veh_reg_wasm.js";
+//     await init();
+//     const client = new Client("/proxy/verify");
+//     const outcome = await client.check("ABC-123", "2021-03-04");
+//
+// Browsers won't post cross-origin to the real endpoint, so `endpoint` is
+// normally a proxy on the embedding page's own origin that forwards the
+// request as is. Range scans are left to the caller, one `check` per date.
+#[wasm_bindgen]
+pub struct Client {
+    endpoint: String,
+    classifier: Classifier,
+    http: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct Outcome {
+    vehicle_no: String,
+    date: String,
+    status: u16,
+    classification: &'static str,
+    rule: String,
+    // Labelled fields from the record page; empty unless it's a hit
+    fields: Vec<(String, String)>,
+    body: String,
+}
+
+#[wasm_bindgen]
+impl Client {
+    // `rules` is the classifier rules as JSON, as saved in the app's settings
+    // file; the built-in rules are used without it
+    #[wasm_bindgen(constructor)]
+    pub fn new(endpoint: String, rules: Option<String>) -> Result<Client, JsError> {
+        let rules: Vec<ClassifierRule> = match rules {
+            Some(json) => serde_json::from_str(&json).map_err(|e| JsError::new(&format!("Invalid classifier rules - {}", e)))?,
+            None => classify::default_rules(),
+        };
+        let classifier = Classifier::new(&rules).map_err(|e| JsError::new(&e))?;
+        Ok(Client { endpoint, classifier, http: reqwest::Client::new() })
+    }
+
+    // Resolves to { vehicle_no, date, status, classification, rule, fields, body }
+    pub async fn check(&self, vehicle_no: String, date: String) -> Result<JsValue, JsError> {
+        let response = self.http
+        .post(&self.endpoint)
+        .header("Content-Type", form::content_type())
+        .body(form::multipart_body(&vehicle_no, &date))
+        .send()
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(|e| JsError::new(&e.to_string()))?;
+
+        let (classification, rule) = self.classifier.classify(status, &body);
+        let fields = if classification == Classification::Hit { record::parse_fields(&body) } else { Vec::new() };
+        let outcome = Outcome { vehicle_no, date, status, classification: classification.label(), rule, fields, body };
+        serde_wasm_bindgen::to_value(&outcome).map_err(|e| JsError::new(&e.to_string()))
+    }
+}